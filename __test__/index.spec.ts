@@ -1,11 +1,29 @@
 import { PDFDocument } from 'pdf-lib'
 import { fileURLToPath } from 'node:url'
 import { dirname } from 'node:path'
+import { X509Certificate } from 'node:crypto'
 import fs from 'node:fs'
+import os from 'node:os'
 import path from 'node:path'
 import test from 'ava'
 
-import { signPdfWithPath, signPdf } from '../index'
+import {
+  signPdfWithPath,
+  signPdf,
+  signPdfTo,
+  signPdfAsync,
+  signBytes,
+  listSignatures,
+  extractSignerCertificate,
+  checkIntegrity,
+  addDocumentTimestamp,
+  readCertificate,
+  readChainInfo,
+  validatePdf,
+  PdfSignStream,
+  SaveFormat,
+  SignatureLevel,
+} from '../index'
 
 const __filename = fileURLToPath(import.meta.url)
 const __dirname = dirname(__filename)
@@ -52,3 +70,226 @@ test('testing fixDocument function', async (t) => {
   )
   t.true(Buffer.isBuffer(pdfSigned.toBuffer()))
 })
+
+test('sign arbitrary bytes as detached and enveloped CMS', (t) => {
+  const certificate = {
+    pfxPath: path.join(__dirname, 'certificado-a1.pfx'),
+    pfxPassword: '123456',
+  }
+  const data = Buffer.from('<NFe><infNFe>teste</infNFe></NFe>')
+
+  const detached = signBytes(certificate, data, true)
+  const enveloped = signBytes(certificate, data, false)
+
+  t.true(Buffer.isBuffer(detached))
+  t.false(detached.includes(data))
+  t.true(enveloped.includes(data))
+})
+
+test('list signatures of a doubly signed PDF', (t) => {
+  const certificate = {
+    pfxPath: path.join(__dirname, 'certificado-a1.pfx'),
+    pfxPassword: '123456',
+  }
+  const pdfBytes = fs.readFileSync(path.join(__dirname, 'pdf_sample_2.pdf'))
+
+  const once = signPdf(certificate, pdfBytes, { reason: 'Primeira' }).toBuffer()
+  const twice = signPdf(certificate, once, { reason: 'Segunda' }).toBuffer()
+
+  const signatures = listSignatures(twice)
+  t.is(signatures.length, 2)
+  t.deepEqual(
+    signatures.map((s) => s.reason),
+    ['Primeira', 'Segunda'],
+  )
+  t.is(signatures[0].subFilter, 'adbe.pkcs7.detached')
+  t.is(signatures[0].byteRange.length, 4)
+})
+
+test('empty reason omits /Reason instead of the default', (t) => {
+  const certificate = {
+    pfxPath: path.join(__dirname, 'certificado-a1.pfx'),
+    pfxPassword: '123456',
+  }
+  const pdfBytes = fs.readFileSync(path.join(__dirname, 'pdf_sample_2.pdf'))
+
+  const signed = signPdf(certificate, pdfBytes, { reason: '' }).toBuffer()
+
+  t.is(listSignatures(signed)[0].reason, undefined)
+  t.false(signed.includes('/Reason'))
+})
+
+test('padesLevel and tsaUrl reach the signing config', (t) => {
+  const certificate = {
+    pfxPath: path.join(__dirname, 'certificado-a1.pfx'),
+    pfxPassword: '123456',
+  }
+  const pdfBytes = fs.readFileSync(path.join(__dirname, 'pdf_sample_2.pdf'))
+  const tsaUrl = 'http://127.0.0.1:9/tsa'
+
+  // Em BB a TSA não é consultada
+  t.notThrows(() => signPdf(certificate, pdfBytes, { padesLevel: SignatureLevel.BB, tsaUrl }))
+
+  const error = t.throws(() => signPdf(certificate, pdfBytes, { padesLevel: SignatureLevel.BT, tsaUrl }))
+  t.is((error as Error & { code: string }).code, 'TIMESTAMP_ERROR')
+  t.true(error?.message.includes(tsaUrl))
+})
+
+test('sign a PDF written in chunks like a single buffer', (t) => {
+  const certificate = {
+    pfxPath: path.join(__dirname, 'certificado-a1.pfx'),
+    pfxPassword: '123456',
+  }
+  const pdfBytes = fs.readFileSync(path.join(__dirname, 'pdf_sample_2.pdf'))
+  const config = { reason: 'Aprovado', location: 'Brasil' }
+
+  const stream = new PdfSignStream(certificate, config)
+  const chunkSize = Math.ceil(pdfBytes.length / 5)
+  for (let start = 0; start < pdfBytes.length; start += chunkSize) {
+    stream.write(pdfBytes.subarray(start, start + chunkSize))
+  }
+  const chunked = stream.finish().toBuffer()
+  const single = signPdf(certificate, pdfBytes, config).toBuffer()
+
+  // Só o instante e o CMS mudam entre as duas assinaturas
+  t.is(chunked.length, single.length)
+  t.true(chunked.subarray(0, pdfBytes.length).equals(pdfBytes))
+  const [fromChunks] = listSignatures(chunked)
+  const [fromBuffer] = listSignatures(single)
+  t.deepEqual(fromChunks.byteRange, fromBuffer.byteRange)
+  t.is(fromChunks.reason, fromBuffer.reason)
+  t.is(fromChunks.fieldName, fromBuffer.fieldName)
+})
+
+test('validatePdf reports the structure of valid and malformed uploads', (t) => {
+  const sample = fs.readFileSync(path.join(__dirname, 'pdf_sample_2.pdf'))
+  t.deepEqual(validatePdf(sample), {
+    isValid: true,
+    pdfVersion: '1.4',
+    hasAcroform: false,
+    firstPageFound: true,
+    catalogFound: true,
+  })
+
+  const docx = validatePdf(Buffer.from('PK\x03\x04word/document.xml'))
+  t.false(docx.isValid)
+  t.is(docx.pdfVersion, undefined)
+  t.false(docx.catalogFound)
+  t.regex(docx.error ?? '', /%PDF-x\.y/)
+
+  const truncated = validatePdf(sample.subarray(0, 64))
+  t.false(truncated.isValid)
+  t.is(truncated.pdfVersion, '1.4')
+  t.truthy(truncated.error)
+})
+
+test('signPdfTo writes the signed PDF to a file and resolves with its path', async (t) => {
+  const certificate = {
+    pfxPath: path.join(__dirname, 'certificado-a1.pfx'),
+    pfxPassword: '123456',
+  }
+  const pdfBytes = fs.readFileSync(path.join(__dirname, 'pdf_sample_2.pdf'))
+  const output = path.join(os.tmpdir(), `pdfsigner-sign-to-${process.pid}.pdf`)
+
+  const location = await signPdfTo(certificate, pdfBytes, { padesLevel: SignatureLevel.BB }, {
+    format: SaveFormat.File,
+    path: output,
+  })
+  t.is(location, output)
+  const signed = fs.readFileSync(output)
+  fs.unlinkSync(output)
+  t.is(listSignatures(signed).length, 1)
+
+  // Destino S3 sem credenciais é recusado antes de assinar
+  const error = t.throws(() => signPdfTo(certificate, pdfBytes, null, { format: SaveFormat.S3, path: 'a.pdf' }))
+  t.is((error as Error & { code: string }).code, 'INVALID_ARGUMENT')
+})
+
+test('signPdfAsync asks pfxPasswordCallback for the password off the JS thread', async (t) => {
+  const pdfBytes = fs.readFileSync(path.join(__dirname, 'pdf_sample_2.pdf'))
+  const certificate = {
+    pfxPath: path.join(__dirname, 'certificado-a1.pfx'),
+    pfxPasswordCallback: () => '123456',
+  }
+
+  const signed = await signPdfAsync(certificate, pdfBytes, { padesLevel: SignatureLevel.BB })
+  t.is(listSignatures(signed.toBuffer()).length, 1)
+
+  // As funções síncronas não podem esperar pelo callback
+  const error = t.throws(() => signPdf(certificate, pdfBytes))
+  t.is((error as Error & { code: string }).code, 'INVALID_ARGUMENT')
+
+  // Erro lançado pelo callback rejeita a promise
+  const failing = {
+    pfxPath: certificate.pfxPath,
+    pfxPasswordCallback: (): string => {
+      throw new Error('cofre indisponível')
+    },
+  }
+  await t.throwsAsync(() => signPdfAsync(failing, pdfBytes))
+})
+
+test('extractSignerCertificate reads who signed the document', (t) => {
+  const certificate = {
+    pfxPath: path.join(__dirname, 'certificado-a1.pfx'),
+    pfxPassword: '123456',
+  }
+  const signed = signPdfWithPath(certificate, path.join(__dirname, 'pdf_sample_2.pdf'), {
+    padesLevel: SignatureLevel.BB,
+  }).toBuffer()
+
+  const [signer] = readChainInfo(certificate)
+  const extracted = extractSignerCertificate(signed)
+  t.is(extracted.commonName, signer.commonName)
+  t.is(extracted.serialNumber, signer.serialNumber)
+  t.is(extracted.thumbprintSha256, signer.thumbprintSha256)
+
+  const error = t.throws(() => extractSignerCertificate(fs.readFileSync(path.join(__dirname, 'pdf_sample_2.pdf'))))
+  t.is((error as Error & { code: string }).code, 'INVALID_PDF')
+})
+
+test('checkIntegrity flags bytes appended after the last signature', (t) => {
+  const signed = signPdfWithPath(
+    { pfxPath: path.join(__dirname, 'certificado-a1.pfx'), pfxPassword: '123456' },
+    path.join(__dirname, 'pdf_sample_2.pdf'),
+    { padesLevel: SignatureLevel.BB },
+  ).toBuffer()
+
+  const untouched = checkIntegrity(signed)
+  t.is(untouched.signatures.length, 1)
+  t.true(untouched.signatures[0].unmodified)
+  t.false(untouched.appended)
+
+  const resaved = checkIntegrity(Buffer.concat([signed, Buffer.from('\n% re-salvo\n')]))
+  t.true(resaved.signatures[0].unmodified)
+  t.true(resaved.appended)
+  t.is(resaved.appendedBytes, 12)
+})
+
+test('addDocumentTimestamp requires a TSA', (t) => {
+  const pdfBytes = fs.readFileSync(path.join(__dirname, 'pdf_sample_2.pdf'))
+
+  const error = t.throws(() => addDocumentTimestamp(pdfBytes))
+  t.is((error as Error & { code: string }).code, 'SIGNING_ERROR')
+
+  // padesLevel não traz a TSA do ITI de volta: continua sem rede
+  const withLevel = t.throws(() => addDocumentTimestamp(pdfBytes, { padesLevel: SignatureLevel.BT }))
+  t.is((withLevel as Error & { code: string }).code, 'SIGNING_ERROR')
+
+  const unreachable = t.throws(() => addDocumentTimestamp(pdfBytes, { tsaUrl: 'http://127.0.0.1:9/tsa' }))
+  t.is((unreachable as Error & { code: string }).code, 'TIMESTAMP_ERROR')
+})
+
+test('readCertificate accepts the same certificate as DER or PEM', (t) => {
+  const pem = fs.readFileSync(path.join(__dirname, 'certificado-entrada.pem'))
+  const der = new X509Certificate(pem).raw
+
+  const fromPem = readCertificate(pem)
+  const fromDer = readCertificate(der)
+  t.is(fromPem.commonName, 'Certificado de Entrada')
+  t.true(fromPem.isSelfSigned)
+  t.deepEqual(fromDer, fromPem)
+
+  const error = t.throws(() => readCertificate(Buffer.from('nada')))
+  t.is((error as Error & { code: string }).code, 'DECODING_ERROR')
+})
@@ -0,0 +1,89 @@
+//! Segunda assinatura num PDF de ~1 MB que já tem uma assinatura e um campo vazio
+//!
+//! `generico`: sem `fill_existing_field`, a atualização cria widget, regrava o
+//! AcroForm e o Catalog. `so_campo`: preenche o campo pré-criado, e a atualização
+//! leva só o dicionário de assinatura e o campo com /V.
+//!
+//! O binding napi não linka fora do Node: rode com
+//! `cargo bench --bench resign --no-default-features --features cms-rustcrypto`
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use pdfsigner_rs::{PadesLevel, PdfSigner, SignatureConfig};
+
+/// Tamanho do stream acrescentado ao PDF de exemplo
+const FILLER_BYTES: usize = 1024 * 1024;
+
+/// PDF de exemplo com uma atualização incremental que acrescenta um stream de 1 MB
+fn large_pdf() -> Vec<u8> {
+  let mut pdf = std::fs::read("__test__/pdf_sample_2.pdf").unwrap();
+  let text = String::from_utf8_lossy(&pdf).to_string();
+  let prev_xref = text[text.rfind("startxref").unwrap() + 9..]
+    .split_whitespace()
+    .next()
+    .unwrap()
+    .to_string();
+  let root = text[text.find("/Root").unwrap() + 5..]
+    .split_whitespace()
+    .next()
+    .unwrap()
+    .to_string();
+
+  pdf.push(b'\n');
+  let filler_pos = pdf.len();
+  pdf.extend_from_slice(format!("90 0 obj\n<< /Length {} >>\nstream\n", FILLER_BYTES).as_bytes());
+  pdf.extend((0..FILLER_BYTES).map(|i| b'a' + (i % 26) as u8));
+  pdf.extend_from_slice(b"\nendstream\nendobj\n");
+  let xref_start = pdf.len();
+  pdf.extend_from_slice(
+    format!(
+      "xref\n90 1\n{:010} 00000 n \ntrailer\n<< /Size 91 /Prev {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+      filler_pos, prev_xref, root, xref_start
+    )
+    .as_bytes(),
+  );
+  pdf
+}
+
+fn config(field: Option<&str>) -> SignatureConfig {
+  SignatureConfig {
+    pades_level: PadesLevel::BB,
+    validate_icp_brasil: false,
+    fill_existing_field: field.map(str::to_string),
+    ..SignatureConfig::default()
+  }
+}
+
+fn bench_resign(c: &mut Criterion) {
+  let signer = PdfSigner::from_pfx_file("__test__/certificado-a1.pfx", "123456").unwrap();
+  let mut prepared = large_pdf();
+  for (name, rect) in [
+    ("Aprovador", [50.0, 50.0, 250.0, 100.0]),
+    ("Testemunha", [300.0, 50.0, 500.0, 100.0]),
+  ] {
+    prepared = PdfSigner::add_empty_signature_field(prepared, name, rect, 0).unwrap();
+  }
+  let signed_once = signer
+    .sign_pdf(prepared, &config(Some("Aprovador")))
+    .unwrap();
+
+  let mut group = c.benchmark_group("re-assinatura 1 MB");
+  group.sample_size(50);
+  group.bench_function("generico", |b| {
+    b.iter_batched(
+      || signed_once.clone(),
+      |pdf| signer.sign_pdf(pdf, &config(None)).unwrap(),
+      BatchSize::LargeInput,
+    )
+  });
+  group.bench_function("so_campo", |b| {
+    b.iter_batched(
+      || signed_once.clone(),
+      |pdf| signer.sign_pdf(pdf, &config(Some("Testemunha"))).unwrap(),
+      BatchSize::LargeInput,
+    )
+  });
+  group.finish();
+}
+
+criterion_group!(benches, bench_resign);
+criterion_main!(benches);
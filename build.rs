@@ -1,3 +1,6 @@
 fn main() {
-  napi_build::setup();
+  // O binding napi só existe no build nativo; o build wasm não tem o que ligar
+  if std::env::var_os("CARGO_FEATURE_NATIVE").is_some() {
+    napi_build::setup();
+  }
 }
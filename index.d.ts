@@ -5,19 +5,146 @@ export declare class PdfSigned {
   constructor(data: Array<number>)
   credentialsProvider(s3Info: S3Info): PdfSigned
   toBuffer(): Buffer
+  contentHash(algorithm?: HashAlgorithm | undefined | null): string
+  signedContentHash(algorithm?: HashAlgorithm | undefined | null): string
+  result(): SignResult
   save(path: string, format: SaveFormat): Promise<void>
 }
 
+export declare class PdfSignStream {
+  constructor(certificate: CertificateInfo, config?: Config | undefined | null)
+  write(chunk: Buffer): void
+  finish(): PdfSigned
+}
+
+export declare function addDocumentTimestamp(pdfData: Buffer, config?: Config | undefined | null): Buffer
+
+export declare function addEmptySignatureField(pdfData: Buffer, fieldName: string, rect: Array<number>, page?: number | undefined | null, reasons?: Array<string> | undefined | null): Buffer
+
+export interface AppearanceElement {
+  /** [x, y, largura, altura] em pontos, a partir do canto inferior esquerdo do campo */
+  rect: Array<number>
+  /** Imagem JPEG; tem precedência sobre `qrCode` e `text` */
+  image?: Buffer
+  /** URL do QR code; `{sha256}` vira o SHA-256 (hex) do PDF antes da assinatura */
+  qrCode?: string
+  text?: string
+  /** Padrão: 10 */
+  fontSize?: number
+}
+
+export interface CertInfo {
+  commonName: string
+  organization?: string
+  email?: string
+  validFrom: string
+  validUntil: string
+  serialNumber?: string
+  subjectDn: string
+  issuerDn: string
+  keyUsage: Array<string>
+  extendedKeyUsage: Array<string>
+  cpf?: string
+  cnpj?: string
+  expired: boolean
+  /** Ausente quando a assinatura do certificado não pôde ser conferida */
+  isSelfSigned?: boolean
+  /** Impressões digitais (hex do digest do DER) */
+  thumbprintSha1: string
+  thumbprintSha256: string
+}
+
 export interface CertificateInfo {
   pfxPath?: string
   pfxData?: Buffer
-  pfxPassword: string
+  /** Senha literal; tem precedência sobre `pfx_password_env` */
+  pfxPassword?: string
+  /** Nome da variável de ambiente que guarda a senha, para não trafegá-la pelo JS */
+  pfxPasswordEnv?: string
+  /**
+   * Chamada para obter a senha quando nenhuma das opções acima é informada; só vale
+   * em `sign_pdf_async`, que a chama fora da thread do JS
+   */
+  pfxPasswordCallback?: () => string
+  /** Número de série (hex) do certificado que assina, num PFX com várias identidades */
+  signerSerialNumber?: string
+  /** CN do certificado que assina; vale quando `signer_serial_number` não é informado */
+  signerCommonName?: string
 }
 
+/** Certificado vindo do JS: Buffer com o DER ou o PEM (vale o primeiro bloco CERTIFICATE) */
+export type CertificateInput = Buffer
+
 export interface Config {
   reason?: string
+  allowedReasons?: Array<string>
   location?: string
   contactInfo?: string
+  signerName?: string
+  trustAnchors?: Array<CertificateInput>
+  trustAnchorsPath?: string
+  replaceTrustAnchors?: boolean
+  fillExistingField?: string
+  selfVerify?: boolean
+  pdfaMode?: boolean
+  /** Tamanho máximo do PDF de entrada, em bytes */
+  maxInputBytes?: number
+  /** Camadas da aparência visível, desenhadas em ordem no campo de `fillExistingField` */
+  appearance?: Array<AppearanceElement>
+  /** Formato strftime do `{date}` nos textos da aparência; padrão: "%d/%m/%Y %H:%M:%S" */
+  appearanceDateFormat?: string
+  /** Padrão: BB, sem chamadas de rede */
+  padesLevel?: SignatureLevel
+  /** TSA do carimbo do tempo (BT ou acima); padrão: TSA do ITI */
+  tsaUrl?: string
+  /** Padrão: Sha256 */
+  digestAlgorithm?: HashAlgorithm
+  includeOcsp?: boolean
+  includeCrl?: boolean
+  validateIcpBrasil?: boolean
+  /** Assina mesmo sem digitalSignature/nonRepudiation no keyUsage do certificado */
+  ignoreKeyUsage?: boolean
+  /**
+   * Tempo limite de cada requisição de saída (TSA, OCSP, CRL e S3), em milissegundos;
+   * padrão: 30000
+   */
+  networkTimeoutMs?: number
+}
+
+export declare const enum ErrorLanguage {
+  Portuguese = 'Portuguese',
+  English = 'English'
+}
+
+export declare const enum HashAlgorithm {
+  Sha1 = 'Sha1',
+  Sha256 = 'Sha256',
+  Sha384 = 'Sha384',
+  Sha512 = 'Sha512'
+}
+
+export declare const enum S3Encryption {
+  Aes256 = 'Aes256',
+  AwsKms = 'AwsKms'
+}
+
+export interface IntegrityReport {
+  /** Uma entrada por assinatura, na ordem das atualizações incrementais */
+  signatures: Array<SignatureIntegrity>
+  /** Se há bytes depois do trecho coberto pela última assinatura */
+  appended: boolean
+  appendedBytes: number
+}
+
+export interface PdfValidation {
+  isValid: boolean
+  /** Versão do cabeçalho %PDF-x.y (ex: "1.7") */
+  pdfVersion?: string
+  hasAcroform: boolean
+  firstPageFound: boolean
+  catalogFound: boolean
+  /** Motivo do primeiro problema encontrado, quando inválido */
+  error?: string
 }
 
 export interface S3Info {
@@ -27,6 +154,10 @@ export interface S3Info {
   endpoint: string
   region?: string
   providerName?: string
+  /** Criptografia no servidor exigida pelo bucket; ausente mantém o padrão do bucket */
+  sse?: S3Encryption
+  /** Chave KMS usada com `sse` AwsKms; sem ela vale a chave padrão do bucket */
+  sseKmsKeyId?: string
 }
 
 export declare const enum SaveFormat {
@@ -34,6 +165,80 @@ export declare const enum SaveFormat {
   S3 = 'S3'
 }
 
+/** Destino de `signPdfTo`: arquivo local ou objeto no S3 */
+export interface SignDestination {
+  format: SaveFormat
+  /** Caminho do arquivo ou, no S3, a chave do objeto */
+  path: string
+  /** Bucket e credenciais; obrigatório com `format` S3 */
+  s3Info?: S3Info
+}
+
+export interface SignResult {
+  signerName?: string
+  signingDate?: string
+  fieldName?: string
+  signatureObj?: number
+  fieldObj?: number
+}
+
+export declare const enum SignatureLevel {
+  BB = 'BB',
+  BT = 'BT',
+  BLT = 'BLT',
+  BLTA = 'BLTA'
+}
+
+export interface SignatureIntegrity {
+  fieldName?: string
+  /** O conteúdo coberto pelo /ByteRange confere com o messageDigest do CMS */
+  unmodified: boolean
+  /** Por que a conferência falhou ou não pôde ser feita */
+  error?: string
+}
+
+export interface SignatureSummary {
+  fieldName?: string
+  signerName?: string
+  reason?: string
+  signingDate?: string
+  subFilter?: string
+  byteRange: Array<number>
+  timestampTime?: string
+}
+
+export declare function checkIntegrity(pdfData: Buffer): IntegrityReport
+
+export declare function clearCaches(): void
+
+export declare function extractSignerCertificate(pdfData: Buffer): CertInfo
+
+export declare function isLinearized(pdfData: Buffer): boolean
+
+export declare function isSigned(pdfData: Buffer): boolean
+
+export declare function listSignatures(pdfData: Buffer): Array<SignatureSummary>
+
+export declare function rectFromTopLeft(pdfData: Buffer, rect: Array<number>, page?: number | undefined | null): Array<number>
+
+export declare function pageMediaBox(pdfData: Buffer, page?: number | undefined | null): Array<number>
+
+export declare function readCertificate(certificate: CertificateInput): CertInfo
+
+export declare function readChainInfo(certificate: CertificateInfo): Array<CertInfo>
+
+export declare function setErrorLanguage(language: ErrorLanguage): void
+
+export declare function signBytes(certificate: CertificateInfo, data: Buffer, detached: boolean): Buffer
+
 export declare function signPdf(certificate: CertificateInfo, pdfData: Buffer, config?: Config | undefined | null): PdfSigned
 
+export declare function signPdfAsync(certificate: CertificateInfo, pdfData: Buffer, config?: Config | undefined | null): Promise<PdfSigned>
+
+export declare function signPdfFileToFile(certificate: CertificateInfo, inputPath: string, outputPath: string, config?: Config | undefined | null): void
+
+export declare function signPdfTo(certificate: CertificateInfo, pdfData: Buffer, config: Config | undefined | null, destination: SignDestination): Promise<string>
+
 export declare function signPdfWithPath(certificate: CertificateInfo, pdfPath: string, config?: Config | undefined | null): PdfSigned
+
+export declare function validatePdf(pdfData: Buffer): PdfValidation
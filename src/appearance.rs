@@ -0,0 +1,499 @@
+/// Aparência visível da assinatura: as camadas de `SignatureAppearance` compostas
+/// num único form XObject, referenciado em /AP /N do widget
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use qrcode::{Color, QrCode};
+use sha2::{Digest, Sha256};
+
+use crate::error::{PdfSignError, Result};
+use crate::signature_config::{AppearanceContent, SignatureAppearance};
+
+/// Nome do recurso da fonte no form XObject
+const FONT_RESOURCE: &str = "/F1";
+
+/// Entrelinha do texto, em múltiplos do tamanho da fonte
+const LINE_SPACING: f64 = 1.2;
+
+/// Margem clara em volta do QR code, em módulos (a mínima da ISO/IEC 18004)
+const QR_QUIET_ZONE: usize = 4;
+
+/// Marcador trocado pelo SHA-256 (hex) do documento na URL do QR code
+pub const QR_SHA256_PLACEHOLDER: &str = "{sha256}";
+
+/// Marcador trocado pela data da assinatura nos textos da aparência
+pub const TEXT_DATE_PLACEHOLDER: &str = "{date}";
+
+/// Objetos da aparência numerados a partir de `first_obj`, já serializados
+///
+/// O primeiro é o form XObject de /AP /N, do tamanho do campo (`width` x `height`);
+/// depois vêm a fonte (só quando há texto) e as imagens, na ordem das camadas.
+/// `document` é o PDF antes da assinatura, cujo hash pode ir na URL do QR code, e
+/// `signing_time` o instante gravado em /M, desenhado no lugar de `{date}`
+pub fn build_appearance_objects(
+  appearance: &SignatureAppearance,
+  width: f64,
+  height: f64,
+  first_obj: usize,
+  document: &[u8],
+  signing_time: DateTime<Utc>,
+) -> Result<Vec<Vec<u8>>> {
+  let mut next_obj = first_obj + 1;
+  let font_obj = appearance
+    .elements
+    .iter()
+    .any(|element| matches!(element.content, AppearanceContent::Text { .. }))
+    .then(|| {
+      next_obj += 1;
+      next_obj - 1
+    });
+
+  let mut content = Vec::new();
+  let mut images = Vec::new();
+  for (index, element) in appearance.elements.iter().enumerate() {
+    let [x, y, w, h] = element.rect;
+    if !element.rect.iter().all(|n| n.is_finite()) || w <= 0.0 || h <= 0.0 {
+      return Err(PdfSignError::SigningError(format!(
+        "elemento {} da aparência sem largura e altura positivas",
+        index
+      )));
+    }
+    match &element.content {
+      AppearanceContent::Image(data) => {
+        let name = format!("/Im{}", images.len() + 1);
+        content.extend_from_slice(
+          format!("q\n{} 0 0 {} {} {} cm\n{} Do\nQ\n", w, h, x, y, name).as_bytes(),
+        );
+        images.push((name, next_obj, image_object(next_obj, data, index)?));
+        next_obj += 1;
+      }
+      AppearanceContent::QrCode(url) => {
+        // Módulos quadrados: o código ocupa o maior quadrado centralizado na camada
+        let side = w.min(h);
+        let name = format!("/Im{}", images.len() + 1);
+        content.extend_from_slice(
+          format!(
+            "q\n{} 0 0 {} {} {} cm\n{} Do\nQ\n",
+            side,
+            side,
+            x + (w - side) / 2.0,
+            y + (h - side) / 2.0,
+            name
+          )
+          .as_bytes(),
+        );
+        let url = if url.contains(QR_SHA256_PLACEHOLDER) {
+          url.replace(
+            QR_SHA256_PLACEHOLDER,
+            &hex::encode(Sha256::digest(document)),
+          )
+        } else {
+          url.clone()
+        };
+        images.push((name, next_obj, qr_object(next_obj, &url, index)?));
+        next_obj += 1;
+      }
+      AppearanceContent::Text { text, font_size } => {
+        if !font_size.is_finite() || *font_size <= 0.0 {
+          return Err(PdfSignError::SigningError(format!(
+            "elemento {} da aparência com tamanho de fonte inválido",
+            index
+          )));
+        }
+        // Recorta no retângulo da camada; a primeira linha encosta no topo
+        content.extend_from_slice(
+          format!(
+            "q\n{} {} {} {} re W n\nBT\n0 g\n{} {} Tf\n{} TL\n{} {} Td\n",
+            x,
+            y,
+            w,
+            h,
+            FONT_RESOURCE,
+            font_size,
+            font_size * LINE_SPACING,
+            x,
+            y + h - font_size
+          )
+          .as_bytes(),
+        );
+        let text = if text.contains(TEXT_DATE_PLACEHOLDER) {
+          text.replace(
+            TEXT_DATE_PLACEHOLDER,
+            &format_signing_date(appearance, signing_time)?,
+          )
+        } else {
+          text.clone()
+        };
+        for (line_index, line) in text.lines().enumerate() {
+          if line_index > 0 {
+            content.extend_from_slice(b"T*\n");
+          }
+          content.extend_from_slice(&win_ansi_literal(line));
+          content.extend_from_slice(b" Tj\n");
+        }
+        content.extend_from_slice(b"ET\nQ\n");
+      }
+    }
+  }
+
+  let mut resources = String::new();
+  if let Some(font_obj) = font_obj {
+    resources.push_str(&format!("/Font << {} {} 0 R >> ", FONT_RESOURCE, font_obj));
+  }
+  if !images.is_empty() {
+    let entries: Vec<String> = images
+      .iter()
+      .map(|(name, obj, _)| format!("{} {} 0 R", name, obj))
+      .collect();
+    resources.push_str(&format!("/XObject << {} >> ", entries.join(" ")));
+  }
+
+  let mut form = format!(
+    "{} 0 obj\n<<\n/Type /XObject\n/Subtype /Form\n/BBox [0 0 {} {}]\n/Resources << {}>>\n/Length {}\n>>\nstream\n",
+    first_obj,
+    width,
+    height,
+    resources,
+    content.len()
+  )
+  .into_bytes();
+  form.extend_from_slice(&content);
+  form.extend_from_slice(b"\nendstream\nendobj\n");
+
+  let mut objects = vec![form];
+  if let Some(font_obj) = font_obj {
+    objects.push(
+      format!(
+        "{} 0 obj\n<<\n/Type /Font\n/Subtype /Type1\n/BaseFont /Helvetica\n/Encoding /WinAnsiEncoding\n>>\nendobj\n",
+        font_obj
+      )
+      .into_bytes(),
+    );
+  }
+  objects.extend(images.into_iter().map(|(_, _, object)| object));
+  Ok(objects)
+}
+
+/// Imagem JPEG como XObject /DCTDecode, sem recodificar os bytes
+fn image_object(obj: usize, data: &[u8], index: usize) -> Result<Vec<u8>> {
+  let (width, height, components) = jpeg_dimensions(data).ok_or_else(|| {
+    PdfSignError::SigningError(format!(
+      "elemento {} da aparência: imagem não é um JPEG",
+      index
+    ))
+  })?;
+  let color_space = match components {
+    1 => "/DeviceGray",
+    3 => "/DeviceRGB",
+    // JPEGs CMYK do Photoshop gravam os canais invertidos
+    4 => "/DeviceCMYK\n/Decode [1 0 1 0 1 0 1 0]",
+    _ => {
+      return Err(PdfSignError::SigningError(format!(
+        "elemento {} da aparência: JPEG com {} componentes de cor",
+        index, components
+      )))
+    }
+  };
+  Ok(image_xobject(
+    obj,
+    width as usize,
+    height as usize,
+    &format!(
+      "/ColorSpace {}\n/BitsPerComponent 8\n/Filter /DCTDecode",
+      color_space
+    ),
+    data,
+  ))
+}
+
+/// QR code como imagem de 1 bit por módulo, já com a margem clara
+fn qr_object(obj: usize, url: &str, index: usize) -> Result<Vec<u8>> {
+  let code = QrCode::new(url.as_bytes()).map_err(|e| {
+    PdfSignError::SigningError(format!("elemento {} da aparência: QR code: {}", index, e))
+  })?;
+  let modules = code.width();
+  let size = modules + 2 * QR_QUIET_ZONE;
+  let row_bytes = size.div_ceil(8);
+  // DeviceGray de 1 bit: 1 é claro, 0 é escuro; as linhas começam no topo
+  let mut bitmap = vec![0xFF; row_bytes * size];
+  for (i, color) in code.to_colors().into_iter().enumerate() {
+    if color == Color::Dark {
+      let (row, col) = (i / modules + QR_QUIET_ZONE, i % modules + QR_QUIET_ZONE);
+      bitmap[row * row_bytes + col / 8] &= !(0x80 >> (col % 8));
+    }
+  }
+  Ok(image_xobject(
+    obj,
+    size,
+    size,
+    "/ColorSpace /DeviceGray\n/BitsPerComponent 1",
+    &bitmap,
+  ))
+}
+
+/// XObject de imagem com os bytes do stream como vieram
+fn image_xobject(obj: usize, width: usize, height: usize, format: &str, data: &[u8]) -> Vec<u8> {
+  let mut object = format!(
+    "{} 0 obj\n<<\n/Type /XObject\n/Subtype /Image\n/Width {}\n/Height {}\n{}\n/Length {}\n>>\nstream\n",
+    obj,
+    width,
+    height,
+    format,
+    data.len()
+  )
+  .into_bytes();
+  object.extend_from_slice(data);
+  object.extend_from_slice(b"\nendstream\nendobj\n");
+  object
+}
+
+/// Largura, altura e número de componentes lidos do marcador SOF do JPEG
+fn jpeg_dimensions(data: &[u8]) -> Option<(u16, u16, u8)> {
+  if !data.starts_with(&[0xFF, 0xD8]) {
+    return None;
+  }
+  let mut pos = 2;
+  while pos + 1 < data.len() {
+    if data[pos] != 0xFF {
+      return None;
+    }
+    let marker = data[pos + 1];
+    // Preenchimento (FF FF) e marcadores sem segmento
+    if marker == 0xFF {
+      pos += 1;
+      continue;
+    }
+    if marker == 0x01 || (0xD0..=0xD9).contains(&marker) {
+      pos += 2;
+      continue;
+    }
+    let length = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+    // SOF0..SOF15, menos DHT (C4), JPG (C8) e DAC (CC)
+    if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+      let segment = data.get(pos + 4..pos + 10)?;
+      let height = u16::from_be_bytes([segment[1], segment[2]]);
+      let width = u16::from_be_bytes([segment[3], segment[4]]);
+      return (width > 0 && height > 0).then_some((width, height, segment[5]));
+    }
+    pos += 2 + length;
+  }
+  None
+}
+
+/// `signing_time` no `date_format` e no fuso da aparência; formatos inválidos viram
+/// erro em vez do pânico de `DateTime::format`
+fn format_signing_date(
+  appearance: &SignatureAppearance,
+  signing_time: DateTime<Utc>,
+) -> Result<String> {
+  if StrftimeItems::new(&appearance.date_format).any(|item| matches!(item, Item::Error)) {
+    return Err(PdfSignError::SigningError(format!(
+      "formato de data da aparência inválido: \"{}\"",
+      appearance.date_format
+    )));
+  }
+  Ok(
+    signing_time
+      .with_timezone(&appearance.date_offset)
+      .format(&appearance.date_format)
+      .to_string(),
+  )
+}
+
+/// String literal PDF em WinAnsiEncoding (Latin-1 nos acentos do português)
+fn win_ansi_literal(text: &str) -> Vec<u8> {
+  let mut literal = vec![b'('];
+  for c in text.chars() {
+    match c {
+      '\\' | '(' | ')' => literal.extend_from_slice(&[b'\\', c as u8]),
+      c if (c as u32) < 0x20 => literal.push(b' '),
+      c => literal.push(u8::try_from(c as u32).unwrap_or(b'?')),
+    }
+  }
+  literal.push(b')');
+  literal
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::signature_config::AppearanceElement;
+  use chrono::{FixedOffset, TimeZone};
+
+  /// JPEG mínimo: SOI, APP0 vazio e SOF0 de 40x20 em RGB
+  fn tiny_jpeg() -> Vec<u8> {
+    let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x02];
+    jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x14, 0x00, 0x28, 0x03]);
+    jpeg.extend_from_slice(&[0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01]);
+    jpeg.extend_from_slice(&[0xFF, 0xD9]);
+    jpeg
+  }
+
+  #[test]
+  fn test_jpeg_dimensions() {
+    assert_eq!(jpeg_dimensions(&tiny_jpeg()), Some((40, 20, 3)));
+    assert_eq!(jpeg_dimensions(b"\x89PNG\r\n\x1a\n"), None);
+    assert_eq!(jpeg_dimensions(&[0xFF, 0xD8, 0xFF, 0xE0]), None);
+  }
+
+  #[test]
+  fn test_win_ansi_literal_escapes_and_keeps_latin1() {
+    assert_eq!(
+      win_ansi_literal("João (RH)"),
+      b"(Jo\xe3o \\(RH\\))".to_vec()
+    );
+    assert_eq!(win_ansi_literal("日"), b"(?)".to_vec());
+  }
+
+  #[test]
+  fn test_format_signing_date() {
+    let signing_time = Utc.with_ymd_and_hms(2024, 3, 15, 2, 5, 9).unwrap();
+    let mut appearance = SignatureAppearance::default();
+    // Antes das 3h UTC, a data em Brasília ainda é a do dia anterior
+    assert_eq!(
+      format_signing_date(&appearance, signing_time).unwrap(),
+      "14/03/2024 23:05:09"
+    );
+    appearance.date_offset = FixedOffset::east_opt(0).unwrap();
+    appearance.date_format = "%Y-%m-%d".to_string();
+    assert_eq!(
+      format_signing_date(&appearance, signing_time).unwrap(),
+      "2024-03-15"
+    );
+    appearance.date_format = "%d/%m/%".to_string();
+    assert!(matches!(
+      format_signing_date(&appearance, signing_time),
+      Err(PdfSignError::SigningError(_))
+    ));
+  }
+
+  #[test]
+  fn test_layers_are_drawn_in_order_with_shared_resources() {
+    let appearance = SignatureAppearance {
+      elements: vec![
+        AppearanceElement {
+          content: AppearanceContent::Image(tiny_jpeg()),
+          rect: [0.0, 0.0, 50.0, 50.0],
+        },
+        AppearanceElement {
+          content: AppearanceContent::Text {
+            text: "Assinado por\nMaria".to_string(),
+            font_size: 10.0,
+          },
+          rect: [55.0, 0.0, 145.0, 50.0],
+        },
+      ],
+      ..Default::default()
+    };
+    let objects = build_appearance_objects(&appearance, 200.0, 50.0, 30, b"", Utc::now()).unwrap();
+    assert_eq!(objects.len(), 3);
+
+    let form = String::from_utf8_lossy(&objects[0]);
+    assert!(form.starts_with("30 0 obj\n"));
+    assert!(form.contains("/BBox [0 0 200 50]"));
+    assert!(form.contains("/Font << /F1 31 0 R >>"));
+    assert!(form.contains("/XObject << /Im1 32 0 R >>"));
+    // A imagem vem antes do texto: o texto fica por cima
+    let image_pos = form.find("/Im1 Do").unwrap();
+    let text_pos = form.find("(Assinado por) Tj\nT*\n(Maria) Tj").unwrap();
+    assert!(image_pos < text_pos);
+
+    assert!(String::from_utf8_lossy(&objects[1]).contains("/BaseFont /Helvetica"));
+    let image = String::from_utf8_lossy(&objects[2]);
+    assert!(image.starts_with("32 0 obj\n"));
+    assert!(image.contains("/Width 40\n/Height 20\n/ColorSpace /DeviceRGB"));
+  }
+
+  /// Lê de volta o texto do QR code gravado no XObject de 1 bit
+  fn decode_qr_object(object: &[u8]) -> String {
+    let text = String::from_utf8_lossy(object);
+    let size: usize = text[text.find("/Width ").unwrap() + 7..]
+      .split_whitespace()
+      .next()
+      .unwrap()
+      .parse()
+      .unwrap();
+    let start = object.windows(7).position(|w| w == b"stream\n").unwrap() + 7;
+    let row_bytes = size.div_ceil(8);
+    let bitmap = &object[start..start + row_bytes * size];
+
+    // Cada módulo vira 4x4 pixels para o detector
+    const SCALE: usize = 4;
+    let mut image =
+      rqrr::PreparedImage::prepare_from_greyscale(size * SCALE, size * SCALE, |x, y| {
+        let (row, col) = (y / SCALE, x / SCALE);
+        if bitmap[row * row_bytes + col / 8] & (0x80 >> (col % 8)) == 0 {
+          0
+        } else {
+          255
+        }
+      });
+    let grids = image.detect_grids();
+    assert_eq!(grids.len(), 1);
+    grids[0].decode().unwrap().1
+  }
+
+  #[test]
+  fn test_qr_code_layer_encodes_the_url() {
+    let document = b"%PDF-1.7 documento";
+    let appearance = SignatureAppearance {
+      elements: vec![AppearanceElement {
+        content: AppearanceContent::QrCode(
+          "https://validar.exemplo.gov.br/?hash={sha256}".to_string(),
+        ),
+        rect: [120.0, 0.0, 80.0, 60.0],
+      }],
+      ..Default::default()
+    };
+    let objects =
+      build_appearance_objects(&appearance, 200.0, 60.0, 10, document, Utc::now()).unwrap();
+    assert_eq!(objects.len(), 2);
+
+    // Quadrado de 60 pt centralizado na camada de 80x60
+    let form = String::from_utf8_lossy(&objects[0]);
+    assert!(form.contains("/XObject << /Im1 11 0 R >>"));
+    assert!(form.contains("60 0 0 60 130 0 cm\n/Im1 Do"));
+
+    let image = String::from_utf8_lossy(&objects[1]);
+    assert!(image.contains("/Subtype /Image"));
+    assert!(image.contains("/BitsPerComponent 1"));
+    assert_eq!(
+      decode_qr_object(&objects[1]),
+      format!(
+        "https://validar.exemplo.gov.br/?hash={}",
+        hex::encode(Sha256::digest(document))
+      )
+    );
+  }
+
+  #[test]
+  fn test_invalid_layers_are_rejected() {
+    let element = |content, rect| SignatureAppearance {
+      elements: vec![AppearanceElement { content, rect }],
+      ..Default::default()
+    };
+    let text = || AppearanceContent::Text {
+      text: "x".to_string(),
+      font_size: 10.0,
+    };
+    for appearance in [
+      element(text(), [0.0, 0.0, 0.0, 10.0]),
+      element(text(), [0.0, 0.0, f64::NAN, 10.0]),
+      element(
+        AppearanceContent::Text {
+          text: "x".to_string(),
+          font_size: 0.0,
+        },
+        [0.0, 0.0, 10.0, 10.0],
+      ),
+      element(
+        AppearanceContent::Image(b"GIF89a".to_vec()),
+        [0.0, 0.0, 10.0, 10.0],
+      ),
+    ] {
+      assert!(matches!(
+        build_appearance_objects(&appearance, 10.0, 10.0, 1, b"", Utc::now()),
+        Err(PdfSignError::SigningError(_))
+      ));
+    }
+  }
+}
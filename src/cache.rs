@@ -0,0 +1,114 @@
+/// Caches do processo para assinaturas em lote
+///
+/// Âncoras de confiança já interpretadas e CRLs baixadas valem para todas as
+/// assinaturas do processo, inclusive as feitas em paralelo pelo binding assíncrono.
+/// As âncoras são indexadas pelo SHA-256 do conteúdo e as CRLs pela URL do ponto
+/// de distribuição, até o nextUpdate de cada uma
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use openssl::asn1::Asn1Time;
+use openssl::x509::{X509Crl, X509};
+use sha2::{Digest, Sha256};
+
+use crate::error::Result;
+
+struct CachedCrl {
+  der: Vec<u8>,
+  expires_at: Instant,
+}
+
+static TRUST_ANCHORS: LazyLock<Mutex<HashMap<[u8; 32], Vec<X509>>>> =
+  LazyLock::new(Default::default);
+static CRLS: LazyLock<Mutex<HashMap<String, CachedCrl>>> = LazyLock::new(Default::default);
+
+// Um pânico com o lock na mão não invalida o cache: no pior caso falta uma entrada
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+  mutex
+    .lock()
+    .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Âncoras de `data` já interpretadas; na primeira vez usa `parse` e guarda o resultado
+pub fn trust_anchors(
+  data: &[u8],
+  parse: impl FnOnce(&[u8]) -> Result<Vec<X509>>,
+) -> Result<Vec<X509>> {
+  let key: [u8; 32] = Sha256::digest(data).into();
+  if let Some(anchors) = lock(&TRUST_ANCHORS).get(&key) {
+    return Ok(anchors.clone());
+  }
+  let anchors = parse(data)?;
+  lock(&TRUST_ANCHORS).insert(key, anchors.clone());
+  Ok(anchors)
+}
+
+/// CRL baixada de `url` que ainda não passou do nextUpdate
+pub fn crl(url: &str) -> Option<Vec<u8>> {
+  let mut crls = lock(&CRLS);
+  match crls.get(url) {
+    Some(cached) if cached.expires_at > Instant::now() => Some(cached.der.clone()),
+    Some(_) => {
+      crls.remove(url);
+      None
+    }
+    None => None,
+  }
+}
+
+/// Guarda a CRL de `url` até o nextUpdate; CRLs sem nextUpdate ou já vencidas não entram
+pub fn store_crl(url: &str, der: &[u8]) {
+  let Some(remaining) = crl_remaining_validity(der) else {
+    return;
+  };
+  lock(&CRLS).insert(
+    url.to_string(),
+    CachedCrl {
+      der: der.to_vec(),
+      expires_at: Instant::now() + remaining,
+    },
+  );
+}
+
+/// Tempo até o nextUpdate da CRL, se ele existir e ainda não tiver passado
+fn crl_remaining_validity(der: &[u8]) -> Option<Duration> {
+  let crl = X509Crl::from_der(der).ok()?;
+  let now = Asn1Time::days_from_now(0).ok()?;
+  let diff = now.diff(crl.next_update()?).ok()?;
+  let seconds = i64::from(diff.days) * 86_400 + i64::from(diff.secs);
+  u64::try_from(seconds)
+    .ok()
+    .filter(|&seconds| seconds > 0)
+    .map(Duration::from_secs)
+}
+
+/// Esvazia os caches de âncoras e de CRLs (ex: após trocar a cadeia confiável)
+pub fn clear_caches() {
+  lock(&TRUST_ANCHORS).clear();
+  lock(&CRLS).clear();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::issue_identity;
+  use crate::trust_store::parse_anchors;
+
+  #[test]
+  fn test_trust_anchors_are_parsed_once() {
+    let (_, root) = issue_identity("AC Raiz em Cache", None, true);
+    let pem = root.to_pem().unwrap();
+    let parses = std::cell::Cell::new(0);
+    let parse = |data: &[u8]| {
+      parses.set(parses.get() + 1);
+      parse_anchors(data)
+    };
+
+    let first = trust_anchors(&pem, parse).unwrap();
+    let second = trust_anchors(&pem, parse).unwrap();
+
+    assert_eq!(parses.get(), 1);
+    assert_eq!(first[0].to_der().unwrap(), second[0].to_der().unwrap());
+  }
+}
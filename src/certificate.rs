@@ -1,35 +1,103 @@
-use der_parser::asn1_rs::FromDer;
+use der_parser::asn1_rs::{Any, FromDer};
+use x509_parser::extensions::GeneralName;
+use x509_parser::pem::Pem;
 use x509_parser::prelude::X509Certificate;
 
 use crate::error::{PdfSignError, Result};
+use crate::signature_config::DigestAlgorithm;
+
+/// otherName ICP-Brasil com os dados da pessoa física titular (nascimento, CPF, NIS, RG)
+const OID_ICP_PF_DATA: &str = "2.16.76.1.3.1";
+/// otherName ICP-Brasil com o CNPJ da pessoa jurídica titular
+const OID_ICP_CNPJ: &str = "2.16.76.1.3.3";
+/// otherName ICP-Brasil com os dados do responsável pela pessoa jurídica
+const OID_ICP_PJ_RESPONSIBLE_DATA: &str = "2.16.76.1.3.4";
+
+/// Identificadores ICP-Brasil do titular, lidos do subjectAltName
+///
+/// Em certificados de pessoa jurídica `cpf` é o do responsável pelo certificado
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IcpBrasilId {
+  pub cpf: Option<String>,
+  pub cnpj: Option<String>,
+}
+
+impl IcpBrasilId {
+  /// Tipo do certificado: com CNPJ é um e-CNPJ (selo), só com CPF um e-CPF
+  pub fn kind(&self) -> Option<IcpBrasilKind> {
+    if self.cnpj.is_some() {
+      Some(IcpBrasilKind::Seal)
+    } else if self.cpf.is_some() {
+      Some(IcpBrasilKind::Signature)
+    } else {
+      None
+    }
+  }
+}
+
+/// Classe do certificado ICP-Brasil do signatário
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IcpBrasilKind {
+  /// e-CPF: assinatura de pessoa física
+  Signature,
+  /// e-CNPJ: selo da pessoa jurídica
+  Seal,
+}
 
 /// Estrutura para armazenar certificado X.509
+///
+/// Guarda só o DER; os campos são lidos de um parse feito sob demanda sobre ele
 #[derive(Clone)]
 pub struct Certificate {
   der_bytes: Vec<u8>,
-  parsed: X509Certificate<'static>,
 }
 
 impl Certificate {
   pub fn from_der(der: Vec<u8>) -> Result<Self> {
-    // Converte para 'static lifetime
-    let owned_der = der.clone();
-    let parsed_static = X509Certificate::from_der(&owned_der)
-      .map_err(|e| PdfSignError::DecodingError(format!("Erro ao parsear certificado: {:?}", e)))?
-      .1;
+    X509Certificate::from_der(&der)
+      .map_err(|e| PdfSignError::DecodingError(format!("Erro ao parsear certificado: {:?}", e)))?;
+    Ok(Self { der_bytes: der })
+  }
+
+  /// Parse do DER guardado, que `from_der` já aceitou e que nunca é alterado
+  fn parsed(&self) -> X509Certificate<'_> {
+    match X509Certificate::from_der(&self.der_bytes) {
+      Ok((_, parsed)) => parsed,
+      Err(_) => unreachable!("DER validado em Certificate::from_der"),
+    }
+  }
 
-    Ok(Self {
-      der_bytes: der,
-      parsed: unsafe {
-        std::mem::transmute::<X509Certificate<'_>, X509Certificate<'_>>(parsed_static)
-      },
-    })
+  /// Lê um certificado em DER ou em PEM, onde vale o primeiro bloco CERTIFICATE
+  ///
+  /// É a forma aceita nas entradas de certificado vindas do JS (`CertificateInput`)
+  #[cfg_attr(not(feature = "native"), allow(dead_code))]
+  pub fn from_pem_or_der(data: &[u8]) -> Result<Self> {
+    if !data.windows(11).any(|w| w == b"-----BEGIN ") {
+      return Self::from_der(data.to_vec());
+    }
+    for pem in Pem::iter_from_buffer(data) {
+      let pem =
+        pem.map_err(|e| PdfSignError::DecodingError(format!("Erro ao ler PEM: {:?}", e)))?;
+      if pem.label == "CERTIFICATE" {
+        return Self::from_der(pem.contents);
+      }
+    }
+    Err(PdfSignError::DecodingError(
+      "PEM sem bloco CERTIFICATE".to_string(),
+    ))
   }
 
   pub fn der(&self) -> &[u8] {
     &self.der_bytes
   }
 
+  /// Impressão digital (hex minúsculo) do DER do certificado, como a exibida pelos
+  /// gerenciadores de certificados
+  pub fn thumbprint(&self, algorithm: DigestAlgorithm) -> String {
+    hex::encode(algorithm.digest(&self.der_bytes))
+  }
+
+  #[cfg(feature = "native")]
   pub fn subject_cn(&self) -> Option<String> {
     // Usa OpenSSL para extrair o CN de forma mais confiável
     use openssl::x509::X509;
@@ -38,8 +106,10 @@ impl Certificate {
       let subject = cert.subject_name();
       for entry in subject.entries() {
         if entry.object().nid().as_raw() == openssl::nid::Nid::COMMONNAME.as_raw() {
-          if let Ok(data) = entry.data().as_utf8() {
-            return Some(data.to_string());
+          // `to_string` decodifica qualquer tipo de string ASN.1; o `as_utf8`, obsoleto,
+          // cortava o valor no primeiro NUL
+          if let Ok(data) = entry.data().to_string() {
+            return Some(data);
           }
         }
       }
@@ -48,6 +118,7 @@ impl Certificate {
     None
   }
 
+  #[cfg(feature = "native")]
   pub fn subject_org(&self) -> Option<String> {
     // Usa OpenSSL para extrair a organização
     use openssl::x509::X509;
@@ -56,8 +127,8 @@ impl Certificate {
       let subject = cert.subject_name();
       for entry in subject.entries() {
         if entry.object().nid().as_raw() == openssl::nid::Nid::ORGANIZATIONNAME.as_raw() {
-          if let Ok(data) = entry.data().as_utf8() {
-            return Some(data.to_string());
+          if let Ok(data) = entry.data().to_string() {
+            return Some(data);
           }
         }
       }
@@ -66,15 +137,358 @@ impl Certificate {
     None
   }
 
+  #[cfg(not(feature = "native"))]
+  pub fn subject_cn(&self) -> Option<String> {
+    let parsed = self.parsed();
+    let cn = parsed.subject().iter_common_name().next()?;
+    cn.as_str().ok().map(str::to_string)
+  }
+
+  #[cfg(not(feature = "native"))]
+  pub fn subject_org(&self) -> Option<String> {
+    let parsed = self.parsed();
+    let org = parsed.subject().iter_organization().next()?;
+    org.as_str().ok().map(str::to_string)
+  }
+
+  /// CN do emissor, último recurso para o nome do signatário
+  pub fn issuer_cn(&self) -> Option<String> {
+    let parsed = self.parsed();
+    let cn = parsed.issuer().iter_common_name().next()?;
+    cn.as_str().ok().map(str::to_string)
+  }
+
   pub fn not_before(&self) -> String {
-    self.parsed.validity().not_before.to_string()
+    self.parsed().validity().not_before.to_string()
   }
 
   pub fn not_after(&self) -> String {
-    self.parsed.validity().not_after.to_string()
+    self.parsed().validity().not_after.to_string()
+  }
+
+  /// Indica se o certificado já tinha vencido (notAfter) no instante informado
+  pub fn expired_at(&self, time: chrono::DateTime<chrono::Utc>) -> bool {
+    self.parsed().validity().not_after.timestamp() < time.timestamp()
   }
 
   pub fn serial_number(&self) -> String {
-    hex::encode(self.parsed.serial.to_bytes_be())
+    hex::encode(self.parsed().serial.to_bytes_be())
+  }
+
+  /// DN completo do titular (ex: "C=BR, O=ICP-Brasil, CN=FULANO:12345678909")
+  pub fn subject_dn(&self) -> String {
+    self.parsed().subject().to_string()
+  }
+
+  /// DN completo da AC emissora
+  pub fn issuer_dn(&self) -> String {
+    self.parsed().issuer().to_string()
+  }
+
+  /// Indica se o certificado é autoassinado: emissor igual ao titular e assinatura
+  /// conferida contra a própria chave pública
+  ///
+  /// None quando emissor e titular coincidem, mas a assinatura não pode ser conferida
+  /// (algoritmo não suportado, ou EC e Ed25519 fora do build nativo)
+  pub fn is_self_signed(&self) -> Option<bool> {
+    let parsed = self.parsed();
+    if parsed.subject().as_raw() != parsed.issuer().as_raw() {
+      return Some(false);
+    }
+    crate::cms_signer::verify_certificate_signature(&self.der_bytes, parsed.public_key().raw).ok()
+  }
+
+  /// Usos da chave (keyUsage) com os nomes da RFC 5280; vazio sem a extensão
+  pub fn key_usage(&self) -> Vec<String> {
+    let parsed = self.parsed();
+    let Ok(Some(extension)) = parsed.key_usage() else {
+      return Vec::new();
+    };
+    let usage = extension.value;
+    [
+      (usage.digital_signature(), "digitalSignature"),
+      (usage.non_repudiation(), "nonRepudiation"),
+      (usage.key_encipherment(), "keyEncipherment"),
+      (usage.data_encipherment(), "dataEncipherment"),
+      (usage.key_agreement(), "keyAgreement"),
+      (usage.key_cert_sign(), "keyCertSign"),
+      (usage.crl_sign(), "cRLSign"),
+      (usage.encipher_only(), "encipherOnly"),
+      (usage.decipher_only(), "decipherOnly"),
+    ]
+    .into_iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, name)| name.to_string())
+    .collect()
+  }
+
+  /// Se o keyUsage permite assinar documentos (digitalSignature ou nonRepudiation);
+  /// sem a extensão, a chave não tem restrição de uso (RFC 5280, 4.2.1.3)
+  pub fn allows_signing(&self) -> bool {
+    match self.parsed().key_usage() {
+      Ok(Some(extension)) => {
+        extension.value.digital_signature() || extension.value.non_repudiation()
+      }
+      Ok(None) => true,
+      Err(_) => false,
+    }
+  }
+
+  /// Finalidades (extendedKeyUsage); OIDs sem nome conhecido vêm em notação pontuada
+  pub fn extended_key_usage(&self) -> Vec<String> {
+    let parsed = self.parsed();
+    let Ok(Some(extension)) = parsed.extended_key_usage() else {
+      return Vec::new();
+    };
+    let usage = extension.value;
+    let mut names: Vec<String> = [
+      (usage.any, "anyExtendedKeyUsage"),
+      (usage.server_auth, "serverAuth"),
+      (usage.client_auth, "clientAuth"),
+      (usage.code_signing, "codeSigning"),
+      (usage.email_protection, "emailProtection"),
+      (usage.time_stamping, "timeStamping"),
+      (usage.ocsp_signing, "OCSPSigning"),
+    ]
+    .into_iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, name)| name.to_string())
+    .collect();
+    names.extend(usage.other.iter().map(|oid| oid.to_id_string()));
+    names
+  }
+
+  /// CPF e CNPJ dos otherName ICP-Brasil (2.16.76.1.3.x) do subjectAltName
+  pub fn icp_brasil_id(&self) -> IcpBrasilId {
+    let parsed = self.parsed();
+    let mut id = IcpBrasilId::default();
+    let Ok(Some(san)) = parsed.subject_alternative_name() else {
+      return id;
+    };
+
+    for name in &san.value.general_names {
+      let GeneralName::OtherName(oid, value) = name else {
+        continue;
+      };
+      let Some(text) = other_name_text(value) else {
+        continue;
+      };
+      match oid.to_id_string().as_str() {
+        // PF: nascimento (8) + CPF (11) + NIS + RG; o CPF do titular tem precedência
+        OID_ICP_PF_DATA => id.cpf = digits(&text, 8, 11).or(id.cpf),
+        OID_ICP_PJ_RESPONSIBLE_DATA if id.cpf.is_none() => id.cpf = digits(&text, 8, 11),
+        OID_ICP_CNPJ => id.cnpj = digits(&text, 0, 14),
+        _ => {}
+      }
+    }
+
+    id
+  }
+}
+
+/// Texto do valor de um otherName: `[0] EXPLICIT` com OCTET STRING ou string ASN.1
+fn other_name_text(value: &[u8]) -> Option<String> {
+  let (_, explicit) = Any::from_der(value).ok()?;
+  let (_, inner) = Any::from_der(explicit.data).ok()?;
+  Some(inner.data.iter().map(|&c| c as char).collect())
+}
+
+/// Trecho numérico do campo; a ICP-Brasil preenche com zeros o dado ausente
+fn digits(text: &str, start: usize, len: usize) -> Option<String> {
+  let value = text.get(start..start + len)?;
+  (value.bytes().all(|c| c.is_ascii_digit()) && value.bytes().any(|c| c != b'0'))
+    .then(|| value.to_string())
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+  use super::*;
+  use crate::test_support::{
+    ec_identity, icp_brasil_identity, identity_with_subject, issue_identity, self_signed_identity,
+  };
+
+  fn certificate(cert: &openssl::x509::X509) -> Certificate {
+    Certificate::from_der(cert.to_der().unwrap()).unwrap()
+  }
+
+  #[test]
+  fn test_icp_brasil_person_certificate_details() {
+    let (ca_key, ca_cert) = issue_identity("AC Teste", None, true);
+    let (_, cert) = icp_brasil_identity(
+      "FULANO DE TAL:12345678909",
+      // nascimento + CPF + NIS + RG + órgão expedidor
+      &[(
+        OID_ICP_PF_DATA,
+        "0101198012345678909000000000000000000001234567SSPSP",
+      )],
+      (&ca_key, &ca_cert),
+    );
+    let cert = certificate(&cert);
+
+    assert_eq!(
+      cert.subject_dn(),
+      "C=BR, O=ICP-Brasil, CN=FULANO DE TAL:12345678909"
+    );
+    assert_eq!(cert.issuer_dn(), "CN=AC Teste");
+    assert_eq!(
+      cert.key_usage(),
+      ["digitalSignature", "nonRepudiation", "keyEncipherment"]
+    );
+    assert_eq!(cert.extended_key_usage(), ["clientAuth", "emailProtection"]);
+    assert_eq!(
+      cert.icp_brasil_id(),
+      IcpBrasilId {
+        cpf: Some("12345678909".to_string()),
+        cnpj: None,
+      }
+    );
+    assert_eq!(cert.icp_brasil_id().kind(), Some(IcpBrasilKind::Signature));
+  }
+
+  #[test]
+  fn test_icp_brasil_company_certificate_ids() {
+    let (ca_key, ca_cert) = issue_identity("AC Teste", None, true);
+    let (_, cert) = icp_brasil_identity(
+      "EMPRESA LTDA:11222333000181",
+      &[
+        (
+          OID_ICP_PJ_RESPONSIBLE_DATA,
+          "150719759876543210000000000000000000000000000000",
+        ),
+        (OID_ICP_CNPJ, "11222333000181"),
+      ],
+      (&ca_key, &ca_cert),
+    );
+
+    assert_eq!(
+      certificate(&cert).icp_brasil_id(),
+      IcpBrasilId {
+        cpf: Some("98765432100".to_string()),
+        cnpj: Some("11222333000181".to_string()),
+      }
+    );
+    assert_eq!(
+      certificate(&cert).icp_brasil_id().kind(),
+      Some(IcpBrasilKind::Seal)
+    );
+
+    // Sem otherName ICP-Brasil e sem extensões de uso
+    let plain = certificate(&ca_cert);
+    assert_eq!(plain.icp_brasil_id(), IcpBrasilId::default());
+    assert_eq!(plain.icp_brasil_id().kind(), None);
+    assert_eq!(plain.key_usage(), ["keyCertSign", "cRLSign"]);
+    assert!(!plain.allows_signing());
+    assert!(plain.extended_key_usage().is_empty());
+  }
+
+  #[test]
+  fn test_icp_brasil_digits_ignore_zero_fill() {
+    assert_eq!(digits("0000000000000000000", 8, 11), None);
+    assert_eq!(digits("01011980123", 8, 11), None);
+    assert_eq!(
+      digits("0101198012345678909", 8, 11).as_deref(),
+      Some("12345678909")
+    );
+  }
+
+  #[test]
+  fn test_subject_cn_and_org_keep_accents() {
+    let (_, cert) = identity_with_subject(
+      &[("O", "Associação São João"), ("CN", "José Conceição")],
+      None,
+    );
+    let cert = certificate(&cert);
+    assert_eq!(cert.subject_cn().as_deref(), Some("José Conceição"));
+    assert_eq!(cert.subject_org().as_deref(), Some("Associação São João"));
+  }
+
+  #[test]
+  fn test_pem_and_der_inputs_give_the_same_certificate() {
+    let (key, cert) = issue_identity("Certificado de Entrada", None, false);
+    let from_der = Certificate::from_pem_or_der(&cert.to_der().unwrap()).unwrap();
+    let from_pem = Certificate::from_pem_or_der(&cert.to_pem().unwrap()).unwrap();
+    assert_eq!(from_der.der(), from_pem.der());
+    assert_eq!(from_der.subject_cn(), from_pem.subject_cn());
+
+    // Num PEM com a chave antes, vale o primeiro bloco CERTIFICATE
+    let bundle = [
+      key.private_key_to_pem_pkcs8().unwrap(),
+      cert.to_pem().unwrap(),
+    ]
+    .concat();
+    assert_eq!(
+      Certificate::from_pem_or_der(&bundle).unwrap().der(),
+      from_der.der()
+    );
+
+    let only_key = key.private_key_to_pem_pkcs8().unwrap();
+    assert!(matches!(
+      Certificate::from_pem_or_der(&only_key),
+      Err(PdfSignError::DecodingError(_))
+    ));
+    assert!(Certificate::from_pem_or_der(b"nada").is_err());
+  }
+
+  #[test]
+  fn test_clone_outlives_the_original() {
+    let (_, cert) = issue_identity("Titular", None, false);
+    let der = cert.to_der().unwrap();
+    let original = Certificate::from_der(der.clone()).unwrap();
+    let copy = original.clone();
+    drop(original);
+    assert_eq!(copy.der(), der);
+    assert_eq!(copy.subject_dn(), "CN=Titular");
+
+    // DER truncado é recusado já na criação, não no primeiro acesso
+    assert!(matches!(
+      Certificate::from_der(der[..der.len() - 1].to_vec()),
+      Err(PdfSignError::DecodingError(_))
+    ));
+  }
+
+  #[test]
+  fn test_is_self_signed() {
+    let (_, cert) = self_signed_identity("Autoassinado");
+    assert_eq!(certificate(&cert).is_self_signed(), Some(true));
+    let (_, cert) = ec_identity("Autoassinado EC");
+    assert_eq!(certificate(&cert).is_self_signed(), Some(true));
+
+    let (ca_key, ca_cert) = issue_identity("AC Teste", None, true);
+    assert_eq!(certificate(&ca_cert).is_self_signed(), Some(true));
+    let (_, cert) = issue_identity("Titular", Some((&ca_key, &ca_cert)), false);
+    assert_eq!(certificate(&cert).is_self_signed(), Some(false));
+
+    // Mesmo DN de emissor e titular, mas assinado por outra chave
+    let (_, cert) = issue_identity("AC Teste", Some((&ca_key, &ca_cert)), false);
+    assert_eq!(
+      certificate(&cert).subject_dn(),
+      certificate(&cert).issuer_dn()
+    );
+    assert_eq!(certificate(&cert).is_self_signed(), Some(false));
+  }
+
+  #[test]
+  fn test_is_self_signed_is_unknown_for_unsupported_algorithms() {
+    use openssl::{dsa::Dsa, hash::MessageDigest, pkey::PKey, x509::X509Builder, x509::X509Name};
+
+    // DSA: emissor igual ao titular, mas a assinatura não pode ser conferida
+    let key = PKey::from_dsa(Dsa::generate(2048).unwrap()).unwrap();
+    let mut name = X509Name::builder().unwrap();
+    name.append_entry_by_text("CN", "Autoassinado DSA").unwrap();
+    let name = name.build();
+    let mut builder = X509Builder::new().unwrap();
+    builder.set_version(2).unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder.set_issuer_name(&name).unwrap();
+    builder.set_pubkey(&key).unwrap();
+    builder
+      .set_not_before(&openssl::asn1::Asn1Time::days_from_now(0).unwrap())
+      .unwrap();
+    builder
+      .set_not_after(&openssl::asn1::Asn1Time::days_from_now(1).unwrap())
+      .unwrap();
+    builder.sign(&key, MessageDigest::sha256()).unwrap();
+
+    assert_eq!(certificate(&builder.build()).is_self_signed(), None);
   }
 }
@@ -0,0 +1,382 @@
+/// Verificações comuns aos backends de construção do CMS
+///
+/// O CMS é montado por `openssl_cms` (build nativo) ou `rust_cms` (Rust puro,
+/// também usado no wasm), conforme `SignatureConfig::cms_backend`
+use cms::cert::CertificateChoices;
+use cms::content_info::ContentInfo;
+use cms::signed_data::{SignedData, SignerIdentifier};
+use const_oid::db::rfc5912::{
+  ECDSA_WITH_SHA_256, ECDSA_WITH_SHA_384, ECDSA_WITH_SHA_512, ID_RSASSA_PSS, RSA_ENCRYPTION,
+  SHA_1_WITH_RSA_ENCRYPTION, SHA_256_WITH_RSA_ENCRYPTION, SHA_384_WITH_RSA_ENCRYPTION,
+  SHA_512_WITH_RSA_ENCRYPTION,
+};
+use const_oid::db::rfc8410::ID_ED_25519;
+use const_oid::ObjectIdentifier;
+use der::asn1::OctetStringRef;
+use der::{Decode, Encode};
+use rsa::pkcs1::RsaPssParams;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::RsaPublicKey;
+
+use crate::error::{PdfSignError, Result};
+use crate::rust_cms;
+use crate::signature_config::{DigestAlgorithm, SignatureScheme};
+
+/// Tipo da chave privada carregada do PFX ou do token PKCS#11
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+  Rsa,
+  Ed25519,
+  Ec,
+  // Só o caminho OpenSSL e o token carregam chaves que não são RSA
+  #[cfg_attr(not(any(feature = "native", feature = "pkcs11")), allow(dead_code))]
+  Other,
+}
+
+/// Conteúdo a assinar: os próprios dados ou o digest já calculado deles
+///
+/// O digest pré-calculado permite assinar arquivos lidos em blocos, sem reuni-los
+/// na memória; nesse caso o CMS só pode ser detached
+#[derive(Debug, Clone, Copy)]
+pub enum CmsContent<'a> {
+  Data(&'a [u8]),
+  Digest(&'a [u8]),
+}
+
+impl CmsContent<'_> {
+  /// Digest dos dados no algoritmo informado (valor do atributo messageDigest)
+  pub fn message_digest(&self, digest: DigestAlgorithm) -> Vec<u8> {
+    match self {
+      CmsContent::Data(data) => digest.digest(data),
+      CmsContent::Digest(hash) => hash.to_vec(),
+    }
+  }
+}
+
+/// Confere se a chave e o digest permitem o esquema de assinatura pedido
+pub fn check_signature_scheme(
+  scheme: SignatureScheme,
+  key_type: KeyType,
+  digest: DigestAlgorithm,
+) -> Result<()> {
+  let compatible = match scheme {
+    SignatureScheme::Pkcs1v15 | SignatureScheme::Pss => key_type == KeyType::Rsa,
+    SignatureScheme::Ed25519 => key_type == KeyType::Ed25519,
+    SignatureScheme::Ecdsa => key_type == KeyType::Ec,
+  };
+  if !compatible {
+    return Err(PdfSignError::SigningError(format!(
+      "esquema de assinatura {:?} não é suportado pela chave {:?} do certificado",
+      scheme, key_type
+    )));
+  }
+
+  // RFC 8419: com atributos assinados, Ed25519 usa SHA-512 no messageDigest
+  if scheme == SignatureScheme::Ed25519 && digest != DigestAlgorithm::Sha512 {
+    return Err(PdfSignError::SigningError(format!(
+      "Ed25519 exige digest_algorithm Sha512 (recebido {:?})",
+      digest
+    )));
+  }
+
+  Ok(())
+}
+
+/// Garante que o CMS usa o mesmo algoritmo de digest do ByteRange
+///
+/// Confere o digestAlgorithm do SignerInfo e o atributo messageDigest contra o
+/// hash dos dados calculado localmente; qualquer divergência invalida a assinatura no PDF
+#[allow(dead_code)]
+pub fn check_digest_consistency(
+  cms_der: &[u8],
+  data: &[u8],
+  digest: DigestAlgorithm,
+) -> Result<()> {
+  check_message_digest(cms_der, &digest.digest(data), digest)
+}
+
+/// Como `check_digest_consistency`, com o hash dos dados já calculado
+pub fn check_message_digest(
+  cms_der: &[u8],
+  expected: &[u8],
+  digest: DigestAlgorithm,
+) -> Result<()> {
+  let content_info = ContentInfo::from_der(cms_der)
+    .map_err(|e| PdfSignError::DecodingError(format!("Erro ao decodificar CMS: {}", e)))?;
+  let signed_data = content_info
+    .content
+    .decode_as::<SignedData>()
+    .map_err(|e| PdfSignError::DecodingError(format!("Erro ao decodificar SignedData: {}", e)))?;
+
+  let signer_info = signed_data
+    .signer_infos
+    .0
+    .get(0)
+    .ok_or_else(|| PdfSignError::SigningError("CMS sem SignerInfo".to_string()))?;
+
+  if signer_info.digest_alg.oid != digest.oid() {
+    return Err(PdfSignError::SigningError(format!(
+      "digestAlgorithm do CMS ({}) diverge do digest do ByteRange ({})",
+      signer_info.digest_alg.oid,
+      digest.oid()
+    )));
+  }
+
+  let message_digest = signer_info
+    .signed_attrs
+    .as_ref()
+    .and_then(|attrs| {
+      attrs
+        .iter()
+        .find(|attr| attr.oid == const_oid::db::rfc5911::ID_MESSAGE_DIGEST)
+    })
+    .and_then(|attr| attr.values.get(0))
+    .and_then(|value| value.decode_as::<OctetStringRef<'_>>().ok())
+    .ok_or_else(|| PdfSignError::SigningError("CMS sem atributo messageDigest".to_string()))?;
+
+  if message_digest.as_bytes() != expected {
+    return Err(PdfSignError::SigningError(
+      "messageDigest do CMS não corresponde ao hash do ByteRange".to_string(),
+    ));
+  }
+
+  Ok(())
+}
+
+/// Confere o CMS gravado em /Contents como um validador: messageDigest contra o
+/// digest do ByteRange e a assinatura dos atributos contra o certificado embutido
+///
+/// `contents` é o valor com os delimitadores < > e o padding após o CMS
+pub fn verify_contents(contents: &[u8], expected: &[u8], digest: DigestAlgorithm) -> Result<()> {
+  let content_info = decode_contents(contents)?;
+  check_message_digest(
+    &content_info.to_der().map_err(decode_error)?,
+    expected,
+    digest,
+  )?;
+
+  let signed_data = decode_signed_data(&content_info)?;
+  let signer_info = signed_data
+    .signer_infos
+    .0
+    .get(0)
+    .ok_or_else(|| PdfSignError::SigningError("CMS sem SignerInfo".to_string()))?;
+  let signed_attrs = signer_info
+    .signed_attrs
+    .as_ref()
+    .ok_or_else(|| PdfSignError::SigningError("CMS sem atributos assinados".to_string()))?
+    .to_der()
+    .map_err(decode_error)?;
+
+  let signer_cert = find_signer_certificate(&signed_data)?;
+  let spki = signer_cert
+    .tbs_certificate
+    .subject_public_key_info
+    .to_der()
+    .map_err(decode_error)?;
+
+  let verified = verify_signature_value(
+    &spki,
+    signer_info.signature_algorithm.oid,
+    digest,
+    &signed_attrs,
+    signer_info.signature.as_bytes(),
+  )?;
+  if !verified {
+    return Err(PdfSignError::SigningError(
+      "a assinatura do CMS não confere com o certificado do signatário".to_string(),
+    ));
+  }
+  Ok(())
+}
+
+/// DER do certificado do signatário do CMS gravado em /Contents
+///
+/// `contents` é o valor com os delimitadores < > e o padding, como em `verify_contents`;
+/// a assinatura não é conferida
+pub fn signer_certificate_der(contents: &[u8]) -> Result<Vec<u8>> {
+  let signed_data = decode_signed_data(&decode_contents(contents)?)?;
+  find_signer_certificate(&signed_data)?
+    .to_der()
+    .map_err(decode_error)
+}
+
+/// Algoritmo do SignerInfo e messageDigest do CMS gravado em /Contents
+///
+/// É o que a assinatura declara ter coberto; a assinatura em si não é conferida
+pub fn signed_message_digest(contents: &[u8]) -> Result<(DigestAlgorithm, Vec<u8>)> {
+  let signed_data = decode_signed_data(&decode_contents(contents)?)?;
+  let signer_info = signed_data
+    .signer_infos
+    .0
+    .get(0)
+    .ok_or_else(|| PdfSignError::SigningError("CMS sem SignerInfo".to_string()))?;
+  let digest = [
+    DigestAlgorithm::Sha1,
+    DigestAlgorithm::Sha256,
+    DigestAlgorithm::Sha384,
+    DigestAlgorithm::Sha512,
+  ]
+  .into_iter()
+  .find(|digest| digest.oid() == signer_info.digest_alg.oid)
+  .ok_or_else(|| {
+    PdfSignError::SigningError(format!(
+      "digestAlgorithm do CMS não suportado: {}",
+      signer_info.digest_alg.oid
+    ))
+  })?;
+  let message_digest = signer_info
+    .signed_attrs
+    .as_ref()
+    .and_then(|attrs| {
+      attrs
+        .iter()
+        .find(|attr| attr.oid == const_oid::db::rfc5911::ID_MESSAGE_DIGEST)
+    })
+    .and_then(|attr| attr.values.get(0))
+    .and_then(|value| value.decode_as::<OctetStringRef<'_>>().ok())
+    .ok_or_else(|| PdfSignError::SigningError("CMS sem atributo messageDigest".to_string()))?;
+  Ok((digest, message_digest.as_bytes().to_vec()))
+}
+
+fn decode_error(e: der::Error) -> PdfSignError {
+  PdfSignError::DecodingError(format!("Erro ao decodificar CMS: {}", e))
+}
+
+/// ContentInfo do valor de /Contents, entre < > e em hex
+fn decode_contents(contents: &[u8]) -> Result<ContentInfo> {
+  let hex_value = contents
+    .strip_prefix(b"<")
+    .and_then(|value| value.strip_suffix(b">"))
+    .ok_or_else(|| PdfSignError::SigningError("/Contents não está entre < >".to_string()))?;
+  let der = hex::decode(hex_value)
+    .map_err(|e| PdfSignError::SigningError(format!("/Contents não é hex válido: {}", e)))?;
+
+  // O CMS ocupa o início do placeholder; o restante é padding
+  let mut reader = der::SliceReader::new(&der).map_err(decode_error)?;
+  ContentInfo::decode(&mut reader).map_err(decode_error)
+}
+
+fn decode_signed_data(content_info: &ContentInfo) -> Result<SignedData> {
+  content_info
+    .content
+    .decode_as::<SignedData>()
+    .map_err(|e| PdfSignError::DecodingError(format!("Erro ao decodificar SignedData: {}", e)))
+}
+
+/// Certificado do primeiro SignerInfo, achado no CMS pelo issuerAndSerialNumber
+fn find_signer_certificate(signed_data: &SignedData) -> Result<&x509_cert::Certificate> {
+  let signer_info = signed_data
+    .signer_infos
+    .0
+    .get(0)
+    .ok_or_else(|| PdfSignError::SigningError("CMS sem SignerInfo".to_string()))?;
+  let SignerIdentifier::IssuerAndSerialNumber(sid) = &signer_info.sid else {
+    return Err(PdfSignError::SigningError(
+      "SignerInfo sem issuerAndSerialNumber".to_string(),
+    ));
+  };
+  signed_data
+    .certificates
+    .iter()
+    .flat_map(|set| set.0.iter())
+    .find_map(|choice| match choice {
+      CertificateChoices::Certificate(cert)
+        if cert.tbs_certificate.issuer == sid.issuer
+          && cert.tbs_certificate.serial_number == sid.serial_number =>
+      {
+        Some(cert)
+      }
+      _ => None,
+    })
+    .ok_or_else(|| {
+      PdfSignError::SigningError("certificado do signatário ausente do CMS".to_string())
+    })
+}
+
+/// Confere a assinatura do certificado `cert_der` contra a chave pública `spki` (DER)
+///
+/// Serve para saber se o certificado foi assinado pela própria chave (autoassinado)
+pub(crate) fn verify_certificate_signature(cert_der: &[u8], spki: &[u8]) -> Result<bool> {
+  let cert = x509_cert::Certificate::from_der(cert_der).map_err(decode_error)?;
+  let algorithm = &cert.signature_algorithm;
+  let digest = match algorithm.oid {
+    SHA_1_WITH_RSA_ENCRYPTION => DigestAlgorithm::Sha1,
+    SHA_256_WITH_RSA_ENCRYPTION | ECDSA_WITH_SHA_256 => DigestAlgorithm::Sha256,
+    SHA_384_WITH_RSA_ENCRYPTION | ECDSA_WITH_SHA_384 => DigestAlgorithm::Sha384,
+    SHA_512_WITH_RSA_ENCRYPTION | ECDSA_WITH_SHA_512 => DigestAlgorithm::Sha512,
+    // Ed25519 assina o TBS inteiro; o digest é ignorado
+    ID_ED_25519 => DigestAlgorithm::Sha256,
+    ID_RSASSA_PSS => {
+      let params = algorithm
+        .parameters
+        .as_ref()
+        .and_then(|params| params.decode_as::<RsaPssParams<'_>>().ok())
+        .ok_or_else(|| {
+          PdfSignError::DecodingError("parâmetros RSASSA-PSS inválidos".to_string())
+        })?;
+      [
+        DigestAlgorithm::Sha1,
+        DigestAlgorithm::Sha256,
+        DigestAlgorithm::Sha384,
+        DigestAlgorithm::Sha512,
+      ]
+      .into_iter()
+      .find(|digest| digest.oid() == params.hash.oid)
+      .ok_or_else(|| {
+        PdfSignError::DecodingError(format!(
+          "digest RSASSA-PSS não suportado: {}",
+          params.hash.oid
+        ))
+      })?
+    }
+    oid => {
+      return Err(PdfSignError::DecodingError(format!(
+        "algoritmo de assinatura do certificado não suportado: {}",
+        oid
+      )))
+    }
+  };
+  let tbs = cert.tbs_certificate.to_der().map_err(decode_error)?;
+  let signature = cert.signature.as_bytes().ok_or_else(|| {
+    PdfSignError::DecodingError("assinatura do certificado com bits soltos".to_string())
+  })?;
+  verify_signature_value(spki, algorithm.oid, digest, &tbs, signature)
+}
+
+/// Verifica a assinatura dos atributos: RSA em Rust puro, os demais pelo OpenSSL
+fn verify_signature_value(
+  spki: &[u8],
+  algorithm: ObjectIdentifier,
+  digest: DigestAlgorithm,
+  signed_attrs: &[u8],
+  signature: &[u8],
+) -> Result<bool> {
+  let rsa_pkcs1 = [
+    RSA_ENCRYPTION,
+    SHA_1_WITH_RSA_ENCRYPTION,
+    SHA_256_WITH_RSA_ENCRYPTION,
+    SHA_384_WITH_RSA_ENCRYPTION,
+    SHA_512_WITH_RSA_ENCRYPTION,
+  ];
+  if rsa_pkcs1.contains(&algorithm) || algorithm == ID_RSASSA_PSS {
+    let key = RsaPublicKey::from_public_key_der(spki).map_err(|e| {
+      PdfSignError::DecodingError(format!("Erro ao decodificar chave pública RSA: {}", e))
+    })?;
+    let hashed = digest.digest(signed_attrs);
+    let result = if algorithm == ID_RSASSA_PSS {
+      key.verify(rust_cms::pss(digest).0, &hashed, signature)
+    } else {
+      key.verify(rust_cms::padding(digest), &hashed, signature)
+    };
+    return Ok(result.is_ok());
+  }
+
+  #[cfg(feature = "native")]
+  return crate::openssl_cms::verify_signature(spki, digest, signed_attrs, signature);
+  #[cfg(not(feature = "native"))]
+  Err(PdfSignError::SigningError(format!(
+    "algoritmo de assinatura {} só pode ser conferido no build nativo",
+    algorithm
+  )))
+}
@@ -1,43 +1,217 @@
+use std::cell::Cell;
+use std::fmt;
+
 use thiserror::Error;
 
+// Display é implementado à mão para trocar o idioma das mensagens (ver `set_error_language`)
 #[derive(Error, Debug)]
 #[allow(dead_code)]
 pub enum PdfSignError {
-  #[error("Erro ao ler arquivo: {0}")]
   IoError(#[from] std::io::Error),
 
-  #[error("Certificado inválido ou senha incorreta")]
   InvalidCertificate,
 
-  #[error("PDF inválido: {0}")]
+  InvalidPassword,
+
   InvalidPdf(String),
 
-  #[error("Erro ao assinar: {0}")]
   SigningError(String),
 
-  #[error("Erro na validação da cadeia ICP-Brasil: {0}")]
   IcpBrasilValidationError(String),
 
-  #[error("Erro ao obter timestamp: {0}")]
   TimestampError(String),
 
-  #[error("Erro de rede: {0}")]
   NetworkError(String),
 
-  #[error("Erro ao decodificar: {0}")]
   DecodingError(String),
 
-  #[error("Erro RSA: {0}")]
   RsaError(String),
 
-  #[error("Erro AWS S3: {0}")]
   AwsS3Error(String),
+
+  KeyUsageError(String),
+
+  UnsupportedPlatform(String),
+}
+
+/// Idioma das mensagens de erro
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorLanguage {
+  /// Português (padrão)
+  #[default]
+  Portuguese,
+  /// Inglês
+  English,
+}
+
+thread_local! {
+  static ERROR_LANGUAGE: Cell<ErrorLanguage> = const { Cell::new(ErrorLanguage::Portuguese) };
+}
+
+/// Define o idioma das mensagens de erro formatadas nesta thread
+///
+/// Só os textos fixos de cada variante são traduzidos; o detalhe que acompanha a
+/// variante (ex: o motivo de um `InvalidPdf`) continua como foi gerado
+pub fn set_error_language(language: ErrorLanguage) {
+  ERROR_LANGUAGE.with(|current| current.set(language));
+}
+
+/// Idioma das mensagens de erro nesta thread
+pub fn error_language() -> ErrorLanguage {
+  ERROR_LANGUAGE.with(Cell::get)
+}
+
+/// Escolhe o texto no idioma de erro da thread
+pub(crate) fn localized(portuguese: &'static str, english: &'static str) -> &'static str {
+  match error_language() {
+    ErrorLanguage::Portuguese => portuguese,
+    ErrorLanguage::English => english,
+  }
+}
+
+impl fmt::Display for PdfSignError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let (prefix, detail): (&str, &dyn fmt::Display) = match self {
+      PdfSignError::IoError(e) => (localized("Erro ao ler arquivo", "Error reading file"), e),
+      PdfSignError::InvalidCertificate => {
+        return f.write_str(localized(
+          "Certificado inválido ou senha incorreta",
+          "Invalid certificate or wrong password",
+        ))
+      }
+      PdfSignError::InvalidPassword => {
+        return f.write_str(localized(
+          "Senha do certificado incorreta: a verificação de integridade (MAC) do PKCS#12 falhou",
+          "Wrong certificate password: the PKCS#12 integrity check (MAC) failed",
+        ))
+      }
+      PdfSignError::InvalidPdf(detail) => (localized("PDF inválido", "Invalid PDF"), detail),
+      PdfSignError::SigningError(detail) => (localized("Erro ao assinar", "Signing error"), detail),
+      PdfSignError::IcpBrasilValidationError(detail) => (
+        localized(
+          "Erro na validação da cadeia ICP-Brasil",
+          "ICP-Brasil chain validation error",
+        ),
+        detail,
+      ),
+      PdfSignError::TimestampError(detail) => (
+        localized("Erro ao obter timestamp", "Timestamp error"),
+        detail,
+      ),
+      PdfSignError::NetworkError(detail) => (localized("Erro de rede", "Network error"), detail),
+      PdfSignError::DecodingError(detail) => {
+        (localized("Erro ao decodificar", "Decoding error"), detail)
+      }
+      PdfSignError::RsaError(detail) => (localized("Erro RSA", "RSA error"), detail),
+      PdfSignError::AwsS3Error(detail) => (localized("Erro AWS S3", "AWS S3 error"), detail),
+      PdfSignError::KeyUsageError(detail) => (
+        localized(
+          "O uso da chave do certificado não permite assinar",
+          "The certificate key usage does not allow signing",
+        ),
+        detail,
+      ),
+      PdfSignError::UnsupportedPlatform(detail) => (
+        localized(
+          "Não suportado nesta plataforma",
+          "Not supported on this platform",
+        ),
+        detail,
+      ),
+    };
+    write!(f, "{}: {}", prefix, detail)
+  }
+}
+
+impl PdfSignError {
+  /// Código estável do erro, para tratamento programático (ex: `code` do erro no Node)
+  pub fn code(&self) -> &'static str {
+    match self {
+      PdfSignError::IoError(_) => "IO_ERROR",
+      PdfSignError::InvalidCertificate => "INVALID_CERTIFICATE",
+      PdfSignError::InvalidPassword => "INVALID_PASSWORD",
+      PdfSignError::InvalidPdf(_) => "INVALID_PDF",
+      PdfSignError::SigningError(_) => "SIGNING_ERROR",
+      PdfSignError::IcpBrasilValidationError(_) => "ICP_BRASIL_VALIDATION_ERROR",
+      PdfSignError::TimestampError(_) => "TIMESTAMP_ERROR",
+      PdfSignError::NetworkError(_) => "NETWORK_ERROR",
+      PdfSignError::DecodingError(_) => "DECODING_ERROR",
+      PdfSignError::RsaError(_) => "RSA_ERROR",
+      PdfSignError::AwsS3Error(_) => "AWS_S3_ERROR",
+      PdfSignError::KeyUsageError(_) => "KEY_USAGE_ERROR",
+      PdfSignError::UnsupportedPlatform(_) => "UNSUPPORTED_PLATFORM",
+    }
+  }
 }
 
 pub type Result<T> = std::result::Result<T, PdfSignError>;
 
-impl From<PdfSignError> for napi::Error {
+/// Erro JS com `code` igual a `PdfSignError::code` e a mensagem legível
+#[cfg(feature = "native")]
+impl From<PdfSignError> for napi::Error<&'static str> {
   fn from(err: PdfSignError) -> Self {
-    napi::Error::new(napi::Status::GenericFailure, err.to_string())
+    napi::Error::new(err.code(), err.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_error_codes() {
+    let io = std::io::Error::new(std::io::ErrorKind::NotFound, "x");
+    let cases = [
+      (PdfSignError::IoError(io), "IO_ERROR"),
+      (PdfSignError::InvalidCertificate, "INVALID_CERTIFICATE"),
+      (PdfSignError::InvalidPassword, "INVALID_PASSWORD"),
+      (PdfSignError::InvalidPdf(String::new()), "INVALID_PDF"),
+      (PdfSignError::SigningError(String::new()), "SIGNING_ERROR"),
+      (
+        PdfSignError::IcpBrasilValidationError(String::new()),
+        "ICP_BRASIL_VALIDATION_ERROR",
+      ),
+      (
+        PdfSignError::TimestampError(String::new()),
+        "TIMESTAMP_ERROR",
+      ),
+      (PdfSignError::NetworkError(String::new()), "NETWORK_ERROR"),
+      (PdfSignError::DecodingError(String::new()), "DECODING_ERROR"),
+      (PdfSignError::RsaError(String::new()), "RSA_ERROR"),
+      (PdfSignError::AwsS3Error(String::new()), "AWS_S3_ERROR"),
+      (
+        PdfSignError::KeyUsageError(String::new()),
+        "KEY_USAGE_ERROR",
+      ),
+      (
+        PdfSignError::UnsupportedPlatform(String::new()),
+        "UNSUPPORTED_PLATFORM",
+      ),
+    ];
+    for (error, code) in cases {
+      assert_eq!(error.code(), code, "{:?}", error);
+    }
+  }
+
+  #[test]
+  fn test_error_language_changes_display() {
+    let error = PdfSignError::InvalidPdf("ByteRange".to_string());
+    assert_eq!(error.to_string(), "PDF inválido: ByteRange");
+
+    set_error_language(ErrorLanguage::English);
+    assert_eq!(error.to_string(), "Invalid PDF: ByteRange");
+    assert_eq!(
+      PdfSignError::InvalidCertificate.to_string(),
+      "Invalid certificate or wrong password"
+    );
+
+    // O idioma é por thread: as outras continuam em português
+    let other_thread = std::thread::spawn(|| PdfSignError::InvalidCertificate.to_string())
+      .join()
+      .unwrap();
+    assert_eq!(other_thread, "Certificado inválido ou senha incorreta");
+
+    set_error_language(ErrorLanguage::Portuguese);
+    assert_eq!(error.to_string(), "PDF inválido: ByteRange");
   }
 }
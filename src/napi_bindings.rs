@@ -0,0 +1,1396 @@
+/// Binding Node (napi) da biblioteca
+///
+/// Só existe no build com a feature `native`; o build wasm expõe apenas a API Rust
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::certificate::Certificate;
+use crate::error::{self as pdf_error, localized, PdfSignError};
+use crate::pdfsigner::{self, PdfSigner, SignerSelector};
+use crate::signature_config::{
+  self, AppearanceContent, DigestAlgorithm, PadesLevel, SignatureAppearance, SignatureConfig,
+  TrustAnchorsMode, DEFAULT_NETWORK_TIMEOUT, ITI_TSA_URL,
+};
+use crate::signatures;
+use crate::utils;
+use aws_config::timeout::TimeoutConfig;
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_s3::operation::put_object::builders::PutObjectFluentBuilder;
+use aws_sdk_s3::types::ServerSideEncryption;
+use aws_sdk_s3::{self as s3, primitives::ByteStream};
+use chrono::Utc;
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::ThreadsafeFunction;
+use napi_derive::napi;
+
+#[napi(object)]
+pub struct S3Info {
+  pub bucket: String,
+  pub access_key: String,
+  pub secret_key: String,
+  pub endpoint: String,
+  pub region: Option<String>,
+  pub provider_name: Option<String>,
+  /// Criptografia no servidor exigida pelo bucket; ausente mantém o padrão do bucket
+  pub sse: Option<S3Encryption>,
+  /// Chave KMS usada com `sse` AwsKms; sem ela vale a chave padrão do bucket
+  pub sse_kms_key_id: Option<String>,
+}
+
+#[napi(string_enum)]
+pub enum S3Encryption {
+  Aes256,
+  AwsKms,
+}
+
+#[napi(object, object_to_js = false)]
+pub struct CertificateInfo {
+  pub pfx_path: Option<String>,
+  pub pfx_data: Option<Buffer>,
+  /// Senha literal; tem precedência sobre `pfx_password_env`
+  pub pfx_password: Option<String>,
+  /// Nome da variável de ambiente que guarda a senha, para não trafegá-la pelo JS
+  pub pfx_password_env: Option<String>,
+  /// Chamada para obter a senha quando nenhuma das opções acima é informada; só vale
+  /// em `sign_pdf_async`, que a chama fora da thread do JS
+  pub pfx_password_callback: Option<ThreadsafeFunction<(), String, (), Status, false>>,
+  /// Número de série (hex) do certificado que assina, num PFX com várias identidades
+  pub signer_serial_number: Option<String>,
+  /// CN do certificado que assina; vale quando `signer_serial_number` não é informado
+  pub signer_common_name: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct Config {
+  pub reason: Option<String>,
+  pub allowed_reasons: Option<Vec<String>>,
+  pub location: Option<String>,
+  pub contact_info: Option<String>,
+  pub signer_name: Option<String>,
+  pub trust_anchors: Option<Vec<Buffer>>,
+  pub trust_anchors_path: Option<String>,
+  pub replace_trust_anchors: Option<bool>,
+  pub fill_existing_field: Option<String>,
+  pub self_verify: Option<bool>,
+  pub pdfa_mode: Option<bool>,
+  /// Tamanho máximo do PDF de entrada, em bytes
+  pub max_input_bytes: Option<i64>,
+  /// Camadas da aparência visível, desenhadas em ordem no campo de `fillExistingField`
+  pub appearance: Option<Vec<AppearanceElement>>,
+  /// Formato strftime do `{date}` nos textos da aparência; padrão: "%d/%m/%Y %H:%M:%S"
+  pub appearance_date_format: Option<String>,
+  /// Padrão: BB, sem chamadas de rede
+  pub pades_level: Option<SignatureLevel>,
+  /// TSA do carimbo do tempo (BT ou acima); padrão: TSA do ITI
+  pub tsa_url: Option<String>,
+  /// Padrão: Sha256
+  pub digest_algorithm: Option<HashAlgorithm>,
+  pub include_ocsp: Option<bool>,
+  pub include_crl: Option<bool>,
+  pub validate_icp_brasil: Option<bool>,
+  /// Assina mesmo sem digitalSignature/nonRepudiation no keyUsage do certificado
+  pub ignore_key_usage: Option<bool>,
+  /// Tempo limite de cada requisição de saída (TSA, OCSP, CRL e S3), em milissegundos;
+  /// padrão: 30000
+  pub network_timeout_ms: Option<u32>,
+}
+
+#[napi(object)]
+pub struct AppearanceElement {
+  /// [x, y, largura, altura] em pontos, a partir do canto inferior esquerdo do campo
+  pub rect: Vec<f64>,
+  /// Imagem JPEG; tem precedência sobre `qr_code` e `text`
+  pub image: Option<Buffer>,
+  /// URL do QR code; `{sha256}` vira o SHA-256 (hex) do PDF antes da assinatura
+  pub qr_code: Option<String>,
+  pub text: Option<String>,
+  /// Padrão: 10
+  pub font_size: Option<f64>,
+}
+
+#[napi(object)]
+pub struct PdfValidation {
+  pub is_valid: bool,
+  /// Versão do cabeçalho %PDF-x.y (ex: "1.7")
+  pub pdf_version: Option<String>,
+  pub has_acroform: bool,
+  pub first_page_found: bool,
+  pub catalog_found: bool,
+  /// Motivo do primeiro problema encontrado, quando inválido
+  pub error: Option<String>,
+}
+
+#[napi(object)]
+pub struct IntegrityReport {
+  /// Uma entrada por assinatura, na ordem das atualizações incrementais
+  pub signatures: Vec<SignatureIntegrity>,
+  /// Se há bytes depois do trecho coberto pela última assinatura
+  pub appended: bool,
+  pub appended_bytes: i64,
+}
+
+#[napi(object)]
+pub struct SignatureIntegrity {
+  pub field_name: Option<String>,
+  /// O conteúdo coberto pelo /ByteRange confere com o messageDigest do CMS
+  pub unmodified: bool,
+  /// Por que a conferência falhou ou não pôde ser feita
+  pub error: Option<String>,
+}
+
+#[napi(object)]
+pub struct SignatureSummary {
+  pub field_name: Option<String>,
+  pub signer_name: Option<String>,
+  pub reason: Option<String>,
+  pub signing_date: Option<String>,
+  pub sub_filter: Option<String>,
+  pub byte_range: Vec<i64>,
+  pub timestamp_time: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SignResult {
+  pub signer_name: Option<String>,
+  pub signing_date: Option<String>,
+  pub field_name: Option<String>,
+  pub signature_obj: Option<u32>,
+  pub field_obj: Option<u32>,
+}
+
+impl From<&pdfsigner::SignatureRecord> for SignResult {
+  fn from(record: &pdfsigner::SignatureRecord) -> Self {
+    Self {
+      signer_name: record.signer_name.clone(),
+      signing_date: Some(record.signing_time.to_rfc3339()),
+      field_name: record.field_name.clone(),
+      signature_obj: Some(record.signature_obj as u32),
+      field_obj: record.field_obj.map(|obj| obj as u32),
+    }
+  }
+}
+
+#[napi(object)]
+pub struct CertInfo {
+  pub common_name: String,
+  pub organization: Option<String>,
+  pub email: Option<String>,
+  pub valid_from: String,
+  pub valid_until: String,
+  pub serial_number: Option<String>,
+  pub subject_dn: String,
+  pub issuer_dn: String,
+  pub key_usage: Vec<String>,
+  pub extended_key_usage: Vec<String>,
+  pub cpf: Option<String>,
+  pub cnpj: Option<String>,
+  pub expired: bool,
+  /// Ausente quando a assinatura do certificado não pôde ser conferida
+  pub is_self_signed: Option<bool>,
+  /// Impressões digitais (hex do digest do DER)
+  pub thumbprint_sha1: String,
+  pub thumbprint_sha256: String,
+}
+
+impl From<pdfsigner::CertificateInfo> for CertInfo {
+  fn from(info: pdfsigner::CertificateInfo) -> Self {
+    Self {
+      common_name: info.common_name,
+      organization: info.organization,
+      email: info.email,
+      valid_from: info.valid_from,
+      valid_until: info.valid_until,
+      serial_number: info.serial_number,
+      subject_dn: info.subject_dn,
+      issuer_dn: info.issuer_dn,
+      key_usage: info.key_usage,
+      extended_key_usage: info.extended_key_usage,
+      cpf: info.cpf,
+      cnpj: info.cnpj,
+      expired: info.expired,
+      is_self_signed: info.is_self_signed,
+      thumbprint_sha1: info.thumbprint_sha1,
+      thumbprint_sha256: info.thumbprint_sha256,
+    }
+  }
+}
+
+#[napi(string_enum)]
+pub enum ErrorLanguage {
+  Portuguese,
+  English,
+}
+
+#[napi(string_enum)]
+pub enum HashAlgorithm {
+  Sha1,
+  Sha256,
+  Sha384,
+  Sha512,
+}
+
+impl From<HashAlgorithm> for DigestAlgorithm {
+  fn from(algorithm: HashAlgorithm) -> Self {
+    match algorithm {
+      HashAlgorithm::Sha1 => DigestAlgorithm::Sha1,
+      HashAlgorithm::Sha256 => DigestAlgorithm::Sha256,
+      HashAlgorithm::Sha384 => DigestAlgorithm::Sha384,
+      HashAlgorithm::Sha512 => DigestAlgorithm::Sha512,
+    }
+  }
+}
+
+#[napi(string_enum)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum SignatureLevel {
+  BB,
+  BT,
+  BLT,
+  BLTA,
+}
+
+impl From<SignatureLevel> for PadesLevel {
+  fn from(level: SignatureLevel) -> Self {
+    match level {
+      SignatureLevel::BB => PadesLevel::BB,
+      SignatureLevel::BT => PadesLevel::BT,
+      SignatureLevel::BLT => PadesLevel::BLT,
+      SignatureLevel::BLTA => PadesLevel::BLTA,
+    }
+  }
+}
+
+#[napi(string_enum)]
+pub enum SaveFormat {
+  File,
+  S3,
+}
+
+// Destino de `signPdfTo`: arquivo local ou objeto no S3
+#[napi(object)]
+pub struct SignDestination {
+  pub format: SaveFormat,
+  /// Caminho do arquivo ou, no S3, a chave do objeto
+  pub path: String,
+  /// Bucket e credenciais; obrigatório com `format` S3
+  pub s3_info: Option<S3Info>,
+}
+
+#[napi(constructor)]
+pub struct PdfSigned {
+  pub data: Arc<Vec<u8>>,
+  #[napi(skip)]
+  pub s3_info: Option<S3Info>,
+  // Em milissegundos: campos do construtor napi precisam ser conversíveis para JS
+  #[napi(skip)]
+  pub network_timeout_ms: u32,
+  // Já convertido: campos do construtor napi precisam ser conversíveis para JS
+  #[napi(skip)]
+  pub signature: Option<SignResult>,
+}
+
+#[napi]
+impl PdfSigned {
+  pub fn new(data: Vec<u8>) -> Self {
+    PdfSigned {
+      data: Arc::new(data),
+      s3_info: None,
+      network_timeout_ms: DEFAULT_NETWORK_TIMEOUT.as_millis() as u32,
+      signature: None,
+    }
+  }
+
+  // Guarda o que a assinatura gravou, exposto por result()
+  fn with_signature(mut self, signature: Option<pdfsigner::SignatureRecord>) -> Self {
+    self.signature = signature.as_ref().map(SignResult::from);
+    self
+  }
+
+  // Mantém o tempo limite de rede da assinatura para o upload ao S3
+  fn with_network_timeout(mut self, network_timeout: Duration) -> Self {
+    self.network_timeout_ms = timeout_ms(network_timeout);
+    self
+  }
+
+  #[napi]
+  pub fn credentials_provider(&self, s3_info: S3Info) -> Self {
+    PdfSigned {
+      data: Arc::clone(&self.data),
+      s3_info: Some(s3_info),
+      network_timeout_ms: self.network_timeout_ms,
+      signature: self.signature.clone(),
+    }
+  }
+
+  // Signatário (/Name), data de /M, campo e objetos gravados pela assinatura;
+  // tudo ausente num PdfSigned criado pelo construtor
+  #[napi]
+  pub fn result(&self) -> SignResult {
+    self.signature.clone().unwrap_or_default()
+  }
+
+  #[napi]
+  pub fn to_buffer(&self) -> Buffer {
+    Buffer::from(self.data.as_slice())
+  }
+
+  // Hash hex do PDF assinado inteiro, para logs de auditoria (SHA-256 por padrão)
+  #[napi]
+  pub fn content_hash(&self, algorithm: Option<HashAlgorithm>) -> String {
+    let digest = algorithm.map_or(DigestAlgorithm::Sha256, DigestAlgorithm::from);
+    hex::encode(digest.digest(&self.data))
+  }
+
+  // Hash hex só dos bytes cobertos pelo /ByteRange da última assinatura
+  #[napi]
+  pub fn signed_content_hash(
+    &self,
+    algorithm: Option<HashAlgorithm>,
+  ) -> Result<String, &'static str> {
+    let digest = algorithm.map_or(DigestAlgorithm::Sha256, DigestAlgorithm::from);
+    signatures::byte_range_digest(&self.data, digest)
+      .map(hex::encode)
+      .map_err(coded(localized(
+        "Erro ao calcular hash do ByteRange",
+        "Error computing the ByteRange hash",
+      )))
+  }
+
+  #[napi]
+  pub async fn save(&self, path: String, format: SaveFormat) -> Result<()> {
+    match format {
+      SaveFormat::File => tokio::fs::write(&path, self.data.as_ref())
+        .await
+        .map_err(|e| Error::from_reason(format!("Erro ao salvar PDF: {}", e))),
+      SaveFormat::S3 => match &self.s3_info {
+        Some(s3_info) => upload_to_s3(&self.data, s3_info, path, self.network_timeout_ms)
+          .await
+          .map_err(Error::from_reason),
+        None => Err(Error::from_reason("S3 credentials not provided")),
+      },
+    }
+  }
+}
+
+// Envia o PDF assinado ao bucket de `s3_info`, na chave `key`
+//
+// Os erros saem como texto: napi::Error não pode ser criado fora do Node (testes)
+async fn upload_to_s3(
+  data: &Arc<Vec<u8>>,
+  s3_info: &S3Info,
+  key: String,
+  network_timeout_ms: u32,
+) -> std::result::Result<(), String> {
+  let access_key = s3_info.access_key.clone();
+  let secret_key = s3_info.secret_key.clone();
+  let provider_name = s3_info.provider_name.clone().unwrap_or_default();
+  let endpoint = s3_info.endpoint.clone();
+  let region = s3_info.region.clone().unwrap();
+
+  let credentials = aws_sdk_s3::config::Credentials::new(
+    access_key.leak() as &str,
+    secret_key.leak() as &str,
+    None,
+    None,
+    provider_name.leak() as &str,
+  );
+  let config = aws_config::defaults(BehaviorVersion::latest())
+    .endpoint_url(endpoint)
+    .credentials_provider(credentials)
+    .region(Region::new(region))
+    .timeout_config(
+      TimeoutConfig::builder()
+        .operation_timeout(Duration::from_millis(network_timeout_ms as u64))
+        .build(),
+    )
+    .load()
+    .await;
+  let client = s3::Client::new(&config);
+  put_object_request(&client, data, s3_info, key)?
+    .send()
+    .await
+    .map_err(|e| format!("Erro ao fazer upload para S3: {}", e))?;
+  Ok(())
+}
+
+// PutObject do PDF assinado no bucket de `s3_info`, já com a criptografia pedida
+fn put_object_request(
+  client: &s3::Client,
+  data: &Arc<Vec<u8>>,
+  s3_info: &S3Info,
+  key: String,
+) -> std::result::Result<PutObjectFluentBuilder, String> {
+  let request = client
+    .put_object()
+    .bucket(s3_info.bucket.clone())
+    .key(key)
+    .body(upload_body(data));
+  with_server_side_encryption(request, s3_info)
+}
+
+// Grava o PDF assinado no destino e devolve onde ele ficou: o caminho do arquivo ou
+// a URL s3://bucket/chave do objeto
+async fn deliver(
+  data: Arc<Vec<u8>>,
+  destination: SignDestination,
+  network_timeout_ms: u32,
+) -> std::result::Result<String, String> {
+  match destination.format {
+    SaveFormat::File => {
+      tokio::fs::write(&destination.path, data.as_ref())
+        .await
+        .map_err(|e| format!("Erro ao salvar PDF: {}", e))?;
+      Ok(destination.path)
+    }
+    SaveFormat::S3 => {
+      let s3_info = destination
+        .s3_info
+        .ok_or_else(|| "S3 credentials not provided".to_string())?;
+      let location = format!("s3://{}/{}", s3_info.bucket, destination.path);
+      upload_to_s3(&data, &s3_info, destination.path, network_timeout_ms).await?;
+      Ok(location)
+    }
+  }
+}
+
+// Tempo limite em milissegundos, saturado no u32 que o JS recebe
+fn timeout_ms(timeout: Duration) -> u32 {
+  timeout.as_millis().min(u32::MAX as u128) as u32
+}
+
+// Arc<Vec<u8>> não implementa AsRef<[u8]>, exigido por Bytes::from_owner
+struct SharedPdf(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for SharedPdf {
+  fn as_ref(&self) -> &[u8] {
+    self.0.as_slice()
+  }
+}
+
+// Corpo do upload ao S3 compartilhando o buffer do PDF assinado, sem copiá-lo
+fn upload_body(data: &Arc<Vec<u8>>) -> ByteStream {
+  ByteStream::from(bytes::Bytes::from_owner(SharedPdf(Arc::clone(data))))
+}
+
+// Cabeçalhos x-amz-server-side-encryption do upload, conforme `sse` e `sse_kms_key_id`
+fn with_server_side_encryption(
+  request: PutObjectFluentBuilder,
+  s3_info: &S3Info,
+) -> std::result::Result<PutObjectFluentBuilder, String> {
+  match (&s3_info.sse, &s3_info.sse_kms_key_id) {
+    (None, None) => Ok(request),
+    (Some(S3Encryption::Aes256), None) => {
+      Ok(request.server_side_encryption(ServerSideEncryption::Aes256))
+    }
+    (Some(S3Encryption::AwsKms), key_id) => Ok(
+      request
+        .server_side_encryption(ServerSideEncryption::AwsKms)
+        .set_ssekms_key_id(key_id.clone()),
+    ),
+    (_, Some(_)) => Err("sseKmsKeyId exige sse AwsKms".to_string()),
+  }
+}
+
+// Senha do PFX literal ou da variável de ambiente, nessa ordem; None se nenhuma foi informada
+fn pfx_password_from(
+  literal: Option<String>,
+  env_var: Option<&str>,
+) -> std::result::Result<Option<String>, String> {
+  match (literal, env_var) {
+    (Some(password), _) => Ok(Some(password)),
+    (None, Some(env_var)) => std::env::var(env_var).map(Some).map_err(|_| {
+      format!(
+        "{} {} {}",
+        localized(
+          "Erro ao carregar certificado: variável de ambiente",
+          "Error loading certificate: environment variable",
+        ),
+        env_var,
+        localized("não definida", "is not set")
+      )
+    }),
+    (None, None) => Ok(None),
+  }
+}
+
+// Com pfxPassword e pfxPasswordEnv juntos, avisa o Node (process.emitWarning) que a
+// variável é ignorada; o aviso não impede a assinatura, então falhas ao emiti-lo são ignoradas
+fn warn_ignored_password_env(env: &Env, certificate: &CertificateInfo) {
+  let (Some(_), Some(env_var)) = (&certificate.pfx_password, &certificate.pfx_password_env) else {
+    return;
+  };
+  let message = format!(
+    "pdfsigner-rs: {} {}",
+    localized(
+      "pfxPassword e pfxPasswordEnv informados; usando pfxPassword e ignorando",
+      "pfxPassword and pfxPasswordEnv given; using pfxPassword and ignoring",
+    ),
+    env_var
+  );
+  let _ = env
+    .get_global()
+    .and_then(|global| global.get_named_property::<Object>("process"))
+    .and_then(|process| process.get_named_property::<Function<String, Unknown>>("emitWarning"))
+    .and_then(|emit_warning| emit_warning.call(message));
+}
+
+// Nas funções síncronas, `Result<T, &'static str>` leva ao JS um erro com `code`
+// estável (ex: "INVALID_PDF") em vez de GenericFailure; as assíncronas só aceitam Status
+//
+// Código dos erros de argumentos ausentes ou malformados, detectados antes da biblioteca
+const INVALID_ARGUMENT: &str = "INVALID_ARGUMENT";
+
+// Converte o PdfSignError em erro JS com o código da variante e a mensagem com contexto
+fn coded(context: &str) -> impl Fn(PdfSignError) -> Error<&'static str> + '_ {
+  move |e| Error::new(e.code(), format!("{}: {}", context, e))
+}
+
+// Carrega o assinador nas funções síncronas: a senha vem de pfxPassword ou pfxPasswordEnv,
+// já que a thread do JS não pode esperar pelo pfxPasswordCallback
+fn load_signer(env: &Env, certificate: CertificateInfo) -> Result<PdfSigner, &'static str> {
+  warn_ignored_password_env(env, &certificate);
+  let password = pfx_password_from(
+    certificate.pfx_password.clone(),
+    certificate.pfx_password_env.as_deref(),
+  )
+  .map_err(|e| Error::new(INVALID_ARGUMENT, e))?;
+  match password {
+    Some(password) => open_signer(certificate, &password),
+    None if certificate.pfx_password_callback.is_some() => Err(Error::new(
+      INVALID_ARGUMENT,
+      localized(
+        "Erro ao carregar certificado: pfxPasswordCallback só é aceito em signPdfAsync",
+        "Error loading certificate: pfxPasswordCallback is only accepted by signPdfAsync",
+      )
+      .to_string(),
+    )),
+    None => Err(missing_password()),
+  }
+}
+
+// Erro de certificado sem nenhuma das fontes de senha
+fn missing_password() -> Error<&'static str> {
+  Error::new(
+    INVALID_ARGUMENT,
+    localized(
+      "Erro ao carregar certificado: informe pfxPassword, pfxPasswordEnv ou pfxPasswordCallback",
+      "Error loading certificate: provide pfxPassword, pfxPasswordEnv or pfxPasswordCallback",
+    )
+    .to_string(),
+  )
+}
+
+// Carrega o assinador a partir do caminho ou dos bytes do PFX
+fn open_signer(certificate: CertificateInfo, password: &str) -> Result<PdfSigner, &'static str> {
+  let selector = match (
+    certificate.signer_serial_number,
+    certificate.signer_common_name,
+  ) {
+    (Some(serial), _) => Some(SignerSelector::SerialNumber(serial)),
+    (None, Some(cn)) => Some(SignerSelector::SubjectCn(cn)),
+    (None, None) => None,
+  };
+
+  let signer = if let Some(pfx_path) = certificate.pfx_path {
+    match &selector {
+      Some(selector) => PdfSigner::from_pfx_file_with_signer(&pfx_path, password, selector),
+      None => PdfSigner::from_pfx_file(&pfx_path, password),
+    }
+  } else if let Some(pfx_data) = certificate.pfx_data {
+    match &selector {
+      Some(selector) => PdfSigner::from_pfx_bytes_with_signer(&pfx_data, password, selector),
+      None => PdfSigner::from_pfx_bytes(&pfx_data, password),
+    }
+  } else {
+    return Err(Error::new(
+      INVALID_ARGUMENT,
+      localized(
+        "Erro ao carregar certificado: informe pfxPath ou pfxData",
+        "Error loading certificate: provide pfxPath or pfxData",
+      )
+      .to_string(),
+    ));
+  };
+
+  signer.map_err(coded(localized(
+    "Erro ao carregar certificado",
+    "Error loading certificate",
+  )))
+}
+
+// Converte a Config do Node na configuração de assinatura
+fn build_signature_config(config: Option<Config>) -> SignatureConfig {
+  // Sem pades_level, a assinatura fica na básica, sem chamadas de rede
+  let mut signature_config = SignatureConfig::default();
+  if let Some(cfg) = config {
+    // Sem motivo, vale o padrão do certificado; "" omite /Reason
+    signature_config.reason = cfg.reason;
+    signature_config.allowed_reasons = cfg.allowed_reasons;
+    if let Some(location) = cfg.location {
+      signature_config.location = location;
+    }
+    if let Some(contact_info) = cfg.contact_info {
+      signature_config.contact_info = contact_info;
+    }
+    signature_config.signer_name = cfg.signer_name;
+    // Sem raízes embutidas, a cadeia só é validada quando o chamador informa âncoras
+    if let Some(trust_anchors) = cfg.trust_anchors {
+      signature_config.trust_anchors = Some(trust_anchors.into_iter().map(Vec::from).collect());
+      signature_config.validate_icp_brasil = true;
+    }
+    if let Some(trust_anchors_path) = cfg.trust_anchors_path {
+      signature_config.trust_anchors_path = Some(trust_anchors_path);
+      signature_config.validate_icp_brasil = true;
+    }
+    if cfg.replace_trust_anchors.unwrap_or(false) {
+      signature_config.trust_anchors_mode = TrustAnchorsMode::Replace;
+    }
+    if let Some(validate_icp_brasil) = cfg.validate_icp_brasil {
+      signature_config.validate_icp_brasil = validate_icp_brasil;
+    }
+    if let Some(pades_level) = cfg.pades_level {
+      signature_config.pades_level = pades_level.into();
+    }
+    // A partir de BT, sem tsaUrl vale a TSA do ITI
+    signature_config.tsa_url = cfg.tsa_url.or_else(|| {
+      (signature_config.pades_level >= PadesLevel::BT).then(|| ITI_TSA_URL.to_string())
+    });
+    if let Some(digest_algorithm) = cfg.digest_algorithm {
+      signature_config.digest_algorithm = digest_algorithm.into();
+    }
+    if let Some(include_ocsp) = cfg.include_ocsp {
+      signature_config.include_ocsp = include_ocsp;
+    }
+    if let Some(include_crl) = cfg.include_crl {
+      signature_config.include_crl = include_crl;
+    }
+    if let Some(network_timeout_ms) = cfg.network_timeout_ms {
+      signature_config.network_timeout = Duration::from_millis(network_timeout_ms.into());
+    }
+    signature_config.fill_existing_field = cfg.fill_existing_field;
+    signature_config.self_verify = cfg.self_verify.unwrap_or(false);
+    signature_config.pdfa_mode = cfg.pdfa_mode.unwrap_or(false);
+    signature_config.ignore_key_usage = cfg.ignore_key_usage.unwrap_or(false);
+    signature_config.max_input_bytes = cfg.max_input_bytes.map(|max| max.max(0) as u64);
+    signature_config.appearance = cfg.appearance.map(|elements| {
+      let defaults = SignatureAppearance::default();
+      SignatureAppearance {
+        elements: elements.into_iter().map(appearance_element).collect(),
+        date_format: cfg.appearance_date_format.unwrap_or(defaults.date_format),
+        ..defaults
+      }
+    });
+  }
+  signature_config
+}
+
+// Um rect fora do formato vira área vazia, recusada ao montar a aparência
+fn appearance_element(element: AppearanceElement) -> signature_config::AppearanceElement {
+  let content = match (element.image, element.qr_code) {
+    (Some(image), _) => AppearanceContent::Image(image.into()),
+    (None, Some(url)) => AppearanceContent::QrCode(url),
+    (None, None) => AppearanceContent::Text {
+      text: element.text.unwrap_or_default(),
+      font_size: element.font_size.unwrap_or(10.0),
+    },
+  };
+  signature_config::AppearanceElement {
+    content,
+    rect: element.rect.try_into().unwrap_or([0.0; 4]),
+  }
+}
+
+// Função para assinar PDF
+#[napi]
+pub fn sign_pdf(
+  env: &Env,
+  certificate: CertificateInfo,
+  pdf_data: Buffer,
+  config: Option<Config>,
+) -> Result<PdfSigned, &'static str> {
+  let signer = load_signer(env, certificate)?;
+
+  let signature_config = build_signature_config(config);
+
+  let (signed_buffer, report) = signer
+    .sign_pdf_with_report(pdf_data.into(), &signature_config)
+    .map_err(coded(localized("Erro ao assinar PDF", "Error signing PDF")))?;
+
+  Ok(
+    PdfSigned::new(signed_buffer)
+      .with_network_timeout(signature_config.network_timeout)
+      .with_signature(report.signature),
+  )
+}
+
+// Função para assinar PDF a partir de um caminho
+#[napi]
+pub fn sign_pdf_with_path(
+  env: &Env,
+  certificate: CertificateInfo,
+  pdf_path: String,
+  config: Option<Config>,
+) -> Result<PdfSigned, &'static str> {
+  let signer = load_signer(env, certificate)?;
+
+  let signature_config = build_signature_config(config);
+
+  let (signed_buffer, report) = signer
+    .sign_pdf_with_path_and_report(&pdf_path, &signature_config)
+    .map_err(coded(localized("Erro ao assinar PDF", "Error signing PDF")))?;
+
+  Ok(
+    PdfSigned::new(signed_buffer)
+      .with_network_timeout(signature_config.network_timeout)
+      .with_signature(report.signature),
+  )
+}
+
+// Assina como signPdf, fora da thread do JS; é a única que aceita pfxPasswordCallback,
+// chamado de lá como ThreadsafeFunction. Erros de argumentos rejeitam já na chamada
+#[napi]
+pub fn sign_pdf_async<'env>(
+  env: &'env Env,
+  certificate: CertificateInfo,
+  pdf_data: Buffer,
+  config: Option<Config>,
+) -> Result<PromiseRaw<'env, PdfSigned>, &'static str> {
+  warn_ignored_password_env(env, &certificate);
+  let password = pfx_password_from(
+    certificate.pfx_password.clone(),
+    certificate.pfx_password_env.as_deref(),
+  )
+  .map_err(|e| Error::new(INVALID_ARGUMENT, e))?;
+  if password.is_none() && certificate.pfx_password_callback.is_none() {
+    return Err(missing_password());
+  }
+
+  let signature_config = build_signature_config(config);
+
+  // As assíncronas só rejeitam com Status: o código do erro fica de fora
+  env
+    .spawn_future(async move {
+      let password = match (password, &certificate.pfx_password_callback) {
+        (Some(password), _) => password,
+        (None, Some(callback)) => callback.call_async_catch(()).await.map_err(|e| {
+          Error::from_reason(format!(
+            "{}: {}",
+            localized(
+              "Erro ao obter a senha do pfxPasswordCallback",
+              "Error getting the password from pfxPasswordCallback",
+            ),
+            e.reason
+          ))
+        })?,
+        (None, None) => unreachable!("conferido antes de agendar a assinatura"),
+      };
+      let signer = open_signer(certificate, &password).map_err(|e| Error::from_reason(e.reason))?;
+      let (signed_buffer, report) = signer
+        .sign_pdf_with_report(pdf_data.to_vec(), &signature_config)
+        .map_err(|e| {
+          Error::from_reason(format!(
+            "{}: {}",
+            localized("Erro ao assinar PDF", "Error signing PDF"),
+            e
+          ))
+        })?;
+      Ok(
+        PdfSigned::new(signed_buffer)
+          .with_network_timeout(signature_config.network_timeout)
+          .with_signature(report.signature),
+      )
+    })
+    .map_err(|e| Error::new("GenericFailure", e.reason.clone()))
+}
+
+// Assina e grava direto no destino, sem devolver o PDF ao JS; resolve com o caminho
+// do arquivo ou a URL s3:// do objeto. Erros de assinatura rejeitam já na chamada
+#[napi]
+pub fn sign_pdf_to<'env>(
+  env: &'env Env,
+  certificate: CertificateInfo,
+  pdf_data: Buffer,
+  config: Option<Config>,
+  destination: SignDestination,
+) -> Result<PromiseRaw<'env, String>, &'static str> {
+  if matches!(destination.format, SaveFormat::S3) && destination.s3_info.is_none() {
+    return Err(Error::new(
+      INVALID_ARGUMENT,
+      localized(
+        "Destino S3 sem s3Info (bucket e credenciais)",
+        "S3 destination without s3Info (bucket and credentials)",
+      )
+      .to_string(),
+    ));
+  }
+  let signer = load_signer(env, certificate)?;
+
+  let signature_config = build_signature_config(config);
+
+  let signed = signer
+    .sign_pdf(pdf_data.into(), &signature_config)
+    .map_err(coded(localized("Erro ao assinar PDF", "Error signing PDF")))?;
+
+  let network_timeout_ms = timeout_ms(signature_config.network_timeout);
+  env
+    .spawn_future(async move {
+      deliver(Arc::new(signed), destination, network_timeout_ms)
+        .await
+        .map_err(Error::from_reason)
+    })
+    .map_err(|e| Error::new("GenericFailure", e.reason.clone()))
+}
+
+// Assinatura de um PDF recebido em partes, como os chunks de um stream do Node
+#[napi]
+pub struct PdfSignStream {
+  signer: PdfSigner,
+  config: SignatureConfig,
+  data: Vec<u8>,
+}
+
+#[napi]
+impl PdfSignStream {
+  #[napi(constructor)]
+  pub fn new(
+    env: &Env,
+    certificate: CertificateInfo,
+    config: Option<Config>,
+  ) -> Result<Self, &'static str> {
+    Ok(PdfSignStream {
+      signer: load_signer(env, certificate)?,
+      config: build_signature_config(config),
+      data: Vec::new(),
+    })
+  }
+
+  // Acrescenta um pedaço do PDF; acima de maxInputBytes falha já na escrita
+  #[napi]
+  pub fn write(&mut self, chunk: Buffer) -> Result<(), &'static str> {
+    pdfsigner::check_input_size((self.data.len() + chunk.len()) as u64, &self.config).map_err(
+      coded(localized("Erro ao receber PDF", "Error receiving PDF")),
+    )?;
+    self.data.extend_from_slice(&chunk);
+    Ok(())
+  }
+
+  // Assina o que foi escrito até aqui; o stream volta a ficar vazio
+  #[napi]
+  pub fn finish(&mut self) -> Result<PdfSigned, &'static str> {
+    let (signed_buffer, report) = self
+      .signer
+      .sign_pdf_with_report(std::mem::take(&mut self.data), &self.config)
+      .map_err(coded(localized("Erro ao assinar PDF", "Error signing PDF")))?;
+
+    Ok(
+      PdfSigned::new(signed_buffer)
+        .with_network_timeout(self.config.network_timeout)
+        .with_signature(report.signature),
+    )
+  }
+}
+
+// Assina um PDF de arquivo para arquivo, sem carregar o documento inteiro na memória
+#[napi]
+pub fn sign_pdf_file_to_file(
+  env: &Env,
+  certificate: CertificateInfo,
+  input_path: String,
+  output_path: String,
+  config: Option<Config>,
+) -> Result<(), &'static str> {
+  let signer = load_signer(env, certificate)?;
+
+  let signature_config = build_signature_config(config);
+
+  signer
+    .sign_pdf_file_to_file(&input_path, &output_path, &signature_config)
+    .map(|_| ())
+    .map_err(coded(localized("Erro ao assinar PDF", "Error signing PDF")))
+}
+
+// Insere um campo de assinatura vazio, para ser assinado depois (ex: por outra parte)
+#[napi]
+pub fn add_empty_signature_field(
+  pdf_data: Buffer,
+  field_name: String,
+  rect: Vec<f64>,
+  page: Option<u32>,
+  reasons: Option<Vec<String>>,
+) -> Result<Buffer, &'static str> {
+  let rect: [f64; 4] = rect.try_into().map_err(|_| {
+    Error::new(
+      INVALID_ARGUMENT,
+      localized(
+        "Erro ao adicionar campo de assinatura: rect deve ter 4 números",
+        "Error adding signature field: rect must have 4 numbers",
+      )
+      .to_string(),
+    )
+  })?;
+
+  let prepared = PdfSigner::add_empty_signature_field_with_reasons(
+    pdf_data.into(),
+    &field_name,
+    rect,
+    page.unwrap_or(0) as usize,
+    &reasons.unwrap_or_default(),
+  )
+  .map_err(coded(localized(
+    "Erro ao adicionar campo de assinatura",
+    "Error adding signature field",
+  )))?;
+
+  Ok(Buffer::from(prepared))
+}
+
+// Acrescenta só um carimbo do tempo do documento (/DocTimeStamp) da TSA de `tsaUrl`,
+// sem certificado, campo de assinatura ou AcroForm
+#[napi]
+pub fn add_document_timestamp(
+  pdf_data: Buffer,
+  config: Option<Config>,
+) -> Result<Buffer, &'static str> {
+  // A TSA precisa ser informada: aqui não vale a do ITI usada a partir de BT
+  let tsa_url = config.as_ref().and_then(|cfg| cfg.tsa_url.clone());
+  let signature_config = SignatureConfig {
+    tsa_url,
+    ..build_signature_config(config)
+  };
+  PdfSigner::add_document_timestamp(pdf_data.into(), &signature_config)
+    .map(Buffer::from)
+    .map_err(coded(localized(
+      "Erro ao carimbar o documento",
+      "Error timestamping the document",
+    )))
+}
+
+// /MediaBox da página ([x1, y1, x2, y2] em pontos), herdada da árvore /Pages quando preciso
+#[napi]
+pub fn page_media_box(pdf_data: Buffer, page: Option<u32>) -> Result<Vec<f64>, &'static str> {
+  PdfSigner::page_media_box(&pdf_data, page.unwrap_or(0) as usize)
+    .map(|media_box| media_box.to_vec())
+    .map_err(coded(localized(
+      "Erro ao ler a /MediaBox da página",
+      "Error reading the page /MediaBox",
+    )))
+}
+
+// Converte um retângulo medido do canto superior esquerdo da página para o /Rect do PDF
+#[napi]
+pub fn rect_from_top_left(
+  pdf_data: Buffer,
+  rect: Vec<f64>,
+  page: Option<u32>,
+) -> Result<Vec<f64>, &'static str> {
+  let rect: [f64; 4] = rect.try_into().map_err(|_| {
+    Error::new(
+      INVALID_ARGUMENT,
+      localized(
+        "Erro ao converter retângulo: rect deve ter 4 números",
+        "Error converting rectangle: rect must have 4 numbers",
+      )
+      .to_string(),
+    )
+  })?;
+
+  let media_box =
+    PdfSigner::page_media_box(&pdf_data, page.unwrap_or(0) as usize).map_err(coded(localized(
+      "Erro ao ler a /MediaBox da página",
+      "Error reading the page /MediaBox",
+    )))?;
+
+  Ok(PdfSigner::rect_from_top_left(rect, media_box).to_vec())
+}
+
+// Idioma das mensagens de erro das chamadas seguintes (o `code` não muda)
+#[napi]
+pub fn set_error_language(language: ErrorLanguage) {
+  pdf_error::set_error_language(match language {
+    ErrorLanguage::Portuguese => pdf_error::ErrorLanguage::Portuguese,
+    ErrorLanguage::English => pdf_error::ErrorLanguage::English,
+  });
+}
+
+// Esvazia os caches do processo (âncoras de confiança e CRLs baixadas)
+#[napi]
+pub fn clear_caches() {
+  crate::cache::clear_caches();
+}
+
+// Indica se o PDF já tem uma assinatura de fato (campos vazios não contam)
+#[napi]
+pub fn is_signed(pdf_data: Buffer) -> bool {
+  signatures::is_signed(&pdf_data)
+}
+
+// Indica se o PDF é linearizado ("fast web view"); a assinatura invalida as dicas
+#[napi]
+pub fn is_linearized(pdf_data: Buffer) -> bool {
+  utils::is_linearized(&pdf_data)
+}
+
+// Confere se o PDF tem cabeçalho, Catalog e primeira página legíveis, sem assinar
+#[napi]
+pub fn validate_pdf(pdf_data: Buffer) -> PdfValidation {
+  let validation = utils::validate_pdf(&pdf_data);
+  PdfValidation {
+    is_valid: validation.is_valid,
+    pdf_version: validation.pdf_version,
+    has_acroform: validation.has_acroform,
+    first_page_found: validation.first_page_found,
+    catalog_found: validation.catalog_found,
+    error: validation.error,
+  }
+}
+
+// Lista as assinaturas já presentes no PDF, na ordem das atualizações incrementais
+#[napi]
+pub fn list_signatures(pdf_data: Buffer) -> Vec<SignatureSummary> {
+  signatures::list_signatures(&pdf_data)
+    .into_iter()
+    .map(|entry| SignatureSummary {
+      field_name: entry.field_name,
+      signer_name: entry.signer_name,
+      reason: entry.reason,
+      signing_date: entry.signing_date,
+      sub_filter: entry.sub_filter,
+      byte_range: entry.byte_range.into_iter().map(|n| n as i64).collect(),
+      timestamp_time: entry.timestamp_time.map(|time| time.to_rfc3339()),
+    })
+    .collect()
+}
+
+// Confere, para cada assinatura, se o conteúdo coberto ainda é o assinado e se algo
+// foi acrescentado depois da última
+#[napi]
+pub fn check_integrity(pdf_data: Buffer) -> IntegrityReport {
+  let report = signatures::check_integrity(&pdf_data);
+  IntegrityReport {
+    signatures: report
+      .signatures
+      .into_iter()
+      .map(|signature| SignatureIntegrity {
+        field_name: signature.field_name,
+        unmodified: signature.unmodified,
+        error: signature.error,
+      })
+      .collect(),
+    appended: report.appended_bytes > 0,
+    appended_bytes: report.appended_bytes as i64,
+  }
+}
+
+// Certificado de quem fez a última assinatura do PDF, lido do CMS em /Contents
+#[napi]
+pub fn extract_signer_certificate(pdf_data: Buffer) -> Result<CertInfo, &'static str> {
+  signatures::extract_signer_certificate(&pdf_data)
+    .map(CertInfo::from)
+    .map_err(coded(localized(
+      "Erro ao ler o certificado do signatário",
+      "Error reading the signer certificate",
+    )))
+}
+
+// Dados de um certificado avulso vindo do JS (`CertificateInput`: Buffer com o DER ou o PEM)
+#[napi]
+pub fn read_certificate(certificate: Buffer) -> Result<CertInfo, &'static str> {
+  Certificate::from_pem_or_der(&certificate)
+    .map(|cert| CertInfo::from(pdfsigner::certificate_info(&cert, Utc::now())))
+    .map_err(coded(localized(
+      "Erro ao ler o certificado",
+      "Error reading the certificate",
+    )))
+}
+
+// Função para assinar bytes arbitrários (XML, JSON...) e retornar o PKCS#7 em DER
+#[napi]
+pub fn sign_bytes(
+  env: &Env,
+  certificate: CertificateInfo,
+  data: Buffer,
+  detached: bool,
+) -> Result<Buffer, &'static str> {
+  let signer = load_signer(env, certificate)?;
+
+  let cms = signer
+    .sign_data_cms(&data, &SignatureConfig::default(), detached)
+    .map_err(coded(localized(
+      "Erro ao assinar dados",
+      "Error signing data",
+    )))?;
+
+  Ok(Buffer::from(cms))
+}
+
+// Certificado do signatário e cadeia do PFX, do signatário até a raiz
+#[napi]
+pub fn read_chain_info(
+  env: &Env,
+  certificate: CertificateInfo,
+) -> Result<Vec<CertInfo>, &'static str> {
+  let signer = load_signer(env, certificate)?;
+  Ok(
+    signer
+      .chain_info()
+      .into_iter()
+      .map(CertInfo::from)
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_upload_body_shares_signed_buffer() {
+    let signed = PdfSigned::new(vec![7u8; 8 * 1024 * 1024]);
+
+    // O corpo aponta para o mesmo buffer: nada do PDF é copiado
+    let body = upload_body(&signed.data);
+    assert_eq!(body.bytes().unwrap().as_ptr(), signed.data.as_ptr());
+    assert_eq!(Arc::strong_count(&signed.data), 2);
+  }
+
+  fn s3_info(sse: Option<S3Encryption>, sse_kms_key_id: Option<&str>) -> S3Info {
+    S3Info {
+      bucket: "assinados".to_string(),
+      access_key: "AKIA".to_string(),
+      secret_key: "segredo".to_string(),
+      endpoint: "http://localhost:9000".to_string(),
+      region: Some("us-east-1".to_string()),
+      provider_name: None,
+      sse,
+      sse_kms_key_id: sse_kms_key_id.map(str::to_string),
+    }
+  }
+
+  #[test]
+  fn test_put_object_server_side_encryption() {
+    let client = s3::Client::from_conf(
+      s3::Config::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .region(Region::new("us-east-1"))
+        .build(),
+    );
+    let put = |info: &S3Info| with_server_side_encryption(client.put_object(), info);
+
+    let plain = put(&s3_info(None, None)).unwrap();
+    assert_eq!(plain.get_server_side_encryption(), &None);
+
+    let aes = put(&s3_info(Some(S3Encryption::Aes256), None)).unwrap();
+    assert_eq!(
+      aes.get_server_side_encryption(),
+      &Some(ServerSideEncryption::Aes256)
+    );
+    assert_eq!(aes.get_ssekms_key_id(), &None);
+
+    let kms = put(&s3_info(
+      Some(S3Encryption::AwsKms),
+      Some("alias/assinados"),
+    ))
+    .unwrap();
+    assert_eq!(
+      kms.get_server_side_encryption(),
+      &Some(ServerSideEncryption::AwsKms)
+    );
+    assert_eq!(kms.get_ssekms_key_id().as_deref(), Some("alias/assinados"));
+
+    assert!(put(&s3_info(
+      Some(S3Encryption::Aes256),
+      Some("alias/assinados")
+    ))
+    .is_err());
+    assert!(put(&s3_info(None, Some("alias/assinados"))).is_err());
+  }
+
+  #[test]
+  fn test_deliver_to_file_and_put_object_to_s3() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let signed = Arc::new(b"%PDF-1.4 assinado".to_vec());
+
+    let path = std::env::temp_dir().join(format!("pdfsigner-deliver-{}.pdf", std::process::id()));
+    let destination = SignDestination {
+      format: SaveFormat::File,
+      path: path.to_string_lossy().to_string(),
+      s3_info: None,
+    };
+    let location = runtime
+      .block_on(deliver(Arc::clone(&signed), destination, 5_000))
+      .unwrap();
+    assert_eq!(location, path.to_string_lossy());
+    assert_eq!(std::fs::read(&path).unwrap(), *signed);
+    std::fs::remove_file(&path).unwrap();
+
+    // O PutObject que o destino S3 envia: bucket e chave do destino, o próprio buffer
+    // assinado como corpo e a criptografia pedida
+    let client = s3::Client::from_conf(
+      s3::Config::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .region(Region::new("us-east-1"))
+        .build(),
+    );
+    let info = s3_info(Some(S3Encryption::Aes256), None);
+    let request =
+      put_object_request(&client, &signed, &info, "contratos/123.pdf".to_string()).unwrap();
+    assert_eq!(request.get_bucket().as_deref(), Some("assinados"));
+    assert_eq!(request.get_key().as_deref(), Some("contratos/123.pdf"));
+    assert_eq!(
+      request.get_server_side_encryption(),
+      &Some(ServerSideEncryption::Aes256)
+    );
+    assert_eq!(
+      request.get_body().as_ref().and_then(ByteStream::bytes),
+      Some(signed.as_slice())
+    );
+
+    // Sem s3Info não há para onde enviar
+    let destination = SignDestination {
+      format: SaveFormat::S3,
+      path: "contratos/123.pdf".to_string(),
+      s3_info: None,
+    };
+    assert!(runtime
+      .block_on(deliver(signed, destination, 5_000))
+      .is_err());
+  }
+
+  #[test]
+  fn test_pdf_signed_result_exposes_signature_record() {
+    assert!(PdfSigned::new(vec![1, 2, 3]).result().signer_name.is_none());
+
+    let pfx_path = concat!(env!("CARGO_MANIFEST_DIR"), "/__test__/certificado-a1.pfx");
+    let pdf_path = concat!(env!("CARGO_MANIFEST_DIR"), "/__test__/pdf_sample_2.pdf");
+    let signer = PdfSigner::from_pfx_file(pfx_path, "123456").unwrap();
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BB,
+      validate_icp_brasil: false,
+      ..SignatureConfig::default()
+    };
+    let (signed, report) = signer
+      .sign_pdf_with_path_and_report(pdf_path, &config)
+      .unwrap();
+    let signed = PdfSigned::new(signed).with_signature(report.signature);
+
+    let result = signed.result();
+    let entry = &signatures::list_signatures(&signed.data)[0];
+    assert_eq!(result.signer_name, entry.signer_name);
+    assert!(result.signer_name.is_some());
+    assert_eq!(result.field_name, entry.field_name);
+    assert_eq!(result.signature_obj, Some(entry.object_number as u32));
+    assert!(result.signing_date.is_some());
+    // O S3 não perde o resultado
+    let with_s3 = signed.credentials_provider(s3_info(None, None));
+    assert_eq!(with_s3.result().field_obj, result.field_obj);
+  }
+
+  #[test]
+  fn test_pfx_password_from_env_opens_pfx() {
+    let env_var = format!("PDFSIGNER_TEST_PFX_PASSWORD_{}", std::process::id());
+    std::env::set_var(&env_var, "123456");
+
+    let password = pfx_password_from(None, Some(&env_var)).unwrap().unwrap();
+    let pfx_path = concat!(env!("CARGO_MANIFEST_DIR"), "/__test__/certificado-a1.pfx");
+    assert!(PdfSigner::from_pfx_file(pfx_path, &password).is_ok());
+
+    // A senha literal prevalece sobre a variável de ambiente
+    let password = pfx_password_from(Some("literal".to_string()), Some(&env_var)).unwrap();
+    assert_eq!(password.as_deref(), Some("literal"));
+    std::env::remove_var(&env_var);
+
+    let err = pfx_password_from(None, Some(&env_var)).unwrap_err();
+    assert!(err.contains("não definida"));
+    // Sem nenhuma das duas, a senha vem do callback
+    assert_eq!(pfx_password_from(None, None), Ok(None));
+  }
+
+  #[test]
+  fn test_build_signature_config_network_timeout() {
+    let config = build_signature_config(Some(Config {
+      network_timeout_ms: Some(1500),
+      ..Config::default()
+    }));
+    assert_eq!(config.network_timeout, Duration::from_millis(1500));
+
+    // Sem networkTimeoutMs, vale o padrão da biblioteca
+    let config = build_signature_config(Some(Config::default()));
+    assert_eq!(config.network_timeout, DEFAULT_NETWORK_TIMEOUT);
+    assert_eq!(
+      build_signature_config(None).network_timeout,
+      DEFAULT_NETWORK_TIMEOUT
+    );
+  }
+
+  #[test]
+  fn test_build_signature_config_pades_level_and_tsa() {
+    // Sem padesLevel, B-B sem TSA: nada acessa a rede
+    let config = build_signature_config(None);
+    assert_eq!(config.pades_level, PadesLevel::BB);
+    assert_eq!(config.tsa_url, None);
+    let config = build_signature_config(Some(Config::default()));
+    assert_eq!(config.pades_level, PadesLevel::BB);
+    assert_eq!(config.tsa_url, None);
+
+    // A partir de BT, sem tsaUrl vale a TSA do ITI
+    for (level, expected) in [
+      (SignatureLevel::BT, PadesLevel::BT),
+      (SignatureLevel::BLT, PadesLevel::BLT),
+      (SignatureLevel::BLTA, PadesLevel::BLTA),
+    ] {
+      let config = build_signature_config(Some(Config {
+        pades_level: Some(level),
+        ..Config::default()
+      }));
+      assert_eq!(config.pades_level, expected);
+      assert_eq!(config.tsa_url.as_deref(), Some(ITI_TSA_URL));
+    }
+
+    // tsaUrl informada prevalece, e em B-B ela é mantida
+    let config = build_signature_config(Some(Config {
+      pades_level: Some(SignatureLevel::BT),
+      tsa_url: Some("http://tsa.exemplo.com.br".to_string()),
+      ..Config::default()
+    }));
+    assert_eq!(config.tsa_url.as_deref(), Some("http://tsa.exemplo.com.br"));
+    let config = build_signature_config(Some(Config {
+      pades_level: Some(SignatureLevel::BB),
+      tsa_url: Some("http://tsa.exemplo.com.br".to_string()),
+      ..Config::default()
+    }));
+    assert_eq!(config.pades_level, PadesLevel::BB);
+    assert_eq!(config.tsa_url.as_deref(), Some("http://tsa.exemplo.com.br"));
+  }
+
+  #[test]
+  fn test_build_signature_config_validate_icp_brasil() {
+    assert!(!build_signature_config(None).validate_icp_brasil);
+    assert!(!build_signature_config(Some(Config::default())).validate_icp_brasil);
+
+    let config = build_signature_config(Some(Config {
+      validate_icp_brasil: Some(true),
+      ..Config::default()
+    }));
+    assert!(config.validate_icp_brasil);
+
+    // Âncoras informadas ligam a validação da cadeia
+    let config = build_signature_config(Some(Config {
+      trust_anchors_path: Some("/etc/icp-brasil".to_string()),
+      ..Config::default()
+    }));
+    assert!(config.validate_icp_brasil);
+    assert_eq!(config.trust_anchors_mode, TrustAnchorsMode::Supplement);
+
+    // validateIcpBrasil: false prevalece sobre as âncoras
+    let config = build_signature_config(Some(Config {
+      trust_anchors_path: Some("/etc/icp-brasil".to_string()),
+      replace_trust_anchors: Some(true),
+      validate_icp_brasil: Some(false),
+      ..Config::default()
+    }));
+    assert!(!config.validate_icp_brasil);
+    assert_eq!(config.trust_anchors_mode, TrustAnchorsMode::Replace);
+  }
+}
@@ -0,0 +1,195 @@
+/// Chamadas HTTP para TSA, OCSP e CRL com novas tentativas
+///
+/// Os servidores públicos (em especial timestamp.iti.gov.br) falham de forma
+/// intermitente, então erros de transporte e respostas 5xx/429 são repetidos
+/// com backoff exponencial e jitter antes de desistir
+use std::thread;
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::StatusCode;
+
+use crate::signature_config::{RetryPolicy, TsaAuth};
+
+/// Resultado de uma chamada de rede; o erro carrega a última falha observada
+pub type NetworkResult<T> = std::result::Result<T, String>;
+
+/// Cria o cliente HTTP usado em todas as chamadas de saída
+///
+/// O tempo limite cobre a requisição inteira (conexão, envio e leitura da resposta)
+pub fn http_client(timeout: Duration) -> NetworkResult<Client> {
+  Client::builder()
+    .timeout(timeout)
+    .connect_timeout(timeout)
+    .build()
+    .map_err(|e| format!("Erro ao criar cliente HTTP: {}", e))
+}
+
+/// Envia um POST com corpo binário e retorna o corpo da resposta
+///
+/// `auth` vai no cabeçalho Authorization de cada tentativa
+pub fn post_with_retry(
+  client: &Client,
+  url: &str,
+  content_type: &str,
+  body: &[u8],
+  policy: &RetryPolicy,
+  auth: Option<&TsaAuth>,
+) -> NetworkResult<Vec<u8>> {
+  send_with_retry(policy, || {
+    let request = client
+      .post(url)
+      .header("Content-Type", content_type)
+      .body(body.to_vec());
+    match auth {
+      Some(TsaAuth::Basic { user, password }) => request.basic_auth(user, Some(password)),
+      Some(TsaAuth::Bearer(token)) => request.bearer_auth(token),
+      None => request,
+    }
+  })
+}
+
+/// Envia um GET e retorna o corpo da resposta
+pub fn get_with_retry(client: &Client, url: &str, policy: &RetryPolicy) -> NetworkResult<Vec<u8>> {
+  send_with_retry(policy, || client.get(url))
+}
+
+fn send_with_retry<F>(policy: &RetryPolicy, build_request: F) -> NetworkResult<Vec<u8>>
+where
+  F: Fn() -> RequestBuilder,
+{
+  let mut attempt = 0;
+  loop {
+    let (error, retryable) = match build_request().send() {
+      Ok(response) => {
+        let status = response.status();
+        if status.is_success() {
+          return response
+            .bytes()
+            .map(|body| body.to_vec())
+            .map_err(|e| format!("Erro ao ler resposta: {}", e));
+        }
+        (format!("HTTP {}", status), is_retryable_status(status))
+      }
+      Err(e) if e.is_timeout() => (format!("tempo limite excedido: {}", e), true),
+      Err(e) => (e.to_string(), true),
+    };
+
+    if !retryable || attempt >= policy.max_retries {
+      return Err(format!("{} (após {} tentativa(s))", error, attempt + 1));
+    }
+
+    thread::sleep(backoff_delay(policy, attempt));
+    attempt += 1;
+  }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+  status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Atraso antes da próxima tentativa: base * 2^tentativa, mais até 50% de jitter
+pub fn backoff_delay(policy: &RetryPolicy, attempt: u32) -> Duration {
+  let exponential = policy
+    .base_delay
+    .saturating_mul(2u32.saturating_pow(attempt.min(16)));
+  let jitter_ms = (exponential.as_millis() / 2) as u64;
+  let jitter = if jitter_ms > 0 {
+    Duration::from_millis(rand::thread_rng().gen_range(0..=jitter_ms))
+  } else {
+    Duration::ZERO
+  };
+  exponential + jitter
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Instant;
+
+  use crate::test_support::{MockResponse, MockServer};
+
+  fn fast_policy(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+      max_retries,
+      base_delay: Duration::from_millis(1),
+    }
+  }
+
+  #[test]
+  fn test_backoff_delay_grows_exponentially() {
+    let policy = RetryPolicy {
+      max_retries: 5,
+      base_delay: Duration::from_millis(100),
+    };
+    for attempt in 0..4 {
+      let delay = backoff_delay(&policy, attempt);
+      let base = 100 * 2u64.pow(attempt);
+      assert!(delay >= Duration::from_millis(base));
+      assert!(delay <= Duration::from_millis(base + base / 2));
+    }
+  }
+
+  #[test]
+  fn test_post_retries_server_errors() {
+    let server = MockServer::start(|request_index, _| {
+      if request_index < 2 {
+        MockResponse::status(503)
+      } else {
+        MockResponse::ok(b"resposta".to_vec())
+      }
+    });
+
+    let client = http_client(Duration::from_secs(5)).unwrap();
+    let body = post_with_retry(
+      &client,
+      &server.url(),
+      "application/octet-stream",
+      b"req",
+      &fast_policy(3),
+      None,
+    )
+    .unwrap();
+
+    assert_eq!(body, b"resposta");
+    assert_eq!(server.request_count(), 3);
+    assert!(server
+      .requests()
+      .iter()
+      .all(|request| request.method == "POST" && request.path == "/" && request.body == b"req"));
+  }
+
+  #[test]
+  fn test_retries_exhausted_reports_last_error() {
+    let server = MockServer::start(|_, _| MockResponse::status(500));
+
+    let client = http_client(Duration::from_secs(5)).unwrap();
+    let err = get_with_retry(&client, &server.url(), &fast_policy(2)).unwrap_err();
+
+    assert!(err.contains("HTTP 500"));
+    assert_eq!(server.request_count(), 3);
+  }
+
+  #[test]
+  fn test_timeout_fails_promptly() {
+    let server =
+      MockServer::start(|_, _| MockResponse::ok(Vec::new()).delayed(Duration::from_secs(5)));
+
+    let client = http_client(Duration::from_millis(200)).unwrap();
+    let started = Instant::now();
+    let err = get_with_retry(&client, &server.url(), &fast_policy(0)).unwrap_err();
+
+    assert!(err.contains("tempo limite excedido"));
+    assert!(started.elapsed() < Duration::from_secs(2));
+  }
+
+  #[test]
+  fn test_client_errors_are_not_retried() {
+    let server = MockServer::start(|_, _| MockResponse::status(404));
+
+    let client = http_client(Duration::from_secs(5)).unwrap();
+    assert!(get_with_retry(&client, &server.url(), &fast_policy(3)).is_err());
+    assert_eq!(server.request_count(), 1);
+  }
+}
@@ -0,0 +1,209 @@
+/// Construção do CMS SignedData usando a API CMS de baixo nível do OpenSSL
+///
+/// A API de alto nível (`Pkcs7::sign`) sempre usa o digest padrão da chave,
+/// aqui o signatário é adicionado explicitamente com o algoritmo escolhido
+use std::os::raw::{c_int, c_uint, c_void};
+use std::ptr;
+
+use chrono::{DateTime, Utc};
+use foreign_types::{ForeignType, ForeignTypeRef};
+use openssl::asn1::Asn1Time;
+use openssl::cms::{CMSOptions, CmsContentInfo};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{Id, PKey, PKeyRef, Private};
+use openssl::sign::Verifier;
+use openssl::stack::StackRef;
+use openssl::x509::{X509Ref, X509};
+
+use crate::cms_signer::CmsContent;
+use crate::error::{PdfSignError, Result};
+use crate::signature_config::{DigestAlgorithm, SignatureScheme};
+
+extern "C" {
+  fn CMS_add1_signer(
+    cms: *mut openssl_sys::CMS_ContentInfo,
+    signer: *mut openssl_sys::X509,
+    pk: *mut openssl_sys::EVP_PKEY,
+    md: *const openssl_sys::EVP_MD,
+    flags: c_uint,
+  ) -> *mut c_void;
+
+  fn CMS_SignerInfo_get0_pkey_ctx(si: *mut c_void) -> *mut openssl_sys::EVP_PKEY_CTX;
+
+  fn CMS_signed_add1_attr_by_NID(
+    si: *mut c_void,
+    nid: c_int,
+    attr_type: c_int,
+    bytes: *const c_void,
+    len: c_int,
+  ) -> c_int;
+
+  fn CMS_final(
+    cms: *mut openssl_sys::CMS_ContentInfo,
+    data: *mut openssl_sys::BIO,
+    dcont: *mut openssl_sys::BIO,
+    flags: c_uint,
+  ) -> c_int;
+
+  fn CMS_final_digest(
+    cms: *mut openssl_sys::CMS_ContentInfo,
+    md: *const u8,
+    mdlen: c_uint,
+    dcont: *mut openssl_sys::BIO,
+    flags: c_uint,
+  ) -> c_int;
+}
+
+/// Parâmetros do signatário para a construção do CMS
+pub struct CmsParams<'a> {
+  pub cert: &'a X509Ref,
+  pub pkey: &'a PKeyRef<Private>,
+  pub chain: &'a StackRef<X509>,
+  pub digest: DigestAlgorithm,
+  pub scheme: SignatureScheme,
+  pub detached: bool,
+  /// Instante do atributo signingTime (o mesmo do /M no PDF)
+  pub signing_time: DateTime<Utc>,
+}
+
+/// Salt do PSS com o tamanho do digest (RSA_PSS_SALTLEN_DIGEST)
+const RSA_PSS_SALTLEN_DIGEST: c_int = -1;
+
+fn message_digest(digest: DigestAlgorithm) -> MessageDigest {
+  match digest {
+    DigestAlgorithm::Sha1 => MessageDigest::sha1(),
+    DigestAlgorithm::Sha256 => MessageDigest::sha256(),
+    DigestAlgorithm::Sha384 => MessageDigest::sha384(),
+    DigestAlgorithm::Sha512 => MessageDigest::sha512(),
+  }
+}
+
+fn openssl_error(context: &str) -> PdfSignError {
+  PdfSignError::SigningError(format!("{}: {:?}", context, ErrorStack::get()))
+}
+
+/// Assina o conteúdo e retorna o ContentInfo SignedData em DER
+pub fn sign(params: &CmsParams<'_>, content: CmsContent<'_>) -> Result<Vec<u8>> {
+  // NOSMIMECAP: remove S/MIME capabilities (não usado em PDF)
+  // PARTIAL: permite adicionar o signatário com o digest escolhido antes de finalizar
+  let mut flags = CMSOptions::BINARY | CMSOptions::NOSMIMECAP | CMSOptions::PARTIAL;
+  if params.detached {
+    flags |= CMSOptions::DETACHED;
+  }
+  // KEY_PARAM: mantém o contexto da chave aberto para configurar o padding PSS
+  if params.scheme == SignatureScheme::Pss {
+    flags |= CMSOptions::KEY_PARAM;
+  }
+  let md = message_digest(params.digest);
+
+  unsafe {
+    let raw = openssl_sys::CMS_sign(
+      ptr::null_mut(),
+      ptr::null_mut(),
+      params.chain.as_ptr(),
+      ptr::null_mut(),
+      flags.bits(),
+    );
+    if raw.is_null() {
+      return Err(openssl_error("Erro ao criar CMS"));
+    }
+    let cms = CmsContentInfo::from_ptr(raw);
+
+    let signer_info = CMS_add1_signer(
+      cms.as_ptr(),
+      params.cert.as_ptr(),
+      params.pkey.as_ptr(),
+      md.as_ptr(),
+      flags.bits(),
+    );
+    if signer_info.is_null() {
+      return Err(openssl_error("Erro ao adicionar signatário ao CMS"));
+    }
+
+    // signingTime explícito: sem ele o OpenSSL usa o relógio ao finalizar, que pode
+    // divergir do /M já gravado no PDF
+    let signing_time = Asn1Time::from_unix(params.signing_time.timestamp())
+      .map_err(|e| PdfSignError::SigningError(format!("Erro ao montar signingTime: {:?}", e)))?;
+    let time_type = openssl_sys::ASN1_STRING_type(signing_time.as_ptr() as *const _);
+    if CMS_signed_add1_attr_by_NID(
+      signer_info,
+      openssl_sys::NID_pkcs9_signingTime,
+      time_type,
+      signing_time.as_ptr() as *const c_void,
+      -1,
+    ) <= 0
+    {
+      return Err(openssl_error("Erro ao adicionar signingTime ao CMS"));
+    }
+
+    // O OpenSSL grava id-RSASSA-PSS com hash, MGF1 e salt a partir do contexto
+    if params.scheme == SignatureScheme::Pss {
+      let pkey_ctx = CMS_SignerInfo_get0_pkey_ctx(signer_info);
+      if pkey_ctx.is_null()
+        || openssl_sys::EVP_PKEY_CTX_set_rsa_padding(pkey_ctx, openssl_sys::RSA_PKCS1_PSS_PADDING)
+          <= 0
+        || openssl_sys::EVP_PKEY_CTX_set_rsa_pss_saltlen(pkey_ctx, RSA_PSS_SALTLEN_DIGEST) <= 0
+        || openssl_sys::EVP_PKEY_CTX_set_rsa_mgf1_md(pkey_ctx, md.as_ptr() as *mut _) <= 0
+      {
+        return Err(openssl_error("Erro ao configurar RSASSA-PSS"));
+      }
+    }
+
+    let finalized = match content {
+      CmsContent::Data(data) => {
+        let bio = openssl_sys::BIO_new_mem_buf(data.as_ptr() as *const c_void, data.len() as c_int);
+        if bio.is_null() {
+          return Err(openssl_error("Erro ao criar BIO"));
+        }
+        let finalized = CMS_final(cms.as_ptr(), bio, ptr::null_mut(), flags.bits());
+        openssl_sys::BIO_free_all(bio);
+        finalized
+      }
+      // messageDigest vem pronto: o OpenSSL só assina os atributos
+      CmsContent::Digest(hash) => {
+        if !params.detached {
+          return Err(PdfSignError::SigningError(
+            "CMS a partir do digest só pode ser detached".to_string(),
+          ));
+        }
+        CMS_final_digest(
+          cms.as_ptr(),
+          hash.as_ptr(),
+          hash.len() as c_uint,
+          ptr::null_mut(),
+          flags.bits(),
+        )
+      }
+    };
+    if finalized <= 0 {
+      return Err(openssl_error("Erro ao finalizar CMS"));
+    }
+
+    cms
+      .to_der()
+      .map_err(|e| PdfSignError::SigningError(format!("Erro ao serializar CMS: {:?}", e)))
+  }
+}
+
+/// Verifica a assinatura dos atributos com a chave pública (SPKI em DER)
+///
+/// Cobre as chaves que o backend Rust não verifica (Ed25519, EC)
+pub fn verify_signature(
+  spki: &[u8],
+  digest: DigestAlgorithm,
+  signed_attrs: &[u8],
+  signature: &[u8],
+) -> Result<bool> {
+  let pkey = PKey::public_key_from_der(spki)
+    .map_err(|e| PdfSignError::DecodingError(format!("Erro ao carregar chave pública: {:?}", e)))?;
+  // Ed25519 assina a mensagem inteira, sem digest separado
+  let verifier = if pkey.id() == Id::ED25519 {
+    Verifier::new_without_digest(&pkey)
+  } else {
+    Verifier::new(message_digest(digest), &pkey)
+  };
+  verifier
+    .and_then(|mut verifier| verifier.verify_oneshot(signature, signed_attrs))
+    .map_err(|e| PdfSignError::SigningError(format!("Erro ao verificar assinatura: {:?}", e)))
+}
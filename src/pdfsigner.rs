@@ -1,23 +1,92 @@
+#[cfg(feature = "native")]
 use base64::Engine;
+use chrono::{DateTime, SubsecRound, Utc};
+#[cfg(feature = "native")]
 use rsa::pkcs8::DecodePrivateKey;
 use rsa::RsaPrivateKey;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::Path;
+#[cfg(feature = "native")]
+use std::sync::OnceLock;
 
-use crate::certificate::Certificate;
+use crate::appearance::build_appearance_objects;
+use crate::certificate::{Certificate, IcpBrasilKind};
+use crate::cms_signer::{self, CmsContent, KeyType};
 use crate::error::{PdfSignError, Result};
-use crate::signature_config::SignatureConfig;
+#[cfg(feature = "native")]
+use crate::openssl_cms::{self, CmsParams};
+#[cfg(feature = "pkcs11")]
+use crate::pkcs11::TokenKey;
+#[cfg(feature = "native")]
+use crate::revocation::{collect_revocation_data, RevocationData};
+use crate::rust_cms::{self, RustCmsParams, SignerKey};
+#[cfg(feature = "native")]
+use crate::sig_dict::{push_span, BYTE_RANGE_PLACEHOLDER};
+use crate::sig_dict::{SigDictBuilder, DATE_PLACEHOLDER};
+use crate::signature_config::{
+  AppearanceContent, CmsBackend, ContentsPadding, DigestAlgorithm, PadesLevel, PadesLevelMode,
+  SignatureConfig, SubFilter, XrefFormat,
+};
+use crate::signatures::{byte_range_digest, checked_byte_range, list_signatures, SignatureEntry};
+#[cfg(feature = "native")]
+use crate::timestamp;
+use crate::transforms::{build_lock_dict, build_reference_entry, SigReference};
+#[cfg(feature = "native")]
+use crate::trust_store;
+#[cfg(feature = "native")]
+use crate::utils::pdf_name;
 use crate::utils::{
-  extract_catalog_info, extract_first_page_info, get_next_object_number, remove_trailing_newline,
+  dict_body, dict_entries, dict_entry_value, dict_has_key, endobj_position, extract_catalog_info,
+  extract_first_page_info, extract_page_mediabox, find_docmdp_permission, find_object_section,
+  find_page_object, find_signature_field, find_startxref, get_next_object_number, is_linearized,
+  is_xref_section, object_generation, parse_object_header, parse_pdf_version, parse_trailer,
+  pdf_literal_string, read_number_after, read_pdf_skeleton, read_ref_array, read_reference,
+  read_xref_stream, remove_dict_entry, remove_trailing_newline, resolve_array, resolve_dict,
+  trailing_newline_len, TrailerInfo,
 };
 
+/// Tamanho dos blocos lidos do arquivo em `sign_pdf_file_to_file`
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Bytes lidos no offset do startxref para confirmar que ali começa uma xref
+const XREF_PROBE_LEN: usize = 4096;
+
+/// Motivo gravado quando a configuração não informa um e o certificado é ICP-Brasil
+const ICP_BRASIL_REASON: &str = "Assinatura digital conforme ICP-Brasil";
+
+/// Motivo padrão do selo de um e-CNPJ ICP-Brasil
+const ICP_BRASIL_SEAL_REASON: &str = "Selo digital de pessoa jurídica conforme ICP-Brasil";
+
+/// Carrega uma única vez os providers "legacy" e "default" do OpenSSL 3.x
+///
+/// O legado traz algoritmos antigos de PFX como RC2-40-CBC. Os guards ficam vivos até
+/// o fim do processo: soltá-los ao fim de cada chamada descarregava o provider enquanto
+/// outra thread ainda o usava, e assinaturas em lote falhavam de forma intermitente
+#[cfg(feature = "native")]
+fn load_openssl_providers() {
+  use openssl::provider::Provider;
+
+  static PROVIDERS: OnceLock<(Option<Provider>, Option<Provider>)> = OnceLock::new();
+  PROVIDERS.get_or_init(|| {
+    (
+      Provider::load(None, "legacy").ok(),
+      Provider::load(None, "default").ok(),
+    )
+  });
+}
+
 /// Estrutura principal para assinatura de PDFs
 pub struct PdfSigner {
-  _private_key: RsaPrivateKey,
+  // Ausente quando a chave do PFX não é RSA (ex: Ed25519, só pelo backend OpenSSL)
+  _private_key: Option<RsaPrivateKey>,
   _certificate: Certificate,
   _cert_chain: Vec<Certificate>,
   _pem_content: String,
+  // Chave em token PKCS#11; quando presente, o CMS é sempre montado pelo backend Rust
+  #[cfg(feature = "pkcs11")]
+  _token_key: Option<TokenKey>,
 }
 
 impl PdfSigner {
@@ -27,73 +96,214 @@ impl PdfSigner {
     Self::from_pfx_bytes(&pfx_data, password)
   }
 
+  /// Cria um assinador com um certificado do repositório do sistema (Windows/macOS)
+  ///
+  /// `subject_or_thumbprint` é o CN do titular ou a impressão digital SHA-1 em hex.
+  /// A assinatura com a chave do repositório (CNG, Keychain) ainda não é suportada em
+  /// nenhuma plataforma: retorna `UnsupportedPlatform` orientando a exportar o
+  /// certificado A1 como PFX ou, nos tokens A3, a usar `from_pkcs11`
+  #[cfg(feature = "system-keystore")]
+  pub fn from_system_cert(subject_or_thumbprint: &str) -> Result<Self> {
+    let guidance = if cfg!(any(target_os = "windows", target_os = "macos")) {
+      "a assinatura com a chave do repositório do sistema ainda não é suportada"
+    } else {
+      "o repositório de certificados do sistema só existe no Windows e no macOS"
+    };
+    Err(PdfSignError::UnsupportedPlatform(format!(
+      "certificado \"{}\": {}; exporte o certificado (A1) como PFX e use from_pfx_file, \
+       ou use from_pkcs11 (feature pkcs11) com o módulo do token A3",
+      subject_or_thumbprint, guidance
+    )))
+  }
+
+  /// Cria um assinador com a chave de um token PKCS#11 (certificado A3, HSM)
+  ///
+  /// `slot` é o ID do slot no módulo e `key_label` o CKA_LABEL da chave privada. O
+  /// certificado do signatário e a cadeia são lidos do token; como a chave não sai
+  /// dele, o CMS é montado pelo backend Rust qualquer que seja `cms_backend`
+  #[cfg(feature = "pkcs11")]
+  pub fn from_pkcs11<P: AsRef<Path>>(
+    module_path: P,
+    slot: u64,
+    pin: &str,
+    key_label: &str,
+  ) -> Result<Self> {
+    let identity = crate::pkcs11::open(module_path.as_ref(), slot, pin, key_label)?;
+    Ok(Self {
+      _private_key: None,
+      _certificate: identity.certificate,
+      _cert_chain: identity.chain,
+      _pem_content: String::new(),
+      _token_key: Some(identity.key),
+    })
+  }
+
   /// Cria um novo assinador a partir de bytes PFX/P12
   pub fn from_pfx_bytes(pfx_data: &[u8], password: &str) -> Result<Self> {
-    Self::from_pfx_bytes_openssl(pfx_data, password)
+    Self::load_pfx(pfx_data, password, None)
+  }
+
+  /// Cria um assinador com um certificado específico de um PFX com várias identidades
+  ///
+  /// O certificado escolhido por `signer` assina com a chave privada correspondente;
+  /// os demais certificados do arquivo viram a cadeia
+  pub fn from_pfx_file_with_signer<P: AsRef<Path>>(
+    path: P,
+    password: &str,
+    signer: &SignerSelector,
+  ) -> Result<Self> {
+    let pfx_data = fs::read(path)?;
+    Self::from_pfx_bytes_with_signer(&pfx_data, password, signer)
+  }
+
+  /// Versão de [`PdfSigner::from_pfx_file_with_signer`] para bytes PFX/P12
+  pub fn from_pfx_bytes_with_signer(
+    pfx_data: &[u8],
+    password: &str,
+    signer: &SignerSelector,
+  ) -> Result<Self> {
+    Self::load_pfx(pfx_data, password, Some(signer))
+  }
+
+  fn load_pfx(pfx_data: &[u8], password: &str, signer: Option<&SignerSelector>) -> Result<Self> {
+    #[cfg(feature = "native")]
+    return Self::from_pfx_bytes_openssl(pfx_data, password, signer);
+    #[cfg(not(feature = "native"))]
+    return Self::from_pfx_bytes_rust(pfx_data, password, signer);
+  }
+
+  /// Extrai chave e certificados em Rust puro (build sem OpenSSL)
+  #[cfg(not(feature = "native"))]
+  fn from_pfx_bytes_rust(
+    pfx_data: &[u8],
+    password: &str,
+    signer: Option<&SignerSelector>,
+  ) -> Result<Self> {
+    let contents = crate::pfx::parse(pfx_data, password, signer)?;
+    let mut certificates = contents.certificates.into_iter().map(Certificate::from_der);
+
+    let certificate = certificates
+      .next()
+      .ok_or(PdfSignError::InvalidCertificate)??;
+    let cert_chain = certificates.filter_map(|cert| cert.ok()).collect();
+
+    Ok(Self {
+      _private_key: Some(contents.private_key),
+      _certificate: certificate,
+      _cert_chain: cert_chain,
+      _pem_content: String::new(),
+      #[cfg(feature = "pkcs11")]
+      _token_key: None,
+    })
   }
 
   /// Extrai chave e certificados usando o openssl crate
-  fn from_pfx_bytes_openssl(pfx_data: &[u8], password: &str) -> Result<Self> {
+  #[cfg(feature = "native")]
+  fn from_pfx_bytes_openssl(
+    pfx_data: &[u8],
+    password: &str,
+    signer: Option<&SignerSelector>,
+  ) -> Result<Self> {
     use openssl::pkcs12::Pkcs12;
-    use openssl::provider::Provider;
+    use openssl::pkey::PKey;
 
-    // Carrega o provider legado e o provider padrão do OpenSSL 3.x
-    // Isso é necessário para suportar algoritmos antigos como RC2-40-CBC
-    let _legacy = Provider::load(None, "legacy").ok();
-    let _default = Provider::load(None, "default").ok();
+    load_openssl_providers();
 
     let pkcs12 = Pkcs12::from_der(pfx_data)
       .map_err(|e| PdfSignError::DecodingError(format!("Erro ao parsear PKCS#12: {:?}", e)))?;
 
+    // "mac verify failure" é a senha errada; o resto é arquivo corrompido ou não suportado
     let parsed = pkcs12.parse2(password).map_err(|e| {
-      PdfSignError::DecodingError(format!("Erro ao descriptografar PKCS#12: {:?}", e))
+      if e
+        .errors()
+        .iter()
+        .any(|error| error.reason() == Some("mac verify failure"))
+      {
+        PdfSignError::InvalidPassword
+      } else {
+        PdfSignError::DecodingError(format!("Erro ao descriptografar PKCS#12: {:?}", e))
+      }
     })?;
 
-    // Cria conteúdo PEM ANTES de consumir o parsed
-    let pem_content = Self::create_pem_from_openssl(&parsed)?;
+    let pkey = parsed.pkey.ok_or_else(|| {
+      PdfSignError::DecodingError("Nenhuma chave privada encontrada no PKCS#12".to_string())
+    })?;
+    // O certificado da chave primeiro, depois a cadeia
+    let mut certs: Vec<openssl::x509::X509> = parsed
+      .cert
+      .into_iter()
+      .chain(parsed.ca.into_iter().flatten())
+      .collect();
+    if certs.is_empty() {
+      return Err(PdfSignError::InvalidCertificate);
+    }
 
-    // Extrai a chave privada
-    let private_key_der = if let Some(pkey) = parsed.pkey {
-      pkey.private_key_to_der().map_err(|e| {
-        PdfSignError::DecodingError(format!("Erro ao exportar chave privada: {:?}", e))
-      })?
-    } else {
-      return Err(PdfSignError::DecodingError(
-        "Nenhuma chave privada encontrada no PKCS#12".to_string(),
-      ));
+    // O OpenSSL só devolve a primeira chave do arquivo; com várias identidades, a do
+    // certificado escolhido vem da leitura de todas as bags
+    let pkey = match signer {
+      // Ferramentas fora do OpenSSL podem deixar o certificado da chave só entre os da
+      // cadeia (sem `cert`): o signatário é o que carrega a chave pública do par
+      None => {
+        let pos = certs
+          .iter()
+          .position(|cert| cert.public_key().is_ok_and(|key| key.public_eq(&pkey)))
+          .ok_or(PdfSignError::InvalidCertificate)?;
+        let cert = certs.remove(pos);
+        certs.insert(0, cert);
+        pkey
+      }
+      Some(signer) => {
+        let pos = certs
+          .iter()
+          .position(|cert| cert.to_der().is_ok_and(|der| signer.matches(&der)))
+          .ok_or_else(|| signer.not_found())?;
+        let cert = certs.remove(pos);
+        let cert_key = cert.public_key().map_err(|e| {
+          PdfSignError::DecodingError(format!("Erro ao ler chave pública: {:?}", e))
+        })?;
+        certs.insert(0, cert);
+        if cert_key.public_eq(&pkey) {
+          pkey
+        } else {
+          let (keys, _) = crate::pfx::bags(pfx_data, password).unwrap_or_default();
+          keys
+            .iter()
+            .filter_map(|der| PKey::private_key_from_pkcs8(der).ok())
+            .find(|key| cert_key.public_eq(key))
+            .ok_or_else(|| signer.key_not_found())?
+        }
+      }
     };
 
-    // Extrai certificados
+    let pem_content = Self::create_pem_from_openssl(&pkey, &certs)?;
+
+    let private_key_der = pkey.private_key_to_der().map_err(|e| {
+      PdfSignError::DecodingError(format!("Erro ao exportar chave privada: {:?}", e))
+    })?;
+    let key_id = pkey.id();
+
     let mut cert_ders = Vec::new();
-    if let Some(cert) = parsed.cert {
+    for cert in &certs {
       let cert_der = cert.to_der().map_err(|e| {
         PdfSignError::DecodingError(format!("Erro ao exportar certificado: {:?}", e))
       })?;
       cert_ders.push(cert_der);
     }
 
-    if let Some(chain) = parsed.ca {
-      for cert in chain {
-        let cert_der = cert.to_der().map_err(|e| {
-          PdfSignError::DecodingError(format!("Erro ao exportar certificado da cadeia: {:?}", e))
+    // Decodifica a chave privada RSA; Ed25519 e EC ficam só no PEM, usado pelo OpenSSL
+    let private_key = if key_id != openssl::pkey::Id::RSA {
+      None
+    } else {
+      let key = RsaPrivateKey::from_pkcs8_der(&private_key_der)
+        .or_else(|_| {
+          use rsa::pkcs1::DecodeRsaPrivateKey;
+          RsaPrivateKey::from_pkcs1_der(&private_key_der)
+        })
+        .map_err(|e| {
+          PdfSignError::DecodingError(format!("Erro ao decodificar chave privada: {:?}", e))
         })?;
-        cert_ders.push(cert_der);
-      }
-    }
-
-    if cert_ders.is_empty() {
-      return Err(PdfSignError::InvalidCertificate);
-    }
-
-    // Decodifica a chave privada RSA
-    let private_key: RsaPrivateKey = RsaPrivateKey::from_pkcs8_der(&private_key_der)
-      .or_else(|_| {
-        use rsa::pkcs1::DecodeRsaPrivateKey;
-        RsaPrivateKey::from_pkcs1_der(&private_key_der)
-      })
-      .map_err(|e| {
-        PdfSignError::DecodingError(format!("Erro ao decodificar chave privada: {:?}", e))
-      })?;
+      Some(key)
+    };
 
     // Parseia o primeiro certificado
     let certificate: Certificate = Certificate::from_der(cert_ders[0].clone())?;
@@ -111,106 +321,582 @@ impl PdfSigner {
       _certificate: certificate,
       _cert_chain: cert_chain,
       _pem_content: pem_content,
+      #[cfg(feature = "pkcs11")]
+      _token_key: None,
     })
   }
 
-  /// Cria conteúdo PEM usando o OpenSSL diretamente
-  fn create_pem_from_openssl(parsed: &openssl::pkcs12::ParsedPkcs12_2) -> Result<String> {
+  /// Cria conteúdo PEM usando o OpenSSL diretamente: chave, certificado e cadeia
+  #[cfg(feature = "native")]
+  fn create_pem_from_openssl(
+    pkey: &openssl::pkey::PKeyRef<openssl::pkey::Private>,
+    certs: &[openssl::x509::X509],
+  ) -> Result<String> {
     let mut pem = String::new();
 
     // Exporta chave privada
-    if let Some(ref pkey) = parsed.pkey {
-      let key_pem = pkey.private_key_to_pem_pkcs8().map_err(|e| {
-        PdfSignError::DecodingError(format!("Erro ao exportar chave privada PEM: {:?}", e))
-      })?;
-      pem.push_str(&String::from_utf8_lossy(&key_pem));
-    }
+    let key_pem = pkey.private_key_to_pem_pkcs8().map_err(|e| {
+      PdfSignError::DecodingError(format!("Erro ao exportar chave privada PEM: {:?}", e))
+    })?;
+    pem.push_str(&String::from_utf8_lossy(&key_pem));
 
-    // Exporta certificado principal
-    if let Some(ref cert) = parsed.cert {
+    // Exporta certificado principal e cadeia
+    for cert in certs {
       let cert_pem = cert.to_pem().map_err(|e| {
         PdfSignError::DecodingError(format!("Erro ao exportar certificado PEM: {:?}", e))
       })?;
       pem.push_str(&String::from_utf8_lossy(&cert_pem));
     }
 
-    // Exporta cadeia de certificados
-    if let Some(ref chain) = parsed.ca {
-      for cert in chain {
-        let cert_pem = cert.to_pem().map_err(|e| {
-          PdfSignError::DecodingError(format!(
-            "Erro ao exportar certificado da cadeia PEM: {:?}",
-            e
-          ))
-        })?;
-        pem.push_str(&String::from_utf8_lossy(&cert_pem));
-      }
-    }
-
     Ok(pem)
   }
 
   /// Assina um PDF a partir de bytes e retorna o buffer assinado
-  pub fn sign_pdf_bytes(&self, mut pdf_data: Vec<u8>, config: &SignatureConfig) -> Result<Vec<u8>> {
+  pub fn sign_pdf_bytes(&self, pdf_data: Vec<u8>, config: &SignatureConfig) -> Result<Vec<u8>> {
+    self
+      .sign_pdf_with_report(pdf_data, config)
+      .map(|(signed, _)| signed)
+  }
+
+  /// Assina um PDF a partir de bytes e retorna o buffer assinado com o relatório da assinatura
+  ///
+  /// O relatório traz o nível PAdES efetivamente produzido, que pode ser menor que o
+  /// pedido quando a configuração não o alcança (ver `PadesLevelMode`)
+  pub fn sign_pdf_with_report(
+    &self,
+    pdf_data: Vec<u8>,
+    config: &SignatureConfig,
+  ) -> Result<(Vec<u8>, SignReport)> {
+    check_input_size(pdf_data.len() as u64, config)?;
+    let (config, mut report) = normalize_pades_level(config)?;
+    let signed = self.sign_normalized(pdf_data, &config, &mut report)?;
+    Ok((signed, report))
+  }
+
+  /// Calcula o que a assinatura faria com o documento, sem assinar
+  ///
+  /// Roda as mesmas verificações e monta a mesma atualização incremental da
+  /// assinatura, mas não cria o CMS nem acessa a rede (TSA, OCSP, CRL); a cadeia
+  /// também não é validada contra as âncoras de confiança
+  pub fn analyze(&self, pdf_data: &[u8], config: &SignatureConfig) -> Result<SignPlan> {
+    check_input_size(pdf_data.len() as u64, config)?;
+    let (config, report) = normalize_pades_level(config)?;
+    check_key_usage(&self._certificate, &config)?;
+    check_reason(&signature_reason(&self._certificate, &config), &config)?;
+    check_document(pdf_data, &config)?;
+    if config.pdfa_mode {
+      check_pdfa_declaration(pdf_data, pdf_data)?;
+    }
+
+    let pdf_data = remove_trailing_newline(pdf_data.to_vec());
+    if let Some(prev_xref) = find_startxref(&pdf_data) {
+      check_prev_xref(pdf_data.get(prev_xref..).unwrap_or_default(), prev_xref)?;
+    }
+    let catalog_info = extract_catalog_info(&pdf_data)?;
+    let first_page_obj = extract_first_page_info(&pdf_data)?.first_page_obj;
+    let field_name = match (&config.fill_existing_field, &config.usage_rights) {
+      (Some(field_name), _) => Some(field_name.clone()),
+      (None, Some(_)) => None,
+      (None, None) => Some(unused_signature_field_name(&pdf_data)),
+    };
+    let update = self.build_incremental_update(&pdf_data, pdf_data.len(), &config)?;
+
+    Ok(SignPlan {
+      catalog_obj: catalog_info.catalog_obj,
+      pages_obj: catalog_info.pages_ref,
+      first_page_obj,
+      // O dicionário de assinatura leva o primeiro número livre, vindo do /Size
+      next_obj: update.sig_obj,
+      has_acroform: catalog_info.has_acroform,
+      field_name,
+      contents_offset: update.base + update.placeholder_pos,
+      contents_len: update.placeholder_len,
+      pades_level: report.pades_level,
+      warnings: report.warnings,
+      linearized: is_linearized(&pdf_data),
+    })
+  }
+
+  /// Assina com a configuração já ajustada ao nível PAdES alcançável; completa o
+  /// relatório com o carimbo do tempo e o que a assinatura gravou
+  fn sign_normalized(
+    &self,
+    mut pdf_data: Vec<u8>,
+    config: &SignatureConfig,
+    report: &mut SignReport,
+  ) -> Result<Vec<u8>> {
+    self.check_signable(&pdf_data, config)?;
+    if config.pdfa_mode {
+      check_pdfa_declaration(pdf_data.as_slice(), &pdf_data)?;
+    }
+
     // CRÍTICO: Remove trailing newlines ANTES de processar (node-signpdf faz isso!)
     pdf_data = remove_trailing_newline(pdf_data);
+    let increment = self.signed_increment(&pdf_data, config)?;
+
+    let mut output = pdf_data;
+    output.extend_from_slice(&increment.bytes);
+    if config.self_verify {
+      verify_last_signature(&output, config.digest_algorithm).map_err(self_verify_failed)?;
+    }
+    report.linearized = is_linearized(&output);
+    report.tsa_url = increment.tsa_url;
+    report.timestamp_time = increment.timestamp_time;
+    report.signature = Some(increment.signature);
+    report.certificate_kind = self._certificate.icp_brasil_id().kind();
+    Ok(output)
+  }
+
+  /// Atualização incremental já assinada para `pdf_data` (sem os fins de linha finais)
+  fn signed_increment(&self, pdf_data: &[u8], config: &SignatureConfig) -> Result<SignedIncrement> {
+    if let Some(prev_xref) = find_startxref(pdf_data) {
+      check_prev_xref(pdf_data.get(prev_xref..).unwrap_or_default(), prev_xref)?;
+    }
+
+    let mut update = self.build_incremental_update(pdf_data, pdf_data.len(), config)?;
+
+    // Conteúdo coberto pelo ByteRange: o original inteiro e a atualização sem o placeholder
+    let mut to_sign = Vec::with_capacity(pdf_data.len() + update.tail.len());
+    to_sign.extend_from_slice(pdf_data);
+    for part in update.signed_tail() {
+      to_sign.extend_from_slice(part);
+    }
+    let timestamp = self.fill_signature(&mut update, CmsContent::Data(&to_sign), config)?;
+    let (tsa_url, timestamp_time) = timestamp.unzip();
+    // O /DSS do B-LT vem depois da revisão assinada e fica fora do ByteRange
+    let byte_range = update.byte_range();
+    self.append_revocation_data(&mut update, config)?;
+    Ok(SignedIncrement {
+      signature: update.record(),
+      bytes: update.tail,
+      base_len: pdf_data.len(),
+      byte_range,
+      tsa_url,
+      timestamp_time,
+    })
+  }
+
+  /// Assina e devolve só a atualização incremental, sem copiar o documento
+  ///
+  /// Para integradores que já guardam o original e só precisam dos bytes a acrescentar
+  /// (dicionário de assinatura, widget, AcroForm, Catalog, xref e trailer). A
+  /// invariante é `&pdf_data[..base_len] + bytes == sign_pdf(pdf_data)`: o original
+  /// perde os fins de linha finais, e o ByteRange e os offsets da xref já contam com
+  /// essa concatenação. Acrescentar `bytes` a qualquer outro conteúdo (inclusive ao
+  /// original com o "\n" final) invalida a assinatura
+  pub fn sign_incremental_only(
+    &self,
+    pdf_data: &[u8],
+    config: &SignatureConfig,
+  ) -> Result<SignedIncrement> {
+    check_input_size(pdf_data.len() as u64, config)?;
+    let (config, _) = normalize_pades_level(config)?;
+    let config = &config;
+    self.check_signable(pdf_data, config)?;
+    if config.pdfa_mode {
+      check_pdfa_declaration(pdf_data, pdf_data)?;
+    }
+
+    let base_len = pdf_data.len() - trailing_newline_len(pdf_data);
+    let base = &pdf_data[..base_len];
+    let increment = self.signed_increment(base, config)?;
+    if config.self_verify {
+      let mut output = Vec::with_capacity(base_len + increment.bytes.len());
+      output.extend_from_slice(base);
+      output.extend_from_slice(&increment.bytes);
+      verify_last_signature(&output, config.digest_algorithm).map_err(self_verify_failed)?;
+    }
+    Ok(increment)
+  }
+
+  /// Acrescenta a assinatura sem o CMS, para ser completada com um CMS feito fora
+  ///
+  /// /ByteRange e /M já saem definitivos e /Contents fica zerado; a outra parte
+  /// assina o digest de `byte_range_digest` sobre o PDF devolvido e o CMS é gravado
+  /// com `complete_signature_for_field`. O campo é `fill_existing_field` ou, sem ele,
+  /// o próximo SignatureN. Não há carimbo do tempo nem /DSS, e a rede não é acessada
+  pub fn prepare_signature(&self, pdf_data: Vec<u8>, config: &SignatureConfig) -> Result<Vec<u8>> {
+    check_input_size(pdf_data.len() as u64, config)?;
+    self.check_signable(&pdf_data, config)?;
+    if config.pdfa_mode {
+      check_pdfa_declaration(pdf_data.as_slice(), &pdf_data)?;
+    }
+
+    let pdf_data = remove_trailing_newline(pdf_data);
+    if let Some(prev_xref) = find_startxref(&pdf_data) {
+      check_prev_xref(pdf_data.get(prev_xref..).unwrap_or_default(), prev_xref)?;
+    }
+    let update = self.build_incremental_update(&pdf_data, pdf_data.len(), config)?;
+    let mut output = pdf_data;
+    output.extend_from_slice(&update.tail);
+    Ok(output)
+  }
+
+  /// Acrescenta só um carimbo do tempo do documento (/Type /DocTimeStamp), sem assinatura
+  ///
+  /// A atualização incremental traz apenas o dicionário do carimbo, com o token RFC 3161
+  /// da TSA em /Contents (/SubFilter /ETSI.RFC3161), e a xref com o trailer: nenhum
+  /// campo, widget ou AcroForm é criado e o Catalog fica como está. O token cobre o
+  /// ByteRange com o `digest_algorithm` da configuração, e a TSA vem de `tsa_url` e
+  /// `tsa_urls`. Serve para selar PDFs de arquivo só com um instante confiável
+  #[cfg(feature = "native")]
+  pub fn add_document_timestamp(pdf_data: Vec<u8>, config: &SignatureConfig) -> Result<Vec<u8>> {
+    check_input_size(pdf_data.len() as u64, config)?;
+    let tsa_urls = config.tsa_candidates();
+    if tsa_urls.is_empty() {
+      return Err(PdfSignError::SigningError(
+        "carimbo do documento exige uma TSA (tsa_url ou tsa_urls)".to_string(),
+      ));
+    }
+    if config.offline {
+      return Err(PdfSignError::SigningError(
+        "carimbo do documento exige acesso à TSA, desligado por offline".to_string(),
+      ));
+    }
+    check_document(&pdf_data, config)?;
+
+    let mut output = remove_trailing_newline(pdf_data);
+    let (trailer_info, next_obj) = update_trailer(&output)?;
+    let prev_xref = trailer_info.startxref.unwrap_or_default();
+    check_prev_xref(output.get(prev_xref..).unwrap_or_default(), prev_xref)?;
+    let catalog_obj = extract_catalog_info(&output)?.catalog_obj;
+    let root = root_reference(&output, &trailer_info, catalog_obj);
+    let next_obj = next_obj as usize;
+
+    // Como no B-T, o token da TSA leva os certificados dela
+    let sig_size = 32000;
+    let mut dict = format!(
+      "{} 0 obj\n<<\n/Type /DocTimeStamp\n/Filter {}\n/SubFilter /ETSI.RFC3161\n/ByteRange ",
+      next_obj,
+      pdf_name(&config.filter)
+    );
+    let byte_range_span = push_span(&mut dict, BYTE_RANGE_PLACEHOLDER);
+    dict.push_str("\n/Contents ");
+    let contents_span = push_span(&mut dict, &format!("<{}>", "0".repeat(sig_size)));
+    dict.push_str("\n>>\nendobj\n");
+
+    // O "\n" separador, o dicionário e a xref; o Catalog continua sendo o /Root
+    let base = output.len();
+    let dict_pos = base + 1;
+    output.push(b'\n');
+    output.extend_from_slice(dict.as_bytes());
+    let xref_start = output.len();
+    let trailer = XrefTrailer {
+      size: next_obj + 1,
+      prev: prev_xref,
+      root,
+    };
+    let section = build_xref_section(
+      &mut vec![(next_obj, dict_pos)],
+      &output[..base],
+      next_obj,
+      xref_start,
+      &trailer,
+      config.xref_format,
+    );
+    output.extend_from_slice(&section);
+
+    let contents = dict_pos + contents_span.start..dict_pos + contents_span.end;
+    let range = [0, contents.start, contents.end, output.len() - contents.end];
+    check_byte_range_coverage(&range, output.len())?;
+    write_byte_range_value(
+      &mut output[dict_pos + byte_range_span.start..dict_pos + byte_range_span.end],
+      &range,
+    )?;
+
+    let mut hasher = config.digest_algorithm.hasher();
+    hasher.update(&output[..contents.start]);
+    hasher.update(&output[contents.end..]);
+    let (token, _) = timestamp::request_timestamp(&tsa_urls, &hasher.finalize(), config)?;
+    let token_hex = pad_signature_hex(&token, sig_size, config.contents_padding)?;
+    output[contents.start + 1..contents.end - 1].copy_from_slice(token_hex.as_bytes());
+    Ok(output)
+  }
+
+  /// Assina um PDF lendo o arquivo em blocos e grava o resultado em `output_path`
+  ///
+  /// O original não é carregado: os scanners trabalham sobre o esqueleto do PDF
+  /// (sem o conteúdo dos streams), o digest do ByteRange é calculado bloco a bloco
+  /// e só a atualização incremental (assinatura, campos, xref) fica em memória.
+  /// `output_path` precisa ser diferente de `input_path`.
+  pub fn sign_pdf_file_to_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    &self,
+    input_path: P,
+    output_path: Q,
+    config: &SignatureConfig,
+  ) -> Result<SignReport> {
+    let (config, report) = normalize_pades_level(config)?;
+    let config = &config;
+    let input_path = input_path.as_ref();
+    let output_path = output_path.as_ref();
+    if fs::canonicalize(output_path).ok() == Some(fs::canonicalize(input_path)?) {
+      return Err(PdfSignError::InvalidPdf(
+        "o PDF assinado não pode sobrescrever o arquivo de entrada".to_string(),
+      ));
+    }
+
+    let mut input = File::open(input_path)?;
+    check_input_size(input.metadata()?.len(), config)?;
+    let base = signed_length(&mut input)?;
+
+    input.rewind()?;
+    let skeleton = read_pdf_skeleton((&mut input).take(base as u64))?;
+    self.check_signable(&skeleton, config)?;
+    // O esqueleto descarta o XMP: o /Metadata do Catalog é lido do próprio arquivo
+    if config.pdfa_mode {
+      input.rewind()?;
+      check_pdfa_declaration((&mut input).take(base as u64), &skeleton)?;
+    }
+    // O esqueleto não preserva os offsets: a xref anterior é conferida no próprio arquivo
+    if let Some(prev_xref) = find_startxref(&skeleton) {
+      let mut section = Vec::new();
+      if prev_xref < base {
+        input.seek(SeekFrom::Start(prev_xref as u64))?;
+        (&mut input)
+          .take(XREF_PROBE_LEN.min(base - prev_xref) as u64)
+          .read_to_end(&mut section)?;
+      }
+      check_prev_xref(&section, prev_xref)?;
+    }
+    let mut update = self.build_incremental_update(&skeleton, base, config)?;
+
+    // Digest do ByteRange: o original lido em blocos, seguido da atualização sem o placeholder
+    input.rewind()?;
+    let mut hasher = config.digest_algorithm.hasher();
+    let mut reader = (&mut input).take(base as u64);
+    let mut chunk = vec![0u8; STREAM_CHUNK_SIZE];
+    loop {
+      let read = reader.read(&mut chunk)?;
+      if read == 0 {
+        break;
+      }
+      hasher.update(&chunk[..read]);
+    }
+    for part in update.signed_tail() {
+      hasher.update(part);
+    }
+    let hash = hasher.finalize();
+    let timestamp = self.fill_signature(&mut update, CmsContent::Digest(&hash), config)?;
+    self.append_revocation_data(&mut update, config)?;
+
+    input.rewind()?;
+    let mut output = BufWriter::new(File::create(output_path)?);
+    io::copy(&mut (&mut input).take(base as u64), &mut output)?;
+    output.write_all(&update.tail)?;
+    output.flush()?;
+    drop(output);
+
+    // Um arquivo com assinatura inválida não fica no lugar do PDF assinado
+    if config.self_verify {
+      if let Err(error) = verify_signed_file(output_path, &update.tail, config.digest_algorithm) {
+        let _ = fs::remove_file(output_path);
+        return Err(self_verify_failed(error));
+      }
+    }
+    let (tsa_url, timestamp_time) = timestamp.unzip();
+    Ok(SignReport {
+      tsa_url,
+      timestamp_time,
+      signature: Some(update.record()),
+      linearized: is_linearized(&skeleton),
+      certificate_kind: self._certificate.icp_brasil_id().kind(),
+      ..report
+    })
+  }
+
+  /// Assina um PDF mapeado em memória (somente leitura) e grava o resultado em `output_path`
+  ///
+  /// Ao contrário de `sign_pdf_with_path`, o original não é copiado para um `Vec`: os
+  /// scanners trabalham sobre o esqueleto do PDF, como em `sign_pdf_file_to_file`, e o
+  /// digest do ByteRange e a cópia para a saída leem direto do mapeamento. Só a
+  /// atualização incremental fica em memória. O arquivo de entrada não pode ser
+  /// alterado durante a assinatura, e `output_path` precisa ser diferente dele.
+  #[cfg(feature = "mmap")]
+  pub fn sign_pdf_mapped_to_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    &self,
+    input_path: P,
+    output_path: Q,
+    config: &SignatureConfig,
+  ) -> Result<SignReport> {
+    let (config, report) = normalize_pades_level(config)?;
+    let config = &config;
+    let input_path = input_path.as_ref();
+    let output_path = output_path.as_ref();
+    if fs::canonicalize(output_path).ok() == Some(fs::canonicalize(input_path)?) {
+      return Err(PdfSignError::InvalidPdf(
+        "o PDF assinado não pode sobrescrever o arquivo de entrada".to_string(),
+      ));
+    }
+
+    let input = File::open(input_path)?;
+    check_input_size(input.metadata()?.len(), config)?;
+    // SAFETY: o mapeamento é só de leitura; truncar ou reescrever o arquivo enquanto ele
+    // existe é proibido pelo contrato documentado acima
+    let mapped = unsafe { memmap2::Mmap::map(&input)? };
+    let base = &mapped[..mapped.len() - trailing_newline_len(&mapped)];
+
+    // Os scanners convertem o documento em texto: no esqueleto, sem o conteúdo dos
+    // streams, essa cópia temporária não cresce com o arquivo
+    let skeleton = read_pdf_skeleton(base)?;
+    self.check_signable(&skeleton, config)?;
+    if config.pdfa_mode {
+      check_pdfa_declaration(base, &skeleton)?;
+    }
+    if let Some(prev_xref) = find_startxref(&skeleton) {
+      check_prev_xref(base.get(prev_xref..).unwrap_or_default(), prev_xref)?;
+    }
+    let mut update = self.build_incremental_update(&skeleton, base.len(), config)?;
+
+    // Digest do ByteRange sobre o mapeamento, seguido da atualização sem o placeholder
+    let mut hasher = config.digest_algorithm.hasher();
+    hasher.update(base);
+    for part in update.signed_tail() {
+      hasher.update(part);
+    }
+    let hash = hasher.finalize();
+    let timestamp = self.fill_signature(&mut update, CmsContent::Digest(&hash), config)?;
+    self.append_revocation_data(&mut update, config)?;
+
+    let mut output = BufWriter::new(File::create(output_path)?);
+    output.write_all(base)?;
+    output.write_all(&update.tail)?;
+    output.flush()?;
+    drop(output);
+
+    // Um arquivo com assinatura inválida não fica no lugar do PDF assinado
+    if config.self_verify {
+      if let Err(error) = verify_signed_file(output_path, &update.tail, config.digest_algorithm) {
+        let _ = fs::remove_file(output_path);
+        return Err(self_verify_failed(error));
+      }
+    }
+    let (tsa_url, timestamp_time) = timestamp.unzip();
+    Ok(SignReport {
+      tsa_url,
+      timestamp_time,
+      signature: Some(update.record()),
+      linearized: is_linearized(&skeleton),
+      certificate_kind: self._certificate.icp_brasil_id().kind(),
+      ..report
+    })
+  }
+
+  /// Recusa documentos que não podem receber a assinatura
+  fn check_signable(&self, pdf_data: &[u8], config: &SignatureConfig) -> Result<()> {
+    check_key_usage(&self._certificate, config)?;
+    check_reason(&signature_reason(&self._certificate, config), config)?;
+    check_document(pdf_data, config)?;
+
+    // Valida a cadeia do signatário antes de tocar no documento
+    if config.validate_icp_brasil {
+      #[cfg(feature = "native")]
+      {
+        let chain = self.openssl_chain()?;
+        trust_store::validate_chain(&chain[0], &chain[1..], config)?;
+      }
+      #[cfg(not(feature = "native"))]
+      return Err(requires_native("validate_icp_brasil"));
+    }
+
+    Ok(())
+  }
+
+  /// Monta a atualização incremental com o dicionário de assinatura ainda sem o CMS
+  ///
+  /// `pdf_data` é o PDF sem os fins de linha finais (ou o esqueleto dele) e `base`
+  /// o tamanho real desse trecho no arquivo, origem dos offsets da atualização
+  fn build_incremental_update(
+    &self,
+    pdf_data: &[u8],
+    base: usize,
+    config: &SignatureConfig,
+  ) -> Result<IncrementalUpdate> {
+    // Captura o timestamp AGORA (antes de assinar): o mesmo instante vai para /M, para
+    // o atributo signingTime do CMS e para a data desenhada na aparência - Adobe Reader
+    // valida os dois primeiros!
+    let signing_time = config.signing_time.unwrap_or_else(Utc::now);
 
     // 1. Cria estrutura PKCS#7/CMS para assinatura (será substituído depois)
-    let _signature_cms = self.create_pkcs7_signature(&pdf_data, config)?;
+    let _signature_cms = self.create_pkcs7_signature(pdf_data, config)?;
 
     // 2. Cria o dicionário de assinatura PDF
 
     // Calcula o tamanho necessário para a assinatura (com padding moderado)
     // Uma assinatura PKCS#7 típica com cadeia de certificados pode ter ~7-8KB
     // JavaScript que funciona usa ~8KB, vamos usar o mesmo
-    let sig_size = 16000; // 16KB de espaço para a assinatura (8000 hex chars)
+    // Com carimbo do tempo o token da TSA (certificados inclusos) vai junto no CMS
+    let timestamped = config.pades_level >= PadesLevel::BT && !config.tsa_candidates().is_empty();
+    let sig_size = if timestamped {
+      32000
+    } else {
+      16000 // 16KB de espaço para a assinatura (8000 hex chars)
+    };
     let sig_placeholder = "<".to_string() + &"0".repeat(sig_size) + ">";
 
     // 3. Monta o PDF com o dicionário de assinatura
-    let next_obj = get_next_object_number(&pdf_data)?;
+    let (trailer_info, next_obj) = update_trailer(pdf_data)?;
 
-    // Extrai o nome do signatário do certificado (CN - Common Name)
-    let signer_name = self
-      ._certificate
-      .subject_cn()
-      .unwrap_or_else(|| "Unknown".to_string());
+    let signer_name = signer_display_name(&self._certificate, config);
+    let reason = signature_reason(&self._certificate, config);
 
     // IMPORTANTE: A data será definida DEPOIS, junto com a assinatura PKCS7
     // para garantir que /M e signingTime sejam idênticos (Adobe valida isso!)
-    // Usando placeholder de tamanho fixo: D:YYYYMMDDHHmmSSZ = 18 caracteres
-    let date_placeholder = "D:00000000000000Z";
-
     // JavaScript: ByteRange antes de Contents, e DEPOIS de Contents vêm os outros campos!
     // Estrutura: /ByteRange [...] /Contents <...zeros...> /Reason (...) /M (...) etc
     // IMPORTANTE: JavaScript usa EXATAMENTE 17 espaços DEPOIS do ] (padrão fixo)
-    // Placeholder: 7 dígitos cada (suporta até 9.999.999 bytes = ~10MB)
-    let sig_dict = format!(
-            "{} 0 obj\n<<\n/Type /Sig\n/Filter /Adobe.PPKLite\n/SubFilter /adbe.pkcs7.detached\n/ByteRange [0000000 0000000 0000000 0000000]                 \n/Contents {}\n/Reason ({})\n/M ({})\n/ContactInfo ({})\n/Name ({})\n/Location ({})\n/Prop_Build <<\n/Filter <<\n/Name /Adobe.PPKLite\n>>\n>>\n>>\nendobj\n",
-            next_obj,
-            sig_placeholder,
-            config.reason,
-            date_placeholder,
-            config.contact_info,
-            signer_name,
-            config.location
-        );
+    // Placeholder: 10 dígitos cada (suporta até 9.999.999.999 bytes = ~10GB)
+    // Bloqueio de campos: /Reference FieldMDP no dicionário de assinatura e /Lock no widget
+    // Direitos de uso: /Reference UR3, e a assinatura fica só em /Perms do Catalog
+    // Extrai informações do PDF de forma robusta (funciona com PDFs reconstruídos)
+    let catalog_info = extract_catalog_info(pdf_data)?;
+    let reference_digest = config
+      .reference_digest_method
+      .unwrap_or(config.digest_algorithm);
+    let references: Vec<SigReference> = match (&config.usage_rights, &config.lock) {
+      (Some(rights), _) => vec![SigReference::ur3(rights, reference_digest)],
+      (None, Some(lock)) => vec![SigReference::field_mdp(
+        lock,
+        (
+          catalog_info.catalog_obj,
+          object_generation(pdf_data, catalog_info.catalog_obj),
+        ),
+        reference_digest,
+      )],
+      (None, None) => Vec::new(),
+    };
+    let reference_entry = build_reference_entry(&references)?;
+    let lock_entry = config
+      .lock
+      .as_ref()
+      .map(|lock| format!("/Lock {}\n", build_lock_dict(lock)))
+      .unwrap_or_default();
+
+    let (sig_dict, spans) = SigDictBuilder {
+      filter: &config.filter,
+      sub_filter: config.sub_filter.name(),
+      contents_placeholder: &sig_placeholder,
+      reason: &reason,
+      contact_info: &config.contact_info,
+      name: &signer_name,
+      location: &config.location,
+      reference_entry: &reference_entry,
+      app_name: &config.app_name,
+      app_version: &config.app_version,
+      text_encoding: config.text_encoding,
+      order: config.sig_dict_order,
+      text_limit: config.text_limit,
+    }
+    .build(next_obj)?;
 
     // 4. Insere a assinatura no PDF usando ATUALIZAÇÃO INCREMENTAL
     // CRÍTICO: NÃO modificar o PDF original! Apenas adicionar novos objetos!
     // Isso garante que o ByteRange seja válido e a assinatura seja aceita
 
+    // `output` guarda só o que vem depois do PDF original; as posições somam `base`
     let mut output = Vec::new();
 
-    // Extrai informações do PDF de forma robusta (funciona com PDFs reconstruídos)
-    let catalog_info = extract_catalog_info(&pdf_data)?;
-    let page_info = extract_first_page_info(&pdf_data)?;
-
     let catalog_obj = catalog_info.catalog_obj;
     let pages_ref = catalog_info.pages_ref;
-    let first_page_obj = page_info.first_page_obj;
-
-    // Copia o PDF original INTEIRO sem modificações
-    output.extend_from_slice(&pdf_data);
 
     // CRÍTICO: Adiciona \n após o PDF original (remove_trailing_newline removeu!)
     // Node-signpdf faz isso implicitamente ao usar Buffer.concat com '\n'
@@ -218,239 +904,641 @@ impl PdfSigner {
 
     // IMPORTANTE: Calcular posições ANTES de adicionar os objetos
     // As posições devem ser relativas ao tamanho atual do output
-    let sig_dict_pos = output.len();
+    let sig_dict_pos = base + output.len();
 
-    // Adiciona o dicionário de assinatura
+    // Adiciona o dicionário de assinatura, anotando onde caem os placeholders na atualização
+    let byte_range_pos = output.len() + spans.byte_range.start;
+    let placeholder_pos = output.len() + spans.contents.start;
+    let date_pos = output.len() + spans.date.start;
     output.extend_from_slice(sig_dict.as_bytes());
 
-    // Calcula posição do AcroForm
-    let acroform_pos = output.len();
+    // Objetos desta atualização incremental: (número, offset)
+    let mut xref_entries: Vec<(usize, usize)> = vec![(next_obj as usize, sig_dict_pos)];
 
-    // Adiciona referência ao campo de assinatura no catálogo
-    // JavaScript que funciona tem /Type /AcroForm e /SigFlags 3
-    let acroform = format!(
-      "{} 0 obj\n<<\n/Type /AcroForm\n/SigFlags 3\n/Fields [{} 0 R]\n>>\nendobj\n",
-      next_obj + 1,
-      next_obj + 2
-    );
-    output.extend_from_slice(acroform.as_bytes());
+    // AcroForm já presente no Catalog: é regravado com os campos que já tinha
+    let existing_acroform = find_acroform(pdf_data, catalog_obj);
+
+    // Re-assinatura de um campo pré-criado num formulário que já tem /SigFlags 3: só o
+    // dicionário de assinatura e o /V do campo mudam, AcroForm e Catalog ficam como estão
+    let field_only = config.fill_existing_field.is_some()
+      && config.usage_rights.is_none()
+      && existing_acroform
+        .is_some_and(|(_, body)| read_number_after(body, b"/SigFlags") == Some(3));
+
+    // Campo que recebe a assinatura (objeto, nome), para o relatório
+    let mut signed_field = None;
+    let (acroform_ref, next_free_obj) = match &config.fill_existing_field {
+      // Assinatura de direitos de uso não é campo de formulário: o AcroForm fica como está
+      None if config.usage_rights.is_some() => (
+        existing_acroform.and_then(|(acroform_obj, _)| acroform_obj),
+        (next_obj + 1) as usize,
+      ),
+      None => {
+        // Só o widget novo precisa da página: um campo pré-criado já traz o próprio /P
+        let first_page_obj = extract_first_page_info(pdf_data)?.first_page_obj;
+
+        // Calcula posição do AcroForm
+        let acroform_pos = base + output.len();
+
+        // Adiciona referência ao campo de assinatura no catálogo
+        // JavaScript que funciona tem /Type /AcroForm e /SigFlags 3
+        // Um AcroForm existente mantém /Fields, /NeedAppearances e /DR; o campo novo vai no fim
+        let sig_field_obj = (next_obj + 1) as usize;
+        let (acroform_obj, acroform) = match existing_acroform {
+          Some((acroform_obj, body)) => {
+            // Um AcroForm direto no Catalog passa a ser um objeto próprio
+            let acroform_obj = acroform_obj.unwrap_or(sig_field_obj + 1);
+            (
+              acroform_obj,
+              format!(
+                "{} {} obj\n<<{}\n/SigFlags 3\n>>\nendobj\n",
+                acroform_obj,
+                object_generation(pdf_data, acroform_obj),
+                append_acroform_field(pdf_data, body, sig_field_obj)
+              ),
+            )
+          }
+          None => (
+            sig_field_obj + 1,
+            format!(
+              "{} 0 obj\n<<\n/Type /AcroForm\n/SigFlags 3\n/Fields [{} 0 R]\n>>\nendobj\n",
+              sig_field_obj + 1,
+              sig_field_obj
+            ),
+          ),
+        };
+        output.extend_from_slice(acroform.as_bytes());
 
-    // Calcula posição do sig_field
-    let sig_field_pos = output.len();
+        // Calcula posição do sig_field
+        let sig_field_pos = base + output.len();
 
-    // JavaScript que funciona tem campos adicionais no widget de assinatura
-    // IMPORTANTE: /P deve referenciar o objeto da primeira página, não hardcoded como 1 0 R
-    let sig_field = format!(
-            "{} 0 obj\n<<\n/Type /Annot\n/Subtype /Widget\n/FT /Sig\n/Rect [0 0 0 0]\n/V {} 0 R\n/T (Signature1)\n/F 4\n/P {} 0 R\n>>\nendobj\n",
-            next_obj + 2,
+        // JavaScript que funciona tem campos adicionais no widget de assinatura
+        // IMPORTANTE: /P deve referenciar o objeto da primeira página, não hardcoded como 1 0 R
+        let field_name = unused_signature_field_name(pdf_data);
+        let sig_field = format!(
+            "{} 0 obj\n<<\n/Type /Annot\n/Subtype /Widget\n/FT /Sig\n/Rect [0 0 0 0]\n/V {} 0 R\n/T ({})\n/F 4\n/P {} {} R\n{}>>\nendobj\n",
+            sig_field_obj,
             next_obj,
-            first_page_obj
+            field_name,
+            first_page_obj,
+            object_generation(pdf_data, first_page_obj),
+            lock_entry
+        );
+        output.extend_from_slice(sig_field.as_bytes());
+        signed_field = Some((sig_field_obj, field_name));
+
+        xref_entries.push((acroform_obj, acroform_pos));
+        xref_entries.push((sig_field_obj, sig_field_pos));
+        let next_free_obj = if acroform_obj > sig_field_obj {
+          acroform_obj + 1
+        } else {
+          sig_field_obj + 1
+        };
+        (Some(acroform_obj), next_free_obj)
+      }
+      Some(field_name) => {
+        // Campo pré-criado no template: reaproveita /Rect e /P, só adiciona /V
+        let field_obj = find_signature_field(pdf_data, field_name).ok_or_else(|| {
+          PdfSignError::InvalidPdf(format!(
+            "campo de assinatura \"{}\" não encontrado",
+            field_name
+          ))
+        })?;
+        let field_dict = find_object_section(pdf_data, field_obj)
+          .and_then(dict_body)
+          .ok_or_else(|| {
+            PdfSignError::InvalidPdf(format!("campo \"{}\" com dicionário inválido", field_name))
+          })?;
+        if dict_has_key(field_dict, b"/V") {
+          return Err(PdfSignError::InvalidPdf(format!(
+            "campo de assinatura \"{}\" já está assinado",
+            field_name
+          )));
+        }
+
+        // Aparência visível do tamanho do /Rect, numerada logo depois da assinatura;
+        // substitui a /AP que o campo trouxer
+        let appearance_objects = match &config.appearance {
+          Some(appearance) => {
+            let [x1, y1, x2, y2] = read_rect(field_dict)
+              .filter(|[x1, y1, x2, y2]| x1 != x2 && y1 != y2)
+              .ok_or_else(|| {
+                PdfSignError::InvalidPdf(format!(
+                  "campo \"{}\" sem área (/Rect) para a aparência visível",
+                  field_name
+                ))
+              })?;
+            build_appearance_objects(
+              appearance,
+              (x2 - x1).abs(),
+              (y2 - y1).abs(),
+              (next_obj + 1) as usize,
+              pdf_data,
+              signing_time,
+            )?
+          }
+          None => Vec::new(),
+        };
+        let after_appearance = next_obj as usize + 1 + appearance_objects.len();
+
+        let field_pos = base + output.len();
+        output.extend_from_slice(
+          format!(
+            "{} {} obj\n<<",
+            field_obj,
+            object_generation(pdf_data, field_obj)
+          )
+          .as_bytes(),
+        );
+        if appearance_objects.is_empty() {
+          output.extend_from_slice(field_dict);
+        } else {
+          for (_, entry) in dict_entries(field_dict)
+            .into_iter()
+            .filter(|(key, _)| *key != b"/AP")
+          {
+            output.push(b'\n');
+            output.extend_from_slice(entry);
+          }
+          output.extend_from_slice(format!("\n/AP << /N {} 0 R >>", next_obj + 1).as_bytes());
+        }
+        output.extend_from_slice(
+          format!("\n/V {} 0 R\n{}>>\nendobj\n", next_obj, lock_entry).as_bytes(),
         );
-    output.extend_from_slice(sig_field.as_bytes());
+        xref_entries.push((field_obj, field_pos));
+        signed_field = Some((field_obj, field_name.clone()));
+
+        for (offset, object) in appearance_objects.iter().enumerate() {
+          xref_entries.push((next_obj as usize + 1 + offset, base + output.len()));
+          output.extend_from_slice(object);
+        }
+
+        // O AcroForm existente já lista o campo; só garante /SigFlags 3
+        let acroform_pos = base + output.len();
+        match existing_acroform {
+          Some((acroform_obj, _)) if field_only => (acroform_obj, after_appearance),
+          Some((acroform_obj, body)) => {
+            let next_free_obj = after_appearance + usize::from(acroform_obj.is_none());
+            let acroform_obj = acroform_obj.unwrap_or(after_appearance);
+            output.extend_from_slice(
+              format!(
+                "{} {} obj\n<<{}\n/SigFlags 3\n>>\nendobj\n",
+                acroform_obj,
+                object_generation(pdf_data, acroform_obj),
+                remove_dict_entry(body, b"/SigFlags")
+              )
+              .as_bytes(),
+            );
+            xref_entries.push((acroform_obj, acroform_pos));
+            (Some(acroform_obj), next_free_obj)
+          }
+          None => {
+            output.extend_from_slice(
+              format!(
+                "{} 0 obj\n<<\n/Type /AcroForm\n/SigFlags 3\n/Fields [{} 0 R]\n>>\nendobj\n",
+                after_appearance, field_obj
+              )
+              .as_bytes(),
+            );
+            xref_entries.push((after_appearance, acroform_pos));
+            (Some(after_appearance), after_appearance + 1)
+          }
+        }
+      }
+    };
 
     // CRÍTICO: Adiciona um NOVO Catalog que substitui o original na atualização incremental
     // Isso é o que o JavaScript faz! Não modifica o Catalog original, cria um novo!
-    let new_catalog_pos = output.len();
-
-    // IMPORTANTE: Preserva estruturas adicionais do Catalog original se existirem
-    // PDFs reconstruídos podem ter campos personalizados que precisam ser mantidos
-    let new_catalog =
-      build_updated_catalog(catalog_obj, pages_ref, (next_obj + 1) as usize, &pdf_data)?;
-
-    output.extend_from_slice(new_catalog.as_bytes());
-
-    // Encontra o startxref anterior
-    let pdf_str_for_xref = String::from_utf8_lossy(&pdf_data);
-    let prev_xref = if let Some(pos) = pdf_str_for_xref.rfind("startxref\n") {
-      let start = pos + "startxref\n".len();
-      if let Some(end) = pdf_str_for_xref[start..].find("\n") {
-        pdf_str_for_xref[start..start + end]
-          .trim()
-          .parse::<usize>()
-          .unwrap_or(0)
-      } else {
-        0
-      }
+    // No caminho só do campo o Catalog vigente continua valendo; o /DSS parte dele
+    let unchanged_catalog = if field_only {
+      find_object_section(pdf_data, catalog_obj).map(|catalog| [catalog, b"endobj\n"].concat())
     } else {
-      0
+      let new_catalog_pos = base + output.len();
+
+      // IMPORTANTE: Preserva estruturas adicionais do Catalog original se existirem
+      // PDFs reconstruídos podem ter campos personalizados que precisam ser mantidos
+      let perms: Vec<String> = config
+        .usage_rights
+        .iter()
+        .map(|_| format!("/Perms << /UR3 {} 0 R >>", next_obj))
+        .collect();
+      let new_catalog =
+        build_updated_catalog(catalog_obj, pages_ref, acroform_ref, pdf_data, &perms)?;
+
+      output.extend_from_slice(new_catalog.as_bytes());
+      xref_entries.push((catalog_obj, new_catalog_pos));
+      None
     };
 
+    // startxref anterior, já exigido por update_trailer
+    let prev_xref = trailer_info.startxref.unwrap_or_default();
+
     // Cria xref table incremental
     // IMPORTANTE: Formato correto de subsecções no xref
-    // Primeiro uma entrada para o objeto 0 (sempre f = free)
-    // Depois os 3 novos objetos em sequência
-    // Depois uma subsecção para o Catalog que está sendo substituído
-    let xref_start = output.len();
-    let xref = format!(
-            "xref\n0 1\n0000000000 65535 f \n{} 1\n{:010} 00000 n \n{} 3\n{:010} 00000 n \n{:010} 00000 n \n{:010} 00000 n \n",
-            catalog_obj,
-            new_catalog_pos,
-            next_obj,
-            sig_dict_pos,
-            acroform_pos,
-            sig_field_pos
-        );
-    output.extend_from_slice(xref.as_bytes());
-
-    // Adiciona trailer
+    // Uma subsecção por faixa de objetos consecutivos (novos e substituídos)
+    let xref_start = base + output.len();
     // IMPORTANTE: Usa catalog_obj como Root (agora aponta para o novo Catalog)
-    let trailer = format!(
-      "trailer\n<<\n/Size {}\n/Prev {}\n/Root {} 0 R\n>>\nstartxref\n{}\n%%EOF\n",
-      next_obj + 3,
-      prev_xref,
-      catalog_obj,
-      xref_start
-    );
-    output.extend_from_slice(trailer.as_bytes());
-
-    // 5. CRÍTICO: Encontra ByteRange e calcula posições EXATAMENTE como node-signpdf
-    // Node-signpdf: busca o placeholder, depois busca /Contents APÓS o ByteRange
-
-    let byte_range_search = b"/ByteRange [0000000 0000000 0000000 0000000]                 ";
-    let range_pos = output
-      .windows(byte_range_search.len())
-      .position(|w| w == byte_range_search)
-      .ok_or_else(|| PdfSignError::InvalidPdf("ByteRange não encontrado".to_string()))?;
+    let trailer = XrefTrailer {
+      size: next_free_obj,
+      prev: prev_xref,
+      root: root_reference(pdf_data, &trailer_info, catalog_obj),
+    };
+    output.extend_from_slice(&build_xref_section(
+      &mut xref_entries,
+      pdf_data,
+      next_obj as usize,
+      xref_start,
+      &trailer,
+      config.xref_format,
+    ));
+    // A xref stream ocupa o primeiro número livre
+    let next_free_obj = match config.xref_format {
+      XrefFormat::Table => next_free_obj,
+      XrefFormat::Stream => next_free_obj + 1,
+    };
 
-    let byterange_placeholder_len = byte_range_search.len();
-    let byterange_end = range_pos + byterange_placeholder_len;
+    // 5. O par < > precisa delimitar exatamente o espaço reservado
+    check_contents_placeholder(
+      &output[placeholder_pos..placeholder_pos + spans.contents.len()],
+      sig_size,
+    )?;
 
-    // CRÍTICO: Busca /Contents DEPOIS do ByteRange (node-signpdf faz assim!)
-    let contents_tag_pos = output[byterange_end..]
-      .windows(b"/Contents ".len())
-      .position(|w| w == b"/Contents ")
-      .ok_or_else(|| {
-        PdfSignError::InvalidPdf("/Contents não encontrado após ByteRange".to_string())
-      })?
-      + byterange_end;
-
-    // Busca o '<' que inicia o placeholder da assinatura (DEPOIS do /Contents)
-    let placeholder_pos = output[contents_tag_pos..]
-      .windows(1)
-      .position(|w| w == b"<")
-      .ok_or_else(|| PdfSignError::InvalidPdf("< não encontrado após /Contents".to_string()))?
-      + contents_tag_pos;
-
-    // Busca o '>' que termina o placeholder
-    let placeholder_end = output[placeholder_pos..]
-      .windows(1)
-      .position(|w| w == b">")
-      .ok_or_else(|| PdfSignError::InvalidPdf("> não encontrado após <".to_string()))?
-      + placeholder_pos;
-
-    let placeholder_length_with_brackets = (placeholder_end + 1) - placeholder_pos;
-
-    // 6. Calcula ByteRange EXATAMENTE como node-signpdf
-    let byte_range_values = [
-      0,
+    let mut update = IncrementalUpdate {
+      base,
+      tail: output,
+      byte_range_pos,
+      byte_range_len: spans.byte_range.len(),
       placeholder_pos,
-      placeholder_pos + placeholder_length_with_brackets,
-      output.len() - (placeholder_pos + placeholder_length_with_brackets),
-    ];
-
-    // 7. Cria string do ByteRange com PADDING DINÂMICO (como node-signpdf!)
-    let byte_range_str_raw = format!(
-      "/ByteRange [{} {} {} {}]",
-      byte_range_values[0], byte_range_values[1], byte_range_values[2], byte_range_values[3]
-    );
+      placeholder_len: spans.contents.len(),
+      date_pos,
+      sig_size,
+      signing_time,
+      sig_obj: next_obj as usize,
+      signer_name,
+      signed_field,
+      dss_target: DssTarget {
+        catalog_obj,
+        pages_ref,
+        acroform_ref,
+        first_obj: next_free_obj,
+        prev_xref: xref_start,
+        unchanged_catalog,
+        xref_format: config.xref_format,
+        previous_dss: DssContents::read(pdf_data, catalog_obj),
+      },
+    };
+    // 7. Grava ByteRange e /M sobre os placeholders, mantendo os tamanhos
+    update.write_byte_range()?;
+    update.write_signing_time()?;
+    // Assinatura de direitos de uso não tem widget: fica só em /Perms
+    update.check_references(pdf_data, next_obj as usize, config.usage_rights.is_none())?;
+    Ok(update)
+  }
 
-    // CRÍTICO: Padding dinâmico até o tamanho do placeholder original!
-    let padding_needed = byterange_placeholder_len - byte_range_str_raw.len();
-    let byte_range_str = format!("{}{}", byte_range_str_raw, " ".repeat(padding_needed));
+  /// Assina o conteúdo coberto pelo ByteRange e grava o CMS no placeholder de /Contents
+  ///
+  /// Devolve a TSA que emitiu o carimbo do tempo e o genTime dele, quando há um
+  fn fill_signature(
+    &self,
+    update: &mut IncrementalUpdate,
+    content: CmsContent<'_>,
+    config: &SignatureConfig,
+  ) -> Result<Option<Timestamped>> {
+    // adbe.pkcs7.sha1: o CMS não é destacado e encapsula o SHA-1 do ByteRange
+    let sha1_hash;
+    let (content, detached) = match config.sub_filter {
+      SubFilter::Pkcs7Detached => (content, true),
+      SubFilter::Pkcs7Sha1 => {
+        sha1_hash = content.message_digest(DigestAlgorithm::Sha1);
+        (CmsContent::Data(&sha1_hash), false)
+      }
+    };
+    // O signingTime do CMS é o instante já gravado em /M
+    let final_cms = self.create_pkcs7(content, config, detached, update.signing_time)?;
 
-    // 8. Substitui ByteRange MANTENDO O TAMANHO (node-signpdf faz assim!)
-    if byte_range_str.len() != byterange_placeholder_len {
-      return Err(PdfSignError::InvalidPdf(format!(
-        "ByteRange com padding ({}) != placeholder ({})",
-        byte_range_str.len(),
-        byterange_placeholder_len
-      )));
-    }
+    // O digest do ByteRange e o do CMS precisam ser o mesmo algoritmo
+    cms_signer::check_message_digest(
+      &final_cms,
+      &content.message_digest(config.digest_algorithm),
+      config.digest_algorithm,
+    )?;
 
-    output[range_pos..range_pos + byterange_placeholder_len]
-      .copy_from_slice(byte_range_str.as_bytes());
+    let tsa_urls = config.tsa_candidates();
+    let timestamped = config.pades_level >= PadesLevel::BT && !tsa_urls.is_empty();
 
-    // 9. Prepara o conteúdo a ser assinado (excluindo o placeholder da assinatura)
-    let mut to_sign = Vec::new();
-    to_sign.extend_from_slice(
-      &output[byte_range_values[0]..byte_range_values[0] + byte_range_values[1]],
-    );
-    to_sign.extend_from_slice(
-      &output[byte_range_values[2]..byte_range_values[2] + byte_range_values[3]],
-    );
+    // PAdES-B-T: carimbo do tempo sobre o valor da assinatura, como atributo não assinado
+    let (final_cms, timestamp) = match timestamped {
+      #[cfg(feature = "native")]
+      true => {
+        let (token, url) = timestamp::request_signature_timestamp(&tsa_urls, &final_cms, config)?;
+        let gen_time = timestamp::token_gen_time(&token)?;
+        (
+          timestamp::embed_signature_timestamp(&final_cms, &token)?,
+          Some((url, gen_time)),
+        )
+      }
+      #[cfg(not(feature = "native"))]
+      true => return Err(requires_native("PAdES-B-T (carimbo do tempo)")),
+      false => (final_cms, None),
+    };
 
-    // 10. Captura o timestamp AGORA (antes de assinar) para garantir que /M e signingTime
-    // no PKCS7 sejam idênticos - Adobe Reader valida isso!
-    let now = chrono::Utc::now();
-    let date_str = format!("D:{}Z", now.format("%Y%m%d%H%M%S"));
+    // Codifica a assinatura em hex, completando o tamanho do placeholder
+    let padded_sig_hex = pad_signature_hex(&final_cms, update.sig_size, config.contents_padding)?;
+    let final_sig_hex = format!("<{}>", padded_sig_hex);
 
-    // Substitui o placeholder da data pelo timestamp real
-    let date_placeholder_bytes = b"D:00000000000000Z";
-    let date_pos = output
-      .windows(date_placeholder_bytes.len())
-      .position(|w| w == date_placeholder_bytes)
-      .ok_or_else(|| PdfSignError::InvalidPdf("Placeholder de data não encontrado".to_string()))?;
+    // 12. Substitui placeholder pela assinatura real - usa placeholder_pos que já foi calculado!
+    let sig_bytes = final_sig_hex.as_bytes();
 
-    let date_bytes = date_str.as_bytes();
-    if date_bytes.len() != date_placeholder_bytes.len() {
+    // Verifica que o tamanho é exatamente o mesmo
+    if sig_bytes.len() != update.placeholder_len {
       return Err(PdfSignError::InvalidPdf(format!(
-        "Data tem tamanho errado: {} vs {}",
-        date_bytes.len(),
-        date_placeholder_bytes.len()
+        "Tamanho da assinatura final ({}) diferente do placeholder ({})",
+        sig_bytes.len(),
+        update.placeholder_len
       )));
     }
-    output[date_pos..date_pos + date_bytes.len()].copy_from_slice(date_bytes);
 
-    // IMPORTANTE: Recalcula to_sign após substituir a data!
-    to_sign.clear();
-    to_sign.extend_from_slice(
-      &output[byte_range_values[0]..byte_range_values[0] + byte_range_values[1]],
-    );
-    to_sign.extend_from_slice(
-      &output[byte_range_values[2]..byte_range_values[2] + byte_range_values[3]],
-    );
+    update.tail[update.placeholder_pos..update.placeholder_pos + sig_bytes.len()]
+      .copy_from_slice(sig_bytes);
+    Ok(timestamp)
+  }
+
+  /// 13. PAdES-B-LT: grava certificados, OCSP e CRL em uma nova atualização incremental (/DSS)
+  fn append_revocation_data(
+    &self,
+    update: &mut IncrementalUpdate,
+    config: &SignatureConfig,
+  ) -> Result<()> {
+    if config.pades_level < PadesLevel::BLT || !(config.include_ocsp || config.include_crl) {
+      return Ok(());
+    }
+
+    #[cfg(not(feature = "native"))]
+    {
+      let _ = update;
+      Err(requires_native("PAdES-B-LT (OCSP/CRL)"))
+    }
+    #[cfg(feature = "native")]
+    {
+      let revocation_data = collect_revocation_data(&self.openssl_chain()?, config)?;
+      update.tail = append_dss(
+        std::mem::take(&mut update.tail),
+        update.base,
+        &update.dss_target,
+        &revocation_data,
+      )?;
+      Ok(())
+    }
+  }
+
+  /// Certificado do signatário seguido da cadeia, no formato do OpenSSL
+  #[cfg(feature = "native")]
+  fn openssl_chain(&self) -> Result<Vec<openssl::x509::X509>> {
+    std::iter::once(&self._certificate)
+      .chain(self._cert_chain.iter())
+      .map(|cert| {
+        openssl::x509::X509::from_der(cert.der()).map_err(|e| {
+          PdfSignError::DecodingError(format!("Erro ao carregar certificado: {:?}", e))
+        })
+      })
+      .collect()
+  }
+
+  /// Assina um PDF com configuração completa
+  pub fn sign_pdf(&self, pdf_data: Vec<u8>, config: &SignatureConfig) -> Result<Vec<u8>> {
+    self.sign_pdf_bytes(pdf_data, config)
+  }
 
-    // Usa a API OpenSSL para criar o PKCS#7 corretamente
-    // IMPORTANTE: Isso deve acontecer IMEDIATAMENTE após capturar o timestamp
-    // para que o signingTime no PKCS7 seja o mais próximo possível do /M
-    let final_cms = self.create_pkcs7_detached(&to_sign, config)?;
+  /// /MediaBox da página `page` (a partir de 0), como [x1 y1 x2 y2] em pontos
+  ///
+  /// Herdada da árvore /Pages quando a página não a define
+  pub fn page_media_box(pdf_data: &[u8], page: usize) -> Result<[f64; 4]> {
+    let page_obj = find_page_object(pdf_data, page)
+      .ok_or_else(|| PdfSignError::InvalidPdf(format!("página {} não encontrada", page)))?;
+    extract_page_mediabox(pdf_data, page_obj)
+      .ok_or_else(|| PdfSignError::InvalidPdf(format!("página {} sem /MediaBox", page)))
+  }
+
+  /// Converte um retângulo medido do canto superior esquerdo da página para o /Rect do PDF
+  ///
+  /// `rect` é [x1 y1 x2 y2] em pontos, com y crescendo para baixo a partir do topo da
+  /// `media_box` (ver `page_media_box`); o resultado tem a origem no canto inferior
+  /// esquerdo, como `add_empty_signature_field` espera. /Rotate não é considerado
+  pub fn rect_from_top_left(rect: [f64; 4], media_box: [f64; 4]) -> [f64; 4] {
+    let [left, _, _, top] = media_box;
+    [
+      left + rect[0].min(rect[2]),
+      top - rect[1].max(rect[3]),
+      left + rect[0].max(rect[2]),
+      top - rect[1].min(rect[3]),
+    ]
+  }
 
-    // Codifica a assinatura em hex
-    let sig_hex = hex::encode(&final_cms);
+  /// Insere um campo de assinatura vazio (/FT /Sig sem /V) para ser assinado depois
+  ///
+  /// Atualização incremental com o widget em `rect` ([x1 y1 x2 y2], em pontos) na
+  /// página `page` (a partir de 0), o campo acrescentado ao AcroForm (criado se não
+  /// existir) e às /Annots da página. Não exige certificado
+  pub fn add_empty_signature_field(
+    pdf_data: Vec<u8>,
+    field_name: &str,
+    rect: [f64; 4],
+    page: usize,
+  ) -> Result<Vec<u8>> {
+    Self::add_empty_signature_field_with_reasons(pdf_data, field_name, rect, page, &[])
+  }
 
-    // Verifica se a assinatura cabe no placeholder (sem os delimitadores < >)
-    if sig_hex.len() > sig_size {
+  /// Grava um CMS destacado no /Contents do campo preparado por `prepare_signature`
+  ///
+  /// Só o placeholder muda: /ByteRange, xref e o resto do arquivo continuam como
+  /// estavam. Cada parte completa o próprio campo antes de a seguinte preparar o dela,
+  /// já que o ByteRange de uma assinatura cobre as anteriores
+  pub fn complete_signature_for_field(
+    mut prepared_pdf: Vec<u8>,
+    field_name: &str,
+    cms_der: &[u8],
+  ) -> Result<Vec<u8>> {
+    let entry = list_signatures(&prepared_pdf)
+      .into_iter()
+      .rev()
+      .find(|entry| entry.field_name.as_deref() == Some(field_name))
+      .ok_or_else(|| {
+        PdfSignError::InvalidPdf(format!("campo \"{}\" sem assinatura preparada", field_name))
+      })?;
+    let range = checked_byte_range(Some(&entry), prepared_pdf.len())?;
+    let placeholder = prepared_pdf[range[1]..range[2]]
+      .strip_prefix(b"<")
+      .and_then(|contents| contents.strip_suffix(b">"))
+      .ok_or_else(|| {
+        PdfSignError::InvalidPdf(format!(
+          "campo \"{}\" sem /Contents entre os trechos do /ByteRange",
+          field_name
+        ))
+      })?;
+    if placeholder.iter().any(|&c| c != b'0') {
       return Err(PdfSignError::InvalidPdf(format!(
-        "Assinatura muito grande: {} bytes, mas placeholder tem apenas {} bytes",
-        sig_hex.len(),
-        sig_size
+        "campo de assinatura \"{}\" já está assinado",
+        field_name
       )));
     }
 
-    // Preenche com zeros para manter o tamanho do placeholder
-    let padded_sig_hex = format!("{}{}", sig_hex, "0".repeat(sig_size - sig_hex.len()));
-    let final_sig_hex = format!("<{}>", padded_sig_hex);
-
-    // 12. Substitui placeholder pela assinatura real - usa placeholder_pos que já foi calculado!
-    let sig_bytes = final_sig_hex.as_bytes();
+    let padded = pad_signature_hex(cms_der, placeholder.len(), ContentsPadding::Zero)?;
+    prepared_pdf[range[1] + 1..range[2] - 1].copy_from_slice(padded.as_bytes());
+    Ok(prepared_pdf)
+  }
 
-    // Verifica que o tamanho é exatamente o mesmo
-    if sig_bytes.len() != placeholder_length_with_brackets {
+  /// Como `add_empty_signature_field`, restringindo o motivo a uma lista
+  ///
+  /// Os motivos vão no dicionário de valores-semente do campo (/SV /Reasons, com o
+  /// bit 4 de /Ff ligado), e o Acrobat os oferece como lista ao assinar o campo
+  pub fn add_empty_signature_field_with_reasons(
+    pdf_data: Vec<u8>,
+    field_name: &str,
+    rect: [f64; 4],
+    page: usize,
+    reasons: &[String],
+  ) -> Result<Vec<u8>> {
+    if parse_pdf_version(&pdf_data).is_none() {
+      return Err(PdfSignError::InvalidPdf(
+        "arquivo não é um PDF (cabeçalho %PDF-x.y ausente)".to_string(),
+      ));
+    }
+    if find_docmdp_permission(&pdf_data)? == Some(1) {
+      return Err(PdfSignError::InvalidPdf(
+        "documento certificado (DocMDP /P 1) não permite alterações, nem novas assinaturas"
+          .to_string(),
+      ));
+    }
+    if field_name.is_empty() {
+      return Err(PdfSignError::InvalidPdf(
+        "nome do campo de assinatura vazio".to_string(),
+      ));
+    }
+    if find_signature_field(&pdf_data, field_name).is_some() {
       return Err(PdfSignError::InvalidPdf(format!(
-        "Tamanho da assinatura final ({}) diferente do placeholder ({})",
-        sig_bytes.len(),
-        placeholder_length_with_brackets
+        "campo de assinatura \"{}\" já existe",
+        field_name
       )));
     }
 
-    output[placeholder_pos..placeholder_pos + sig_bytes.len()].copy_from_slice(sig_bytes);
+    let mut pdf_data = remove_trailing_newline(pdf_data);
+    let (trailer_info, field_obj) = update_trailer(&pdf_data)?;
+    let prev_xref = trailer_info.startxref.unwrap_or_default();
+    check_prev_xref(pdf_data.get(prev_xref..).unwrap_or_default(), prev_xref)?;
 
-    Ok(output)
-  }
+    let catalog_info = extract_catalog_info(&pdf_data)?;
+    let catalog_obj = catalog_info.catalog_obj;
+    let page_obj = find_page_object(&pdf_data, page)
+      .ok_or_else(|| PdfSignError::InvalidPdf(format!("página {} não encontrada", page)))?;
+    let page_dict = find_object_section(&pdf_data, page_obj)
+      .and_then(dict_body)
+      .ok_or_else(|| {
+        PdfSignError::InvalidPdf(format!("página {} com dicionário inválido", page))
+      })?;
+    let field_obj = field_obj as usize;
+    let base = pdf_data.len();
 
-  /// Assina um PDF com configuração completa
-  pub fn sign_pdf(&self, pdf_data: Vec<u8>, config: &SignatureConfig) -> Result<Vec<u8>> {
-    self.sign_pdf_bytes(pdf_data, config)
+    let mut output = vec![b'\n'];
+    let mut xref_entries = Vec::new();
+
+    let seed_value = if reasons.is_empty() {
+      String::new()
+    } else {
+      let reasons: Vec<String> = reasons.iter().map(|r| pdf_literal_string(r)).collect();
+      format!(
+        "\n/SV << /Type /SV /Reasons [{}] /Ff 8 >>",
+        reasons.join(" ")
+      )
+    };
+    xref_entries.push((field_obj, base + output.len()));
+    output.extend_from_slice(
+      format!(
+        "{} 0 obj\n<<\n/Type /Annot\n/Subtype /Widget\n/FT /Sig\n/T {}\n/Rect [{} {} {} {}]\n/F 4\n/P {} {} R{}\n>>\nendobj\n",
+        field_obj,
+        pdf_literal_string(field_name),
+        rect[0],
+        rect[1],
+        rect[2],
+        rect[3],
+        page_obj,
+        object_generation(&pdf_data, page_obj),
+        seed_value
+      )
+      .as_bytes(),
+    );
+
+    // O widget precisa estar nas /Annots da página para aparecer no leitor
+    xref_entries.push((page_obj, base + output.len()));
+    output.extend_from_slice(
+      format!(
+        "{} {} obj\n<<{}\n>>\nendobj\n",
+        page_obj,
+        object_generation(&pdf_data, page_obj),
+        append_array_ref(
+          &pdf_data,
+          String::from_utf8_lossy(page_dict).to_string(),
+          "/Annots",
+          field_obj
+        )
+      )
+      .as_bytes(),
+    );
+
+    // Sem assinatura ainda, o AcroForm não recebe /SigFlags
+    let existing_acroform = find_acroform(&pdf_data, catalog_obj);
+    let (acroform_obj, acroform_body) = match existing_acroform {
+      Some((acroform_obj, body)) => (
+        acroform_obj.unwrap_or(field_obj + 1),
+        append_array_ref(
+          &pdf_data,
+          String::from_utf8_lossy(body).to_string(),
+          "/Fields",
+          field_obj,
+        ),
+      ),
+      None => (
+        field_obj + 1,
+        format!("\n/Type /AcroForm\n/Fields [{} 0 R]", field_obj),
+      ),
+    };
+    xref_entries.push((acroform_obj, base + output.len()));
+    output.extend_from_slice(
+      format!(
+        "{} {} obj\n<<{}\n>>\nendobj\n",
+        acroform_obj,
+        object_generation(&pdf_data, acroform_obj),
+        acroform_body
+      )
+      .as_bytes(),
+    );
+
+    xref_entries.push((catalog_obj, base + output.len()));
+    let catalog = build_updated_catalog(
+      catalog_obj,
+      catalog_info.pages_ref,
+      Some(acroform_obj),
+      &pdf_data,
+      &[],
+    )?;
+    output.extend_from_slice(catalog.as_bytes());
+
+    let xref_start = base + output.len();
+    let next_free_obj = acroform_obj.max(field_obj) + 1;
+    output.extend_from_slice(build_xref_table(&mut xref_entries, &pdf_data, field_obj).as_bytes());
+    output.extend_from_slice(
+      format!(
+        "trailer\n<<\n/Size {}\n/Prev {}\n/Root {} {} R\n>>\nstartxref\n{}\n%%EOF\n",
+        next_free_obj,
+        prev_xref,
+        catalog_obj,
+        root_reference(&pdf_data, &trailer_info, catalog_obj).1,
+        xref_start
+      )
+      .as_bytes(),
+    );
+
+    pdf_data.extend_from_slice(&output);
+    Ok(pdf_data)
   }
 
   /// Assina um PDF com configuração completa
@@ -459,29 +1547,141 @@ impl PdfSigner {
     input_path: P,
     config: &SignatureConfig,
   ) -> Result<Vec<u8>> {
-    let pdf_data = fs::read(input_path)?;
-    self.sign_pdf(pdf_data, config)
+    self
+      .sign_pdf_with_path_and_report(input_path, config)
+      .map(|(signed, _)| signed)
   }
 
-  /// Cria estrutura PKCS#7/CMS detached usando OpenSSL
-  fn create_pkcs7_detached(&self, data: &[u8], _config: &SignatureConfig) -> Result<Vec<u8>> {
-    use openssl::pkcs7::{Pkcs7, Pkcs7Flags};
+  /// Como `sign_pdf_with_path`, devolvendo também o relatório da assinatura
+  pub fn sign_pdf_with_path_and_report<P: AsRef<Path>>(
+    &self,
+    input_path: P,
+    config: &SignatureConfig,
+  ) -> Result<(Vec<u8>, SignReport)> {
+    // O tamanho vem dos metadados: um arquivo acima do limite nem chega a ser lido
+    let mut input = File::open(input_path)?;
+    let len = input.metadata()?.len();
+    check_input_size(len, config)?;
+    let mut pdf_data = Vec::with_capacity(len as usize);
+    input.read_to_end(&mut pdf_data)?;
+    self.sign_pdf_with_report(pdf_data, config)
+  }
+
+  /// Assina dados arbitrários e retorna a estrutura CMS (PKCS#7) em DER
+  ///
+  /// Com `detached = true` o conteúdo não é encapsulado (formato usado em PDFs);
+  /// com `detached = false` gera a forma "enveloping", em que o CMS carrega os dados assinados.
+  pub fn sign_data_cms(
+    &self,
+    data: &[u8],
+    config: &SignatureConfig,
+    detached: bool,
+  ) -> Result<Vec<u8>> {
+    let signing_time = config.signing_time.unwrap_or_else(Utc::now);
+    self.create_pkcs7(CmsContent::Data(data), config, detached, signing_time)
+  }
+
+  /// Cria estrutura PKCS#7/CMS com o backend configurado, detached ou com o conteúdo encapsulado
+  fn create_pkcs7(
+    &self,
+    content: CmsContent<'_>,
+    config: &SignatureConfig,
+    detached: bool,
+    signing_time: DateTime<Utc>,
+  ) -> Result<Vec<u8>> {
+    #[cfg(feature = "pkcs11")]
+    if let Some(token) = &self._token_key {
+      return self.create_pkcs7_rust(
+        SignerKey::Token(token),
+        token.key_type(),
+        content,
+        config,
+        detached,
+        signing_time,
+      );
+    }
+
+    match config.cms_backend {
+      #[cfg(feature = "native")]
+      CmsBackend::OpenSsl => self.create_pkcs7_openssl(content, config, detached, signing_time),
+      CmsBackend::Rust => {
+        let key = self._private_key.as_ref().ok_or_else(|| {
+          PdfSignError::SigningError(
+            "o backend Rust do CMS suporta apenas chaves RSA; use CmsBackend::OpenSsl".to_string(),
+          )
+        })?;
+        self.create_pkcs7_rust(
+          SignerKey::Rsa(key),
+          KeyType::Rsa,
+          content,
+          config,
+          detached,
+          signing_time,
+        )
+      }
+    }
+  }
+
+  /// Certificados da cadeia que vão no CMS junto com o do signatário
+  fn cms_chain(&self, config: &SignatureConfig) -> &[Certificate] {
+    if config.include_chain_in_cms {
+      &self._cert_chain
+    } else {
+      &[]
+    }
+  }
+
+  /// Cria estrutura PKCS#7/CMS com o backend Rust e a chave informada
+  fn create_pkcs7_rust(
+    &self,
+    key: SignerKey<'_>,
+    key_type: KeyType,
+    content: CmsContent<'_>,
+    config: &SignatureConfig,
+    detached: bool,
+    signing_time: DateTime<Utc>,
+  ) -> Result<Vec<u8>> {
+    cms_signer::check_signature_scheme(config.signature_scheme, key_type, config.digest_algorithm)?;
+    let params = RustCmsParams {
+      key,
+      cert: &self._certificate,
+      chain: self.cms_chain(config),
+      digest: config.digest_algorithm,
+      scheme: config.signature_scheme,
+      detached,
+      signing_time,
+    };
+    rust_cms::sign(&params, content)
+  }
+
+  /// Cria estrutura PKCS#7/CMS usando OpenSSL
+  #[cfg(feature = "native")]
+  fn create_pkcs7_openssl(
+    &self,
+    content: CmsContent<'_>,
+    config: &SignatureConfig,
+    detached: bool,
+    signing_time: DateTime<Utc>,
+  ) -> Result<Vec<u8>> {
     use openssl::pkey::PKey;
     use openssl::stack::Stack;
     use openssl::x509::X509;
 
-    // Carrega TUDO do mesmo PEM para garantir compatibilidade
-    use openssl::provider::Provider;
-
-    // Garante que os providers estão carregados
-    let _legacy = Provider::load(None, "legacy").ok();
-    let _default = Provider::load(None, "default").ok();
+    load_openssl_providers();
 
+    // Carrega TUDO do mesmo PEM para garantir compatibilidade
     let pem_bytes = self._pem_content.as_bytes();
 
     let pkey = PKey::private_key_from_pem(pem_bytes).map_err(|e| {
       PdfSignError::DecodingError(format!("Erro ao carregar chave privada: {:?}", e))
     })?;
+    let key_type = match pkey.id() {
+      openssl::pkey::Id::RSA => KeyType::Rsa,
+      openssl::pkey::Id::ED25519 => KeyType::Ed25519,
+      openssl::pkey::Id::EC => KeyType::Ec,
+      _ => KeyType::Other,
+    };
+    cms_signer::check_signature_scheme(config.signature_scheme, key_type, config.digest_algorithm)?;
 
     // Carrega o primeiro certificado do mesmo PEM
     let cert = X509::from_pem(pem_bytes)
@@ -491,7 +1691,7 @@ impl PdfSigner {
     let mut certs = Stack::new()
       .map_err(|e| PdfSignError::DecodingError(format!("Erro ao criar stack: {:?}", e)))?;
 
-    for cert_chain in &self._cert_chain {
+    for cert_chain in self.cms_chain(config) {
       let cert_pem = format!(
         "-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n",
         base64::engine::general_purpose::STANDARD
@@ -510,20 +1710,19 @@ impl PdfSigner {
       }
     }
 
-    // Cria PKCS#7 detached (sem incluir o conteúdo, mas COM atributos assinados)
-    // NOSMIMECAP: remove S/MIME capabilities (não usado em PDF)
-    // Não usar NOATTR pois ele remove TODOS atributos incluindo messageDigest que é obrigatório
-    let flags = Pkcs7Flags::DETACHED | Pkcs7Flags::BINARY | Pkcs7Flags::NOSMIMECAP;
-
-    let pkcs7 = Pkcs7::sign(&cert, &pkey, &certs, data, flags)
-      .map_err(|e| PdfSignError::DecodingError(format!("Erro ao criar PKCS#7: {:?}", e)))?;
-
-    // Converte para DER
-    let pkcs7_der = pkcs7
-      .to_der()
-      .map_err(|e| PdfSignError::DecodingError(format!("Erro ao serializar PKCS#7: {:?}", e)))?;
+    // Cria o CMS (COM atributos assinados), incluindo o conteúdo apenas no modo enveloping
+    // O signatário é adicionado com o digest e o esquema configurados, em vez do padrão da chave
+    let params = CmsParams {
+      cert: &cert,
+      pkey: &pkey,
+      chain: &certs,
+      digest: config.digest_algorithm,
+      scheme: config.signature_scheme,
+      detached,
+      signing_time,
+    };
 
-    Ok(pkcs7_der)
+    openssl_cms::sign(&params, content)
   }
 
   /// Cria estrutura PKCS#7/CMS inicial (placeholder)
@@ -535,14 +1734,56 @@ impl PdfSigner {
   /// Retorna informações do certificado
   #[allow(dead_code)]
   pub fn get_certificate_info(&self) -> CertificateInfo {
-    CertificateInfo {
-      common_name: self._certificate.subject_cn().unwrap_or_default(),
-      organization: self._certificate.subject_org(),
-      email: None,
-      valid_from: self._certificate.not_before(),
-      valid_until: self._certificate.not_after(),
-      serial_number: Some(self._certificate.serial_number()),
+    certificate_info(&self._certificate, Utc::now())
+  }
+
+  /// Informações do certificado do signatário e de cada certificado da cadeia,
+  /// na ordem do caminho (signatário, AC emissora, ... até a raiz)
+  ///
+  /// Certificados do PFX que não fazem parte do caminho vêm no fim, na ordem original
+  pub fn chain_info(&self) -> Vec<CertificateInfo> {
+    let now = Utc::now();
+    let mut remaining: Vec<&Certificate> = self._cert_chain.iter().collect();
+    let mut path = vec![&self._certificate];
+    while let Some(current) = path.last() {
+      if current.subject_dn() == current.issuer_dn() {
+        break;
+      }
+      let Some(pos) = remaining
+        .iter()
+        .position(|cert| cert.subject_dn() == current.issuer_dn())
+      else {
+        break;
+      };
+      path.push(remaining.remove(pos));
     }
+    path
+      .into_iter()
+      .chain(remaining)
+      .map(|cert| certificate_info(cert, now))
+      .collect()
+  }
+}
+
+pub(crate) fn certificate_info(cert: &Certificate, now: DateTime<Utc>) -> CertificateInfo {
+  let icp_brasil_id = cert.icp_brasil_id();
+  CertificateInfo {
+    common_name: cert.subject_cn().unwrap_or_default(),
+    organization: cert.subject_org(),
+    email: None,
+    valid_from: cert.not_before(),
+    valid_until: cert.not_after(),
+    serial_number: Some(cert.serial_number()),
+    subject_dn: cert.subject_dn(),
+    issuer_dn: cert.issuer_dn(),
+    key_usage: cert.key_usage(),
+    extended_key_usage: cert.extended_key_usage(),
+    cpf: icp_brasil_id.cpf,
+    cnpj: icp_brasil_id.cnpj,
+    expired: cert.expired_at(now),
+    is_self_signed: cert.is_self_signed(),
+    thumbprint_sha1: cert.thumbprint(DigestAlgorithm::Sha1),
+    thumbprint_sha256: cert.thumbprint(DigestAlgorithm::Sha256),
   }
 }
 
@@ -556,75 +1797,6043 @@ pub struct CertificateInfo {
   pub valid_from: String,
   pub valid_until: String,
   pub serial_number: Option<String>,
+  pub subject_dn: String,
+  pub issuer_dn: String,
+  /// keyUsage com os nomes da RFC 5280 (ex: digitalSignature, nonRepudiation)
+  pub key_usage: Vec<String>,
+  pub extended_key_usage: Vec<String>,
+  /// CPF do titular (ou do responsável, em certificado de pessoa jurídica)
+  pub cpf: Option<String>,
+  pub cnpj: Option<String>,
+  /// Se o certificado já estava vencido no momento da consulta
+  pub expired: bool,
+  /// Emissor igual ao titular e assinado pela própria chave; None quando a assinatura
+  /// não pôde ser conferida (ver `Certificate::is_self_signed`)
+  pub is_self_signed: Option<bool>,
+  /// Impressões digitais do certificado (hex do digest do DER)
+  pub thumbprint_sha1: String,
+  pub thumbprint_sha256: String,
+}
+
+/// Certificado do signatário num PFX com várias identidades
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerSelector {
+  /// Número de série em hex, como em [`CertificateInfo::serial_number`] (aceita `:` e
+  /// espaços entre os bytes e zeros à esquerda)
+  SerialNumber(String),
+  /// CN do titular, comparado sem diferenciar maiúsculas
+  SubjectCn(String),
+}
+
+impl SignerSelector {
+  pub(crate) fn matches(&self, cert_der: &[u8]) -> bool {
+    let Ok(cert) = Certificate::from_der(cert_der.to_vec()) else {
+      return false;
+    };
+    match self {
+      SignerSelector::SerialNumber(serial) => {
+        normalize_serial(serial) == normalize_serial(&cert.serial_number())
+      }
+      SignerSelector::SubjectCn(cn) => cert
+        .subject_cn()
+        .is_some_and(|subject_cn| subject_cn.eq_ignore_ascii_case(cn.trim())),
+    }
+  }
+
+  pub(crate) fn not_found(&self) -> PdfSignError {
+    PdfSignError::DecodingError(format!("nenhum certificado do PKCS#12 tem {}", self))
+  }
+
+  pub(crate) fn key_not_found(&self) -> PdfSignError {
+    PdfSignError::DecodingError(format!(
+      "o PKCS#12 não tem a chave privada do certificado com {}",
+      self
+    ))
+  }
+}
+
+impl std::fmt::Display for SignerSelector {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SignerSelector::SerialNumber(serial) => write!(f, "número de série {}", serial),
+      SignerSelector::SubjectCn(cn) => write!(f, "CN \"{}\"", cn),
+    }
+  }
+}
+
+fn normalize_serial(serial: &str) -> String {
+  let hex: String = serial
+    .chars()
+    .filter(|c| !matches!(c, ':' | ' '))
+    .map(|c| c.to_ascii_lowercase())
+    .collect();
+  hex.trim_start_matches('0').to_string()
+}
+
+/// AcroForm do Catalog vigente como (objeto, corpo do dicionário)
+///
+/// Um AcroForm escrito direto no Catalog (`/AcroForm << ... >>`) não tem objeto e vem
+/// com `None`; quem o regrava dá a ele um objeto novo
+fn find_acroform(pdf_data: &[u8], catalog_obj: usize) -> Option<(Option<usize>, &[u8])> {
+  let catalog = find_object_section(pdf_data, catalog_obj).and_then(dict_body)?;
+  let body = resolve_dict(pdf_data, catalog, b"/AcroForm")?;
+  let acroform_obj = read_reference(catalog, b"/AcroForm").map(|(obj, _)| obj);
+  Some((acroform_obj, body))
+}
+
+/// Corpo do AcroForm existente com o campo novo no fim de /Fields (sem /SigFlags)
+///
+/// /Fields pode ser um array direto ou uma referência a um objeto array; nesse caso
+/// o array é copiado para o AcroForm regravado
+fn append_acroform_field(pdf_data: &[u8], body: &[u8], field_obj: usize) -> String {
+  append_array_ref(
+    pdf_data,
+    remove_dict_entry(body, b"/SigFlags"),
+    "/Fields",
+    field_obj,
+  )
+}
+
+/// Acrescenta "N 0 R" ao array da chave no corpo do dicionário, criando-o se preciso
+///
+/// Um array indireto ("/Annots 9 0 R") é copiado para o dicionário com o item novo,
+/// sem regravar o objeto array original
+fn append_array_ref(pdf_data: &[u8], mut text: String, key: &str, obj: usize) -> String {
+  let field_ref = format!(" {} 0 R", obj);
+  if !dict_has_key(text.as_bytes(), key.as_bytes()) {
+    text.push_str(&format!("\n{} [{}]", key, field_ref.trim_start()));
+    return text;
+  }
+
+  let key_end = text.find(key).unwrap_or_default() + key.len();
+  let value_start = key_end + text[key_end..].len() - text[key_end..].trim_start().len();
+  if text[value_start..].starts_with('[') {
+    if let Some(close) = text[value_start..].find(']') {
+      text.insert_str(value_start + close, &field_ref);
+    }
+    return text;
+  }
+
+  // Chave N 0 R: troca a referência pelo conteúdo do array mais o item novo
+  let value_end = text[value_start..]
+    .find('R')
+    .map_or(text.len(), |r| value_start + r + 1);
+  let items = read_number_after(text.as_bytes(), key.as_bytes())
+    .and_then(|array_obj| find_object_section(pdf_data, array_obj))
+    .and_then(|section| {
+      let start = section.iter().position(|&c| c == b'[')? + 1;
+      let end = section.iter().rposition(|&c| c == b']')?;
+      (end >= start).then(|| String::from_utf8_lossy(&section[start..end]).to_string())
+    })
+    .unwrap_or_default();
+  text.replace_range(
+    value_start..value_end,
+    &format!("[{}{}]", items.trim(), field_ref),
+  );
+  text
+}
+
+/// Primeiro nome SignatureN ainda não usado como /T no documento
+fn unused_signature_field_name(pdf_data: &[u8]) -> String {
+  (1..)
+    .map(|n| format!("Signature{}", n))
+    .find(|name| {
+      let title = format!("/T ({})", name);
+      !pdf_data.windows(title.len()).any(|w| w == title.as_bytes())
+    })
+    .unwrap_or_default()
 }
 
 /// Constrói um novo Catalog preservando campos extras do original
 /// Isso é crítico para PDFs reconstruídos que podem ter metadados personalizados
+///
+/// Cada entrada do Catalog vigente é copiada inteira (/Names, /OpenAction, /Outlines,
+/// /ViewerPreferences, /Metadata, /Lang...), exceto as que a atualização redefine
 fn build_updated_catalog(
   catalog_obj: usize,
   pages_ref: usize,
-  acroform_ref: usize,
+  acroform_ref: Option<usize>,
   pdf_data: &[u8],
+  extra_entries: &[String],
 ) -> Result<String> {
-  // Busca o Catalog original
-  let catalog_pattern = format!("{} 0 obj", catalog_obj);
+  let current = find_object_section(pdf_data, catalog_obj).and_then(dict_body);
+  let mut new_catalog = format!(
+    "{} {} obj\n<<\n/Type /Catalog\n/Pages {}\n{}",
+    catalog_obj,
+    object_generation(pdf_data, catalog_obj),
+    catalog_reference(pdf_data, current, b"/Pages", pages_ref),
+    acroform_ref
+      .map(|acroform_obj| format!(
+        "/AcroForm {}\n",
+        catalog_reference(pdf_data, current, b"/AcroForm", acroform_obj)
+      ))
+      .unwrap_or_default()
+  );
 
-  if let Some(catalog_start) = pdf_data
-    .windows(catalog_pattern.len())
-    .position(|w| w == catalog_pattern.as_bytes())
-  {
-    if let Some(catalog_end) = pdf_data[catalog_start..]
-      .windows(b"endobj".len())
-      .position(|w| w == b"endobj")
-    {
-      let catalog_section = &pdf_data[catalog_start..catalog_start + catalog_end];
-
-      // Extrai campos extras do Catalog (tudo exceto /Type, /Pages e /AcroForm)
-      let catalog_str = String::from_utf8_lossy(catalog_section);
-
-      // Procura o dicionário do catalog (entre << e >>)
-      if let Some(dict_start) = catalog_str.find("<<") {
-        if let Some(dict_end) = catalog_str.rfind(">>") {
-          let dict_content = &catalog_str[dict_start + 2..dict_end];
-
-          // Extrai campos extras (preserva tudo exceto /Type, /Pages, /AcroForm)
-          let mut extra_fields = Vec::new();
-          let lines: Vec<&str> = dict_content.lines().collect();
-
-          for line in lines {
-            let trimmed = line.trim();
-            // Ignora campos que vamos redefinir
-            if !trimmed.starts_with("/Type")
-              && !trimmed.starts_with("/Pages")
-              && !trimmed.starts_with("/AcroForm")
-              && !trimmed.is_empty()
-            {
-              extra_fields.push(trimmed);
-            }
-          }
+  let replaced: Vec<&[u8]> = extra_entries
+    .iter()
+    .filter_map(|entry| entry.split_whitespace().next())
+    .map(str::as_bytes)
+    .chain([b"/Type" as &[u8], b"/Pages"])
+    .chain(acroform_ref.map(|_| b"/AcroForm" as &[u8]))
+    .collect();
+  if let Some(catalog) = current {
+    for (key, entry) in dict_entries(catalog) {
+      if !replaced.contains(&key) {
+        new_catalog.push_str(&String::from_utf8_lossy(entry));
+        new_catalog.push('\n');
+      }
+    }
+  }
 
-          // Constrói o novo Catalog com campos extras preservados
-          let mut new_catalog = format!(
-            "{} 0 obj\n<<\n/Type /Catalog\n/Pages {} 0 R\n/AcroForm {} 0 R\n",
-            catalog_obj, pages_ref, acroform_ref
-          );
+  for entry in extra_entries {
+    new_catalog.push_str(entry);
+    new_catalog.push('\n');
+  }
+  new_catalog.push_str(">>\nendobj\n");
+  Ok(new_catalog)
+}
 
-          // Adiciona campos extras
-          for field in extra_fields {
-            new_catalog.push_str(field);
-            new_catalog.push('\n');
-          }
+/// Referência "N G R" para uma entrada do Catalog
+///
+/// A geração vem da referência que o Catalog vigente já faz ao mesmo objeto; sem ela,
+/// do cabeçalho do objeto no documento (0 para objetos novos)
+fn catalog_reference(pdf_data: &[u8], catalog: Option<&[u8]>, key: &[u8], obj: usize) -> String {
+  let generation = catalog
+    .and_then(|catalog| read_reference(catalog, key))
+    .filter(|(num, _)| *num == obj)
+    .map_or_else(
+      || object_generation(pdf_data, obj),
+      |(_, generation)| generation,
+    );
+  format!("{} {} R", obj, generation)
+}
+
+/// TSA que emitiu o carimbo do tempo e o genTime dele
+type Timestamped = (String, DateTime<Utc>);
+
+/// Resultado de uma assinatura de PDF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignReport {
+  /// Nível PAdES efetivamente produzido (pode ser menor que o pedido)
+  pub pades_level: PadesLevel,
+  /// Avisos da assinatura, como o motivo de um rebaixamento do nível PAdES
+  pub warnings: Vec<String>,
+  /// O PDF de entrada era linearizado: a atualização incremental invalida as dicas de
+  /// linearização, e os leitores passam a abri-lo como um PDF comum
+  pub linearized: bool,
+  /// TSA que emitiu o carimbo do tempo (B-T ou acima), entre `tsa_url` e `tsa_urls`
+  pub tsa_url: Option<String>,
+  /// Instante do carimbo do tempo (genTime do TSTInfo), independente do relógio local do /M
+  pub timestamp_time: Option<DateTime<Utc>>,
+  /// Nome, data, campo e objetos gravados pela assinatura
+  pub signature: Option<SignatureRecord>,
+  /// Classe do certificado ICP-Brasil (e-CPF ou e-CNPJ), que define os padrões de
+  /// /Name e /Reason; None fora da ICP-Brasil
+  pub certificate_kind: Option<IcpBrasilKind>,
+}
+
+/// O que a assinatura gravou no PDF, para log e exibição sem reler o documento
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureRecord {
+  /// Nome gravado em /Name (`signer_name` ou o CN do certificado); None quando omitido
+  pub signer_name: Option<String>,
+  /// Instante gravado em /M, repetido no signingTime do CMS
+  pub signing_time: DateTime<Utc>,
+  /// Campo que recebeu a assinatura (ausente na assinatura de direitos de uso)
+  pub field_name: Option<String>,
+  /// Objeto do dicionário de assinatura
+  pub signature_obj: usize,
+  /// Objeto do campo (widget) cujo /V aponta para a assinatura
+  pub field_obj: Option<usize>,
+}
+
+/// Atualização incremental assinada, devolvida por `PdfSigner::sign_incremental_only`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedIncrement {
+  /// Bytes a acrescentar depois dos `base_len` primeiros bytes do original
+  pub bytes: Vec<u8>,
+  /// Tamanho do original sem os fins de linha finais, onde a atualização começa
+  pub base_len: usize,
+  /// ByteRange gravado, com offsets do documento concatenado
+  pub byte_range: [usize; 4],
+  /// TSA que emitiu o carimbo do tempo (B-T ou acima)
+  pub tsa_url: Option<String>,
+  /// Instante do carimbo do tempo (genTime do TSTInfo)
+  pub timestamp_time: Option<DateTime<Utc>>,
+  /// Nome, data, campo e objetos gravados pela assinatura
+  pub signature: SignatureRecord,
+}
+
+/// O que a assinatura faria com o documento, calculado por `PdfSigner::analyze`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignPlan {
+  /// Objeto do Catalog, regravado na atualização incremental
+  pub catalog_obj: usize,
+  /// Objeto /Pages referenciado pelo Catalog
+  pub pages_obj: usize,
+  /// Primeira página, que recebe o widget da assinatura
+  pub first_page_obj: usize,
+  /// Número do dicionário de assinatura, o primeiro objeto livre
+  pub next_obj: usize,
+  /// Se o Catalog já referencia um AcroForm (os campos dele são mantidos)
+  pub has_acroform: bool,
+  /// Campo que recebe a assinatura; `None` na assinatura de direitos de uso, que não cria campo
+  pub field_name: Option<String>,
+  /// Offset, no PDF assinado, do '<' do placeholder de /Contents (ByteRange[1])
+  pub contents_offset: usize,
+  /// Tamanho do placeholder de /Contents, com os delimitadores < >
+  pub contents_len: usize,
+  /// Nível PAdES que a configuração alcança
+  pub pades_level: PadesLevel,
+  /// Avisos, como o motivo de um rebaixamento do nível PAdES
+  pub warnings: Vec<String>,
+  /// O documento é linearizado, e a assinatura invalidaria as dicas de linearização
+  pub linearized: bool,
+}
+
+/// Nome gravado em /Name: `signer_name` da configuração ou, do certificado, o CN do
+/// titular, a organização (O) ou o CN do emissor; "Unknown" só quando nenhum existe
+fn signer_display_name(certificate: &Certificate, config: &SignatureConfig) -> String {
+  // e-CNPJ: o CN é "RAZÃO SOCIAL:CNPJ" e o O é sempre "ICP-Brasil", então o selo
+  // leva o nome da pessoa jurídica sem o CNPJ
+  let id = certificate.icp_brasil_id();
+  let seal_name = match (id.kind(), id.cnpj, certificate.subject_cn()) {
+    (Some(IcpBrasilKind::Seal), Some(cnpj), Some(cn)) => cn
+      .strip_suffix(cnpj.as_str())
+      .and_then(|name| name.strip_suffix(':'))
+      .map(str::to_string),
+    _ => None,
+  };
+  [
+    config.signer_name.clone(),
+    seal_name,
+    certificate.subject_cn(),
+    certificate.subject_org(),
+    certificate.issuer_cn(),
+  ]
+  .into_iter()
+  .flatten()
+  .find(|name| !name.trim().is_empty())
+  .unwrap_or_else(|| "Unknown".to_string())
+}
+
+/// Recusa entradas acima de `max_input_bytes`
+pub(crate) fn check_input_size(len: u64, config: &SignatureConfig) -> Result<()> {
+  match config.max_input_bytes {
+    Some(max) if len > max => Err(PdfSignError::InvalidPdf(format!(
+      "o PDF tem {} bytes, acima do limite de {} bytes (max_input_bytes)",
+      len, max
+    ))),
+    _ => Ok(()),
+  }
+}
+
+/// Motivo gravado em /Reason: `reason` da configuração ou, na falta dele, o padrão
+/// ICP-Brasil do tipo de certificado (assinatura do e-CPF ou selo do e-CNPJ); vazio
+/// omite a entrada
+fn signature_reason(certificate: &Certificate, config: &SignatureConfig) -> String {
+  match &config.reason {
+    Some(reason) => reason.clone(),
+    None => match certificate.icp_brasil_id().kind() {
+      Some(IcpBrasilKind::Signature) => ICP_BRASIL_REASON.to_string(),
+      Some(IcpBrasilKind::Seal) => ICP_BRASIL_SEAL_REASON.to_string(),
+      None => String::new(),
+    },
+  }
+}
+
+/// Recusa certificados cujo keyUsage não permite assinar, salvo `ignore_key_usage`
+fn check_key_usage(certificate: &Certificate, config: &SignatureConfig) -> Result<()> {
+  if config.ignore_key_usage || certificate.allows_signing() {
+    return Ok(());
+  }
+  Err(PdfSignError::KeyUsageError(format!(
+    "o keyUsage do certificado ({}) não tem digitalSignature nem nonRepudiation; \
+     use ignore_key_usage para assinar assim mesmo",
+    certificate.key_usage().join(", ")
+  )))
+}
+
+/// Recusa um motivo fora do vocabulário de `allowed_reasons`, quando informado
+fn check_reason(reason: &str, config: &SignatureConfig) -> Result<()> {
+  match &config.allowed_reasons {
+    Some(allowed) if !allowed.iter().any(|allowed| allowed == reason) => {
+      Err(PdfSignError::SigningError(format!(
+        "motivo \"{}\" não está entre os permitidos: {}",
+        reason,
+        allowed.join(", ")
+      )))
+    }
+    _ => Ok(()),
+  }
+}
+
+/// Condições do documento e da configuração para assinar, sem validar a cadeia
+fn check_document(pdf_data: &[u8], config: &SignatureConfig) -> Result<()> {
+  check_usage_rights(pdf_data, config)?;
+  if config.sub_filter == SubFilter::Pkcs7Sha1 && config.digest_algorithm != DigestAlgorithm::Sha1 {
+    return Err(PdfSignError::SigningError(
+      "adbe.pkcs7.sha1 exige digest_algorithm Sha1".to_string(),
+    ));
+  }
+  if config.filter.is_empty() {
+    return Err(PdfSignError::SigningError(
+      "filter não pode ser vazio (padrão: Adobe.PPKLite)".to_string(),
+    ));
+  }
+  // O widget novo é invisível: a aparência vai num campo criado antes, com área
+  if config.appearance.is_some() && config.fill_existing_field.is_none() {
+    return Err(PdfSignError::SigningError(
+      "appearance exige fill_existing_field (campo criado com add_empty_signature_field)"
+        .to_string(),
+    ));
+  }
+  if config.pdfa_mode {
+    check_pdfa(pdf_data, config)?;
+  }
 
-          new_catalog.push_str(">>\nendobj\n");
-          return Ok(new_catalog);
+  // Recusa logo de início arquivos que não são PDF (ex: DOCX), antes que os scanners se percam
+  let Some(version) = parse_pdf_version(pdf_data) else {
+    return Err(PdfSignError::InvalidPdf(
+      "arquivo não é um PDF (cabeçalho %PDF-x.y ausente)".to_string(),
+    ));
+  };
+  if config.xref_format == XrefFormat::Stream && version < (1, 5) {
+    return Err(PdfSignError::SigningError(format!(
+      "xref stream exige PDF 1.5 ou superior, e o documento é PDF {}.{}",
+      version.0, version.1
+    )));
+  }
+
+  // Documento certificado com /P 1 não admite alterações: a nova assinatura quebraria a certificação
+  if find_docmdp_permission(pdf_data)? == Some(1) {
+    return Err(PdfSignError::InvalidPdf(
+      "documento certificado (DocMDP /P 1) não permite alterações, nem novas assinaturas"
+        .to_string(),
+    ));
+  }
+  Ok(())
+}
+
+/// Recusa combinações que misturariam a assinatura de direitos de uso com a de aprovação
+fn check_usage_rights(pdf_data: &[u8], config: &SignatureConfig) -> Result<()> {
+  if config.usage_rights.is_none() {
+    return Ok(());
+  }
+  if config.fill_existing_field.is_some() || config.lock.is_some() {
+    return Err(PdfSignError::SigningError(
+      "usage_rights não pode ser combinado com fill_existing_field nem lock".to_string(),
+    ));
+  }
+  // O /Perms existente (DocMDP ou outro UR3) seria perdido ao regravar o Catalog
+  let has_perms = extract_catalog_info(pdf_data)
+    .ok()
+    .and_then(|info| find_object_section(pdf_data, info.catalog_obj))
+    .and_then(dict_body)
+    .is_some_and(|catalog| dict_has_key(catalog, b"/Perms"));
+  if has_perms {
+    return Err(PdfSignError::InvalidPdf(
+      "documento já possui /Perms; a assinatura de direitos de uso não pode ser adicionada"
+        .to_string(),
+    ));
+  }
+  Ok(())
+}
+
+/// Recusa, no modo PDF/A, documentos e operações que quebrariam a conformidade
+///
+/// A assinatura em si já é PDF/A (/SubFilter adbe.pkcs7.detached, widget invisível
+/// e imprimível, sem fontes); aqui ficam as condições que dependem do documento
+fn check_pdfa(pdf_data: &[u8], config: &SignatureConfig) -> Result<()> {
+  let pdfa_error = |detail: &str| PdfSignError::InvalidPdf(format!("modo PDF/A: {}", detail));
+
+  if is_encrypted(pdf_data) {
+    return Err(pdfa_error("documento criptografado (/Encrypt) não é PDF/A"));
+  }
+
+  let catalog_info = extract_catalog_info(pdf_data)?;
+  let catalog = find_object_section(pdf_data, catalog_info.catalog_obj)
+    .and_then(dict_body)
+    .ok_or_else(|| pdfa_error("Catalog ilegível"))?;
+  let is_xmp = read_number_after(catalog, b"/Metadata")
+    .and_then(|metadata| find_object_section(pdf_data, metadata))
+    .and_then(dict_body)
+    .is_some_and(|metadata| dict_has_key(metadata, b"/XML"));
+  if !is_xmp {
+    return Err(pdfa_error(
+      "Catalog sem /Metadata XMP (/Subtype /XML), onde fica a declaração PDF/A",
+    ));
+  }
+
+  // /NeedAppearances true é proibido no PDF/A-2, e o AcroForm é regravado como está
+  let need_appearances =
+    find_acroform(pdf_data, catalog_info.catalog_obj).is_some_and(|(_, acroform)| {
+      dict_entries(acroform)
+        .into_iter()
+        .any(|(key, entry)| key == b"/NeedAppearances" && entry.ends_with(b"true"))
+    });
+  if need_appearances {
+    return Err(pdfa_error("AcroForm com /NeedAppearances true"));
+  }
+
+  // O PDF/A-2 só admite assinaturas adbe.pkcs7.detached (ou PAdES)
+  if config.sub_filter != SubFilter::Pkcs7Detached {
+    return Err(pdfa_error(&format!(
+      "/SubFilter {} não é permitido",
+      config.sub_filter.name()
+    )));
+  }
+
+  // A Helvetica da aparência não é incorporada, o que o PDF/A proíbe
+  let has_text = config.appearance.as_ref().is_some_and(|appearance| {
+    appearance
+      .elements
+      .iter()
+      .any(|element| matches!(element.content, AppearanceContent::Text { .. }))
+  });
+  if has_text {
+    return Err(pdfa_error(
+      "texto na aparência usa fonte não incorporada; use só imagens",
+    ));
+  }
+
+  // O campo do template vira a aparência da assinatura: precisa ser imprimível e,
+  // se tiver área, trazer a própria /AP ou receber a de `appearance`
+  if let Some(field_name) = &config.fill_existing_field {
+    let field = find_signature_field(pdf_data, field_name)
+      .and_then(|field_obj| find_object_section(pdf_data, field_obj))
+      .and_then(dict_body);
+    if let Some(field) = field {
+      const HIDING_FLAGS: usize = 1 | 2 | 32 | 256; // Invisible, Hidden, NoView, ToggleNoView
+      let flags = read_number_after(field, b"/F").unwrap_or(0);
+      if flags & 4 == 0 || flags & HIDING_FLAGS != 0 {
+        return Err(pdfa_error(&format!(
+          "campo \"{}\" precisa ser imprimível e visível (/F com o bit Print)",
+          field_name
+        )));
+      }
+      if rect_has_area(field) && !dict_has_key(field, b"/AP") && config.appearance.is_none() {
+        return Err(pdfa_error(&format!(
+          "campo \"{}\" visível sem aparência (/AP)",
+          field_name
+        )));
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Confere a declaração PDF/A (pdfaid:part) no stream /Metadata do Catalog atual,
+/// lendo o documento em blocos
+///
+/// O Catalog vem de `skeleton` (o documento ou o esqueleto sem os streams); o XMP,
+/// que o PDF/A exige sem compressão, é lido da última revisão do objeto em `reader`
+fn check_pdfa_declaration<R: Read>(mut reader: R, skeleton: &[u8]) -> Result<()> {
+  const MARKER: &[u8] = b"pdfaid:part";
+  const END: &[u8] = b"endobj";
+  let undeclared = || {
+    PdfSignError::InvalidPdf(
+      "modo PDF/A: documento não declara conformidade PDF/A (pdfaid:part no XMP do /Metadata)"
+        .to_string(),
+    )
+  };
+  let catalog_obj = extract_catalog_info(skeleton)?.catalog_obj;
+  let (metadata_obj, generation) = find_object_section(skeleton, catalog_obj)
+    .and_then(dict_body)
+    .and_then(|catalog| read_reference(catalog, b"/Metadata"))
+    .ok_or_else(undeclared)?;
+  let header = format!("{} {} obj", metadata_obj, generation).into_bytes();
+  let find = |data: &[u8], pattern: &[u8]| data.windows(pattern.len()).position(|w| w == pattern);
+
+  let mut chunk = vec![0u8; STREAM_CHUNK_SIZE];
+  let mut window: Vec<u8> = Vec::new();
+  let mut at_start = true;
+  // Dentro de uma revisão do /Metadata e se a última revisão lida declara o PDF/A
+  let mut inside = false;
+  let mut declared = false;
+  loop {
+    let read = reader.read(&mut chunk)?;
+    window.extend_from_slice(&chunk[..read]);
+    let mut pos = 0;
+    loop {
+      if inside {
+        let rest = &window[pos..];
+        match (find(rest, MARKER), find(rest, END)) {
+          (Some(marker), end) if end.is_none_or(|end| marker < end) => {
+            declared = true;
+            pos += marker + MARKER.len();
+          }
+          (_, Some(end)) => {
+            inside = false;
+            pos += end + END.len();
+          }
+          _ => break,
+        }
+      } else {
+        // O cabeçalho "N G obj" não pode continuar um número maior ("1N G obj")
+        let start = pos.max(usize::from(!at_start));
+        let found = (start..window.len())
+          .filter(|&at| window[at..].starts_with(&header))
+          .find(|&at| at == 0 || !window[at - 1].is_ascii_digit());
+        match found {
+          Some(at) => {
+            inside = true;
+            declared = false;
+            pos = at + header.len();
+          }
+          None => break,
         }
       }
     }
+    if read == 0 {
+      break;
+    }
+    // Guarda o fim do bloco para achar os marcadores divididos entre dois blocos,
+    // com o byte anterior ao cabeçalho
+    let keep = header.len().max(MARKER.len()).max(END.len()) + 1;
+    let drained = pos.max(window.len().saturating_sub(keep));
+    if drained > 0 {
+      at_start = false;
+    }
+    window.drain(..drained);
+  }
+  if declared {
+    Ok(())
+  } else {
+    Err(undeclared())
+  }
+}
+
+/// Indica se algum trailer (ou xref stream) do documento declara /Encrypt
+fn is_encrypted(pdf_data: &[u8]) -> bool {
+  let trailers = pdf_data
+    .windows(b"trailer".len())
+    .enumerate()
+    .filter(|(_, w)| *w == b"trailer")
+    .map(|(pos, _)| {
+      let section = &pdf_data[pos..];
+      let end = section
+        .windows(b"startxref".len())
+        .position(|w| w == b"startxref")
+        .unwrap_or(section.len());
+      &section[..end]
+    });
+  // Dicionário da xref stream: do "obj" até o início do stream
+  let xref_streams = pdf_data
+    .windows(b"/XRef".len())
+    .enumerate()
+    .filter(|(_, w)| *w == b"/XRef")
+    .filter_map(|(pos, _)| {
+      let start = pdf_data[..pos].windows(3).rposition(|w| w == b"obj")?;
+      let end = pos
+        + pdf_data[pos..]
+          .windows(b"stream".len())
+          .position(|w| w == b"stream")
+          .unwrap_or(pdf_data.len() - pos);
+      Some(&pdf_data[start..end])
+    });
+  trailers
+    .chain(xref_streams)
+    .any(|dict| dict_has_key(dict, b"/Encrypt"))
+}
+
+/// Os quatro números do /Rect do dicionário
+fn read_rect(dict: &[u8]) -> Option<[f64; 4]> {
+  let (_, entry) = dict_entries(dict)
+    .into_iter()
+    .find(|(key, _)| *key == b"/Rect")?;
+  let numbers: Vec<f64> = String::from_utf8_lossy(entry)
+    .trim_start_matches("/Rect")
+    .trim_matches(|c: char| c.is_whitespace() || c == '[' || c == ']')
+    .split_whitespace()
+    .filter_map(|n| n.parse().ok())
+    .collect();
+  numbers.try_into().ok()
+}
+
+/// Indica se o /Rect do dicionário tem largura e altura
+fn rect_has_area(dict: &[u8]) -> bool {
+  read_rect(dict).is_some_and(|[x1, y1, x2, y2]| x1 != x2 && y1 != y2)
+}
+
+/// Ajusta o nível PAdES ao que a configuração alcança, conforme `pades_level_mode`
+///
+/// Evita que uma assinatura B-B seja entregue como se fosse B-LT
+fn normalize_pades_level(config: &SignatureConfig) -> Result<(SignatureConfig, SignReport)> {
+  let (pades_level, warnings) = config.effective_pades_level();
+  if !warnings.is_empty() && config.pades_level_mode == PadesLevelMode::Strict {
+    return Err(PdfSignError::SigningError(format!(
+      "nível PAdES {:?} não é alcançável com esta configuração: {}",
+      config.pades_level,
+      warnings.join("; ")
+    )));
   }
 
-  // Fallback: cria Catalog básico se não conseguir extrair o original
-  Ok(format!(
-    "{} 0 obj\n<<\n/Type /Catalog\n/Pages {} 0 R\n/AcroForm {} 0 R\n>>\nendobj\n",
-    catalog_obj, pages_ref, acroform_ref
+  let normalized = SignatureConfig {
+    pades_level,
+    ..config.clone()
+  };
+  Ok((
+    normalized,
+    SignReport {
+      pades_level,
+      warnings,
+      linearized: false,
+      tsa_url: None,
+      timestamp_time: None,
+      signature: None,
+      certificate_kind: None,
+    },
   ))
 }
+
+/// Relê a última assinatura do PDF: ByteRange, digest recalculado e CMS
+fn verify_last_signature(pdf: &[u8], digest: DigestAlgorithm) -> Result<()> {
+  let entry = list_signatures(pdf).pop();
+  let range = checked_byte_range(entry.as_ref(), pdf.len())?;
+  cms_signer::verify_contents(
+    &pdf[range[0] + range[1]..range[2]],
+    &expected_message_digest(entry.as_ref(), byte_range_digest(pdf, digest)?, digest),
+    digest,
+  )
+}
+
+/// messageDigest esperado no CMS para o hash do ByteRange
+///
+/// No adbe.pkcs7.sha1 o CMS encapsula o hash do ByteRange, e o messageDigest é o
+/// hash desse conteúdo
+fn expected_message_digest(
+  entry: Option<&SignatureEntry>,
+  byte_range_hash: Vec<u8>,
+  digest: DigestAlgorithm,
+) -> Vec<u8> {
+  match entry.and_then(|entry| entry.sub_filter.as_deref()) {
+    Some(name) if name == SubFilter::Pkcs7Sha1.name() => digest.digest(&byte_range_hash),
+    _ => byte_range_hash,
+  }
+}
+
+/// Como `verify_last_signature`, lendo o arquivo gravado em blocos
+///
+/// O ByteRange vem da atualização `tail`; o conteúdo coberto e o /Contents são
+/// lidos do próprio arquivo
+fn verify_signed_file(path: &Path, tail: &[u8], digest: DigestAlgorithm) -> Result<()> {
+  let mut file = File::open(path)?;
+  let len = file.metadata()?.len() as usize;
+  let entry = list_signatures(tail).pop();
+  let range = checked_byte_range(entry.as_ref(), len)?;
+
+  let mut hasher = digest.hasher();
+  let mut chunk = vec![0u8; STREAM_CHUNK_SIZE];
+  for (start, length) in [(range[0], range[1]), (range[2], range[3])] {
+    file.seek(SeekFrom::Start(start as u64))?;
+    let mut reader = (&mut file).take(length as u64);
+    loop {
+      let read = reader.read(&mut chunk)?;
+      if read == 0 {
+        break;
+      }
+      hasher.update(&chunk[..read]);
+    }
+  }
+
+  let mut contents = vec![0u8; range[2] - range[0] - range[1]];
+  file.seek(SeekFrom::Start((range[0] + range[1]) as u64))?;
+  file.read_exact(&mut contents)?;
+  cms_signer::verify_contents(
+    &contents,
+    &expected_message_digest(entry.as_ref(), hasher.finalize().to_vec(), digest),
+    digest,
+  )
+}
+
+/// Confere que o ByteRange calculado cobre o arquivo inteiro, menos o /Contents
+///
+/// Um intervalo curto deixaria bytes do fim fora da assinatura: conteúdo
+/// acrescentado ali passaria como assinado
+fn check_byte_range_coverage(range: &[usize; 4], len: usize) -> Result<()> {
+  if range[0] != 0 || range[2].checked_add(range[3]) != Some(len) {
+    return Err(PdfSignError::SigningError(format!(
+      "/ByteRange {:?} não cobre o arquivo inteiro ({} bytes)",
+      range, len
+    )));
+  }
+  Ok(())
+}
+
+fn self_verify_failed(error: PdfSignError) -> PdfSignError {
+  PdfSignError::SigningError(format!("auto-verificação da assinatura falhou: {}", error))
+}
+
+/// Trailer vigente da base de uma atualização incremental e o próximo número livre
+///
+/// Sem "startxref" a xref nova não tem /Prev, e o PDF é recusado. O número livre vem
+/// do /Size; só sem ele o arquivo é varrido atrás do maior cabeçalho "N G obj"
+fn update_trailer(pdf_data: &[u8]) -> Result<(TrailerInfo, u32)> {
+  let trailer = parse_trailer(pdf_data);
+  if trailer.startxref.is_none() {
+    return Err(PdfSignError::InvalidPdf(
+      "startxref ausente: a atualização incremental não teria /Prev".to_string(),
+    ));
+  }
+  let next_obj = match trailer
+    .size
+    .filter(|&size| size > 0)
+    .and_then(|size| u32::try_from(size).ok())
+  {
+    Some(size) => size,
+    None => get_next_object_number(pdf_data)?,
+  };
+  Ok((trailer, next_obj))
+}
+
+/// /Root do trailer novo: a geração vem do /Root vigente quando ele aponta para o
+/// mesmo Catalog, e só então do cabeçalho do objeto
+fn root_reference(pdf_data: &[u8], trailer: &TrailerInfo, catalog_obj: usize) -> (usize, u16) {
+  let generation = trailer
+    .root
+    .filter(|(obj, _)| *obj == catalog_obj)
+    .map_or_else(
+      || object_generation(pdf_data, catalog_obj),
+      |(_, generation)| generation,
+    );
+  (catalog_obj, generation)
+}
+
+/// Confere que o startxref do original aponta para uma xref de verdade
+///
+/// O /Prev da atualização repete esse offset; se ele não cai numa seção "xref" ou
+/// num xref stream, a cadeia fica quebrada e os leitores estritos descartam o documento
+fn check_prev_xref(section: &[u8], offset: usize) -> Result<()> {
+  if !is_xref_section(section) {
+    return Err(PdfSignError::InvalidPdf(format!(
+      "startxref aponta para o offset {}, que não é uma seção xref nem um xref stream",
+      offset
+    )));
+  }
+  Ok(())
+}
+
+/// Confere que o `<...>` achado após /Contents é o placeholder inteiro: só zeros, no tamanho reservado
+///
+/// Um '<' ou '>' perdido faria o ByteRange excluir os bytes errados
+fn check_contents_placeholder(placeholder: &[u8], sig_size: usize) -> Result<()> {
+  match placeholder
+    .strip_prefix(b"<")
+    .and_then(|rest| rest.strip_suffix(b">"))
+  {
+    Some(hex) if hex.len() == sig_size && hex.iter().all(|&b| b == b'0') => Ok(()),
+    _ => Err(PdfSignError::InvalidPdf(format!(
+      "placeholder de /Contents com {} bytes não corresponde aos {} reservados",
+      placeholder.len(),
+      sig_size + 2
+    ))),
+  }
+}
+
+/// CMS em hex completado com o padding até `sig_size` caracteres
+fn pad_signature_hex(cms: &[u8], sig_size: usize, padding: ContentsPadding) -> Result<String> {
+  let sig_hex = hex::encode(cms);
+
+  // Verifica se a assinatura cabe no placeholder (sem os delimitadores < >)
+  if sig_hex.len() > sig_size {
+    return Err(PdfSignError::InvalidPdf(format!(
+      "Assinatura muito grande: {} bytes, mas placeholder tem apenas {} bytes",
+      sig_hex.len(),
+      sig_size
+    )));
+  }
+
+  let mut padded = sig_hex;
+  padded.extend(std::iter::repeat_n(
+    padding.hex_char(),
+    sig_size - padded.len(),
+  ));
+
+  // String hex do PDF: só dígitos hex (nenhum '>' que feche o /Contents antes da hora),
+  // em quantidade par e ocupando exatamente o placeholder
+  if padded.len() != sig_size
+    || !padded.len().is_multiple_of(2)
+    || !padded.bytes().all(|b| b.is_ascii_hexdigit())
+  {
+    return Err(PdfSignError::InvalidPdf(format!(
+      "hex da assinatura inválido para o placeholder de {} bytes",
+      sig_size
+    )));
+  }
+
+  Ok(padded)
+}
+
+/// Erro para opções que dependem de rede ou OpenSSL no build sem a feature `native`
+#[cfg(not(feature = "native"))]
+fn requires_native(option: &str) -> PdfSignError {
+  PdfSignError::SigningError(format!(
+    "{} não está disponível neste build (requer a feature \"native\")",
+    option
+  ))
+}
+
+/// Tabela xref de uma atualização incremental: uma subsecção para cada faixa de
+/// objetos consecutivos
+///
+/// Objetos regravados mantêm a geração que têm em `pdf_data`; os novos (a partir de
+/// `first_new_obj`) ficam na geração 0 sem percorrer o documento
+fn build_xref_table(
+  entries: &mut [(usize, usize)],
+  pdf_data: &[u8],
+  first_new_obj: usize,
+) -> String {
+  entries.sort_unstable_by_key(|(obj, _)| *obj);
+
+  // O objeto 0 (cabeça da lista de livres) não é redefinido: a atualização não
+  // libera objetos, e reescrevê-lo apagaria o encadeamento das revisões anteriores
+  let mut xref = String::from("xref\n");
+  let mut start = 0;
+  while start < entries.len() {
+    let mut end = start + 1;
+    while end < entries.len() && entries[end].0 == entries[end - 1].0 + 1 {
+      end += 1;
+    }
+    xref.push_str(&format!("{} {}\n", entries[start].0, end - start));
+    for (obj, offset) in &entries[start..end] {
+      let generation = if *obj < first_new_obj {
+        object_generation(pdf_data, *obj)
+      } else {
+        0
+      };
+      xref.push_str(&format!("{:010} {:05} n \n", offset, generation));
+    }
+    start = end;
+  }
+  xref
+}
+
+/// Dados do trailer da atualização incremental
+struct XrefTrailer {
+  /// Primeiro número livre depois dos objetos da atualização
+  size: usize,
+  prev: usize,
+  root: (usize, u16),
+}
+
+/// Referência cruzada e trailer da atualização incremental, a partir de `xref_start`
+///
+/// Com `XrefFormat::Stream` a xref vira o objeto `trailer.size`, que também entra nas
+/// entradas; quem numera objetos depois da atualização precisa pular esse número
+fn build_xref_section(
+  entries: &mut Vec<(usize, usize)>,
+  pdf_data: &[u8],
+  first_new_obj: usize,
+  xref_start: usize,
+  trailer: &XrefTrailer,
+  format: XrefFormat,
+) -> Vec<u8> {
+  let XrefTrailer { size, prev, root } = *trailer;
+  if format == XrefFormat::Table {
+    let mut section = build_xref_table(entries, pdf_data, first_new_obj).into_bytes();
+    section.extend_from_slice(
+      format!(
+        "trailer\n<<\n/Size {}\n/Prev {}\n/Root {} {} R\n>>\nstartxref\n{}\n%%EOF\n",
+        size, prev, root.0, root.1, xref_start
+      )
+      .as_bytes(),
+    );
+    return section;
+  }
+
+  entries.push((size, xref_start));
+  entries.sort_unstable_by_key(|(obj, _)| *obj);
+  // Tipo (1 byte), offset com os bytes que o maior offset pede e geração (2 bytes)
+  let offset_width = (usize::BITS - xref_start.leading_zeros())
+    .div_ceil(8)
+    .max(1) as usize;
+  let mut index = Vec::new();
+  let mut rows = Vec::new();
+  for run in entries.chunk_by(|a, b| b.0 == a.0 + 1) {
+    index.push(format!("{} {}", run[0].0, run.len()));
+    for &(obj, offset) in run {
+      let generation = if obj < first_new_obj {
+        object_generation(pdf_data, obj)
+      } else {
+        0
+      };
+      rows.push(1u8);
+      rows.extend_from_slice(&offset.to_be_bytes()[usize::BITS as usize / 8 - offset_width..]);
+      rows.extend_from_slice(&generation.to_be_bytes());
+    }
+  }
+
+  let mut section = format!(
+    "{} 0 obj\n<<\n/Type /XRef\n/Size {}\n/W [1 {} 2]\n/Index [{}]\n/Prev {}\n/Root {} {} R\n/Length {}\n>>\nstream\n",
+    size,
+    size + 1,
+    offset_width,
+    index.join(" "),
+    prev,
+    root.0,
+    root.1,
+    rows.len()
+  )
+  .into_bytes();
+  section.extend_from_slice(&rows);
+  section.extend_from_slice(
+    format!("\nendstream\nendobj\nstartxref\n{}\n%%EOF\n", xref_start).as_bytes(),
+  );
+  section
+}
+
+/// Atualização incremental com a assinatura, acrescentada após o PDF original
+///
+/// `tail` é tudo o que vem depois dos `base` bytes do original (começa pelo "\n"
+/// separador); os offsets gravados na xref e no ByteRange já somam `base`
+struct IncrementalUpdate {
+  base: usize,
+  tail: Vec<u8>,
+  /// Posição do valor de /ByteRange dentro de `tail`, anotada ao escrever o dicionário
+  byte_range_pos: usize,
+  /// Tamanho reservado para o valor de /ByteRange, com os espaços de folga
+  byte_range_len: usize,
+  /// Posição do '<' do placeholder de /Contents dentro de `tail`
+  placeholder_pos: usize,
+  /// Tamanho do placeholder, com os delimitadores < >
+  placeholder_len: usize,
+  /// Posição da data de /M dentro de `tail`
+  date_pos: usize,
+  /// Espaço para o CMS em hex, sem os delimitadores
+  sig_size: usize,
+  /// Instante gravado em /M, repetido no signingTime do CMS
+  signing_time: DateTime<Utc>,
+  /// Objeto do dicionário de assinatura
+  sig_obj: usize,
+  /// Nome gravado em /Name (vazio quando a entrada foi omitida)
+  signer_name: String,
+  /// Campo que recebe a assinatura: (objeto, nome); ausente nos direitos de uso
+  signed_field: Option<(usize, String)>,
+  // Só a atualização do /DSS (PAdES-B-LT, build nativo) lê `dss_target`
+  #[cfg_attr(not(feature = "native"), allow(dead_code))]
+  dss_target: DssTarget,
+}
+
+impl IncrementalUpdate {
+  /// O que a assinatura gravou, como `SignatureRecord`
+  fn record(&self) -> SignatureRecord {
+    let (field_obj, field_name) = self.signed_field.clone().unzip();
+    SignatureRecord {
+      signer_name: Some(self.signer_name.clone()).filter(|name| !name.is_empty()),
+      // /M só tem segundos
+      signing_time: self.signing_time.trunc_subsecs(0),
+      field_name,
+      signature_obj: self.sig_obj,
+      field_obj,
+    }
+  }
+
+  /// Resolve as referências do Catalog novo antes de assinar
+  ///
+  /// Cada objeto da xref da atualização precisa estar no offset declarado, e
+  /// /Root -> Catalog -> /Pages e /AcroForm -> /Fields -> widget da assinatura
+  /// precisam existir (na atualização ou no original); erros de numeração
+  /// aparecem aqui, e não no leitor de PDF
+  fn check_references(&self, pdf_data: &[u8], sig_obj: usize, expects_widget: bool) -> Result<()> {
+    let xref = parse_update_xref(&self.tail, self.base).ok_or_else(|| {
+      PdfSignError::InvalidPdf("xref da atualização incremental ilegível".to_string())
+    })?;
+    let resolve = |obj: usize| resolve_update_object(pdf_data, self.base, &self.tail, &xref, obj);
+    let dangling = |what: &str, obj: usize| {
+      PdfSignError::InvalidPdf(format!(
+        "atualização incremental com referência pendente: {} aponta para o objeto {}, que não foi encontrado",
+        what, obj
+      ))
+    };
+
+    for &(obj, offset) in &xref {
+      if resolve(obj).is_none() {
+        return Err(PdfSignError::InvalidPdf(format!(
+          "objeto {} não está no offset {} declarado na xref da atualização",
+          obj, offset
+        )));
+      }
+    }
+
+    // Trailer clássico ou, com xref stream, o dicionário dela
+    let trailer = self
+      .tail
+      .windows(b"trailer".len())
+      .rposition(|w| w == b"trailer")
+      .map(|pos| &self.tail[pos..])
+      .or_else(|| {
+        let xref_start = find_startxref(&self.tail)?.checked_sub(self.base)?;
+        self.tail.get(xref_start..)
+      })
+      .ok_or_else(|| PdfSignError::InvalidPdf("trailer da atualização ausente".to_string()))?;
+    let root = read_number_after(trailer, b"/Root")
+      .ok_or_else(|| PdfSignError::InvalidPdf("trailer da atualização sem /Root".to_string()))?;
+    let catalog = resolve(root)
+      .filter(|section| dict_has_key(section, b"/Catalog"))
+      .ok_or_else(|| dangling("/Root", root))?;
+
+    let pages = read_number_after(catalog, b"/Pages")
+      .ok_or_else(|| PdfSignError::InvalidPdf("Catalog da atualização sem /Pages".to_string()))?;
+    resolve(pages).ok_or_else(|| dangling("/Pages", pages))?;
+    // PDF/A exige que o XMP do documento continue acessível após a assinatura
+    if let Some(metadata) = read_number_after(catalog, b"/Metadata") {
+      resolve(metadata).ok_or_else(|| dangling("/Metadata", metadata))?;
+    }
+
+    // AcroForm por referência ou escrito direto no Catalog
+    let fields = match read_reference(catalog, b"/AcroForm") {
+      Some((acroform, _)) => {
+        let section = resolve(acroform).ok_or_else(|| dangling("/AcroForm", acroform))?;
+        read_ref_array(section, b"/Fields").unwrap_or_default()
+      }
+      None => dict_body(catalog)
+        .and_then(|catalog| dict_entry_value(catalog, b"/AcroForm"))
+        .and_then(|acroform| read_ref_array(acroform, b"/Fields"))
+        .unwrap_or_default(),
+    };
+    for field in fields {
+      resolve(field).ok_or_else(|| dangling("/Fields", field))?;
+    }
+
+    resolve(sig_obj).ok_or_else(|| dangling("/V", sig_obj))?;
+    let value = format!("/V {} 0 R", sig_obj);
+    let has_widget = xref.iter().any(|&(obj, _)| {
+      resolve(obj)
+        .is_some_and(|section| section.windows(value.len()).any(|w| w == value.as_bytes()))
+    });
+    if expects_widget && !has_widget {
+      return Err(PdfSignError::InvalidPdf(format!(
+        "nenhum campo da atualização referencia a assinatura (objeto {})",
+        sig_obj
+      )));
+    }
+    Ok(())
+  }
+
+  /// Grava o ByteRange sobre o placeholder, completando com espaços (como o node-signpdf)
+  fn write_byte_range(&mut self) -> Result<()> {
+    let range = self.byte_range();
+    check_byte_range_coverage(&range, self.base + self.tail.len())?;
+    write_byte_range_value(
+      &mut self.tail[self.byte_range_pos..self.byte_range_pos + self.byte_range_len],
+      &range,
+    )
+  }
+
+  /// Grava `signing_time` em /M, no placeholder D:00000000000000Z
+  fn write_signing_time(&mut self) -> Result<()> {
+    let date = format!("D:{}Z", self.signing_time.format("%Y%m%d%H%M%S"));
+    let placeholder = &mut self.tail[self.date_pos..];
+    if !placeholder.starts_with(DATE_PLACEHOLDER.as_bytes()) {
+      return Err(PdfSignError::InvalidPdf(format!(
+        "placeholder de /M não encontrado no offset {} da atualização",
+        self.date_pos
+      )));
+    }
+    if date.len() != DATE_PLACEHOLDER.len() {
+      return Err(PdfSignError::InvalidPdf(format!(
+        "data de /M \"{}\" tem {} bytes, mas o placeholder tem {}",
+        date,
+        date.len(),
+        DATE_PLACEHOLDER.len()
+      )));
+    }
+    placeholder[..date.len()].copy_from_slice(date.as_bytes());
+    Ok(())
+  }
+
+  /// ByteRange gravado no dicionário, com offsets do documento inteiro
+  ///
+  /// Vale antes de o /DSS ser acrescentado a `tail`
+  fn byte_range(&self) -> [usize; 4] {
+    let contents_start = self.base + self.placeholder_pos;
+    let contents_end = contents_start + self.placeholder_len;
+    [
+      0,
+      contents_start,
+      contents_end,
+      self.base + self.tail.len() - contents_end,
+    ]
+  }
+
+  /// Trechos de `tail` cobertos pelo ByteRange: antes e depois do placeholder
+  fn signed_tail(&self) -> [&[u8]; 2] {
+    [
+      &self.tail[..self.placeholder_pos],
+      &self.tail[self.placeholder_pos + self.placeholder_len..],
+    ]
+  }
+}
+
+/// Grava `range` sobre o placeholder de /ByteRange, completando com espaços
+fn write_byte_range_value(slot: &mut [u8], range: &[usize; 4]) -> Result<()> {
+  let value = format!("[{} {} {} {}]", range[0], range[1], range[2], range[3]);
+  let padding = slot.len().checked_sub(value.len()).ok_or_else(|| {
+    PdfSignError::InvalidPdf(format!(
+      "ByteRange excede o espaço reservado: {} bytes, mas o placeholder tem {}",
+      value.len(),
+      slot.len()
+    ))
+  })?;
+  let value = format!("{}{}", value, " ".repeat(padding));
+  slot.copy_from_slice(value.as_bytes());
+  Ok(())
+}
+
+/// Tamanho do PDF no arquivo sem os fins de linha finais, como em `remove_trailing_newline`
+fn signed_length(file: &mut File) -> Result<usize> {
+  let len = file.metadata()?.len();
+  let window = len.min(4096);
+  file.seek(SeekFrom::Start(len - window))?;
+  let mut end = Vec::with_capacity(window as usize);
+  file.take(window).read_to_end(&mut end)?;
+  Ok(len as usize - trailing_newline_len(&end))
+}
+
+/// Referências da revisão assinada necessárias para a atualização do /DSS
+#[cfg_attr(not(feature = "native"), allow(dead_code))]
+struct DssTarget {
+  catalog_obj: usize,
+  pages_ref: usize,
+  acroform_ref: Option<usize>,
+  first_obj: usize,
+  prev_xref: usize,
+  /// Catalog vigente ("N G obj ... endobj") quando a revisão assinada não o regrava
+  unchanged_catalog: Option<Vec<u8>>,
+  xref_format: XrefFormat,
+  /// /DSS vigente, cujas entradas continuam no /DSS novo
+  previous_dss: DssContents,
+}
+
+/// Entradas de um /DSS já gravado no documento
+///
+/// Cada array guarda os itens como estão ("12 0 R 13 0 R"); /VRI é copiado inteiro
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(not(feature = "native"), allow(dead_code))]
+struct DssContents {
+  certs: String,
+  crls: String,
+  ocsps: String,
+  vri: Option<String>,
+}
+
+impl DssContents {
+  /// /DSS do Catalog vigente, direto ou referenciado; vazio quando não há
+  fn read(pdf_data: &[u8], catalog_obj: usize) -> Self {
+    let Some(dss) = find_object_section(pdf_data, catalog_obj)
+      .and_then(dict_body)
+      .and_then(|catalog| resolve_dict(pdf_data, catalog, b"/DSS"))
+    else {
+      return Self::default();
+    };
+    let items = |key: &[u8]| {
+      resolve_array(pdf_data, dss, key)
+        .map(|items| String::from_utf8_lossy(items).to_string())
+        .unwrap_or_default()
+    };
+    Self {
+      certs: items(b"/Certs"),
+      crls: items(b"/CRLs"),
+      ocsps: items(b"/OCSPs"),
+      vri: dict_entries(dss)
+        .into_iter()
+        .find(|(key, _)| *key == b"/VRI")
+        .map(|(_, entry)| String::from_utf8_lossy(entry).to_string()),
+    }
+  }
+}
+
+/// Entradas (objeto, offset) da xref escrita na atualização incremental, numa
+/// tabela ou na xref stream de `XrefFormat::Stream`
+fn parse_update_xref(tail: &[u8], base: usize) -> Option<Vec<(usize, usize)>> {
+  // "\nxref\n" não casa com o "startxref" do fim
+  let Some(start) = tail.windows(6).rposition(|w| w == b"\nxref\n") else {
+    let xref_start = find_startxref(tail)?.checked_sub(base)?;
+    return read_xref_stream(tail.get(xref_start..)?);
+  };
+  let start = start + 6;
+  let end = start + tail[start..].windows(7).position(|w| w == b"trailer")?;
+  let text = std::str::from_utf8(&tail[start..end]).ok()?;
+
+  let mut entries = Vec::new();
+  let mut lines = text.lines();
+  while let Some(header) = lines.next() {
+    let mut header = header.split_whitespace();
+    let first: usize = header.next()?.parse().ok()?;
+    let count: usize = header.next()?.parse().ok()?;
+    for obj in first..first + count {
+      let offset = lines.next()?.split_whitespace().next()?.parse().ok()?;
+      entries.push((obj, offset));
+    }
+  }
+  Some(entries)
+}
+
+/// Seção do objeto: pelo offset da xref da atualização ou, se ela não o redefine, no original
+fn resolve_update_object<'a>(
+  pdf_data: &'a [u8],
+  base: usize,
+  tail: &'a [u8],
+  xref: &[(usize, usize)],
+  obj: usize,
+) -> Option<&'a [u8]> {
+  let Some(&(_, offset)) = xref.iter().find(|(entry, _)| *entry == obj) else {
+    return find_object_section(pdf_data, obj);
+  };
+  let section = tail.get(offset.checked_sub(base)?..)?;
+  if parse_object_header(section).map(|(num, _)| num) != Some(obj) {
+    return None;
+  }
+  let end = endobj_position(section)?;
+  Some(&section[..end])
+}
+
+/// Acrescenta o Document Security Store (ISO 32000-2, 12.8.4.3) em nova atualização incremental
+///
+/// Cada certificado, CRL e resposta OCSP vira um stream; o Catalog é reescrito
+/// apontando para o /DSS, sem tocar nos bytes cobertos pela assinatura
+#[cfg(feature = "native")]
+fn append_dss(
+  mut output: Vec<u8>,
+  base: usize,
+  target: &DssTarget,
+  data: &RevocationData,
+) -> Result<Vec<u8>> {
+  // O Catalog a reescrever é o da revisão assinada, no fim de `output`
+  let signed_len = output.len();
+  let mut offsets = Vec::new();
+  let mut next_obj = target.first_obj;
+
+  let mut write_streams = |output: &mut Vec<u8>, items: &[Vec<u8>]| -> Vec<usize> {
+    let mut refs = Vec::new();
+    for item in items {
+      offsets.push(base + output.len());
+      output.extend_from_slice(
+        format!(
+          "{} 0 obj\n<<\n/Length {}\n>>\nstream\n",
+          next_obj,
+          item.len()
+        )
+        .as_bytes(),
+      );
+      output.extend_from_slice(item);
+      output.extend_from_slice(b"\nendstream\nendobj\n");
+      refs.push(next_obj);
+      next_obj += 1;
+    }
+    refs
+  };
+
+  let cert_refs = write_streams(&mut output, &data.certs);
+  let crl_refs = write_streams(&mut output, &data.crls);
+  let ocsp_refs = write_streams(&mut output, &data.ocsps);
+
+  let ref_array = |refs: &[usize]| {
+    refs
+      .iter()
+      .map(|r| format!("{} 0 R", r))
+      .collect::<Vec<_>>()
+      .join(" ")
+  };
+  // O /DSS novo substitui o vigente: as entradas das assinaturas anteriores vêm antes
+  let previous = &target.previous_dss;
+  let mut dss_entries = String::new();
+  for (key, old, refs) in [
+    ("/Certs", &previous.certs, &cert_refs),
+    ("/CRLs", &previous.crls, &crl_refs),
+    ("/OCSPs", &previous.ocsps, &ocsp_refs),
+  ] {
+    let items: Vec<String> = [old.clone(), ref_array(refs)]
+      .into_iter()
+      .filter(|items| !items.is_empty())
+      .collect();
+    if !items.is_empty() {
+      dss_entries.push_str(&format!("{} [{}]\n", key, items.join(" ")));
+    }
+  }
+  if let Some(vri) = &previous.vri {
+    dss_entries.push_str(vri);
+    dss_entries.push('\n');
+  }
+
+  let dss_obj = next_obj;
+  offsets.push(base + output.len());
+  output.extend_from_slice(
+    format!(
+      "{} 0 obj\n<<\n/Type /DSS\n{}>>\nendobj\n",
+      dss_obj, dss_entries
+    )
+    .as_bytes(),
+  );
+
+  let catalog_pos = base + output.len();
+  let catalog_source = target
+    .unchanged_catalog
+    .as_deref()
+    .unwrap_or(&output[..signed_len]);
+  let catalog = build_updated_catalog(
+    target.catalog_obj,
+    target.pages_ref,
+    target.acroform_ref,
+    catalog_source,
+    &[format!("/DSS {} 0 R", dss_obj)],
+  )?;
+  let catalog_generation = object_generation(catalog_source, target.catalog_obj);
+
+  let mut xref_entries: Vec<(usize, usize)> = offsets
+    .iter()
+    .enumerate()
+    .map(|(i, &offset)| (target.first_obj + i, offset))
+    .collect();
+  xref_entries.push((target.catalog_obj, catalog_pos));
+
+  let xref_start = catalog_pos + catalog.len();
+  let trailer = XrefTrailer {
+    size: dss_obj + 1,
+    prev: target.prev_xref,
+    root: (target.catalog_obj, catalog_generation),
+  };
+  let xref = build_xref_section(
+    &mut xref_entries,
+    catalog_source,
+    target.first_obj,
+    xref_start,
+    &trailer,
+    target.xref_format,
+  );
+  output.extend_from_slice(catalog.as_bytes());
+  output.extend_from_slice(&xref);
+
+  Ok(output)
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+  use super::*;
+  use cms::content_info::ContentInfo;
+  use cms::signed_data::SignedData;
+  use der::{Decode, Encode};
+  use std::time::Duration;
+
+  use crate::signature_config::{DigestAlgorithm, RetryPolicy, SignatureScheme, TrustAnchorsMode};
+  use crate::test_support::{
+    identity_with_crl, issue_crl, issue_identity, pkcs12_bytes, MockResponse, MockServer, MockTsa,
+  };
+  use crate::timestamp::ID_AA_SIGNATURE_TIME_STAMP_TOKEN;
+
+  fn test_signer() -> PdfSigner {
+    // O assinador descarrega o provider padrão ao cair; os testes que verificam com OpenSSL dependem dele
+    crate::test_support::keep_default_provider();
+    let pfx_path = concat!(env!("CARGO_MANIFEST_DIR"), "/__test__/certificado-a1.pfx");
+    PdfSigner::from_pfx_file(pfx_path, "123456").unwrap()
+  }
+
+  fn sample_pdf() -> Vec<u8> {
+    fs::read(concat!(
+      env!("CARGO_MANIFEST_DIR"),
+      "/__test__/pdf_sample_2.pdf"
+    ))
+    .unwrap()
+  }
+
+  #[test]
+  fn test_from_pfx_wrong_password_is_distinguished() {
+    let pfx_path = concat!(env!("CARGO_MANIFEST_DIR"), "/__test__/certificado-a1.pfx");
+    let pfx = fs::read(pfx_path).unwrap();
+
+    let err = PdfSigner::from_pfx_bytes(&pfx, "654321").err().unwrap();
+    assert!(matches!(err, PdfSignError::InvalidPassword), "{:?}", err);
+    assert!(err.to_string().contains("Senha do certificado incorreta"));
+
+    // Arquivo truncado não é confundido com senha errada
+    let err = PdfSigner::from_pfx_bytes(&pfx[..pfx.len() / 2], "123456")
+      .err()
+      .unwrap();
+    assert!(matches!(err, PdfSignError::DecodingError(_)), "{:?}", err);
+  }
+
+  #[test]
+  fn test_concurrent_signing_with_legacy_rc2_pfx() {
+    use openssl::nid::Nid;
+
+    // PFX antigo (RC2-40 nos certificados, 3DES na chave), como os exportados pelo
+    // Windows até 2019; só abre com o provider legado carregado
+    load_openssl_providers();
+    let (key, cert) = issue_identity("Signatario Legado", None, false);
+    let pfx = openssl::pkcs12::Pkcs12::builder()
+      .name("legado")
+      .pkey(&key)
+      .cert(&cert)
+      .key_algorithm(Nid::PBE_WITHSHA1AND3_KEY_TRIPLEDES_CBC)
+      .cert_algorithm(Nid::PBE_WITHSHA1AND40BITRC2_CBC)
+      .mac_md(openssl::hash::MessageDigest::sha1())
+      .build2("senha")
+      .unwrap()
+      .to_der()
+      .unwrap();
+
+    let signed: Vec<Vec<u8>> = std::thread::scope(|scope| {
+      let workers: Vec<_> = (0..8)
+        .map(|_| {
+          scope.spawn(|| {
+            (0..4)
+              .map(|_| {
+                PdfSigner::from_pfx_bytes(&pfx, "senha")
+                  .and_then(|signer| signer.sign_pdf(sample_pdf(), &offline_config()))
+                  .unwrap()
+              })
+              .collect::<Vec<_>>()
+          })
+        })
+        .collect();
+      workers
+        .into_iter()
+        .flat_map(|worker| worker.join().unwrap())
+        .collect()
+    });
+    assert_eq!(signed.len(), 32);
+    for pdf in &signed {
+      verify_first_signature(pdf);
+    }
+  }
+
+  #[test]
+  #[cfg(feature = "system-keystore")]
+  fn test_from_system_cert_explains_missing_support() {
+    let err = PdfSigner::from_system_cert("FULANO DE TAL:12345678909")
+      .err()
+      .unwrap();
+    assert!(matches!(err, PdfSignError::UnsupportedPlatform(_)));
+    assert_eq!(err.code(), "UNSUPPORTED_PLATFORM");
+    assert!(err.to_string().contains("FULANO DE TAL"));
+    assert!(err.to_string().contains("from_pfx_file"));
+    assert!(err.to_string().contains("from_pkcs11"));
+  }
+
+  #[test]
+  #[cfg(feature = "pkcs11")]
+  fn test_from_pkcs11_missing_module_is_signing_error() {
+    let err = PdfSigner::from_pkcs11("/caminho/inexistente/libpkcs11.so", 0, "1234", "chave")
+      .err()
+      .unwrap();
+    assert!(matches!(err, PdfSignError::SigningError(_)), "{:?}", err);
+    assert!(err.to_string().contains("módulo PKCS#11"));
+  }
+
+  /// Módulo do SoftHSM2: PDFSIGNER_SOFTHSM_MODULE ou os caminhos usuais das distribuições
+  ///
+  /// Um PDFSIGNER_SOFTHSM_MODULE informado é usado como está, sem cair nos caminhos
+  /// usuais: se ele não carregar, o teste falha
+  #[cfg(feature = "pkcs11")]
+  fn softhsm_module() -> std::path::PathBuf {
+    if let Some(module) = std::env::var_os("PDFSIGNER_SOFTHSM_MODULE") {
+      return module.into();
+    }
+    [
+      "/usr/lib/softhsm/libsofthsm2.so",
+      "/usr/lib/x86_64-linux-gnu/softhsm/libsofthsm2.so",
+      "/usr/lib64/pkcs11/libsofthsm2.so",
+      "/usr/local/lib/softhsm/libsofthsm2.so",
+      "/opt/homebrew/lib/softhsm/libsofthsm2.so",
+    ]
+    .map(std::path::PathBuf::from)
+    .into_iter()
+    .find(|path| path.exists())
+    .expect("SoftHSM2 não encontrado; defina PDFSIGNER_SOFTHSM_MODULE")
+  }
+
+  /// Inicializa um token SoftHSM2 num diretório temporário com a chave e o certificado
+  /// do PFX de teste (CKA_ID 01, rótulo "assinatura") e retorna o ID do slot
+  #[cfg(feature = "pkcs11")]
+  fn softhsm_token_with_test_identity(module: &std::path::Path) -> u64 {
+    use cryptoki::context::{CInitializeArgs, Pkcs11};
+    use cryptoki::object::{Attribute, CertificateType, KeyType, ObjectClass};
+    use cryptoki::session::UserType;
+    use cryptoki::types::AuthPin;
+    use rsa::traits::{PrivateKeyParts, PublicKeyParts};
+
+    let dir = std::env::temp_dir().join(format!("pdfsigner-softhsm-{}", std::process::id()));
+    fs::create_dir_all(dir.join("tokens")).unwrap();
+    let conf = dir.join("softhsm2.conf");
+    fs::write(
+      &conf,
+      format!(
+        "directories.tokendir = {}\nobjectstore.backend = file\n",
+        dir.join("tokens").display()
+      ),
+    )
+    .unwrap();
+    std::env::set_var("SOFTHSM2_CONF", &conf);
+
+    let pkcs11 = Pkcs11::new(module).unwrap();
+    pkcs11.initialize(CInitializeArgs::OsThreads).unwrap();
+    let slot = pkcs11.get_all_slots().unwrap()[0];
+    let so_pin = AuthPin::new("so-1234".to_string());
+    pkcs11.init_token(slot, &so_pin, "pdfsigner").unwrap();
+    // O SoftHSM2 move o token inicializado para um slot novo
+    let slot = pkcs11.get_slots_with_initialized_token().unwrap()[0];
+
+    let session = pkcs11.open_rw_session(slot).unwrap();
+    session.login(UserType::So, Some(&so_pin)).unwrap();
+    session.init_pin(&AuthPin::new("1234".to_string())).unwrap();
+    session.logout().unwrap();
+    session
+      .login(UserType::User, Some(&AuthPin::new("1234".to_string())))
+      .unwrap();
+
+    let signer = test_signer();
+    let mut key = signer._private_key.clone().unwrap();
+    key.precompute().unwrap();
+    let primes = key.primes();
+    session
+      .create_object(&[
+        Attribute::Class(ObjectClass::PRIVATE_KEY),
+        Attribute::KeyType(KeyType::RSA),
+        Attribute::Token(true),
+        Attribute::Private(true),
+        Attribute::Sign(true),
+        Attribute::Id(vec![1]),
+        Attribute::Label(b"assinatura".to_vec()),
+        Attribute::Modulus(key.n().to_bytes_be()),
+        Attribute::PublicExponent(key.e().to_bytes_be()),
+        Attribute::PrivateExponent(key.d().to_bytes_be()),
+        Attribute::Prime1(primes[0].to_bytes_be()),
+        Attribute::Prime2(primes[1].to_bytes_be()),
+        Attribute::Exponent1(key.dp().unwrap().to_bytes_be()),
+        Attribute::Exponent2(key.dq().unwrap().to_bytes_be()),
+        Attribute::Coefficient(key.crt_coefficient().unwrap().to_bytes_be()),
+      ])
+      .unwrap();
+
+    let cert = x509_cert::Certificate::from_der(signer._certificate.der()).unwrap();
+    session
+      .create_object(&[
+        Attribute::Class(ObjectClass::CERTIFICATE),
+        Attribute::CertificateType(CertificateType::X_509),
+        Attribute::Token(true),
+        Attribute::Id(vec![1]),
+        Attribute::Label(b"assinatura".to_vec()),
+        Attribute::Subject(cert.tbs_certificate.subject.to_der().unwrap()),
+        Attribute::Value(signer._certificate.der().to_vec()),
+      ])
+      .unwrap();
+
+    slot.id()
+  }
+
+  #[test]
+  #[cfg(feature = "pkcs11")]
+  #[ignore = "requer SoftHSM2 (PDFSIGNER_SOFTHSM_MODULE)"]
+  fn test_sign_pdf_with_softhsm_token() {
+    let module = softhsm_module();
+    let slot = softhsm_token_with_test_identity(&module);
+
+    let err = PdfSigner::from_pkcs11(&module, slot, "0000", "assinatura")
+      .err()
+      .unwrap();
+    assert!(matches!(err, PdfSignError::SigningError(_)), "{:?}", err);
+    let err = PdfSigner::from_pkcs11(&module, slot, "1234", "outra")
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains("não encontrada"), "{}", err);
+
+    let signer = PdfSigner::from_pkcs11(&module, slot, "1234", "assinatura").unwrap();
+    assert_eq!(
+      signer.get_certificate_info().common_name,
+      test_signer().get_certificate_info().common_name
+    );
+
+    // O backend configurado é ignorado: a chave só pode ser usada pelo caminho Rust
+    for scheme in [SignatureScheme::Pkcs1v15, SignatureScheme::Pss] {
+      let config = SignatureConfig {
+        signature_scheme: scheme,
+        ..offline_config()
+      };
+      let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+      verify_first_signature(&signed);
+    }
+  }
+
+  /// Assinatura básica (B-B), sem TSA nem LTV: os testes não acessam a rede
+  fn offline_config() -> SignatureConfig {
+    SignatureConfig {
+      pades_level: PadesLevel::BB,
+      validate_icp_brasil: false,
+      ..SignatureConfig::default()
+    }
+  }
+
+  /// /Contents da primeira assinatura em hex, sem os delimitadores: o intervalo que
+  /// o ByteRange deixa de fora, qualquer que seja o espaçamento do dicionário
+  fn contents_hex(signed: &[u8]) -> &str {
+    let range = &crate::signatures::list_signatures(signed)[0].byte_range;
+    std::str::from_utf8(&signed[range[1] + 1..range[2] - 1]).unwrap()
+  }
+
+  /// Extrai o CMS gravado em /Contents do PDF assinado (sem o padding de zeros)
+  fn extract_cms(signed: &[u8]) -> Vec<u8> {
+    let der = hex::decode(contents_hex(signed)).unwrap();
+
+    let mut reader = der::SliceReader::new(&der).unwrap();
+    let content_info = ContentInfo::decode(&mut reader).unwrap();
+    content_info.to_der().unwrap()
+  }
+
+  fn decode_signed_data(cms_der: &[u8]) -> SignedData {
+    let content_info = ContentInfo::from_der(cms_der).unwrap();
+    content_info.content.decode_as::<SignedData>().unwrap()
+  }
+
+  #[test]
+  fn test_sign_data_cms_detached_has_no_content() {
+    let signer = test_signer();
+    let cms_der = signer
+      .sign_data_cms(b"conteudo de teste", &SignatureConfig::default(), true)
+      .unwrap();
+
+    let signed_data = decode_signed_data(&cms_der);
+    assert!(signed_data.encap_content_info.econtent.is_none());
+  }
+
+  #[test]
+  fn test_sign_data_cms_enveloped_includes_content() {
+    let signer = test_signer();
+    let data = b"conteudo de teste";
+    let cms_der = signer
+      .sign_data_cms(data, &SignatureConfig::default(), false)
+      .unwrap();
+
+    let signed_data = decode_signed_data(&cms_der);
+    let econtent = signed_data.encap_content_info.econtent.unwrap();
+    let econtent_der = econtent.to_der().unwrap();
+    assert!(econtent_der.ends_with(data));
+  }
+
+  #[test]
+  fn test_sign_data_cms_verifies_with_openssl() {
+    use openssl::pkcs7::{Pkcs7, Pkcs7Flags};
+    use openssl::provider::Provider;
+    use openssl::stack::Stack;
+    use openssl::x509::store::X509StoreBuilder;
+
+    // Mantém o provider padrão carregado durante a verificação
+    let _default = Provider::load(None, "default").unwrap();
+    let signer = test_signer();
+    let data = b"<NFe><infNFe>teste</infNFe></NFe>";
+    let store = X509StoreBuilder::new().unwrap().build();
+    let certs = Stack::new().unwrap();
+
+    let detached = signer
+      .sign_data_cms(data, &SignatureConfig::default(), true)
+      .unwrap();
+    let pkcs7 = Pkcs7::from_der(&detached).unwrap();
+    pkcs7
+      .verify(&certs, &store, Some(data), None, Pkcs7Flags::NOVERIFY)
+      .unwrap();
+
+    let enveloped = signer
+      .sign_data_cms(data, &SignatureConfig::default(), false)
+      .unwrap();
+    let pkcs7 = Pkcs7::from_der(&enveloped).unwrap();
+    let mut out = Vec::new();
+    pkcs7
+      .verify(&certs, &store, None, Some(&mut out), Pkcs7Flags::NOVERIFY)
+      .unwrap();
+    assert_eq!(out, data);
+  }
+
+  #[test]
+  fn test_rust_cms_backend_verifies_with_openssl() {
+    use openssl::pkcs7::{Pkcs7, Pkcs7Flags};
+    use openssl::stack::Stack;
+    use openssl::x509::store::X509StoreBuilder;
+
+    let signer = test_signer();
+    let data = b"<NFe><infNFe>teste</infNFe></NFe>";
+    let store = X509StoreBuilder::new().unwrap().build();
+    let certs = Stack::new().unwrap();
+
+    for digest_algorithm in [DigestAlgorithm::Sha256, DigestAlgorithm::Sha1] {
+      let config = SignatureConfig {
+        cms_backend: CmsBackend::Rust,
+        digest_algorithm,
+        ..SignatureConfig::default()
+      };
+
+      let detached = signer.sign_data_cms(data, &config, true).unwrap();
+      cms_signer::check_digest_consistency(&detached, data, digest_algorithm).unwrap();
+      Pkcs7::from_der(&detached)
+        .unwrap()
+        .verify(&certs, &store, Some(data), None, Pkcs7Flags::NOVERIFY)
+        .unwrap();
+
+      let enveloped = signer.sign_data_cms(data, &config, false).unwrap();
+      let mut out = Vec::new();
+      Pkcs7::from_der(&enveloped)
+        .unwrap()
+        .verify(&certs, &store, None, Some(&mut out), Pkcs7Flags::NOVERIFY)
+        .unwrap();
+      assert_eq!(out, data);
+    }
+  }
+
+  #[test]
+  fn test_cms_backends_produce_equivalent_signer_info() {
+    use openssl::cms::{CMSOptions, CmsContentInfo};
+    use openssl::stack::Stack;
+    use openssl::x509::store::X509StoreBuilder;
+
+    let signer = test_signer();
+    let data = b"conteudo assinado pelos dois backends";
+    let signer_infos: Vec<_> = [CmsBackend::OpenSsl, CmsBackend::Rust]
+      .into_iter()
+      .map(|cms_backend| {
+        let config = SignatureConfig {
+          cms_backend,
+          ..SignatureConfig::default()
+        };
+        let cms_der = signer.sign_data_cms(data, &config, true).unwrap();
+
+        // Os dois verificam contra o mesmo certificado do signatário
+        let mut cms = CmsContentInfo::from_der(&cms_der).unwrap();
+        let store = X509StoreBuilder::new().unwrap().build();
+        let certs = Stack::new().unwrap();
+        cms
+          .verify(
+            Some(&certs),
+            Some(&store),
+            Some(data),
+            None,
+            CMSOptions::NO_SIGNER_CERT_VERIFY | CMSOptions::BINARY,
+          )
+          .unwrap();
+
+        decode_signed_data(&cms_der)
+          .signer_infos
+          .0
+          .get(0)
+          .unwrap()
+          .clone()
+      })
+      .collect();
+
+    let attr_oids = |info: &cms::signed_data::SignerInfo| {
+      let mut oids: Vec<_> = info
+        .signed_attrs
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|attr| attr.oid)
+        .collect();
+      oids.sort();
+      oids
+    };
+    let (openssl_info, rust_info) = (&signer_infos[0], &signer_infos[1]);
+    assert_eq!(openssl_info.sid, rust_info.sid);
+    assert_eq!(openssl_info.digest_alg.oid, rust_info.digest_alg.oid);
+    assert_eq!(
+      openssl_info.signature_algorithm.oid,
+      rust_info.signature_algorithm.oid
+    );
+    assert_eq!(attr_oids(openssl_info), attr_oids(rust_info));
+  }
+
+  /// Verifica o CMS com o certificado embutido e devolve o SignerInfo
+  fn verify_and_decode_signer_info(cms_der: &[u8], data: &[u8]) -> cms::signed_data::SignerInfo {
+    use openssl::cms::{CMSOptions, CmsContentInfo};
+    use openssl::stack::Stack;
+    use openssl::x509::store::X509StoreBuilder;
+
+    // Rodando isolado, o provider padrão já foi descarregado pelo assinador
+    crate::test_support::keep_default_provider();
+    let mut cms = CmsContentInfo::from_der(cms_der).unwrap();
+    let store = X509StoreBuilder::new().unwrap().build();
+    let certs = Stack::new().unwrap();
+    cms
+      .verify(
+        Some(&certs),
+        Some(&store),
+        Some(data),
+        None,
+        CMSOptions::NO_SIGNER_CERT_VERIFY | CMSOptions::BINARY,
+      )
+      .unwrap();
+
+    decode_signed_data(cms_der)
+      .signer_infos
+      .0
+      .get(0)
+      .unwrap()
+      .clone()
+  }
+
+  #[test]
+  fn test_pss_signature_algorithm_parameters() {
+    use const_oid::db::rfc5912::{ID_MGF_1, ID_RSASSA_PSS, ID_SHA_256};
+    use rsa::pkcs1::RsaPssParams;
+
+    let signer = test_signer();
+    let data = b"conteudo assinado com PSS";
+
+    for cms_backend in [CmsBackend::OpenSsl, CmsBackend::Rust] {
+      let config = SignatureConfig {
+        signature_scheme: SignatureScheme::Pss,
+        cms_backend,
+        ..SignatureConfig::default()
+      };
+      let cms_der = signer.sign_data_cms(data, &config, true).unwrap();
+      let signer_info = verify_and_decode_signer_info(&cms_der, data);
+
+      assert_eq!(signer_info.signature_algorithm.oid, ID_RSASSA_PSS);
+      let params_der = signer_info
+        .signature_algorithm
+        .parameters
+        .as_ref()
+        .unwrap()
+        .to_der()
+        .unwrap();
+      let params = RsaPssParams::from_der(&params_der).unwrap();
+      assert_eq!(params.hash.oid, ID_SHA_256, "backend {:?}", cms_backend);
+      assert_eq!(params.mask_gen.oid, ID_MGF_1);
+      assert_eq!(params.mask_gen.parameters.unwrap().oid, ID_SHA_256);
+      assert_eq!(params.salt_len, 32);
+    }
+  }
+
+  #[test]
+  fn test_certificate_thumbprints() {
+    let info = test_signer().get_certificate_info();
+    // Conferido com `openssl x509 -fingerprint`; o SHA-1 é o localKeyID do PFX
+    assert_eq!(
+      info.thumbprint_sha256,
+      "ad52795550960afea8be95a460e5ab78b0e9569f429ee1a3d0101a8bd44ecf30"
+    );
+    assert_eq!(
+      info.thumbprint_sha1,
+      "9e245b1437b232e5e1d47bed865edfac4e74ce80"
+    );
+    assert_eq!(
+      test_signer()
+        ._certificate
+        .thumbprint(DigestAlgorithm::Sha512)
+        .len(),
+      128
+    );
+  }
+
+  #[test]
+  fn test_rejects_certificate_without_signing_key_usage() {
+    use crate::test_support::key_encipherment_identity;
+
+    let (key, cert) = key_encipherment_identity("Somente Cifragem");
+    let pfx = pkcs12_bytes(&key, &cert, &[], "segredo");
+    let signer = PdfSigner::from_pfx_bytes(&pfx, "segredo").unwrap();
+    assert_eq!(signer.get_certificate_info().key_usage, ["keyEncipherment"]);
+
+    let err = signer
+      .sign_pdf(sample_pdf(), &offline_config())
+      .unwrap_err();
+    assert!(matches!(err, PdfSignError::KeyUsageError(_)), "{:?}", err);
+    assert_eq!(err.code(), "KEY_USAGE_ERROR");
+    // A mensagem aponta o bit que falta e a saída, e não a senha
+    let message = err.to_string();
+    for expected in [
+      "keyEncipherment",
+      "digitalSignature",
+      "nonRepudiation",
+      "ignore_key_usage",
+    ] {
+      assert!(message.contains(expected), "{}", message);
+    }
+    assert!(!message.contains("senha"));
+    assert!(matches!(
+      signer.analyze(&sample_pdf(), &offline_config()),
+      Err(PdfSignError::KeyUsageError(_))
+    ));
+
+    let config = SignatureConfig {
+      ignore_key_usage: true,
+      ..offline_config()
+    };
+    verify_first_signature(&signer.sign_pdf(sample_pdf(), &config).unwrap());
+  }
+
+  #[test]
+  fn test_ed25519_signature_scheme() {
+    use crate::test_support::ed25519_identity;
+
+    let (key, cert) = ed25519_identity("Signatario Ed25519");
+    let pfx = pkcs12_bytes(&key, &cert, &[], "segredo");
+    let signer = PdfSigner::from_pfx_bytes(&pfx, "segredo").unwrap();
+    let data = b"conteudo assinado com Ed25519";
+
+    // Ed25519 só existe no backend OpenSSL, mesmo com cms-rustcrypto como padrão
+    let openssl = SignatureConfig {
+      cms_backend: CmsBackend::OpenSsl,
+      ..SignatureConfig::default()
+    };
+    let config = SignatureConfig {
+      signature_scheme: SignatureScheme::Ed25519,
+      digest_algorithm: DigestAlgorithm::Sha512,
+      ..openssl.clone()
+    };
+    let cms_der = signer.sign_data_cms(data, &config, true).unwrap();
+    let signer_info = verify_and_decode_signer_info(&cms_der, data);
+    assert_eq!(
+      signer_info.signature_algorithm.oid,
+      const_oid::ObjectIdentifier::new_unwrap("1.3.101.112")
+    );
+
+    // Chave Ed25519 não assina PKCS#1 v1.5, e Ed25519 exige SHA-512
+    let err = signer.sign_data_cms(data, &openssl, true).unwrap_err();
+    assert!(err.to_string().contains("não é suportado pela chave"));
+    let err = signer
+      .sign_data_cms(
+        data,
+        &SignatureConfig {
+          signature_scheme: SignatureScheme::Ed25519,
+          ..openssl.clone()
+        },
+        true,
+      )
+      .unwrap_err();
+    assert!(err.to_string().contains("Sha512"));
+
+    // E a chave RSA do certificado de teste não assina Ed25519
+    let err = test_signer()
+      .sign_data_cms(data, &config, true)
+      .unwrap_err();
+    assert!(matches!(err, PdfSignError::SigningError(_)));
+  }
+
+  #[test]
+  fn test_ecdsa_signature_scheme() {
+    use crate::test_support::ec_identity;
+
+    let (key, cert) = ec_identity("Signatario ECDSA");
+    let pfx = pkcs12_bytes(&key, &cert, &[], "segredo");
+    let signer = PdfSigner::from_pfx_bytes(&pfx, "segredo").unwrap();
+    let config = SignatureConfig {
+      signature_scheme: SignatureScheme::Ecdsa,
+      cms_backend: CmsBackend::OpenSsl,
+      ..offline_config()
+    };
+
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    verify_first_signature(&signed);
+    let signed_data = decode_signed_data(&extract_cms(&signed));
+    assert_eq!(
+      signed_data
+        .signer_infos
+        .0
+        .get(0)
+        .unwrap()
+        .signature_algorithm
+        .oid,
+      const_oid::db::rfc5912::ECDSA_WITH_SHA_256
+    );
+
+    // Chave EC não assina RSA, e a chave RSA de teste não assina ECDSA
+    let err = signer
+      .sign_pdf(
+        sample_pdf(),
+        &SignatureConfig {
+          cms_backend: CmsBackend::OpenSsl,
+          ..offline_config()
+        },
+      )
+      .unwrap_err();
+    assert!(
+      err.to_string().contains("não é suportado pela chave Ec"),
+      "{}",
+      err
+    );
+    let err = test_signer().sign_pdf(sample_pdf(), &config).unwrap_err();
+    assert!(
+      err.to_string().contains("não é suportado pela chave Rsa"),
+      "{}",
+      err
+    );
+  }
+
+  #[test]
+  fn test_sign_pdf_with_rust_cms_backend() {
+    let signer = test_signer();
+    let config = SignatureConfig {
+      cms_backend: CmsBackend::Rust,
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+
+    let signed_data = decode_signed_data(&extract_cms(&signed));
+    let signer_info = signed_data.signer_infos.0.get(0).unwrap();
+    assert_eq!(signer_info.digest_alg.oid, DigestAlgorithm::Sha256.oid());
+    assert!(signed_data.encap_content_info.econtent.is_none());
+  }
+
+  /// signingTime dos atributos assinados do CMS, em segundos desde a época
+  fn cms_signing_time(cms_der: &[u8]) -> u64 {
+    use x509_cert::time::Time;
+
+    let signed_data = decode_signed_data(cms_der);
+    let signer_info = signed_data.signer_infos.0.get(0).unwrap();
+    let attrs: Vec<_> = signer_info
+      .signed_attrs
+      .as_ref()
+      .unwrap()
+      .iter()
+      .filter(|attr| attr.oid == const_oid::db::rfc5911::ID_SIGNING_TIME)
+      .collect();
+    assert_eq!(attrs.len(), 1, "signingTime duplicado");
+    attrs[0]
+      .values
+      .get(0)
+      .unwrap()
+      .to_der()
+      .and_then(|der| Time::from_der(&der))
+      .unwrap()
+      .to_unix_duration()
+      .as_secs()
+  }
+
+  #[test]
+  fn test_cms_signing_time_matches_pdf_date() {
+    use crate::signatures::list_signatures;
+    use chrono::TimeZone;
+
+    let signer = test_signer();
+    let fixed = Utc.with_ymd_and_hms(2024, 3, 15, 12, 34, 56).unwrap();
+
+    for cms_backend in [CmsBackend::OpenSsl, CmsBackend::Rust] {
+      let config = SignatureConfig {
+        cms_backend,
+        ..offline_config()
+      };
+      let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+      let date = list_signatures(&signed)[0].signing_date.clone().unwrap();
+      let pdf_time = chrono::NaiveDateTime::parse_from_str(&date[2..16], "%Y%m%d%H%M%S")
+        .unwrap()
+        .and_utc();
+      assert_eq!(
+        cms_signing_time(&extract_cms(&signed)),
+        pdf_time.timestamp() as u64,
+        "backend {:?}",
+        cms_backend
+      );
+
+      let config = SignatureConfig {
+        signing_time: Some(fixed),
+        ..config
+      };
+      let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+      assert!(String::from_utf8_lossy(&signed).contains("/M (D:20240315123456Z)"));
+      assert_eq!(
+        cms_signing_time(&extract_cms(&signed)),
+        fixed.timestamp() as u64
+      );
+    }
+  }
+
+  #[test]
+  fn test_byte_range_digest_is_stable_for_fixed_signing_time() {
+    use chrono::TimeZone;
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      signing_time: Some(Utc.with_ymd_and_hms(2024, 3, 15, 12, 34, 56).unwrap()),
+      ..offline_config()
+    };
+    let first = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    let second = signer.sign_pdf(sample_pdf(), &config).unwrap();
+
+    let digest = byte_range_digest(&first, DigestAlgorithm::Sha256).unwrap();
+    assert_eq!(digest.len(), 32);
+    assert_eq!(
+      digest,
+      byte_range_digest(&second, DigestAlgorithm::Sha256).unwrap()
+    );
+    assert_eq!(
+      byte_range_digest(&first, DigestAlgorithm::Sha512)
+        .unwrap()
+        .len(),
+      64
+    );
+
+    // Outro instante muda o /M, coberto pelo ByteRange
+    let later = SignatureConfig {
+      signing_time: Some(Utc.with_ymd_and_hms(2024, 3, 15, 12, 34, 57).unwrap()),
+      ..config
+    };
+    let third = signer.sign_pdf(sample_pdf(), &later).unwrap();
+    assert_ne!(
+      digest,
+      byte_range_digest(&third, DigestAlgorithm::Sha256).unwrap()
+    );
+
+    // Sem assinatura não há ByteRange
+    assert!(byte_range_digest(&sample_pdf(), DigestAlgorithm::Sha256).is_err());
+  }
+
+  #[test]
+  fn test_cms_is_deterministic_for_fixed_signing_time() {
+    use chrono::TimeZone;
+    use der::DerOrd;
+
+    let signer = test_signer();
+    for cms_backend in [CmsBackend::OpenSsl, CmsBackend::Rust] {
+      let config = SignatureConfig {
+        cms_backend,
+        signing_time: Some(Utc.with_ymd_and_hms(2024, 3, 15, 12, 34, 56).unwrap()),
+        ..offline_config()
+      };
+      let first = extract_cms(&signer.sign_pdf(sample_pdf(), &config).unwrap());
+      let second = extract_cms(&signer.sign_pdf(sample_pdf(), &config).unwrap());
+      assert_eq!(first, second, "{:?}", cms_backend);
+
+      // Atributos assinados na ordem canônica do SET OF em DER
+      let signed_data = decode_signed_data(&first);
+      let signer_info = signed_data.signer_infos.0.get(0).unwrap();
+      let attrs: Vec<_> = signer_info.signed_attrs.as_ref().unwrap().iter().collect();
+      assert_eq!(attrs.len(), 3, "{:?}", cms_backend);
+      for pair in attrs.windows(2) {
+        assert_eq!(
+          pair[0].der_cmp(pair[1]).unwrap(),
+          std::cmp::Ordering::Less,
+          "{:?}",
+          cms_backend
+        );
+      }
+
+      // O salt aleatório do RSA-PSS muda a assinatura a cada execução
+      let pss = SignatureConfig {
+        signature_scheme: SignatureScheme::Pss,
+        ..config
+      };
+      let first = extract_cms(&signer.sign_pdf(sample_pdf(), &pss).unwrap());
+      let second = extract_cms(&signer.sign_pdf(sample_pdf(), &pss).unwrap());
+      assert_ne!(first, second, "{:?}", cms_backend);
+    }
+  }
+
+  /// CMS coberto pelo ByteRange da primeira assinatura do PDF, verificado com o OpenSSL
+  fn verify_first_signature(signed: &[u8]) {
+    use crate::signatures::list_signatures;
+
+    let range = list_signatures(signed)[0].byte_range.clone();
+    let mut covered = signed[range[0]..range[0] + range[1]].to_vec();
+    covered.extend_from_slice(&signed[range[2]..range[2] + range[3]]);
+    verify_and_decode_signer_info(&extract_cms(signed), &covered);
+  }
+
+  #[test]
+  fn test_sign_pdf_reason_with_angle_brackets() {
+    use crate::signatures::list_signatures;
+
+    let config = SignatureConfig {
+      reason: Some("Aprovado <revisado> por a<b e c>d".to_string()),
+      location: "<Brasil>".to_string(),
+      ..offline_config()
+    };
+    let signed = test_signer().sign_pdf(sample_pdf(), &config).unwrap();
+
+    verify_first_signature(&signed);
+    let entry = &list_signatures(&signed)[0];
+    assert_eq!(entry.reason.as_deref(), config.reason.as_deref());
+  }
+
+  #[test]
+  fn test_sign_pdf_reason_mimicking_placeholders() {
+    use crate::signatures::list_signatures;
+
+    // Texto que imita os próprios placeholders não pode ser tomado por eles
+    let config = SignatureConfig {
+      reason: Some("/Contents <00> D:00000000000000Z".to_string()),
+      location: "/Contents <".to_string(),
+      ..offline_config()
+    };
+    let signed = test_signer().sign_pdf(sample_pdf(), &config).unwrap();
+
+    verify_first_signature(&signed);
+    let entry = &list_signatures(&signed)[0];
+    assert_eq!(entry.reason.as_deref(), config.reason.as_deref());
+    assert!(String::from_utf8_lossy(&signed)
+      .contains(&format!("/Reason ({})", config.reason.as_deref().unwrap())));
+    assert!(!String::from_utf8_lossy(&signed).contains("/M (D:00000000000000Z)"));
+  }
+
+  #[test]
+  fn test_sign_pdf_contents_padding() {
+    for (padding, pad) in [(ContentsPadding::Zero, "0"), (ContentsPadding::F, "f")] {
+      let config = SignatureConfig {
+        contents_padding: padding,
+        ..offline_config()
+      };
+      let signed = test_signer().sign_pdf(sample_pdf(), &config).unwrap();
+      let contents = contents_hex(&signed);
+
+      assert_eq!(contents.len(), 16000);
+      assert!(contents.ends_with(&pad.repeat(64)));
+      verify_first_signature(&signed);
+    }
+  }
+
+  #[test]
+  fn test_contents_placeholder_and_padding_checks() {
+    let placeholder = format!("<{}>", "0".repeat(8));
+    assert!(check_contents_placeholder(placeholder.as_bytes(), 8).is_ok());
+    // '<' perdido antes do placeholder real, ou '>' fechando antes da hora
+    assert!(check_contents_placeholder(b"<00<0000>", 8).is_err());
+    assert!(check_contents_placeholder(b"<0000>", 8).is_err());
+    assert!(check_contents_placeholder(b"<0000ab00>", 8).is_err());
+
+    assert_eq!(
+      pad_signature_hex(&[0x30, 0xab], 8, ContentsPadding::F).unwrap(),
+      "30abffff"
+    );
+    assert_eq!(
+      pad_signature_hex(&[0x30], 6, ContentsPadding::Zero).unwrap(),
+      "300000"
+    );
+    assert!(pad_signature_hex(&[0x30, 0xab, 0x01], 4, ContentsPadding::Zero).is_err());
+    assert!(pad_signature_hex(&[0x30], 5, ContentsPadding::Zero).is_err());
+  }
+
+  #[test]
+  fn test_sign_pdf_rejects_non_pdf() {
+    let signer = test_signer();
+    let result = signer.sign_pdf(
+      b"PK\x03\x04[Content_Types].xml".to_vec(),
+      &SignatureConfig::default(),
+    );
+    assert!(matches!(result, Err(PdfSignError::InvalidPdf(_))));
+  }
+
+  #[test]
+  fn test_sign_pdf_with_field_lock() {
+    use crate::signature_config::FieldLock;
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      lock: Some(FieldLock::Include(vec!["Nome".to_string()])),
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    let signed_str = String::from_utf8_lossy(&signed);
+
+    assert!(
+      signed_str.contains("/Lock <<\n/Type /SigFieldLock\n/Action /Include\n/Fields [(Nome)]")
+    );
+    assert!(signed_str.contains("/Reference [<<\n/Type /SigRef\n/TransformMethod /FieldMDP"));
+    // /Data aponta para o Catalog analisado pelo FieldMDP
+    assert!(signed_str.contains(&format!(
+      "/V /1.2\n>>\n/Data {} 0 R\n/DigestMethod /SHA256\n>>]",
+      extract_catalog_info(&sample_pdf()).unwrap().catalog_obj
+    )));
+  }
+
+  #[test]
+  fn test_reference_digest_method_follows_cms_digest() {
+    use crate::signature_config::FieldLock;
+
+    let signer = test_signer();
+    let signer_info_digest = |signed: &[u8]| {
+      let range = &list_signatures(signed)[0].byte_range;
+      let mut covered = signed[range[0]..range[0] + range[1]].to_vec();
+      covered.extend_from_slice(&signed[range[2]..range[2] + range[3]]);
+      verify_and_decode_signer_info(&extract_cms(signed), &covered)
+        .digest_alg
+        .oid
+    };
+    let config = SignatureConfig {
+      lock: Some(FieldLock::All),
+      digest_algorithm: DigestAlgorithm::Sha512,
+      ..offline_config()
+    };
+
+    // Sem override, /DigestMethod acompanha o digest do CMS
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    assert_eq!(signer_info_digest(&signed), DigestAlgorithm::Sha512.oid());
+    assert!(String::from_utf8_lossy(&signed).contains("/DigestMethod /SHA512\n"));
+
+    // O override muda só a transformação, não o CMS
+    let overridden = SignatureConfig {
+      reference_digest_method: Some(DigestAlgorithm::Sha256),
+      ..config
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &overridden).unwrap();
+    assert_eq!(signer_info_digest(&signed), DigestAlgorithm::Sha512.oid());
+    let signed_str = String::from_utf8_lossy(&signed);
+    assert!(signed_str.contains("/DigestMethod /SHA256\n"));
+    assert!(!signed_str.contains("/DigestMethod /SHA512"));
+  }
+
+  #[test]
+  fn test_sign_pdf_with_usage_rights() {
+    use crate::signature_config::{FieldLock, UsageRights};
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      usage_rights: Some(UsageRights::default()),
+      self_verify: true,
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    let signed_str = String::from_utf8_lossy(&signed);
+
+    let entries = list_signatures(&signed);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].field_name, None);
+    assert!(signed_str.contains(&format!(
+      "/Perms << /UR3 {} 0 R >>",
+      entries[0].object_number
+    )));
+    assert!(signed_str.contains("/Reference [<<\n/Type /SigRef\n/TransformMethod /UR3"));
+    assert!(!signed_str.contains("/FT /Sig"));
+
+    // Não se mistura com a assinatura de aprovação nem com um /Perms existente
+    let with_lock = SignatureConfig {
+      lock: Some(FieldLock::All),
+      ..config.clone()
+    };
+    assert!(matches!(
+      signer.sign_pdf(sample_pdf(), &with_lock),
+      Err(PdfSignError::SigningError(_))
+    ));
+    assert!(matches!(
+      signer.sign_pdf(signed, &config),
+      Err(PdfSignError::InvalidPdf(_))
+    ));
+  }
+
+  #[test]
+  fn test_sign_pdf_prop_build_app() {
+    let signer = test_signer();
+    let config = SignatureConfig {
+      app_name: "Portal Assinador".to_string(),
+      app_version: "1.2.0".to_string(),
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    let signed_str = String::from_utf8_lossy(&signed);
+
+    assert!(signed_str.contains("/Filter /Adobe.PPKLite\n"));
+    assert!(signed_str.contains("/Name /Adobe.PPKLite"));
+    assert!(signed_str.contains("/App <<\n/Name /Portal#20Assinador\n/REx (1.2.0)\n>>"));
+  }
+
+  #[test]
+  fn test_sign_pdf_custom_filter() {
+    let signer = test_signer();
+    let config = SignatureConfig {
+      filter: "Acme.SigHandler".to_string(),
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    let signed_str = String::from_utf8_lossy(&signed);
+
+    assert!(signed_str.contains("/Type /Sig\n/Filter /Acme.SigHandler\n/SubFilter"));
+    assert!(signed_str.contains("/Prop_Build <<\n/Filter <<\n/Name /Acme.SigHandler\n>>"));
+    assert!(!signed_str.contains("Adobe.PPKLite"));
+    verify_first_signature(&signed);
+
+    let empty = SignatureConfig {
+      filter: String::new(),
+      ..offline_config()
+    };
+    assert!(matches!(
+      signer.sign_pdf(sample_pdf(), &empty),
+      Err(PdfSignError::SigningError(_))
+    ));
+  }
+
+  #[test]
+  fn test_sign_pdf_sha1_digest_algorithm() {
+    let signer = test_signer();
+    let config = SignatureConfig {
+      digest_algorithm: DigestAlgorithm::Sha1,
+      ..offline_config()
+    };
+
+    let cms_der = signer.sign_data_cms(b"dados", &config, true).unwrap();
+    let signed_data = decode_signed_data(&cms_der);
+    let signer_info = signed_data.signer_infos.0.get(0).unwrap();
+    assert_eq!(signer_info.digest_alg.oid, DigestAlgorithm::Sha1.oid());
+    assert_eq!(signer_info.digest_alg.oid.to_string(), "1.3.14.3.2.26");
+
+    // A verificação de consistência roda dentro do sign_pdf
+    signer.sign_pdf(sample_pdf(), &config).unwrap();
+  }
+
+  #[test]
+  fn test_sign_pdf_legacy_pkcs7_sha1_sub_filter() {
+    let signer = test_signer();
+    for cms_backend in [CmsBackend::OpenSsl, CmsBackend::Rust] {
+      let config = SignatureConfig {
+        sub_filter: SubFilter::Pkcs7Sha1,
+        digest_algorithm: DigestAlgorithm::Sha1,
+        cms_backend,
+        self_verify: true,
+        ..offline_config()
+      };
+      let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+      let entry = list_signatures(&signed).pop().unwrap();
+      assert_eq!(entry.sub_filter.as_deref(), Some("adbe.pkcs7.sha1"));
+
+      // O CMS não é destacado: o conteúdo encapsulado é o SHA-1 (20 bytes) do ByteRange
+      let signed_data = decode_signed_data(&extract_cms(&signed));
+      let econtent = signed_data.encap_content_info.econtent.unwrap();
+      let byte_range_sha1 = byte_range_digest(&signed, DigestAlgorithm::Sha1).unwrap();
+      assert_eq!(byte_range_sha1.len(), 20);
+      assert!(econtent.to_der().unwrap().ends_with(&byte_range_sha1));
+      let signer_info = signed_data.signer_infos.0.get(0).unwrap();
+      assert_eq!(signer_info.digest_alg.oid, DigestAlgorithm::Sha1.oid());
+      verify_last_signature(&signed, DigestAlgorithm::Sha1).unwrap();
+    }
+
+    // O SHA-1 do /SubFilter não combina com outro digest
+    let config = SignatureConfig {
+      sub_filter: SubFilter::Pkcs7Sha1,
+      ..offline_config()
+    };
+    assert!(matches!(
+      signer.sign_pdf(sample_pdf(), &config),
+      Err(PdfSignError::SigningError(message)) if message.contains("adbe.pkcs7.sha1")
+    ));
+  }
+
+  #[test]
+  fn test_check_digest_consistency_detects_divergence() {
+    let signer = test_signer();
+    let config = SignatureConfig {
+      digest_algorithm: DigestAlgorithm::Sha1,
+      ..SignatureConfig::default()
+    };
+    let cms_der = signer.sign_data_cms(b"dados", &config, true).unwrap();
+
+    assert!(
+      cms_signer::check_digest_consistency(&cms_der, b"dados", DigestAlgorithm::Sha1).is_ok()
+    );
+    assert!(
+      cms_signer::check_digest_consistency(&cms_der, b"dados", DigestAlgorithm::Sha256).is_err()
+    );
+    assert!(
+      cms_signer::check_digest_consistency(&cms_der, b"outros", DigestAlgorithm::Sha1).is_err()
+    );
+  }
+
+  #[test]
+  fn test_sign_report_exposes_timestamp_time() {
+    use chrono::Timelike;
+
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| MockResponse::ok(tsa.respond(&request.body)));
+    let signer = test_signer();
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BT,
+      tsa_url: Some(server.url()),
+      ..offline_config()
+    };
+
+    // O genTime da TSA simulada tem resolução de segundos
+    let before = Utc::now().with_nanosecond(0).unwrap();
+    let (signed, report) = signer.sign_pdf_with_report(sample_pdf(), &config).unwrap();
+    let after = Utc::now();
+
+    let timestamp_time = report.timestamp_time.unwrap();
+    assert!(before <= timestamp_time && timestamp_time <= after);
+    let entries = list_signatures(&signed);
+    assert_eq!(entries[0].timestamp_time, Some(timestamp_time));
+
+    // Sem carimbo (B-B), nem o relatório nem a listagem trazem o instante
+    let (unstamped, report) = signer
+      .sign_pdf_with_report(sample_pdf(), &offline_config())
+      .unwrap();
+    assert_eq!(report.timestamp_time, None);
+    assert_eq!(list_signatures(&unstamped)[0].timestamp_time, None);
+  }
+
+  #[test]
+  fn test_document_timestamp_has_no_acroform() {
+    use crate::timestamp::TstInfo;
+
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| MockResponse::ok(tsa.respond(&request.body)));
+    let config = SignatureConfig {
+      tsa_url: Some(server.url()),
+      ..offline_config()
+    };
+    let original = remove_trailing_newline(sample_pdf());
+    let stamped = PdfSigner::add_document_timestamp(sample_pdf(), &config).unwrap();
+    assert!(stamped.starts_with(&original));
+    assert!(crate::utils::validate_pdf(&stamped).is_valid);
+
+    // Só o carimbo e a xref: nem campo, nem AcroForm, nem Catalog novo
+    let update = String::from_utf8_lossy(&stamped[original.len()..]).to_string();
+    assert!(update.contains("/Type /DocTimeStamp\n"), "{}", update);
+    assert!(update.contains("/SubFilter /ETSI.RFC3161\n"));
+    assert!(!String::from_utf8_lossy(&stamped).contains("/AcroForm"));
+    for key in ["/FT /Sig", "/Catalog", "/Type /Sig\n"] {
+      assert!(
+        !update.contains(key),
+        "{} não deveria estar em {}",
+        key,
+        update
+      );
+    }
+
+    // O ByteRange deixa de fora só o /Contents, e o token carimba o hash dele
+    let start = update.find("/ByteRange [").unwrap() + "/ByteRange [".len();
+    let end = start + update[start..].find(']').unwrap();
+    let range: Vec<usize> = update[start..end]
+      .split_whitespace()
+      .map(|n| n.parse().unwrap())
+      .collect();
+    assert_eq!((range[0], range[2] + range[3]), (0, stamped.len()));
+    let mut covered = stamped[..range[1]].to_vec();
+    covered.extend_from_slice(&stamped[range[2]..]);
+
+    let token = hex::decode(&stamped[range[1] + 1..range[2] - 1]).unwrap();
+    let mut reader = der::SliceReader::new(&token).unwrap();
+    let signed_data = ContentInfo::decode(&mut reader)
+      .unwrap()
+      .content
+      .decode_as::<SignedData>()
+      .unwrap();
+    let tst_info = signed_data
+      .encap_content_info
+      .econtent
+      .unwrap()
+      .decode_as::<der::asn1::OctetString>()
+      .map(|content| TstInfo::from_der(content.as_bytes()).unwrap())
+      .unwrap();
+    assert_eq!(
+      tst_info.message_imprint.hashed_message.as_bytes(),
+      DigestAlgorithm::Sha256.digest(&covered)
+    );
+
+    // Sem TSA não há o que carimbar, e nada é enviado à rede
+    let without_tsa = SignatureConfig {
+      tsa_url: None,
+      tsa_urls: Vec::new(),
+      ..offline_config()
+    };
+    let err = PdfSigner::add_document_timestamp(sample_pdf(), &without_tsa).unwrap_err();
+    assert!(
+      matches!(err, PdfSignError::SigningError(ref message) if message.contains("TSA")),
+      "{}",
+      err
+    );
+    let offline = SignatureConfig {
+      offline: true,
+      ..config
+    };
+    let err = PdfSigner::add_document_timestamp(sample_pdf(), &offline).unwrap_err();
+    assert!(matches!(err, PdfSignError::SigningError(ref message) if message.contains("offline")));
+  }
+
+  #[test]
+  fn test_sign_report_records_signer_and_objects() {
+    let signer = test_signer();
+    let pdf = sample_pdf();
+    let next_obj = get_next_object_number(&pdf).unwrap();
+    let (signed, report) = signer.sign_pdf_with_report(pdf, &offline_config()).unwrap();
+
+    let record = report.signature.unwrap();
+    assert_eq!(record.signer_name, signer._certificate.subject_cn());
+    assert!(record.signer_name.is_some());
+    assert_eq!(record.signature_obj, next_obj as usize);
+    let entry = &list_signatures(&signed)[0];
+    assert_eq!(entry.object_number, record.signature_obj);
+    assert_eq!(entry.field_name, record.field_name);
+    assert_eq!(
+      entry.signing_date.as_deref(),
+      Some(
+        record
+          .signing_time
+          .format("D:%Y%m%d%H%M%SZ")
+          .to_string()
+          .as_str()
+      )
+    );
+    assert!(record.field_obj.is_some());
+
+    // Campo pré-criado: o objeto do campo é o que já existia
+    let prepared =
+      PdfSigner::add_empty_signature_field(sample_pdf(), "Cliente", [50.0, 50.0, 250.0, 100.0], 0)
+        .unwrap();
+    let config = SignatureConfig {
+      fill_existing_field: Some("Cliente".to_string()),
+      ..offline_config()
+    };
+    let (_, report) = signer
+      .sign_pdf_with_report(prepared.clone(), &config)
+      .unwrap();
+    let record = report.signature.unwrap();
+    assert_eq!(record.field_name.as_deref(), Some("Cliente"));
+    let field_obj = record.field_obj.unwrap();
+    assert!(field_obj < record.signature_obj);
+    assert!(
+      String::from_utf8_lossy(find_object_section(&prepared, field_obj).unwrap())
+        .contains("/T (Cliente)")
+    );
+  }
+
+  #[test]
+  fn test_sign_pdf_timestamp_retries_until_tsa_responds() {
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |request_index, request| {
+      if request_index < 2 {
+        MockResponse::status(503)
+      } else {
+        MockResponse::ok(tsa.respond(&request.body))
+      }
+    });
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BT,
+      tsa_url: Some(server.url()),
+      retry_policy: RetryPolicy {
+        max_retries: 3,
+        base_delay: Duration::from_millis(1),
+      },
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+
+    assert_eq!(server.request_count(), 3);
+    let signed_data = decode_signed_data(&extract_cms(&signed));
+    let signer_info = signed_data.signer_infos.0.get(0).unwrap();
+    let unsigned_attrs = signer_info.unsigned_attrs.as_ref().unwrap();
+    assert!(unsigned_attrs
+      .iter()
+      .any(|attr| attr.oid == ID_AA_SIGNATURE_TIME_STAMP_TOKEN));
+  }
+
+  #[test]
+  fn test_sign_pdf_timestamp_retries_exhausted() {
+    let server = MockServer::start(|_, _| MockResponse::status(503));
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BT,
+      tsa_url: Some(server.url()),
+      retry_policy: RetryPolicy {
+        max_retries: 1,
+        base_delay: Duration::from_millis(1),
+      },
+      ..offline_config()
+    };
+    let err = signer.sign_pdf(sample_pdf(), &config).unwrap_err();
+
+    assert!(matches!(err, PdfSignError::TimestampError(_)));
+    assert!(err.to_string().contains("HTTP 503"));
+    assert_eq!(server.request_count(), 2);
+  }
+
+  #[test]
+  fn test_sign_pdf_timestamp_falls_back_to_next_tsa() {
+    let failing = MockServer::start(|_, _| MockResponse::status(500));
+    let tsa = MockTsa::new();
+    let fallback =
+      MockServer::start(move |_, request| MockResponse::ok(tsa.respond(&request.body)));
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BT,
+      tsa_url: Some(failing.url()),
+      tsa_urls: vec![fallback.url()],
+      retry_policy: RetryPolicy {
+        max_retries: 0,
+        base_delay: Duration::from_millis(1),
+      },
+      ..offline_config()
+    };
+    let (signed, report) = signer.sign_pdf_with_report(sample_pdf(), &config).unwrap();
+
+    assert_eq!(failing.request_count(), 1);
+    assert_eq!(fallback.request_count(), 1);
+    assert_eq!(report.pades_level, PadesLevel::BT);
+    assert_eq!(report.tsa_url, Some(fallback.url()));
+    let signed_data = decode_signed_data(&extract_cms(&signed));
+    let signer_info = signed_data.signer_infos.0.get(0).unwrap();
+    assert!(signer_info
+      .unsigned_attrs
+      .as_ref()
+      .unwrap()
+      .iter()
+      .any(|attr| attr.oid == ID_AA_SIGNATURE_TIME_STAMP_TOKEN));
+
+    // Todas falhando: um único TimestampError com o motivo de cada TSA
+    let other = MockServer::start(|_, _| MockResponse::status(503));
+    let config = SignatureConfig {
+      tsa_urls: vec![other.url()],
+      ..config
+    };
+    let err = signer.sign_pdf(sample_pdf(), &config).unwrap_err();
+    assert!(matches!(err, PdfSignError::TimestampError(_)));
+    let message = err.to_string();
+    assert!(message.contains("nenhuma das 2 TSAs"), "{}", message);
+    assert!(message.contains(&failing.url()) && message.contains("HTTP 500"));
+    assert!(message.contains(&other.url()) && message.contains("HTTP 503"));
+  }
+
+  #[test]
+  fn test_sign_pdf_timestamp_sends_tsa_auth() {
+    use crate::signature_config::TsaAuth;
+
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| MockResponse::ok(tsa.respond(&request.body)));
+    let signer = test_signer();
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BT,
+      tsa_url: Some(server.url()),
+      ..offline_config()
+    };
+
+    // Sem credenciais (a TSA do governo), nenhum Authorization
+    signer.sign_pdf(sample_pdf(), &config).unwrap();
+    let basic = TsaAuth::Basic {
+      user: "usuario".to_string(),
+      password: "segredo".to_string(),
+    };
+    signer
+      .sign_pdf(
+        sample_pdf(),
+        &SignatureConfig {
+          tsa_auth: Some(basic.clone()),
+          ..config.clone()
+        },
+      )
+      .unwrap();
+    signer
+      .sign_pdf(
+        sample_pdf(),
+        &SignatureConfig {
+          tsa_auth: Some(TsaAuth::Bearer("token-123".to_string())),
+          ..config
+        },
+      )
+      .unwrap();
+
+    let authorization: Vec<Option<String>> = server
+      .requests()
+      .iter()
+      .map(|request| request.header("Authorization").map(str::to_string))
+      .collect();
+    assert_eq!(
+      authorization,
+      [
+        None,
+        Some("Basic dXN1YXJpbzpzZWdyZWRv".to_string()),
+        Some("Bearer token-123".to_string())
+      ]
+    );
+    assert!(!format!("{:?}", basic).contains("segredo"));
+  }
+
+  #[test]
+  fn test_tsa_auth_is_not_sent_to_fallback_tsa() {
+    use crate::signature_config::TsaAuth;
+
+    let primary = MockServer::start(|_, _| MockResponse::status(503));
+    let tsa = MockTsa::new();
+    let fallback = MockServer::start(move |_, request| {
+      // A alternativa recusa qualquer credencial da TSA principal
+      if request.header("Authorization").is_some() {
+        return MockResponse::status(400);
+      }
+      MockResponse::ok(tsa.respond(&request.body))
+    });
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BT,
+      tsa_url: Some(primary.url()),
+      tsa_urls: vec![fallback.url()],
+      tsa_auth: Some(TsaAuth::Bearer("token-da-principal".to_string())),
+      retry_policy: RetryPolicy {
+        max_retries: 0,
+        base_delay: Duration::from_millis(1),
+      },
+      ..offline_config()
+    };
+    let (_, report) = test_signer()
+      .sign_pdf_with_report(sample_pdf(), &config)
+      .unwrap();
+    assert_eq!(report.tsa_url, Some(fallback.url()));
+
+    assert_eq!(
+      primary.requests()[0].header("Authorization"),
+      Some("Bearer token-da-principal")
+    );
+    assert!(fallback
+      .requests()
+      .iter()
+      .all(|request| request.header("Authorization").is_none()));
+  }
+
+  #[test]
+  fn test_sign_pdf_timestamp_timeout_fails_promptly() {
+    let server =
+      MockServer::start(|_, _| MockResponse::ok(Vec::new()).delayed(Duration::from_secs(5)));
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BT,
+      tsa_url: Some(server.url()),
+      retry_policy: RetryPolicy {
+        max_retries: 0,
+        base_delay: Duration::from_millis(1),
+      },
+      network_timeout: Duration::from_millis(200),
+      ..offline_config()
+    };
+    let started = std::time::Instant::now();
+    let err = signer.sign_pdf(sample_pdf(), &config).unwrap_err();
+
+    assert!(matches!(err, PdfSignError::TimestampError(_)));
+    assert!(err.to_string().contains("tempo limite excedido"));
+    assert!(started.elapsed() < Duration::from_secs(2));
+  }
+
+  #[test]
+  fn test_sign_pdf_blt_appends_dss() {
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| MockResponse::ok(tsa.respond(&request.body)));
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BLT,
+      tsa_url: Some(server.url()),
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    let signed_str = String::from_utf8_lossy(&signed);
+
+    // Certificado autoassinado: sem OCSP/CRL, apenas a cadeia vai para o /DSS
+    assert!(signed_str.contains("/Type /DSS\n/Certs ["));
+    assert!(signed_str.contains("/DSS "));
+    assert_eq!(signed_str.matches("%%EOF").count(), 4);
+  }
+
+  #[test]
+  fn test_resigning_at_blt_keeps_previous_dss_entries() {
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| MockResponse::ok(tsa.respond(&request.body)));
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BLT,
+      tsa_url: Some(server.url()),
+      ..offline_config()
+    };
+    let dss = |pdf: &[u8]| {
+      let catalog_obj = extract_catalog_info(pdf).unwrap().catalog_obj;
+      DssContents::read(pdf, catalog_obj)
+    };
+
+    let first = test_signer().sign_pdf(sample_pdf(), &config).unwrap();
+    let first_dss = dss(&first);
+    assert!(!first_dss.certs.is_empty());
+
+    let (key, cert) = issue_identity("Segundo Signatario", None, false);
+    let second_signer =
+      PdfSigner::from_pfx_bytes(&pkcs12_bytes(&key, &cert, &[], "senha"), "senha").unwrap();
+    let second = second_signer.sign_pdf(first, &config).unwrap();
+    let second_dss = dss(&second);
+
+    // Os certificados da primeira assinatura continuam no /DSS vigente, antes dos novos
+    assert!(second_dss
+      .certs
+      .starts_with(&format!("{} ", first_dss.certs)));
+    assert!(second_dss.certs.len() > first_dss.certs.len());
+
+    // A revisão coberta pela segunda assinatura também aponta para o /DSS anterior
+    let range = &crate::signatures::list_signatures(&second)[1].byte_range;
+    assert_eq!(dss(&second[..range[2] + range[3]]), first_dss);
+  }
+
+  #[test]
+  fn test_dss_contents_reads_inline_and_referenced_arrays() {
+    let pdf = b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R /DSS << /Certs 5 0 R \
+      /OCSPs [7 0 R] /VRI << /ABC << /Cert [8 0 R] >> >> >> >>\nendobj\n\
+      5 0 obj\n[9 0 R 10 0 R]\nendobj\n";
+    assert_eq!(
+      DssContents::read(pdf, 1),
+      DssContents {
+        certs: "9 0 R 10 0 R".to_string(),
+        crls: String::new(),
+        ocsps: "7 0 R".to_string(),
+        vri: Some("/VRI << /ABC << /Cert [8 0 R] >> >>".to_string()),
+      }
+    );
+    assert_eq!(DssContents::read(pdf, 5), DssContents::default());
+  }
+
+  /// PDF 1.7 mínimo cuja referência cruzada é uma xref stream sem filtro
+  fn xref_stream_pdf() -> Vec<u8> {
+    let objects = [
+      "1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n",
+      "2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 1 >>\nendobj\n",
+      "3 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>\nendobj\n",
+    ];
+    let mut pdf = b"%PDF-1.7\n".to_vec();
+    let mut offsets = Vec::new();
+    for object in objects {
+      offsets.push(pdf.len());
+      pdf.extend_from_slice(object.as_bytes());
+    }
+    let xref_start = pdf.len();
+    offsets.push(xref_start);
+    // /W [1 2 1]: o objeto 0 livre, depois os três objetos e a própria xref stream
+    let mut rows = vec![0u8, 0, 0, 255];
+    for offset in offsets {
+      rows.extend_from_slice(&[1, (offset >> 8) as u8, offset as u8, 0]);
+    }
+    pdf.extend_from_slice(
+      format!(
+        "4 0 obj\n<< /Type /XRef /Size 5 /W [1 2 1] /Root 1 0 R /Length {} >>\nstream\n",
+        rows.len()
+      )
+      .as_bytes(),
+    );
+    pdf.extend_from_slice(&rows);
+    pdf.extend_from_slice(
+      format!("\nendstream\nendobj\nstartxref\n{}\n%%EOF\n", xref_start).as_bytes(),
+    );
+    pdf
+  }
+
+  /// Percorre a cadeia /Prev exigindo só xref streams, com cada objeto no offset declarado
+  fn walk_xref_stream_chain(pdf: &[u8]) -> usize {
+    let mut offset = Some(find_startxref(pdf).unwrap());
+    let mut revisions = 0;
+    while let Some(xref_start) = offset {
+      let section = &pdf[xref_start..];
+      assert!(
+        !section.starts_with(b"xref"),
+        "tabela xref em {}",
+        xref_start
+      );
+      let entries = read_xref_stream(section).expect("xref stream ilegível");
+      for (obj, obj_offset) in entries {
+        assert_eq!(
+          parse_object_header(&pdf[obj_offset..]).map(|(num, _)| num),
+          Some(obj),
+          "objeto {} fora do offset {}",
+          obj,
+          obj_offset
+        );
+      }
+      let dict = dict_body(&section[..section.windows(6).position(|w| w == b"stream").unwrap()])
+        .unwrap()
+        .to_vec();
+      assert_eq!(read_reference(&dict, b"/Root"), Some((1, 0)));
+      offset = read_number_after(&dict, b"/Prev");
+      revisions += 1;
+    }
+    revisions
+  }
+
+  #[test]
+  fn test_sign_pdf_with_xref_stream() {
+    let signer = test_signer();
+    let pdf = xref_stream_pdf();
+    let config = SignatureConfig {
+      xref_format: XrefFormat::Stream,
+      self_verify: true,
+      ..offline_config()
+    };
+
+    let signed = signer.sign_pdf(pdf.clone(), &config).unwrap();
+    verify_first_signature(&signed);
+    let update = &signed[pdf.len()..];
+    assert!(!update.windows(7).any(|w| w == b"trailer"));
+    assert!(!update.windows(6).any(|w| w == b"\nxref\n"));
+    assert_eq!(walk_xref_stream_chain(&signed), 2);
+    // /Size conta a própria xref stream, e a assinatura seguinte numera depois dela
+    let xref = &signed[find_startxref(&signed).unwrap()..];
+    assert_eq!(
+      read_number_after(xref, b"/Size"),
+      Some(get_next_object_number(&signed).unwrap() as usize)
+    );
+
+    let twice = signer.sign_pdf(signed, &config).unwrap();
+    assert_eq!(list_signatures(&twice).len(), 2);
+    assert_eq!(walk_xref_stream_chain(&twice), 3);
+
+    // B-LT: a atualização do /DSS também grava xref stream
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| MockResponse::ok(tsa.respond(&request.body)));
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BLT,
+      tsa_url: Some(server.url()),
+      ..config
+    };
+    let with_dss = signer.sign_pdf(pdf.clone(), &config).unwrap();
+    assert!(String::from_utf8_lossy(&with_dss).contains("/Type /DSS\n"));
+    assert_eq!(walk_xref_stream_chain(&with_dss), 3);
+
+    // A tabela continua o padrão, e PDFs antigos não recebem xref stream
+    let err = signer
+      .sign_pdf(
+        sample_pdf(),
+        &SignatureConfig {
+          xref_format: XrefFormat::Stream,
+          ..offline_config()
+        },
+      )
+      .unwrap_err();
+    assert!(err.to_string().contains("PDF 1.5"), "{}", err);
+  }
+
+  #[test]
+  fn test_sign_pdf_blt_reuses_cached_crl() {
+    use std::time::Duration;
+
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let fresh_crl = issue_crl(&root_key, &root, Some(Duration::from_secs(3600)));
+    let open_crl = issue_crl(&root_key, &root, None);
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| match request.path.as_str() {
+      "/fresca.crl" => MockResponse::ok(fresh_crl.clone()),
+      "/sem-prazo.crl" => MockResponse::ok(open_crl.clone()),
+      _ => MockResponse::ok(tsa.respond(&request.body)),
+    });
+    let crl_requests = |path: &str| {
+      server
+        .requests()
+        .iter()
+        .filter(|request| request.method == "GET" && request.path == path)
+        .count()
+    };
+    let signer_with_crl = |path: &str| {
+      let (key, cert) = identity_with_crl(
+        "Signatario",
+        (&root_key, &root),
+        &format!("{}{}", server.url(), path.trim_start_matches('/')),
+      );
+      let pfx = pkcs12_bytes(&key, &cert, std::slice::from_ref(&root), "senha");
+      PdfSigner::from_pfx_bytes(&pfx, "senha").unwrap()
+    };
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BLT,
+      tsa_url: Some(server.url()),
+      include_ocsp: false,
+      include_crl: true,
+      ..offline_config()
+    };
+
+    // Dentro do nextUpdate a segunda assinatura usa a CRL já baixada
+    let signer = signer_with_crl("/fresca.crl");
+    for _ in 0..2 {
+      let (signed, report) = signer.sign_pdf_with_report(sample_pdf(), &config).unwrap();
+      assert_eq!(report.pades_level, PadesLevel::BLT);
+      assert!(String::from_utf8_lossy(&signed).contains("/CRLs ["));
+    }
+    assert_eq!(crl_requests("/fresca.crl"), 1);
+
+    // Sem nextUpdate não há prazo para reaproveitar: baixa de novo
+    let signer_open = signer_with_crl("/sem-prazo.crl");
+    for _ in 0..2 {
+      signer_open.sign_pdf(sample_pdf(), &config).unwrap();
+    }
+    assert_eq!(crl_requests("/sem-prazo.crl"), 2);
+
+    crate::cache::clear_caches();
+    signer.sign_pdf(sample_pdf(), &config).unwrap();
+    assert_eq!(crl_requests("/fresca.crl"), 2);
+  }
+
+  #[test]
+  fn test_offline_signing_makes_no_http_requests() {
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let crl = issue_crl(&root_key, &root, None);
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| match request.method.as_str() {
+      "GET" => MockResponse::ok(crl.clone()),
+      _ => MockResponse::ok(tsa.respond(&request.body)),
+    });
+    let (key, cert) = identity_with_crl(
+      "Signatario",
+      (&root_key, &root),
+      &format!("{}lista.crl", server.url()),
+    );
+    let pfx = pkcs12_bytes(&key, &cert, std::slice::from_ref(&root), "senha");
+    let signer = PdfSigner::from_pfx_bytes(&pfx, "senha").unwrap();
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BLT,
+      tsa_url: Some(server.url()),
+      include_ocsp: true,
+      include_crl: true,
+      offline: true,
+      ..offline_config()
+    };
+
+    // B-LT pedido com TSA e CRL alcançáveis: offline assina em B-B sem tocar a rede
+    let (signed, report) = signer.sign_pdf_with_report(sample_pdf(), &config).unwrap();
+    assert_eq!(server.request_count(), 0);
+    assert_eq!(report.pades_level, PadesLevel::BB);
+    assert_eq!(report.tsa_url, None);
+    assert_eq!(report.warnings.len(), 1);
+    assert!(report.warnings[0].contains("offline"));
+    verify_first_signature(&signed);
+    assert!(!String::from_utf8_lossy(&signed).contains("/DSS"));
+    assert_eq!(list_signatures(&signed)[0].timestamp_time, None);
+
+    // Exigir o nível com Strict é um erro, também sem rede
+    let err = signer
+      .sign_pdf(
+        sample_pdf(),
+        &SignatureConfig {
+          pades_level_mode: PadesLevelMode::Strict,
+          ..config.clone()
+        },
+      )
+      .unwrap_err();
+    assert!(err.to_string().contains("offline"), "{}", err);
+    assert_eq!(server.request_count(), 0);
+
+    // Sem offline, a mesma configuração usa a TSA e baixa a CRL
+    let online = SignatureConfig {
+      offline: false,
+      include_ocsp: false,
+      ..config
+    };
+    let (_, report) = signer.sign_pdf_with_report(sample_pdf(), &online).unwrap();
+    assert_eq!(report.pades_level, PadesLevel::BLT);
+    assert!(server.request_count() >= 2);
+  }
+
+  #[test]
+  fn test_sign_pdf_downgrades_unreachable_pades_level() {
+    let signer = test_signer();
+
+    let (signed, report) = signer
+      .sign_pdf_with_report(sample_pdf(), &offline_config())
+      .unwrap();
+    assert_eq!(report.pades_level, PadesLevel::BB);
+    assert!(report.warnings.is_empty());
+    let baseline_eofs = String::from_utf8_lossy(&signed).matches("%%EOF").count();
+
+    // B-LT sem TSA: assina em B-B, sem carimbo nem /DSS, e avisa no relatório
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BLT,
+      tsa_url: None,
+      ..offline_config()
+    };
+    let (signed, report) = signer.sign_pdf_with_report(sample_pdf(), &config).unwrap();
+    assert_eq!(report.pades_level, PadesLevel::BB);
+    assert_eq!(report.warnings.len(), 1);
+    let signed_str = String::from_utf8_lossy(&signed);
+    assert!(!signed_str.contains("/DSS"));
+    assert_eq!(signed_str.matches("%%EOF").count(), baseline_eofs);
+    let signed_data = decode_signed_data(&extract_cms(&signed));
+    assert!(signed_data
+      .signer_infos
+      .0
+      .get(0)
+      .unwrap()
+      .unsigned_attrs
+      .is_none());
+
+    let strict = SignatureConfig {
+      pades_level_mode: PadesLevelMode::Strict,
+      ..config
+    };
+    let err = signer.sign_pdf(sample_pdf(), &strict).unwrap_err();
+    assert!(matches!(err, PdfSignError::SigningError(ref m) if m.contains("tsa_url")));
+  }
+
+  #[test]
+  fn test_sign_pdf_blt_without_revocation_is_bt() {
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| MockResponse::ok(tsa.respond(&request.body)));
+
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BLT,
+      tsa_url: Some(server.url()),
+      include_ocsp: false,
+      include_crl: false,
+      ..offline_config()
+    };
+    let (signed, report) = test_signer()
+      .sign_pdf_with_report(sample_pdf(), &config)
+      .unwrap();
+    assert_eq!(report.pades_level, PadesLevel::BT);
+    assert!(report.warnings[0].contains("include_ocsp"));
+    assert!(!String::from_utf8_lossy(&signed).contains("/DSS"));
+    assert_eq!(server.request_count(), 1);
+
+    let strict = SignatureConfig {
+      pades_level_mode: PadesLevelMode::Strict,
+      ..config
+    };
+    assert!(test_signer().sign_pdf(sample_pdf(), &strict).is_err());
+    assert_eq!(server.request_count(), 1);
+  }
+
+  #[test]
+  fn test_sign_pdf_validates_chain_against_trust_anchors() {
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let (leaf_key, leaf) = issue_identity("Signatario", Some((&root_key, &root)), false);
+    let pfx = pkcs12_bytes(&leaf_key, &leaf, &[], "senha");
+    let signer = PdfSigner::from_pfx_bytes(&pfx, "senha").unwrap();
+
+    let config = SignatureConfig {
+      validate_icp_brasil: true,
+      trust_anchors_mode: TrustAnchorsMode::Replace,
+      ..offline_config()
+    };
+
+    // Sem a raiz customizada a cadeia não tem âncora confiável
+    let err = signer.sign_pdf(sample_pdf(), &config).unwrap_err();
+    assert!(matches!(err, PdfSignError::IcpBrasilValidationError(_)));
+
+    let config = SignatureConfig {
+      trust_anchors: Some(vec![root.to_pem().unwrap()]),
+      ..config
+    };
+    signer.sign_pdf(sample_pdf(), &config).unwrap();
+  }
+
+  #[test]
+  fn test_sign_pdf_respects_docmdp_permission() {
+    // Atualização incremental com a assinatura de certificação e o Catalog com /Perms /DocMDP
+    let certified = |permission: u8| {
+      let sig_obj = get_next_object_number(&sample_pdf()).unwrap();
+      catalog_update_pdf(
+        &[format!(
+          "<<\n/Type /Sig\n/Reference [<<\n/Type /SigRef\n/TransformMethod /DocMDP\n/TransformParams <<\n/Type /TransformParams\n/P {}\n/V /1.2\n>>\n>>]\n>>",
+          permission
+        )],
+        &[format!("/Perms << /DocMDP {} 0 R >>", sig_obj)],
+      )
+      .0
+    };
+
+    let signer = test_signer();
+    let err = signer
+      .sign_pdf(certified(1), &offline_config())
+      .unwrap_err();
+    assert!(matches!(err, PdfSignError::InvalidPdf(_)));
+    assert!(err.to_string().contains("DocMDP"));
+
+    signer.sign_pdf(certified(2), &offline_config()).unwrap();
+    signer.sign_pdf(certified(3), &offline_config()).unwrap();
+  }
+
+  #[test]
+  fn test_list_signatures_on_pdf_with_cr_line_endings() {
+    use crate::signatures::list_signatures;
+
+    // Fim de linha só com \r (Mac antigo): mesmo tamanho, então os offsets da xref valem
+    let pdf: Vec<u8> = sample_pdf()
+      .into_iter()
+      .map(|byte| if byte == b'\n' { b'\r' } else { byte })
+      .collect();
+    let next_obj = get_next_object_number(&sample_pdf()).unwrap() as usize;
+    assert_eq!(get_next_object_number(&pdf).unwrap() as usize, next_obj);
+
+    let signer = test_signer();
+    let once = signer.sign_pdf(pdf, &offline_config()).unwrap();
+    let twice = signer.sign_pdf(once, &offline_config()).unwrap();
+    verify_first_signature(&twice);
+
+    // Os números novos não reaproveitam os objetos do original
+    let entries = list_signatures(&twice);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].object_number, next_obj);
+    assert!(entries[1].object_number > entries[0].object_number);
+    assert_eq!(entries[0].field_name.as_deref(), Some("Signature1"));
+    assert_eq!(entries[1].field_name.as_deref(), Some("Signature2"));
+  }
+
+  #[test]
+  fn test_list_signatures_on_doubly_signed_pdf() {
+    use crate::signatures::list_signatures;
+
+    let signer = test_signer();
+    let first = SignatureConfig {
+      reason: Some("Primeira".to_string()),
+      ..offline_config()
+    };
+    let second = SignatureConfig {
+      reason: Some("Segunda".to_string()),
+      ..offline_config()
+    };
+    let once = signer.sign_pdf(sample_pdf(), &first).unwrap();
+    let twice = signer.sign_pdf(once, &second).unwrap();
+
+    let entries = list_signatures(&twice);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].reason.as_deref(), Some("Primeira"));
+    assert_eq!(entries[1].reason.as_deref(), Some("Segunda"));
+    // O campo da primeira assinatura continua no AcroForm, então a segunda ganha outro nome
+    assert_eq!(entries[0].field_name.as_deref(), Some("Signature1"));
+    assert_eq!(entries[1].field_name.as_deref(), Some("Signature2"));
+    for entry in &entries {
+      assert_eq!(entry.signer_name.as_deref(), Some("Henrique Mota"));
+      assert_eq!(entry.sub_filter.as_deref(), Some("adbe.pkcs7.detached"));
+      assert_eq!(entry.byte_range.len(), 4);
+      assert!(entry
+        .signing_date
+        .as_deref()
+        .is_some_and(|m| m.starts_with("D:")));
+    }
+    assert!(entries[0].object_number < entries[1].object_number);
+    let covered_end = |range: &[usize]| range[2] + range[3];
+    assert!(covered_end(&entries[0].byte_range) < covered_end(&entries[1].byte_range));
+  }
+
+  /// PDF de exemplo com um campo de assinatura vazio "Assinatura Cliente" (como em templates)
+  fn template_with_empty_field() -> Vec<u8> {
+    let mut pdf = sample_pdf();
+    let catalog = extract_catalog_info(&pdf).unwrap();
+    let page = extract_first_page_info(&pdf).unwrap().first_page_obj;
+    let field_obj = get_next_object_number(&pdf).unwrap() as usize;
+
+    pdf.push(b'\n');
+    let field_pos = pdf.len();
+    pdf.extend_from_slice(
+      format!(
+        "{} 0 obj\n<<\n/Type /Annot\n/Subtype /Widget\n/FT /Sig\n/T (Assinatura Cliente)\n/Rect [50 50 250 100]\n/F 4\n/P {} 0 R\n>>\nendobj\n",
+        field_obj, page
+      )
+      .as_bytes(),
+    );
+    let acroform_pos = pdf.len();
+    pdf.extend_from_slice(
+      format!(
+        "{} 0 obj\n<<\n/Fields [{} 0 R]\n/SigFlags 1\n>>\nendobj\n",
+        field_obj + 1,
+        field_obj
+      )
+      .as_bytes(),
+    );
+    let catalog_pos = pdf.len();
+    let original = sample_pdf();
+    let catalog_text = build_updated_catalog(
+      catalog.catalog_obj,
+      catalog.pages_ref,
+      Some(field_obj + 1),
+      &original,
+      &[],
+    )
+    .unwrap();
+    pdf.extend_from_slice(catalog_text.as_bytes());
+
+    let xref_start = pdf.len();
+    let mut entries = vec![
+      (field_obj, field_pos),
+      (field_obj + 1, acroform_pos),
+      (catalog.catalog_obj, catalog_pos),
+    ];
+    pdf.extend_from_slice(build_xref_table(&mut entries, &pdf, field_obj).as_bytes());
+    pdf.extend_from_slice(
+      format!(
+        "trailer\n<<\n/Size {}\n/Prev {}\n/Root {} 0 R\n>>\nstartxref\n{}\n%%EOF\n",
+        field_obj + 2,
+        find_startxref(&original).unwrap(),
+        catalog.catalog_obj,
+        xref_start
+      )
+      .as_bytes(),
+    );
+    pdf
+  }
+
+  /// PDF de exemplo com um formulário preenchível: campo de texto "Nome", /NeedAppearances e /DR
+  fn form_with_text_field() -> Vec<u8> {
+    let mut pdf = sample_pdf();
+    let original = sample_pdf();
+    let catalog = extract_catalog_info(&pdf).unwrap();
+    let page = extract_first_page_info(&pdf).unwrap().first_page_obj;
+    let field_obj = get_next_object_number(&pdf).unwrap() as usize;
+
+    pdf.push(b'\n');
+    let field_pos = pdf.len();
+    pdf.extend_from_slice(
+      format!(
+        "{} 0 obj\n<<\n/Type /Annot\n/Subtype /Widget\n/FT /Tx\n/T (Nome)\n/V (Maria)\n/Rect [50 700 250 720]\n/F 4\n/P {} 0 R\n>>\nendobj\n",
+        field_obj, page
+      )
+      .as_bytes(),
+    );
+    let acroform_pos = pdf.len();
+    pdf.extend_from_slice(
+      format!(
+        "{} 0 obj\n<<\n/Fields [{} 0 R]\n/NeedAppearances true\n/DR << /Font << /Helv << /Type /Font /Subtype /Type1 /BaseFont /Helvetica >> >> >>\n/DA (/Helv 0 Tf 0 g)\n>>\nendobj\n",
+        field_obj + 1,
+        field_obj
+      )
+      .as_bytes(),
+    );
+    let catalog_pos = pdf.len();
+    let catalog_text = build_updated_catalog(
+      catalog.catalog_obj,
+      catalog.pages_ref,
+      Some(field_obj + 1),
+      &original,
+      &[],
+    )
+    .unwrap();
+    pdf.extend_from_slice(catalog_text.as_bytes());
+
+    let xref_start = pdf.len();
+    let mut entries = vec![
+      (field_obj, field_pos),
+      (field_obj + 1, acroform_pos),
+      (catalog.catalog_obj, catalog_pos),
+    ];
+    pdf.extend_from_slice(build_xref_table(&mut entries, &pdf, field_obj).as_bytes());
+    pdf.extend_from_slice(
+      format!(
+        "trailer\n<<\n/Size {}\n/Prev {}\n/Root {} 0 R\n>>\nstartxref\n{}\n%%EOF\n",
+        field_obj + 2,
+        find_startxref(&original).unwrap(),
+        catalog.catalog_obj,
+        xref_start
+      )
+      .as_bytes(),
+    );
+    pdf
+  }
+
+  /// Acrescenta ao PDF de exemplo uma atualização com `objects` (numerados a partir
+  /// do próximo objeto livre) e um Catalog de uma linha só (como fazem muitos
+  /// geradores) com as entradas `catalog_entries`
+  fn catalog_update_pdf(objects: &[String], catalog_entries: &[String]) -> (Vec<u8>, usize) {
+    let mut pdf = sample_pdf();
+    let original = sample_pdf();
+    let catalog = extract_catalog_info(&pdf).unwrap();
+    let first_obj = get_next_object_number(&pdf).unwrap() as usize;
+
+    pdf.push(b'\n');
+    let mut entries = Vec::new();
+    for (obj, body) in (first_obj..).zip(objects) {
+      entries.push((obj, pdf.len()));
+      pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", obj, body).as_bytes());
+    }
+
+    entries.push((catalog.catalog_obj, pdf.len()));
+    pdf.extend_from_slice(
+      format!(
+        "{} 0 obj\n<< /Type /Catalog /Pages {} 0 R {} >>\nendobj\n",
+        catalog.catalog_obj,
+        catalog.pages_ref,
+        catalog_entries.join(" ")
+      )
+      .as_bytes(),
+    );
+
+    let xref_start = pdf.len();
+    pdf.extend_from_slice(build_xref_table(&mut entries, &pdf, first_obj).as_bytes());
+    pdf.extend_from_slice(
+      format!(
+        "trailer\n<<\n/Size {}\n/Prev {}\n/Root {} 0 R\n>>\nstartxref\n{}\n%%EOF\n",
+        first_obj + objects.len(),
+        find_startxref(&original).unwrap(),
+        catalog.catalog_obj,
+        xref_start
+      )
+      .as_bytes(),
+    );
+    (pdf, catalog.catalog_obj)
+  }
+
+  /// PDF de exemplo com marcadores, destinos nomeados e /OpenAction no Catalog
+  fn navigation_pdf() -> (Vec<u8>, usize, Vec<String>) {
+    let pdf = sample_pdf();
+    let page = extract_first_page_info(&pdf).unwrap().first_page_obj;
+    let outlines_obj = get_next_object_number(&pdf).unwrap() as usize;
+    let (item_obj, dests_obj) = (outlines_obj + 1, outlines_obj + 2);
+
+    let objects = [
+      format!(
+        "<< /Type /Outlines /First {0} 0 R /Last {0} 0 R /Count 1 >>",
+        item_obj
+      ),
+      format!(
+        "<< /Title (Capitulo 1) /Parent {} 0 R /Dest [{} 0 R /Fit] >>",
+        outlines_obj, page
+      ),
+      format!("<< /Names [(inicio) [{} 0 R /XYZ 0 800 0]] >>", page),
+    ];
+    let preserved = vec![
+      format!("/Outlines {} 0 R", outlines_obj),
+      "/PageMode /UseOutlines".to_string(),
+      format!("/OpenAction [{} 0 R\n  /FitH 800]", page),
+      format!("/Names << /Dests {} 0 R >>", dests_obj),
+      "/ViewerPreferences << /DisplayDocTitle true >>".to_string(),
+      "/Lang (pt-BR)".to_string(),
+    ];
+    let (pdf, catalog_obj) = catalog_update_pdf(&objects, &preserved);
+    (pdf, catalog_obj, preserved)
+  }
+
+  #[test]
+  fn test_sign_pdf_keeps_catalog_navigation_entries() {
+    let signer = test_signer();
+    let (pdf, catalog_obj, preserved) = navigation_pdf();
+
+    let signed = signer.sign_pdf(pdf, &offline_config()).unwrap();
+    verify_first_signature(&signed);
+
+    let catalog = String::from_utf8_lossy(find_object_section(&signed, catalog_obj).unwrap());
+    for entry in &preserved {
+      assert!(
+        catalog.contains(entry.as_str()),
+        "{} perdido em {}",
+        entry,
+        catalog
+      );
+    }
+    assert_eq!(catalog.matches("/Type /Catalog").count(), 1);
+    assert!(catalog.contains("/AcroForm "));
+  }
+
+  #[test]
+  fn test_sign_pdf_keeps_catalog_entries_after_endobj_in_string() {
+    // "endobj" dentro de strings não encerra o Catalog, nem nos dicionários aninhados
+    let preserved = vec![
+      "/PageLabels << /Nums [0 << /P (Anexo endobj) >>] >>".to_string(),
+      "/Lang (pt-BR endobj)".to_string(),
+      "/PageMode /UseOutlines".to_string(),
+    ];
+    let (pdf, catalog_obj) = catalog_update_pdf(&[], &preserved);
+
+    let signed = test_signer().sign_pdf(pdf, &offline_config()).unwrap();
+    verify_first_signature(&signed);
+    let catalog = String::from_utf8_lossy(find_object_section(&signed, catalog_obj).unwrap());
+    for entry in &preserved {
+      assert!(
+        catalog.contains(entry.as_str()),
+        "{} perdido em {}",
+        entry,
+        catalog
+      );
+    }
+    assert!(catalog.contains("/AcroForm "));
+  }
+
+  /// Stream /Metadata com um pacote XMP, declarando PDF/A-2b quando `pdfaid`
+  fn xmp_metadata_stream(pdfaid: bool) -> String {
+    let declaration = if pdfaid {
+      " xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\" pdfaid:part=\"2\" pdfaid:conformance=\"B\""
+    } else {
+      ""
+    };
+    let xmp = format!(
+      "<?xpacket begin=\"\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"><rdf:Description rdf:about=\"\"{}/></rdf:RDF></x:xmpmeta>\n<?xpacket end=\"w\"?>",
+      declaration
+    );
+    format!(
+      "<< /Type /Metadata /Subtype /XML /Length {} >>\nstream\n{}\nendstream",
+      xmp.len(),
+      xmp
+    )
+  }
+
+  /// PDF de exemplo com XMP no Catalog; `objects` vêm logo após o objeto do XMP
+  ///
+  /// Devolve o PDF, o Catalog e o objeto do XMP
+  fn xmp_pdf(pdfaid: bool, objects: &[String], entries: &[String]) -> (Vec<u8>, usize, usize) {
+    let metadata_obj = get_next_object_number(&sample_pdf()).unwrap() as usize;
+    let mut all_objects = vec![xmp_metadata_stream(pdfaid)];
+    all_objects.extend_from_slice(objects);
+    let mut all_entries = vec![format!("/Metadata {} 0 R", metadata_obj)];
+    all_entries.extend_from_slice(entries);
+    let (pdf, catalog_obj) = catalog_update_pdf(&all_objects, &all_entries);
+    (pdf, catalog_obj, metadata_obj)
+  }
+
+  #[test]
+  fn test_sign_pdf_keeps_xmp_metadata() {
+    let signer = test_signer();
+    let (pdf, catalog_obj, metadata_obj) = xmp_pdf(true, &[], &[]);
+
+    let signed = signer.sign_pdf(pdf, &offline_config()).unwrap();
+    verify_first_signature(&signed);
+
+    let catalog = String::from_utf8_lossy(find_object_section(&signed, catalog_obj).unwrap());
+    assert!(catalog.contains(&format!("/Metadata {} 0 R", metadata_obj)));
+    let metadata = String::from_utf8_lossy(find_object_section(&signed, metadata_obj).unwrap());
+    assert!(
+      metadata.contains(&xmp_metadata_stream(true)),
+      "{}",
+      metadata
+    );
+  }
+
+  /// Conferência estrutural no estilo do veraPDF para PDF/A-2b assinado
+  fn assert_pdfa_structure(signed: &[u8]) {
+    use crate::signatures::list_signatures;
+
+    walk_xref_chain(signed);
+    assert!(!is_encrypted(signed), "PDF/A não pode ser criptografado");
+
+    // Conformidade declarada no XMP da revisão final, sem compressão
+    let catalog_info = extract_catalog_info(signed).unwrap();
+    let catalog = find_object_section(signed, catalog_info.catalog_obj).unwrap();
+    let metadata_obj = read_number_after(catalog, b"/Metadata").expect("Catalog sem /Metadata");
+    let metadata = find_object_section(signed, metadata_obj).unwrap();
+    let metadata_dict =
+      dict_body(&metadata[..metadata.windows(6).position(|w| w == b"stream").unwrap()]).unwrap();
+    assert!(dict_has_key(metadata_dict, b"/XML"));
+    assert!(!dict_has_key(metadata_dict, b"/Filter"));
+    assert!(metadata.windows(11).any(|w| w == b"pdfaid:part"));
+
+    // AcroForm sem /NeedAppearances true
+    let acroform_obj = read_number_after(catalog, b"/AcroForm").expect("Catalog sem /AcroForm");
+    let acroform = dict_body(find_object_section(signed, acroform_obj).unwrap()).unwrap();
+    assert!(!dict_entries(acroform)
+      .into_iter()
+      .any(|(key, entry)| key == b"/NeedAppearances" && entry.ends_with(b"true")));
+
+    // Assinaturas com /SubFilter permitido e widgets imprimíveis, sem área ou com /AP
+    for field_obj in read_ref_array(acroform, b"/Fields").unwrap() {
+      let field = dict_body(find_object_section(signed, field_obj).unwrap()).unwrap();
+      if !dict_has_key(field, b"/Sig") {
+        continue;
+      }
+      let flags = read_number_after(field, b"/F").unwrap_or(0);
+      assert_eq!(flags & (1 | 2 | 32 | 256), 0, "widget oculto");
+      assert_ne!(flags & 4, 0, "widget sem o bit Print");
+      assert!(!rect_has_area(field) || dict_has_key(field, b"/AP"));
+    }
+    let signatures = list_signatures(signed);
+    assert!(!signatures.is_empty());
+    for signature in signatures {
+      assert!(matches!(
+        signature.sub_filter.as_deref(),
+        Some("adbe.pkcs7.detached") | Some("ETSI.CAdES.detached")
+      ));
+    }
+  }
+
+  #[test]
+  fn test_sign_pdf_pdfa_mode_keeps_structure() {
+    let signer = test_signer();
+    let config = SignatureConfig {
+      pdfa_mode: true,
+      ..offline_config()
+    };
+    let (pdf, _, _) = xmp_pdf(true, &[], &[]);
+
+    let signed = signer.sign_pdf(pdf.clone(), &config).unwrap();
+    verify_first_signature(&signed);
+    assert_pdfa_structure(&signed);
+
+    // Mesmo resultado lendo o arquivo em blocos, em que o XMP não chega ao esqueleto
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("pdfsigner-pdfa-in-{}.pdf", std::process::id()));
+    let output = dir.join(format!("pdfsigner-pdfa-out-{}.pdf", std::process::id()));
+    fs::write(&input, &pdf).unwrap();
+    let result = signer.sign_pdf_file_to_file(&input, &output, &config);
+    let signed_file = fs::read(&output);
+    let _ = fs::remove_file(&input);
+    let _ = fs::remove_file(&output);
+    result.unwrap();
+    assert_pdfa_structure(&signed_file.unwrap());
+  }
+
+  #[test]
+  fn test_check_pdfa_declaration_reads_catalog_metadata_across_chunks() {
+    // Um byte por leitura: os marcadores sempre ficam divididos entre blocos
+    struct ByteReader<'a>(&'a [u8]);
+    impl Read for ByteReader<'_> {
+      fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some((&byte, rest)) = self.0.split_first() else {
+          return Ok(0);
+        };
+        buf[0] = byte;
+        self.0 = rest;
+        Ok(1)
+      }
+    }
+
+    let (pdf, _, _) = xmp_pdf(true, &[], &[]);
+    check_pdfa_declaration(ByteReader(&pdf), &pdf).unwrap();
+    let (pdf, _, _) = xmp_pdf(false, &[xmp_metadata_stream(true)], &[]);
+    assert!(check_pdfa_declaration(ByteReader(&pdf), &pdf).is_err());
+  }
+
+  #[test]
+  fn test_pdfa_mode_rejects_documents_outside_the_standard() {
+    let signer = test_signer();
+    let config = SignatureConfig {
+      pdfa_mode: true,
+      ..offline_config()
+    };
+    let rejected = |pdf: Vec<u8>, expected: &str| match signer.sign_pdf(pdf, &config) {
+      Err(PdfSignError::InvalidPdf(message)) => {
+        assert!(message.contains(expected), "{}", message)
+      }
+      other => panic!(
+        "esperava recusa ({}), veio {:?}",
+        expected,
+        other.map(|_| ())
+      ),
+    };
+
+    rejected(catalog_update_pdf(&[], &[]).0, "/Metadata");
+    rejected(sample_pdf(), "pdfaid:part");
+    rejected(xmp_pdf(false, &[], &[]).0, "pdfaid:part");
+    // A declaração fora do /Metadata do Catalog não conta
+    let (pdf, _, _) = xmp_pdf(false, &[xmp_metadata_stream(true)], &[]);
+    rejected(pdf, "pdfaid:part");
+
+    // /Encrypt no trailer mais recente (fica depois da xref, sem mexer nos offsets)
+    let (pdf, _, _) = xmp_pdf(true, &[], &[]);
+    let trailer = pdf.windows(7).rposition(|w| w == b"trailer").unwrap() + b"trailer\n<<\n".len();
+    let mut encrypted = pdf[..trailer].to_vec();
+    encrypted.extend_from_slice(b"/Encrypt 999 0 R\n");
+    encrypted.extend_from_slice(&pdf[trailer..]);
+    rejected(encrypted, "/Encrypt");
+
+    let acroform_obj = get_next_object_number(&sample_pdf()).unwrap() as usize + 1;
+    let (pdf, _, _) = xmp_pdf(
+      true,
+      &["<< /Fields [] /NeedAppearances true >>".to_string()],
+      &[format!("/AcroForm {} 0 R", acroform_obj)],
+    );
+    rejected(pdf, "/NeedAppearances");
+    let (pdf, _, _) = xmp_pdf(
+      true,
+      &[],
+      &["/AcroForm << /Fields [] /NeedAppearances true >>".to_string()],
+    );
+    rejected(pdf, "/NeedAppearances");
+
+    // Campo do template visível, mas sem aparência própria
+    let (pdf, _, _) = xmp_pdf(true, &[], &[]);
+    let visible =
+      PdfSigner::add_empty_signature_field(pdf.clone(), "Cliente", [50.0, 50.0, 250.0, 100.0], 0)
+        .unwrap();
+    match signer.sign_pdf(
+      visible.clone(),
+      &SignatureConfig {
+        fill_existing_field: Some("Cliente".to_string()),
+        ..config.clone()
+      },
+    ) {
+      Err(PdfSignError::InvalidPdf(message)) => assert!(message.contains("/AP"), "{}", message),
+      other => panic!(
+        "esperava recusa do campo visível, veio {:?}",
+        other.map(|_| ())
+      ),
+    }
+    // A aparência gerada só vale sem texto: a Helvetica não é incorporada
+    let text_appearance = crate::signature_config::SignatureAppearance {
+      elements: vec![crate::signature_config::AppearanceElement {
+        content: AppearanceContent::Text {
+          text: "Cliente".to_string(),
+          font_size: 10.0,
+        },
+        rect: [0.0, 0.0, 200.0, 50.0],
+      }],
+      ..Default::default()
+    };
+    match signer.sign_pdf(
+      visible,
+      &SignatureConfig {
+        fill_existing_field: Some("Cliente".to_string()),
+        appearance: Some(text_appearance),
+        ..config.clone()
+      },
+    ) {
+      Err(PdfSignError::InvalidPdf(message)) => assert!(message.contains("fonte"), "{}", message),
+      other => panic!("esperava recusa do texto, veio {:?}", other.map(|_| ())),
+    }
+    let invisible = PdfSigner::add_empty_signature_field(pdf, "Cliente", [0.0; 4], 0).unwrap();
+    let signed = signer
+      .sign_pdf(
+        invisible,
+        &SignatureConfig {
+          fill_existing_field: Some("Cliente".to_string()),
+          ..config.clone()
+        },
+      )
+      .unwrap();
+    assert_pdfa_structure(&signed);
+
+    // Sem o modo PDF/A nada disso é conferido
+    assert!(signer.sign_pdf(sample_pdf(), &offline_config()).is_ok());
+  }
+
+  #[test]
+  fn test_analyze_reports_plan_without_signing() {
+    use crate::signatures::list_signatures;
+
+    let signer = test_signer();
+    // TSA inalcançável: a análise não pode tocar a rede
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BT,
+      tsa_url: Some("http://127.0.0.1:9/".to_string()),
+      ..offline_config()
+    };
+
+    let plan = signer.analyze(&sample_pdf(), &config).unwrap();
+
+    assert_eq!(plan.catalog_obj, 7);
+    assert_eq!(
+      plan.pages_obj,
+      extract_catalog_info(&sample_pdf()).unwrap().pages_ref
+    );
+    assert_eq!(
+      plan.first_page_obj,
+      extract_first_page_info(&sample_pdf())
+        .unwrap()
+        .first_page_obj
+    );
+    assert_eq!(plan.next_obj, 19);
+    assert!(!plan.has_acroform);
+    assert_eq!(plan.field_name.as_deref(), Some("Signature1"));
+    assert_eq!(plan.pades_level, PadesLevel::BT);
+    assert!(plan.warnings.is_empty());
+
+    // O placeholder previsto é o que a assinatura de fato usa (sem TSA, mesmo tamanho de B-B)
+    let plan = signer.analyze(&sample_pdf(), &offline_config()).unwrap();
+    let signed = signer.sign_pdf(sample_pdf(), &offline_config()).unwrap();
+    let range = &list_signatures(&signed)[0].byte_range;
+    assert_eq!(plan.contents_offset, range[1]);
+    assert_eq!(plan.contents_offset + plan.contents_len, range[2]);
+
+    // Campo existente do template e documentos inválidos
+    let template = template_with_empty_field();
+    let fill = SignatureConfig {
+      fill_existing_field: Some("Assinatura Cliente".to_string()),
+      ..offline_config()
+    };
+    let plan = signer.analyze(&template, &fill).unwrap();
+    assert!(plan.has_acroform);
+    assert_eq!(plan.field_name.as_deref(), Some("Assinatura Cliente"));
+    assert!(matches!(
+      signer.analyze(b"PK\x03\x04 docx", &offline_config()),
+      Err(PdfSignError::InvalidPdf(_))
+    ));
+  }
+
+  #[test]
+  fn test_sign_pdf_keeps_existing_form_fields() {
+    use crate::signatures::list_signatures;
+
+    let form = form_with_text_field();
+    let text_field = get_next_object_number(&sample_pdf()).unwrap() as usize;
+    let signed = test_signer()
+      .sign_pdf(form.clone(), &offline_config())
+      .unwrap();
+
+    // O AcroForm regravado é o mesmo objeto, com o campo de texto e o de assinatura
+    let catalog = extract_catalog_info(&signed).unwrap();
+    let acroform_obj = read_number_after(
+      find_object_section(&signed, catalog.catalog_obj).unwrap(),
+      b"/AcroForm",
+    )
+    .unwrap();
+    assert_eq!(acroform_obj, text_field + 1);
+    let acroform = String::from_utf8_lossy(find_object_section(&signed, acroform_obj).unwrap());
+    let sig_field = list_signatures(&signed)[0].object_number + 1;
+    assert!(
+      acroform.contains(&format!("/Fields [{} 0 R {} 0 R]", text_field, sig_field)),
+      "{}",
+      acroform
+    );
+    assert!(acroform.contains("/NeedAppearances true"));
+    assert!(acroform.contains("/DR << /Font << /Helv"));
+    assert!(acroform.contains("/DA (/Helv 0 Tf 0 g)"));
+    assert!(acroform.contains("/SigFlags 3"));
+
+    // O campo de texto não é tocado e a assinatura continua válida
+    assert_eq!(
+      find_object_section(&signed, text_field),
+      find_object_section(&form, text_field)
+    );
+    assert_eq!(walk_xref_chain(&signed), walk_xref_chain(&form));
+    verify_first_signature(&signed);
+  }
+
+  /// Formulário com o AcroForm escrito direto no Catalog e um campo de texto
+  fn form_with_inline_acroform() -> (Vec<u8>, usize) {
+    let page = extract_first_page_info(&sample_pdf())
+      .unwrap()
+      .first_page_obj;
+    let text_field = get_next_object_number(&sample_pdf()).unwrap() as usize;
+    let (pdf, _) = catalog_update_pdf(
+      &[format!(
+        "<< /Type /Annot /Subtype /Widget /FT /Tx /T (Nome) /Rect [50 700 250 720] /P {} 0 R >>",
+        page
+      )],
+      &[format!(
+        "/AcroForm << /Fields [{} 0 R] /DA (/Helv 0 Tf 0 g) >>",
+        text_field
+      )],
+    );
+    (pdf, text_field)
+  }
+
+  #[test]
+  fn test_sign_pdf_with_inline_acroform() {
+    use crate::signatures::list_signatures;
+
+    let (form, text_field) = form_with_inline_acroform();
+    let signer = test_signer();
+    let signed = signer.sign_pdf(form.clone(), &offline_config()).unwrap();
+    verify_first_signature(&signed);
+
+    // O AcroForm direto vira um objeto novo, com o campo de texto e o de assinatura
+    let catalog_obj = extract_catalog_info(&signed).unwrap().catalog_obj;
+    let catalog = find_object_section(&signed, catalog_obj).unwrap();
+    let (acroform_obj, _) = read_reference(catalog, b"/AcroForm").expect("/AcroForm sem objeto");
+    assert_ne!(acroform_obj, text_field);
+    let acroform = String::from_utf8_lossy(find_object_section(&signed, acroform_obj).unwrap());
+    let sig_field = list_signatures(&signed)[0].object_number + 1;
+    assert!(
+      acroform.contains(&format!("/Fields [{} 0 R {} 0 R]", text_field, sig_field)),
+      "{}",
+      acroform
+    );
+    assert!(acroform.contains("/DA (/Helv 0 Tf 0 g)"));
+    assert!(acroform.contains("/SigFlags 3"));
+    assert_eq!(
+      find_object_section(&signed, text_field),
+      find_object_section(&form, text_field)
+    );
+
+    // Campo vazio do template no mesmo formulário, assinado depois
+    let prepared =
+      PdfSigner::add_empty_signature_field(form.clone(), "Cliente", [0.0, 0.0, 0.0, 0.0], 0)
+        .unwrap();
+    let prepared_catalog = find_object_section(&prepared, catalog_obj).unwrap();
+    let (acroform_obj, _) = read_reference(prepared_catalog, b"/AcroForm").unwrap();
+    let acroform = String::from_utf8_lossy(find_object_section(&prepared, acroform_obj).unwrap());
+    assert!(
+      acroform.contains(&format!("/Fields [{} 0 R ", text_field)),
+      "{}",
+      acroform
+    );
+    let filled = signer
+      .sign_pdf(
+        prepared,
+        &SignatureConfig {
+          fill_existing_field: Some("Cliente".to_string()),
+          ..offline_config()
+        },
+      )
+      .unwrap();
+    verify_first_signature(&filled);
+  }
+
+  #[test]
+  fn test_usage_rights_keeps_inline_acroform() {
+    use crate::signature_config::UsageRights;
+
+    let (form, text_field) = form_with_inline_acroform();
+    let config = SignatureConfig {
+      usage_rights: Some(UsageRights::default()),
+      self_verify: true,
+      ..offline_config()
+    };
+    let signed = test_signer().sign_pdf(form, &config).unwrap();
+
+    // O formulário não é tocado: o Catalog novo continua com o AcroForm direto
+    let catalog_obj = extract_catalog_info(&signed).unwrap().catalog_obj;
+    let catalog = String::from_utf8_lossy(find_object_section(&signed, catalog_obj).unwrap());
+    assert!(
+      catalog.contains(&format!(
+        "/AcroForm << /Fields [{} 0 R] /DA (/Helv 0 Tf 0 g) >>",
+        text_field
+      )),
+      "{}",
+      catalog
+    );
+    assert!(catalog.contains("/Perms << /UR3 "));
+  }
+
+  #[test]
+  fn test_append_acroform_field() {
+    let pdf = b"9 0 obj\n[3 0 R 4 0 R]\nendobj\n";
+    assert_eq!(
+      append_acroform_field(pdf, b"\n/Fields [3 0 R]\n/SigFlags 1\n", 7),
+      "\n/Fields [3 0 R 7 0 R]\n\n"
+    );
+    assert_eq!(
+      append_acroform_field(pdf, b" /Fields 9 0 R /NeedAppearances true", 7),
+      " /Fields [3 0 R 4 0 R 7 0 R] /NeedAppearances true"
+    );
+    assert_eq!(
+      append_acroform_field(pdf, b" /DA (/Helv 0 Tf 0 g)", 7),
+      " /DA (/Helv 0 Tf 0 g)\n/Fields [7 0 R]"
+    );
+    assert_eq!(
+      unused_signature_field_name(b"/T (Signature1)"),
+      "Signature2"
+    );
+    assert_eq!(unused_signature_field_name(b"/T (Nome)"), "Signature1");
+  }
+
+  /// Amostra com um stream grande (bytes pseudoaleatórios) em atualização incremental
+  fn large_pdf(stream_len: usize) -> Vec<u8> {
+    let mut pdf = sample_pdf();
+    let catalog = extract_catalog_info(&pdf).unwrap();
+    let stream_obj = get_next_object_number(&pdf).unwrap() as usize;
+    let prev_xref = find_startxref(&pdf).unwrap();
+
+    pdf.push(b'\n');
+    let stream_pos = pdf.len();
+    pdf.extend_from_slice(
+      format!(
+        "{} 0 obj\n<<\n/Length {}\n>>\nstream\n",
+        stream_obj, stream_len
+      )
+      .as_bytes(),
+    );
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    pdf.extend((0..stream_len).map(|_| {
+      state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+      (state >> 56) as u8
+    }));
+    pdf.extend_from_slice(b"\nendstream\nendobj\n");
+
+    let xref_start = pdf.len();
+    pdf.extend_from_slice(
+      build_xref_table(&mut [(stream_obj, stream_pos)], &pdf, stream_obj).as_bytes(),
+    );
+    pdf.extend_from_slice(
+      format!(
+        "trailer\n<<\n/Size {}\n/Prev {}\n/Root {} 0 R\n>>\nstartxref\n{}\n%%EOF\n",
+        stream_obj + 1,
+        prev_xref,
+        catalog.catalog_obj,
+        xref_start
+      )
+      .as_bytes(),
+    );
+    pdf
+  }
+
+  #[test]
+  fn test_signature_placeholder_offsets_do_not_depend_on_spacing() {
+    // Espaçamentos que uma busca por "/Contents <" ou "/ByteRange [" não encontraria
+    for separator in [" ", "\n", "\r\n\t ", "  \n"] {
+      let mut dict = format!("5 0 obj\n<<\n/Type /Sig\n/ByteRange{}", separator);
+      let byte_range = push_span(&mut dict, BYTE_RANGE_PLACEHOLDER);
+      dict.push_str(&format!("{}/Contents{}", separator, separator));
+      let contents = push_span(&mut dict, "<0000>");
+      dict.push_str(&format!("{}/M{}(", separator, separator));
+      let date = push_span(&mut dict, "D:00000000000000Z");
+      dict.push_str(")\n>>\nendobj\n");
+
+      assert_eq!(&dict[byte_range], BYTE_RANGE_PLACEHOLDER);
+      assert_eq!(&dict[contents], "<0000>");
+      assert_eq!(&dict[date], "D:00000000000000Z");
+    }
+
+    // No PDF assinado, o placeholder anotado é o intervalo que o ByteRange deixa de fora
+    let signer = test_signer();
+    let plan = signer.analyze(&sample_pdf(), &offline_config()).unwrap();
+    let signed = signer.sign_pdf(sample_pdf(), &offline_config()).unwrap();
+    let range = &crate::signatures::list_signatures(&signed)[0].byte_range;
+    assert_eq!(range[1], plan.contents_offset);
+    assert_eq!(range[2], plan.contents_offset + plan.contents_len);
+    assert_eq!(signed[plan.contents_offset], b'<');
+    assert_eq!(signed[range[2] - 1], b'>');
+  }
+
+  #[test]
+  fn test_empty_text_entries_are_omitted_from_signature_dict() {
+    let signer = test_signer();
+    let sig_dict = |signed: &[u8]| {
+      let entry = &crate::signatures::list_signatures(signed)[0];
+      String::from_utf8_lossy(find_object_section(signed, entry.object_number).unwrap()).to_string()
+    };
+
+    let config = SignatureConfig {
+      reason: Some(String::new()),
+      location: String::new(),
+      contact_info: String::new(),
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    verify_first_signature(&signed);
+    let dict = sig_dict(&signed);
+    for key in ["/Reason", "/Location", "/ContactInfo"] {
+      assert!(!dict.contains(key), "{} não deveria estar em {}", key, dict);
+    }
+    assert!(dict.contains("/Name "));
+    // ByteRange e Contents continuam antes dos demais campos
+    let byte_range = dict.find("/ByteRange").unwrap();
+    let contents = dict.find("/Contents").unwrap();
+    assert!(byte_range < contents && contents < dict.find("/M (").unwrap());
+
+    let config = SignatureConfig {
+      reason: Some("Aprovado".to_string()),
+      contact_info: "suporte@exemplo.com.br".to_string(),
+      ..offline_config()
+    };
+    let dict = sig_dict(&signer.sign_pdf(sample_pdf(), &config).unwrap());
+    assert!(dict.contains("/ContactInfo (suporte@exemplo.com.br)\n"));
+    assert!(dict.contains("/Reason (Aprovado)\n"));
+    assert!(dict.contains(&format!("/Location ({})\n", config.location)));
+  }
+
+  #[test]
+  fn test_date_first_sig_dict_order_signs_and_verifies() {
+    use crate::signature_config::SigDictOrder;
+    use chrono::TimeZone;
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      reason: Some("Aprovado".to_string()),
+      sig_dict_order: SigDictOrder::DateFirst,
+      signing_time: Some(Utc.with_ymd_and_hms(2024, 3, 15, 12, 34, 56).unwrap()),
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    verify_first_signature(&signed);
+
+    let entry = &crate::signatures::list_signatures(&signed)[0];
+    let dict = String::from_utf8_lossy(find_object_section(&signed, entry.object_number).unwrap())
+      .to_string();
+    assert!(
+      dict.contains(">\n/M (D:20240315123456Z)\n/Reason (Aprovado)\n"),
+      "{}",
+      dict
+    );
+  }
+
+  #[test]
+  fn test_text_limit_truncates_or_rejects_long_reason() {
+    use crate::signature_config::{TextLimit, TextOverflow};
+
+    let signer = test_signer();
+    let reason = "Aprovado ".repeat(100);
+    let limit = TextLimit {
+      max_chars: 64,
+      overflow: TextOverflow::Truncate,
+    };
+    let config = SignatureConfig {
+      reason: Some(reason.clone()),
+      text_limit: Some(limit),
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    verify_first_signature(&signed);
+    let entry = &crate::signatures::list_signatures(&signed)[0];
+    assert_eq!(entry.reason.as_deref().map(|r| r.chars().count()), Some(64));
+    assert!(entry.reason.as_deref().unwrap().ends_with("..."));
+
+    let config = SignatureConfig {
+      text_limit: Some(TextLimit {
+        overflow: TextOverflow::Error,
+        ..limit
+      }),
+      ..config
+    };
+    assert!(matches!(
+      signer.sign_pdf(sample_pdf(), &config),
+      Err(PdfSignError::SigningError(_))
+    ));
+  }
+
+  #[test]
+  fn test_text_entries_use_utf16_only_when_not_ascii() {
+    use crate::signature_config::TextEncoding;
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      reason: Some("Aprovação (final)".to_string()),
+      location: "Brasil".to_string(),
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    verify_first_signature(&signed);
+    let entry = &list_signatures(&signed)[0];
+    let dict = String::from_utf8_lossy(find_object_section(&signed, entry.object_number).unwrap())
+      .to_string();
+    assert!(dict.contains("/Location (Brasil)\n"), "{}", dict);
+    assert!(dict.contains("/Reason <FEFF"), "{}", dict);
+    assert_eq!(entry.reason.as_deref(), Some("Aprovação (final)"));
+
+    let config = SignatureConfig {
+      location: "São Paulo".to_string(),
+      ..config
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+    assert!(String::from_utf8_lossy(&signed)
+      .contains("/Location <FEFF005300E3006F0020005000610075006C006F>\n"));
+
+    // Literal mantém os bytes UTF-8, escapando os parênteses
+    let literal = SignatureConfig {
+      text_encoding: TextEncoding::Literal,
+      ..config
+    };
+    let signed = signer.sign_pdf(sample_pdf(), &literal).unwrap();
+    let signed_str = String::from_utf8_lossy(&signed);
+    assert!(signed_str.contains("/Reason (Aprovação \\(final\\))\n"));
+    assert!(signed_str.contains("/Location (São Paulo)\n"));
+  }
+
+  #[test]
+  fn test_default_reason_follows_certificate() {
+    use crate::test_support::icp_brasil_identity;
+
+    let (ca_key, ca_cert) = issue_identity("AC Teste", None, true);
+    let (key, cert) = icp_brasil_identity(
+      "FULANO DE TAL:12345678909",
+      &[(
+        "2.16.76.1.3.1",
+        "0101198012345678909000000000000000000001234567SSPSP",
+      )],
+      (&ca_key, &ca_cert),
+    );
+    let icp_brasil =
+      PdfSigner::from_pfx_bytes(&pkcs12_bytes(&key, &cert, &[], "segredo"), "segredo").unwrap();
+    let (key, cert) = issue_identity("Fulano", Some((&ca_key, &ca_cert)), false);
+    let plain =
+      PdfSigner::from_pfx_bytes(&pkcs12_bytes(&key, &cert, &[], "segredo"), "segredo").unwrap();
+    let reason = |signer: &PdfSigner, config: &SignatureConfig| {
+      let signed = signer.sign_pdf(sample_pdf(), config).unwrap();
+      list_signatures(&signed)[0].reason.clone()
+    };
+
+    // Sem motivo na configuração: padrão ICP-Brasil só para certificados ICP-Brasil
+    assert_eq!(
+      reason(&icp_brasil, &offline_config()).as_deref(),
+      Some("Assinatura digital conforme ICP-Brasil")
+    );
+    assert_eq!(reason(&plain, &offline_config()), None);
+
+    // Motivo vazio é recusa explícita: nenhum /Reason, nem o padrão
+    let opt_out = SignatureConfig {
+      reason: Some(String::new()),
+      ..offline_config()
+    };
+    assert_eq!(reason(&icp_brasil, &opt_out), None);
+
+    // O vocabulário controlado vale para o motivo efetivo, inclusive o padrão
+    let restricted = SignatureConfig {
+      allowed_reasons: Some(vec!["Aprovo".to_string()]),
+      ..offline_config()
+    };
+    let err = icp_brasil.sign_pdf(sample_pdf(), &restricted).unwrap_err();
+    assert!(err
+      .to_string()
+      .contains("\"Assinatura digital conforme ICP-Brasil\" não está entre os permitidos"));
+  }
+
+  #[test]
+  fn test_e_cnpj_signs_as_seal() {
+    use crate::test_support::icp_brasil_identity;
+
+    let (ca_key, ca_cert) = issue_identity("AC Teste", None, true);
+    let (key, cert) = icp_brasil_identity(
+      "EMPRESA LTDA:11222333000181",
+      &[
+        (
+          "2.16.76.1.3.4",
+          "150719759876543210000000000000000000000000000000",
+        ),
+        ("2.16.76.1.3.3", "11222333000181"),
+      ],
+      (&ca_key, &ca_cert),
+    );
+    let seal =
+      PdfSigner::from_pfx_bytes(&pkcs12_bytes(&key, &cert, &[], "segredo"), "segredo").unwrap();
+
+    let (signed, report) = seal
+      .sign_pdf_with_report(sample_pdf(), &offline_config())
+      .unwrap();
+    assert_eq!(report.certificate_kind, Some(IcpBrasilKind::Seal));
+    let entry = &list_signatures(&signed)[0];
+    assert_eq!(entry.signer_name.as_deref(), Some("EMPRESA LTDA"));
+    assert_eq!(
+      entry.reason.as_deref(),
+      Some("Selo digital de pessoa jurídica conforme ICP-Brasil")
+    );
+
+    // signer_name e reason da configuração continuam valendo para o selo
+    let config = SignatureConfig {
+      signer_name: Some("Filial Centro".to_string()),
+      reason: Some("Emissão de nota".to_string()),
+      ..offline_config()
+    };
+    let signed = seal.sign_pdf(sample_pdf(), &config).unwrap();
+    let entry = &list_signatures(&signed)[0];
+    assert_eq!(entry.signer_name.as_deref(), Some("Filial Centro"));
+    assert_eq!(entry.reason.as_deref(), Some("Emissão de nota"));
+
+    // e-CPF mantém o CN inteiro e o motivo de assinatura; fora da ICP-Brasil, sem tipo
+    let (key, cert) = icp_brasil_identity(
+      "FULANO DE TAL:12345678909",
+      &[(
+        "2.16.76.1.3.1",
+        "0101198012345678909000000000000000000001234567SSPSP",
+      )],
+      (&ca_key, &ca_cert),
+    );
+    let person =
+      PdfSigner::from_pfx_bytes(&pkcs12_bytes(&key, &cert, &[], "segredo"), "segredo").unwrap();
+    let (signed, report) = person
+      .sign_pdf_with_report(sample_pdf(), &offline_config())
+      .unwrap();
+    assert_eq!(report.certificate_kind, Some(IcpBrasilKind::Signature));
+    assert_eq!(
+      list_signatures(&signed)[0].signer_name.as_deref(),
+      Some("FULANO DE TAL:12345678909")
+    );
+    let (_, report) = test_signer()
+      .sign_pdf_with_report(sample_pdf(), &offline_config())
+      .unwrap();
+    assert_eq!(report.certificate_kind, None);
+  }
+
+  #[test]
+  fn test_sign_report_flags_linearized_input() {
+    let signer = test_signer();
+    let sample = sample_pdf();
+    assert!(is_linearized(&sample));
+    let (_, report) = signer
+      .sign_pdf_with_report(sample.clone(), &offline_config())
+      .unwrap();
+    assert!(report.linearized);
+    assert!(
+      signer
+        .analyze(&sample, &offline_config())
+        .unwrap()
+        .linearized
+    );
+
+    // Mesmo documento reescrito sem o dicionário /Linearized no primeiro objeto
+    let head_end = sample.windows(6).position(|w| w == b"endobj").unwrap();
+    let mut plain = sample.clone();
+    plain.splice(
+      ..head_end,
+      String::from_utf8_lossy(&sample[..head_end])
+        .replace("/Linearized 1", "/Xinearized 1")
+        .into_bytes(),
+    );
+    assert!(!is_linearized(&plain));
+    let (_, report) = signer
+      .sign_pdf_with_report(plain, &offline_config())
+      .unwrap();
+    assert!(!report.linearized);
+  }
+
+  #[test]
+  fn test_sign_compact_pdf_with_glued_endobj() {
+    // Sem espaço em volta de "endobj", nem entre os objetos
+    let objects = [
+      "1 0 obj<</Type/Catalog/Pages 2 0 R>>endobj",
+      "2 0 obj<</Type/Pages/Kids[3 0 R]/Count 1>>endobj",
+      "3 0 obj<</Type/Page/Parent 2 0 R/MediaBox[0 0 612 792]>>endobj",
+    ];
+    let mut pdf = b"%PDF-1.7\n".to_vec();
+    let mut xref = String::from("xref\n0 4\n0000000000 65535 f \n");
+    for object in objects {
+      xref.push_str(&format!("{:010} 00000 n \n", pdf.len()));
+      pdf.extend_from_slice(object.as_bytes());
+    }
+    pdf.push(b'\n');
+    let xref_start = pdf.len();
+    pdf.extend_from_slice(xref.as_bytes());
+    pdf.extend_from_slice(
+      format!(
+        "trailer\n<< /Size 4 /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        xref_start
+      )
+      .as_bytes(),
+    );
+
+    let signed = test_signer()
+      .sign_pdf(pdf.clone(), &offline_config())
+      .unwrap();
+    verify_first_signature(&signed);
+    walk_xref_chain(&signed);
+    let update = String::from_utf8_lossy(&signed[pdf.len()..]).to_string();
+    // Os novos objetos começam depois do 3, e o Catalog regravado mantém o /Pages
+    assert!(update.starts_with("4 0 obj\n"), "{}", update);
+    assert!(
+      !update.contains("\n2 0 obj") && !update.contains("\n3 0 obj"),
+      "{}",
+      update
+    );
+    assert!(
+      update.contains("1 0 obj\n<<\n/Type /Catalog\n/Pages 2 0 R\n"),
+      "{}",
+      update
+    );
+    assert!(update.contains("/P 3 0 R\n"), "{}", update);
+  }
+
+  #[test]
+  fn test_signing_keeps_object_generations() {
+    // Catalog, Pages e página na geração 1 (objetos reaproveitados após exclusão)
+    let objects = [
+      "1 1 obj\n<< /Type /Catalog /Pages 2 1 R >>\nendobj\n",
+      "2 1 obj\n<< /Type /Pages /Kids [3 1 R] /Count 1 >>\nendobj\n",
+      "3 1 obj\n<< /Type /Page /Parent 2 1 R /MediaBox [0 0 612 792] >>\nendobj\n",
+    ];
+    let mut pdf = b"%PDF-1.7\n".to_vec();
+    let mut xref = String::from("xref\n0 4\n0000000000 65535 f \n");
+    for object in objects {
+      xref.push_str(&format!("{:010} 00001 n \n", pdf.len()));
+      pdf.extend_from_slice(object.as_bytes());
+    }
+    let xref_start = pdf.len();
+    pdf.extend_from_slice(xref.as_bytes());
+    pdf.extend_from_slice(
+      format!(
+        "trailer\n<< /Size 4 /Root 1 1 R >>\nstartxref\n{}\n%%EOF\n",
+        xref_start
+      )
+      .as_bytes(),
+    );
+
+    let signer = test_signer();
+    let signed = signer.sign_pdf(pdf.clone(), &offline_config()).unwrap();
+    verify_first_signature(&signed);
+    walk_xref_chain(&signed);
+    let update = String::from_utf8_lossy(&signed[pdf.len()..]).to_string();
+    assert!(
+      update.contains("1 1 obj\n<<\n/Type /Catalog\n/Pages 2 1 R\n"),
+      "{}",
+      update
+    );
+    assert!(update.contains("/P 3 1 R\n"), "{}", update);
+    assert!(update.contains("/Root 1 1 R\n"), "{}", update);
+    assert!(
+      !update.contains("1 0 obj") && !update.contains("3 0 R"),
+      "{}",
+      update
+    );
+
+    // Campo pré-criado: a página regravada e o /P do widget mantêm a geração
+    let prepared =
+      PdfSigner::add_empty_signature_field(pdf, "Cliente", [50.0, 50.0, 250.0, 100.0], 0).unwrap();
+    walk_xref_chain(&prepared);
+    let page = String::from_utf8_lossy(find_object_section(&prepared, 3).unwrap()).to_string();
+    assert!(page.starts_with("3 1 obj"), "{}", page);
+    let config = SignatureConfig {
+      fill_existing_field: Some("Cliente".to_string()),
+      ..offline_config()
+    };
+    let signed = signer.sign_pdf(prepared, &config).unwrap();
+    verify_first_signature(&signed);
+    walk_xref_chain(&signed);
+    let entry = &crate::signatures::list_signatures(&signed)[0];
+    assert_eq!(entry.field_name.as_deref(), Some("Cliente"));
+  }
+
+  #[test]
+  fn test_recorded_offsets_match_output_scan() {
+    let find = |haystack: &[u8], needle: &[u8], from: usize| {
+      from
+        + haystack[from..]
+          .windows(needle.len())
+          .position(|w| w == needle)
+          .unwrap()
+    };
+
+    let pdf = remove_trailing_newline(sample_pdf());
+    let update = test_signer()
+      .build_incremental_update(&pdf, pdf.len(), &offline_config())
+      .unwrap();
+    let tail = &update.tail;
+
+    // Os offsets anotados na geração são os mesmos que a varredura antiga encontrava
+    let byte_range_pos = find(tail, b"/ByteRange [", 0) + b"/ByteRange ".len();
+    let placeholder_pos = find(tail, b"/Contents <", byte_range_pos) + b"/Contents ".len();
+    let placeholder_end = find(tail, b">", placeholder_pos);
+    let date_pos = find(tail, b"/M (", 0) + b"/M (".len();
+
+    assert_eq!(update.byte_range_pos, byte_range_pos);
+    assert_eq!(update.byte_range_len, BYTE_RANGE_PLACEHOLDER.len());
+    assert_eq!(update.placeholder_pos, placeholder_pos);
+    assert_eq!(
+      update.placeholder_len,
+      placeholder_end + 1 - placeholder_pos
+    );
+    assert_eq!(update.date_pos, date_pos);
+
+    let range = update.byte_range();
+    let written = format!("[{} {} {} {}]", range[0], range[1], range[2], range[3]);
+    assert!(tail[byte_range_pos..].starts_with(written.as_bytes()));
+    assert!(tail[date_pos..].starts_with(b"D:2"));
+  }
+
+  #[test]
+  fn test_recorded_offsets_in_signed_pdf() {
+    let pdf = sample_pdf();
+    let increment = test_signer()
+      .sign_incremental_only(&pdf, &offline_config())
+      .unwrap();
+    let mut signed = pdf[..increment.base_len].to_vec();
+    signed.extend_from_slice(&increment.bytes);
+    verify_first_signature(&signed);
+
+    // ByteRange e /M gravados nos offsets anotados, sem sobra dos placeholders
+    let section = find_object_section(&signed, increment.signature.signature_obj).unwrap();
+    let section = String::from_utf8_lossy(section);
+    let range = increment.byte_range;
+    assert!(section.contains(&format!(
+      "/ByteRange [{} {} {} {}]",
+      range[0], range[1], range[2], range[3]
+    )));
+    let date = format!(
+      "D:{}Z",
+      increment.signature.signing_time.format("%Y%m%d%H%M%S")
+    );
+    assert!(section.contains(&format!("/M ({})", date)), "{}", section);
+    assert!(!section.contains(BYTE_RANGE_PLACEHOLDER));
+    assert!(!section.contains(DATE_PLACEHOLDER));
+
+    // O intervalo fora do ByteRange é exatamente o /Contents
+    assert_eq!(signed[range[1]], b'<');
+    assert_eq!(signed[range[2] - 1], b'>');
+    assert!(signed[..range[1]].ends_with(b"/Contents "));
+    assert_eq!(range[2] + range[3], signed.len());
+
+    let entry = &crate::signatures::list_signatures(&signed)[0];
+    assert_eq!(entry.byte_range, range.to_vec());
+    assert_eq!(entry.signing_date.as_deref(), Some(date.as_str()));
+  }
+
+  #[test]
+  fn test_oversized_byte_range_is_an_error() {
+    let pdf = remove_trailing_newline(sample_pdf());
+    let mut update = test_signer()
+      .build_incremental_update(&pdf, pdf.len(), &offline_config())
+      .unwrap();
+    // Espaço reservado menor que "[0 a b c]"
+    update.byte_range_len = 8;
+    let before = update.tail.clone();
+
+    let result = update.write_byte_range();
+    assert!(
+      matches!(&result, Err(PdfSignError::InvalidPdf(message)) if message.contains("excede o espaço reservado")),
+      "{:?}",
+      result
+    );
+    assert_eq!(update.tail, before);
+  }
+
+  #[test]
+  fn test_misplaced_date_offset_is_an_error() {
+    let pdf = remove_trailing_newline(sample_pdf());
+    let mut update = test_signer()
+      .build_incremental_update(&pdf, pdf.len(), &offline_config())
+      .unwrap();
+    update.date_pos -= 1;
+    let before = update.tail.clone();
+
+    let result = update.write_signing_time();
+    assert!(
+      matches!(&result, Err(PdfSignError::InvalidPdf(message)) if message.contains("placeholder de /M não encontrado")),
+      "{:?}",
+      result
+    );
+    assert_eq!(update.tail, before);
+  }
+
+  #[test]
+  fn test_sign_incremental_only_concatenates_to_sign_pdf_output() {
+    use crate::signatures::list_signatures;
+    use chrono::TimeZone;
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      signing_time: Some(Utc.with_ymd_and_hms(2024, 3, 15, 12, 34, 56).unwrap()),
+      ..offline_config()
+    };
+    let original = sample_pdf();
+
+    let increment = signer.sign_incremental_only(&original, &config).unwrap();
+    let signed = signer.sign_pdf(original.clone(), &config).unwrap();
+
+    // O original termina em "\r": o fim de linha fica de fora da concatenação
+    assert_eq!(increment.base_len, original.len() - 1);
+    let mut concatenated = original[..increment.base_len].to_vec();
+    concatenated.extend_from_slice(&increment.bytes);
+    assert_eq!(concatenated, signed);
+    assert_eq!(
+      increment.byte_range.to_vec(),
+      list_signatures(&signed)[0].byte_range
+    );
+    verify_first_signature(&concatenated);
+  }
+
+  #[test]
+  fn test_max_input_bytes_rejects_before_reading() {
+    let config = SignatureConfig {
+      max_input_bytes: Some(1024 * 1024),
+      ..offline_config()
+    };
+    let signer = test_signer();
+
+    // Arquivo esparso de 64 MB; o pico de memória fica em tests/memory_peak.rs
+    let input =
+      std::env::temp_dir().join(format!("pdfsigner-oversized-{}.pdf", std::process::id()));
+    File::create(&input)
+      .unwrap()
+      .set_len(64 * 1024 * 1024)
+      .unwrap();
+    let output = input.with_extension("signed.pdf");
+    let by_path = signer.sign_pdf_with_path(&input, &config);
+    let to_file = signer.sign_pdf_file_to_file(&input, &output, &config);
+    fs::remove_file(&input).unwrap();
+
+    for result in [by_path.map(|_| ()), to_file.map(|_| ())] {
+      let err = result.unwrap_err();
+      assert!(matches!(err, PdfSignError::InvalidPdf(_)), "{}", err);
+      assert!(err.to_string().contains("max_input_bytes"));
+    }
+    assert!(!output.exists());
+
+    let mut oversized = sample_pdf();
+    oversized.resize(1024 * 1024 + 1, b' ');
+    assert!(matches!(
+      signer.sign_pdf(oversized, &config),
+      Err(PdfSignError::InvalidPdf(_))
+    ));
+    assert!(signer.sign_pdf(sample_pdf(), &config).is_ok());
+  }
+
+  #[test]
+  fn test_sign_pdf_file_to_file_streams_large_pdf() {
+    use crate::signatures::list_signatures;
+
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("pdfsigner-large-{}.pdf", std::process::id()));
+    let output = dir.join(format!("pdfsigner-large-{}-signed.pdf", std::process::id()));
+    let original = large_pdf(12 * 1024 * 1024);
+    fs::write(&input, &original).unwrap();
+
+    let signer = test_signer();
+    signer
+      .sign_pdf_file_to_file(&input, &output, &offline_config())
+      .unwrap();
+    let signed = fs::read(&output).unwrap();
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&output).unwrap();
+
+    assert!(signed.starts_with(&original[..original.len() - 1]));
+
+    let entries = list_signatures(&signed);
+    assert_eq!(entries.len(), 1);
+    let range = &entries[0].byte_range;
+    assert!(range[1] > 10_000_000);
+    assert_eq!(range[2] + range[3], signed.len());
+    let mut covered = signed[range[0]..range[0] + range[1]].to_vec();
+    covered.extend_from_slice(&signed[range[2]..range[2] + range[3]]);
+    verify_and_decode_signer_info(&extract_cms(&signed), &covered);
+  }
+
+  #[test]
+  #[cfg(feature = "mmap")]
+  fn test_mapped_signing_matches_buffered_read() {
+    use chrono::TimeZone;
+
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("pdfsigner-mapped-{}.pdf", std::process::id()));
+    let output = dir.join(format!(
+      "pdfsigner-mapped-{}-signed.pdf",
+      std::process::id()
+    ));
+    fs::write(&input, large_pdf(12 * 1024 * 1024)).unwrap();
+
+    // Instante fixo: com PKCS#1 v1.5 as duas leituras geram os mesmos bytes
+    let config = SignatureConfig {
+      signing_time: Some(Utc.with_ymd_and_hms(2024, 3, 15, 12, 34, 56).unwrap()),
+      ..offline_config()
+    };
+    let signer = test_signer();
+    let report = signer
+      .sign_pdf_mapped_to_file(&input, &output, &config)
+      .unwrap();
+    let mapped = fs::read(&output).unwrap();
+    let buffered = signer.sign_pdf_with_path(&input, &config).unwrap();
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&output).unwrap();
+
+    assert!(
+      mapped == buffered,
+      "assinatura mapeada difere da lida em buffer"
+    );
+    assert_eq!(
+      report.signature.unwrap().signing_time,
+      config.signing_time.unwrap()
+    );
+    verify_first_signature(&mapped);
+  }
+
+  #[test]
+  fn test_check_integrity_flags_appended_and_altered_bytes() {
+    use crate::signatures::check_integrity;
+
+    let signer = test_signer();
+    let once = signer.sign_pdf(sample_pdf(), &offline_config()).unwrap();
+    let signed = signer.sign_pdf(once, &offline_config()).unwrap();
+    let report = check_integrity(&signed);
+    assert_eq!(report.signatures.len(), 2);
+    assert!(report
+      .signatures
+      .iter()
+      .all(|signature| signature.unmodified && signature.error.is_none()));
+    assert_eq!(report.appended_bytes, 0);
+
+    // Bytes depois da última assinatura não mexem no que foi assinado
+    let resaved = b"\n% re-salvo\n";
+    let mut appended = signed.clone();
+    appended.extend_from_slice(resaved);
+    let report = check_integrity(&appended);
+    assert!(report
+      .signatures
+      .iter()
+      .all(|signature| signature.unmodified));
+    assert_eq!(report.appended_bytes, resaved.len());
+
+    // Um byte alterado no original invalida as duas assinaturas
+    let mut altered = signed.clone();
+    let header = altered.windows(8).position(|w| w == b"%PDF-1.4").unwrap();
+    altered[header + 7] = b'7';
+    let report = check_integrity(&altered);
+    assert!(report
+      .signatures
+      .iter()
+      .all(|signature| !signature.unmodified));
+    assert!(report.signatures[0]
+      .error
+      .as_deref()
+      .unwrap()
+      .contains("messageDigest"));
+
+    // adbe.pkcs7.sha1 encapsula o hash do ByteRange
+    let legacy = signer
+      .sign_pdf(
+        sample_pdf(),
+        &SignatureConfig {
+          sub_filter: SubFilter::Pkcs7Sha1,
+          digest_algorithm: DigestAlgorithm::Sha1,
+          ..offline_config()
+        },
+      )
+      .unwrap();
+    assert!(check_integrity(&legacy).signatures[0].unmodified);
+    assert!(check_integrity(&sample_pdf()).signatures.is_empty());
+  }
+
+  #[test]
+  fn test_extract_signer_certificate_from_signed_pdf() {
+    use crate::signatures::extract_signer_certificate;
+
+    let signer = test_signer();
+    let signed = signer.sign_pdf(sample_pdf(), &offline_config()).unwrap();
+    let extracted = extract_signer_certificate(&signed).unwrap();
+    let expected = signer.get_certificate_info();
+    assert_eq!(extracted.common_name, expected.common_name);
+    assert_eq!(extracted.serial_number, expected.serial_number);
+    assert_eq!(extracted.valid_until, expected.valid_until);
+    assert_eq!(extracted.cpf, expected.cpf);
+    assert_eq!(extracted.thumbprint_sha256, expected.thumbprint_sha256);
+
+    // Vale a última assinatura, mesmo com outro signatário antes dela
+    let (key, cert) = issue_identity("Segundo Signatario", None, false);
+    let second = PdfSigner::from_pfx_bytes(&pkcs12_bytes(&key, &cert, &[], "senha"), "senha")
+      .unwrap()
+      .sign_pdf(signed, &offline_config())
+      .unwrap();
+    assert_eq!(
+      extract_signer_certificate(&second).unwrap().common_name,
+      "Segundo Signatario"
+    );
+
+    assert!(matches!(
+      extract_signer_certificate(&sample_pdf()),
+      Err(PdfSignError::InvalidPdf(_))
+    ));
+    // Assinatura preparada, com /Contents ainda zerado
+    let prepared = signer
+      .prepare_signature(sample_pdf(), &offline_config())
+      .unwrap();
+    assert!(matches!(
+      extract_signer_certificate(&prepared),
+      Err(PdfSignError::DecodingError(_))
+    ));
+  }
+
+  #[test]
+  fn test_sign_pdf_above_seven_digit_byte_range() {
+    let original = large_pdf(11 * 1024 * 1024);
+    let signed = test_signer()
+      .sign_pdf(original.clone(), &offline_config())
+      .unwrap();
+    assert!(signed.starts_with(&original[..original.len() - 1]));
+
+    let range = &list_signatures(&signed)[0].byte_range;
+    assert!(range[2] > 10_000_000);
+    assert_eq!(range[2] + range[3], signed.len());
+    verify_first_signature(&signed);
+
+    // O maior ByteRange de 10 dígitos ainda cabe no placeholder
+    let widest = format!("[{0} {0} {0} {0}]", 9_999_999_999u64);
+    assert!(widest.len() <= BYTE_RANGE_PLACEHOLDER.len());
+  }
+
+  #[test]
+  fn test_sign_pdf_file_to_file_refuses_same_path() {
+    let path = std::env::temp_dir().join(format!("pdfsigner-same-{}.pdf", std::process::id()));
+    fs::write(&path, sample_pdf()).unwrap();
+
+    let result = test_signer().sign_pdf_file_to_file(&path, &path, &offline_config());
+    let unchanged = fs::read(&path).unwrap() == sample_pdf();
+    fs::remove_file(&path).unwrap();
+
+    assert!(matches!(result, Err(PdfSignError::InvalidPdf(_))));
+    assert!(unchanged);
+  }
+
+  #[test]
+  fn test_self_verify_accepts_signed_output() {
+    use crate::test_support::ec_identity;
+
+    let signer = test_signer();
+    for cms_backend in [CmsBackend::OpenSsl, CmsBackend::Rust] {
+      for signature_scheme in [SignatureScheme::Pkcs1v15, SignatureScheme::Pss] {
+        let config = SignatureConfig {
+          cms_backend,
+          signature_scheme,
+          self_verify: true,
+          ..offline_config()
+        };
+        signer.sign_pdf(sample_pdf(), &config).unwrap();
+      }
+    }
+
+    // ECDSA é conferido pelo OpenSSL
+    let (key, cert) = ec_identity("Signatario ECDSA");
+    let pfx = pkcs12_bytes(&key, &cert, &[], "segredo");
+    let config = SignatureConfig {
+      signature_scheme: SignatureScheme::Ecdsa,
+      cms_backend: CmsBackend::OpenSsl,
+      self_verify: true,
+      ..offline_config()
+    };
+    PdfSigner::from_pfx_bytes(&pfx, "segredo")
+      .unwrap()
+      .sign_pdf(sample_pdf(), &config)
+      .unwrap();
+
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("pdfsigner-self-verify-{}.pdf", std::process::id()));
+    let output = dir.join(format!(
+      "pdfsigner-self-verify-{}-signed.pdf",
+      std::process::id()
+    ));
+    fs::write(&input, sample_pdf()).unwrap();
+    let config = SignatureConfig {
+      self_verify: true,
+      ..offline_config()
+    };
+    let result = signer.sign_pdf_file_to_file(&input, &output, &config);
+    fs::remove_file(&input).unwrap();
+    result.unwrap();
+    fs::remove_file(&output).unwrap();
+  }
+
+  #[test]
+  fn test_self_verify_detects_corrupted_output() {
+    let digest = DigestAlgorithm::Sha256;
+    let signed = test_signer()
+      .sign_pdf(sample_pdf(), &offline_config())
+      .unwrap();
+    verify_last_signature(&signed, digest).unwrap();
+
+    // Byte coberto pelo ByteRange alterado depois da assinatura
+    let mut covered = signed.clone();
+    covered[20] ^= 0x01;
+    let err = verify_last_signature(&covered, digest).unwrap_err();
+    assert!(err.to_string().contains("messageDigest"), "{}", err);
+
+    // Valor da assinatura adulterado: o messageDigest confere, a assinatura não
+    let range = list_signatures(&signed)[0].byte_range.clone();
+    let cms_hex_len = extract_cms(&signed).len() * 2;
+    let mut forged = signed.clone();
+    let pos = range[1] + cms_hex_len - 4;
+    forged[pos] = if forged[pos] == b'0' { b'1' } else { b'0' };
+    let err = verify_last_signature(&forged, digest).unwrap_err();
+    assert!(err.to_string().contains("não confere"), "{}", err);
+
+    // ByteRange além do fim do arquivo (saída truncada)
+    let err = verify_last_signature(&signed[..signed.len() - 10], digest).unwrap_err();
+    assert!(err.to_string().contains("/ByteRange"), "{}", err);
+
+    // A mesma conferência no arquivo gravado
+    let path = std::env::temp_dir().join(format!("pdfsigner-corrupted-{}.pdf", std::process::id()));
+    fs::write(&path, &covered).unwrap();
+    // A atualização original (sem a corrupção) informa o ByteRange
+    let result = verify_signed_file(&path, &signed, digest);
+    fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+
+    // E o erro de auto-verificação é um SigningError
+    assert!(matches!(
+      self_verify_failed(err),
+      PdfSignError::SigningError(message) if message.contains("auto-verificação")
+    ));
+  }
+
+  #[test]
+  fn test_sign_pdf_fills_existing_empty_field() {
+    use crate::signatures::list_signatures;
+
+    let signer = test_signer();
+    let config = SignatureConfig {
+      fill_existing_field: Some("Assinatura Cliente".to_string()),
+      ..offline_config()
+    };
+    let template = template_with_empty_field();
+    let signed = signer.sign_pdf(template.clone(), &config).unwrap();
+    let update = String::from_utf8_lossy(&signed[template.len()..]);
+
+    // Nenhum campo novo: o widget do template recebe /V e o AcroForm ganha /SigFlags 3
+    assert!(!update.contains("/T (Signature1)"));
+    assert!(update.contains("/T (Assinatura Cliente)\n/Rect [50 50 250 100]"));
+    assert!(update.contains("/SigFlags 3"));
+    assert!(!update.contains("/SigFlags 1"));
+
+    let entries = list_signatures(&signed);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].field_name.as_deref(), Some("Assinatura Cliente"));
+
+    // O mesmo campo não pode ser assinado duas vezes
+    let err = signer.sign_pdf(signed, &config).unwrap_err();
+    assert!(matches!(err, PdfSignError::InvalidPdf(_)));
+    assert!(err.to_string().contains("já está assinado"));
+  }
+
+  #[test]
+  fn test_rect_from_top_left_flips_to_media_box_origin() {
+    // Carta (612 x 792): 50 pt da esquerda e do topo, 200 x 50 pt
+    let media_box = PdfSigner::page_media_box(&sample_pdf(), 0).unwrap();
+    assert_eq!(media_box, [0.0, 0.0, 612.0, 792.0]);
+    let rect = PdfSigner::rect_from_top_left([50.0, 50.0, 250.0, 100.0], media_box);
+    assert_eq!(rect, [50.0, 692.0, 250.0, 742.0]);
+
+    // Cantos em qualquer ordem e /MediaBox que não começa em (0, 0)
+    assert_eq!(
+      PdfSigner::rect_from_top_left([250.0, 100.0, 50.0, 50.0], [10.0, 20.0, 622.0, 812.0]),
+      [60.0, 712.0, 260.0, 762.0]
+    );
+
+    let prepared = PdfSigner::add_empty_signature_field(sample_pdf(), "Cliente", rect, 0).unwrap();
+    let field_obj = find_signature_field(&prepared, "Cliente").unwrap();
+    let field = find_object_section(&prepared, field_obj).unwrap();
+    assert!(String::from_utf8_lossy(field).contains("/Rect [50 692 250 742]"));
+
+    assert!(matches!(
+      PdfSigner::page_media_box(&sample_pdf(), 5),
+      Err(PdfSignError::InvalidPdf(_))
+    ));
+  }
+
+  #[test]
+  fn test_page_media_box_inherited_from_pages_node() {
+    // A4 declarada só no nó /Pages, num array indireto; a página 1 traz a própria
+    let pdf = b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n2 0 obj\n<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 /MediaBox 5 0 R >>\nendobj\n3 0 obj\n<< /Type /Page /Parent 2 0 R >>\nendobj\n4 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>\nendobj\n5 0 obj\n[0 0 595 842]\nendobj\ntrailer\n<< /Size 6 /Root 1 0 R >>\n%%EOF\n";
+
+    let media_box = PdfSigner::page_media_box(pdf, 0).unwrap();
+    assert_eq!(media_box, [0.0, 0.0, 595.0, 842.0]);
+    assert_eq!(
+      PdfSigner::rect_from_top_left([50.0, 50.0, 250.0, 100.0], media_box),
+      [50.0, 742.0, 250.0, 792.0]
+    );
+    assert_eq!(
+      PdfSigner::page_media_box(pdf, 1).unwrap(),
+      [0.0, 0.0, 612.0, 792.0]
+    );
+  }
+
+  #[test]
+  fn test_is_signed_distinguishes_signed_prepared_and_plain_pdfs() {
+    use crate::signatures::is_signed;
+
+    let plain = sample_pdf();
+    assert!(!is_signed(&plain));
+
+    let prepared =
+      PdfSigner::add_empty_signature_field(plain.clone(), "Cliente", [50.0, 50.0, 250.0, 100.0], 0)
+        .unwrap();
+    assert!(!is_signed(&prepared));
+
+    let config = SignatureConfig {
+      fill_existing_field: Some("Cliente".to_string()),
+      ..offline_config()
+    };
+    assert!(is_signed(
+      &test_signer().sign_pdf(prepared, &config).unwrap()
+    ));
+    assert!(is_signed(
+      &test_signer().sign_pdf(plain, &offline_config()).unwrap()
+    ));
+  }
+
+  #[test]
+  fn test_visible_appearance_composes_image_and_text() {
+    use crate::signature_config::{AppearanceElement, SignatureAppearance};
+
+    // JPEG mínimo: SOI, SOF0 de 40x20 em RGB e EOI
+    let mut jpeg = vec![
+      0xFF, 0xD8, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x14, 0x00, 0x28, 0x03,
+    ];
+    jpeg.extend_from_slice(&[
+      0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01, 0xFF, 0xD9,
+    ]);
+    let appearance = SignatureAppearance {
+      elements: vec![
+        AppearanceElement {
+          content: AppearanceContent::Image(jpeg),
+          rect: [0.0, 0.0, 50.0, 50.0],
+        },
+        AppearanceElement {
+          content: AppearanceContent::Text {
+            text: "Assinado digitalmente por\nJoão da Silva".to_string(),
+            font_size: 9.0,
+          },
+          rect: [55.0, 0.0, 145.0, 50.0],
+        },
+      ],
+      ..Default::default()
+    };
+    let prepared =
+      PdfSigner::add_empty_signature_field(sample_pdf(), "Cliente", [50.0, 50.0, 250.0, 100.0], 0)
+        .unwrap();
+    let config = SignatureConfig {
+      fill_existing_field: Some("Cliente".to_string()),
+      appearance: Some(appearance.clone()),
+      ..offline_config()
+    };
+    let signed = test_signer().sign_pdf(prepared.clone(), &config).unwrap();
+    walk_xref_chain(&signed);
+    verify_first_signature(&signed);
+
+    // Um único form XObject em /AP /N, com a fonte e a imagem nos recursos
+    let update = &signed[prepared.len()..];
+    assert_eq!(
+      update
+        .windows(b"/Subtype /Form".len())
+        .filter(|w| *w == b"/Subtype /Form")
+        .count(),
+      1
+    );
+    let field_obj = find_signature_field(&signed, "Cliente").unwrap();
+    let field = find_object_section(&signed, field_obj).unwrap();
+    let form_obj = read_number_after(field, b"/N").unwrap();
+    let form = String::from_utf8_lossy(find_object_section(&signed, form_obj).unwrap()).to_string();
+    assert!(form.contains("/BBox [0 0 200 50]"));
+    let font_obj = read_number_after(form.as_bytes(), b"/F1").unwrap();
+    let image_obj = read_number_after(form.as_bytes(), b"/Im1").unwrap();
+    assert!(
+      String::from_utf8_lossy(find_object_section(&signed, font_obj).unwrap())
+        .contains("/BaseFont /Helvetica")
+    );
+    assert!(
+      String::from_utf8_lossy(find_object_section(&signed, image_obj).unwrap())
+        .contains("/Filter /DCTDecode")
+    );
+    // Acentos em WinAnsi; a imagem é desenhada antes, por baixo do texto
+    let form = find_object_section(&signed, form_obj).unwrap();
+    let drawn_image = form.windows(7).position(|w| w == b"/Im1 Do").unwrap();
+    let drawn_text = form.windows(5).position(|w| w == b"(Jo\xe3o").unwrap();
+    assert!(drawn_image < drawn_text);
+
+    // O widget novo é invisível: sem fill_existing_field a aparência é recusada
+    let config = SignatureConfig {
+      appearance: Some(appearance),
+      ..offline_config()
+    };
+    assert!(matches!(
+      test_signer().sign_pdf(sample_pdf(), &config),
+      Err(PdfSignError::SigningError(_))
+    ));
+  }
+
+  #[test]
+  fn test_appearance_date_is_drawn_in_brasilia_time() {
+    use crate::signature_config::{AppearanceElement, SignatureAppearance};
+    use chrono::TimeZone;
+
+    let text = |format: &str| SignatureAppearance {
+      elements: vec![AppearanceElement {
+        content: AppearanceContent::Text {
+          text: "Assinado digitalmente em {date}".to_string(),
+          font_size: 9.0,
+        },
+        rect: [0.0, 0.0, 200.0, 50.0],
+      }],
+      date_format: format.to_string(),
+      ..Default::default()
+    };
+    let prepared =
+      PdfSigner::add_empty_signature_field(sample_pdf(), "Cliente", [50.0, 50.0, 250.0, 100.0], 0)
+        .unwrap();
+    let config = SignatureConfig {
+      fill_existing_field: Some("Cliente".to_string()),
+      signing_time: Some(Utc.with_ymd_and_hms(2024, 3, 15, 12, 34, 56).unwrap()),
+      ..offline_config()
+    };
+    let sign = |format: &str| {
+      test_signer().sign_pdf(
+        prepared.clone(),
+        &SignatureConfig {
+          appearance: Some(text(format)),
+          ..config.clone()
+        },
+      )
+    };
+
+    // Padrão brasileiro no horário de Brasília; /M continua em UTC
+    let signed = sign("%d/%m/%Y %H:%M:%S").unwrap();
+    verify_first_signature(&signed);
+    let update = String::from_utf8_lossy(&signed[prepared.len()..]).to_string();
+    assert!(update.contains("(Assinado digitalmente em 15/03/2024 09:34:56) Tj"));
+    assert!(update.contains("/M (D:20240315123456Z)"));
+
+    let signed = sign("%Y-%m-%d %H:%M").unwrap();
+    assert!(String::from_utf8_lossy(&signed[prepared.len()..])
+      .contains("(Assinado digitalmente em 2024-03-15 09:34) Tj"));
+
+    assert!(matches!(
+      sign("%d/%m/%Q"),
+      Err(PdfSignError::SigningError(message)) if message.contains("%Q")
+    ));
+  }
+
+  #[test]
+  fn test_complete_signature_for_fields_in_sequence() {
+    let mut pdf = sample_pdf();
+    for (name, rect) in [
+      ("Aprovador", [50.0, 50.0, 250.0, 100.0]),
+      ("Testemunha", [300.0, 50.0, 500.0, 100.0]),
+    ] {
+      pdf = PdfSigner::add_empty_signature_field(pdf, name, rect, 0).unwrap();
+    }
+    let signer = test_signer();
+    // Cada parte assina fora o trecho coberto pelo ByteRange do próprio campo
+    let external_cms = |prepared: &[u8]| {
+      let range = checked_byte_range(list_signatures(prepared).last(), prepared.len()).unwrap();
+      let content = [
+        &prepared[..range[1]],
+        &prepared[range[2]..range[2] + range[3]],
+      ]
+      .concat();
+      signer
+        .sign_data_cms(&content, &offline_config(), true)
+        .unwrap()
+    };
+
+    for field in ["Aprovador", "Testemunha"] {
+      let config = SignatureConfig {
+        fill_existing_field: Some(field.to_string()),
+        ..offline_config()
+      };
+      let prepared = signer.prepare_signature(pdf, &config).unwrap();
+      let cms = external_cms(&prepared);
+      pdf = PdfSigner::complete_signature_for_field(prepared.clone(), field, &cms).unwrap();
+
+      // Só o placeholder mudou
+      assert_eq!(pdf.len(), prepared.len());
+      verify_last_signature(&pdf, DigestAlgorithm::Sha256).unwrap();
+      assert!(matches!(
+        PdfSigner::complete_signature_for_field(pdf.clone(), field, &cms),
+        Err(PdfSignError::InvalidPdf(message)) if message.contains("já está assinado")
+      ));
+    }
+
+    // A primeira assinatura continua válida depois da segunda
+    verify_first_signature(&pdf);
+    walk_xref_chain(&pdf);
+    let fields: Vec<_> = list_signatures(&pdf)
+      .into_iter()
+      .map(|entry| entry.field_name.unwrap())
+      .collect();
+    assert_eq!(fields, ["Aprovador", "Testemunha"]);
+
+    // CMS maior que o placeholder e campo sem assinatura preparada
+    let prepared = signer
+      .prepare_signature(sample_pdf(), &offline_config())
+      .unwrap();
+    let field = list_signatures(&prepared)[0].field_name.clone().unwrap();
+    assert!(matches!(
+      PdfSigner::complete_signature_for_field(prepared.clone(), &field, &vec![0x30; 64 * 1024]),
+      Err(PdfSignError::InvalidPdf(message)) if message.contains("muito grande")
+    ));
+    assert!(PdfSigner::complete_signature_for_field(prepared, "Outro", &[0x30]).is_err());
+  }
+
+  #[test]
+  fn test_resigning_prepared_field_only_rewrites_the_field() {
+    use crate::signatures::list_signatures;
+
+    let mut prepared = sample_pdf();
+    for (name, rect) in [
+      ("Aprovador", [50.0, 50.0, 250.0, 100.0]),
+      ("Testemunha", [300.0, 50.0, 500.0, 100.0]),
+    ] {
+      prepared = PdfSigner::add_empty_signature_field(prepared, name, rect, 0).unwrap();
+    }
+    let fill = |field: &str| SignatureConfig {
+      fill_existing_field: Some(field.to_string()),
+      ..offline_config()
+    };
+    let signer = test_signer();
+    let catalog_obj = extract_catalog_info(&prepared).unwrap().catalog_obj;
+
+    // Primeira assinatura: o AcroForm ainda não tem /SigFlags 3, então ele e o Catalog são regravados
+    let first = signer
+      .sign_pdf(prepared.clone(), &fill("Aprovador"))
+      .unwrap();
+    let update = parse_update_xref(&first[prepared.len()..], prepared.len()).unwrap();
+    assert_eq!(update.len(), 4);
+    assert!(update.iter().any(|(obj, _)| *obj == catalog_obj));
+
+    // Segunda: só o dicionário de assinatura e o campo entram na atualização
+    let second = signer.sign_pdf(first.clone(), &fill("Testemunha")).unwrap();
+    walk_xref_chain(&second);
+    let update = parse_update_xref(&second[first.len()..], first.len()).unwrap();
+    assert_eq!(update.len(), 2, "{:?}", update);
+    assert!(!String::from_utf8_lossy(&second[first.len()..]).contains("/Type /Catalog"));
+    let entries = list_signatures(&second);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[1].field_name.as_deref(), Some("Testemunha"));
+    assert_eq!(
+      entries[1].byte_range[2] + entries[1].byte_range[3],
+      second.len()
+    );
+
+    // O /DSS parte do Catalog vigente, que a atualização assinada não trouxe
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| MockResponse::ok(tsa.respond(&request.body)));
+    let config = SignatureConfig {
+      pades_level: PadesLevel::BLT,
+      tsa_url: Some(server.url()),
+      ..fill("Testemunha")
+    };
+    let with_dss = signer.sign_pdf(first.clone(), &config).unwrap();
+    walk_xref_chain(&with_dss);
+    let catalog =
+      String::from_utf8_lossy(find_object_section(&with_dss, catalog_obj).unwrap()).to_string();
+    assert!(
+      catalog.contains("/DSS ") && catalog.contains("/AcroForm "),
+      "{}",
+      catalog
+    );
+    assert_eq!(list_signatures(&with_dss).len(), 2);
+  }
+
+  #[test]
+  fn test_add_empty_signature_field_then_sign_it() {
+    use crate::signatures::list_signatures;
+
+    let original = sample_pdf();
+    let prepared = PdfSigner::add_empty_signature_field(
+      original.clone(),
+      "Assinatura (Cliente)",
+      [50.0, 50.0, 250.5, 100.0],
+      0,
+    )
+    .unwrap();
+
+    // Campo /FT /Sig sem /V, nas /Annots da primeira página e no AcroForm
+    let field_obj = find_signature_field(&prepared, "Assinatura (Cliente)").unwrap();
+    let field = find_object_section(&prepared, field_obj).unwrap();
+    assert!(!dict_has_key(dict_body(field).unwrap(), b"/V"));
+    assert!(String::from_utf8_lossy(field).contains("/Rect [50 50 250.5 100]"));
+    let page_obj = find_page_object(&prepared, 0).unwrap();
+    let page = String::from_utf8_lossy(find_object_section(&prepared, page_obj).unwrap());
+    assert!(page.contains(&format!("{} 0 R]", field_obj)), "{}", page);
+    let update = String::from_utf8_lossy(&prepared[original.len()..]);
+    assert!(update.contains(&format!("/Fields [{} 0 R]", field_obj)));
+    assert!(!update.contains("/SigFlags"));
+    assert!(list_signatures(&prepared).is_empty());
+    assert_eq!(walk_xref_chain(&prepared), walk_xref_chain(&original));
+
+    // Outra parte assina o campo preparado
+    let config = SignatureConfig {
+      fill_existing_field: Some("Assinatura (Cliente)".to_string()),
+      ..offline_config()
+    };
+    let signed = test_signer().sign_pdf(prepared, &config).unwrap();
+    verify_first_signature(&signed);
+    let entries = list_signatures(&signed);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+      entries[0].field_name.as_deref(),
+      Some("Assinatura (Cliente)")
+    );
+  }
+
+  #[test]
+  fn test_add_empty_signature_field_errors() {
+    let prepared =
+      PdfSigner::add_empty_signature_field(sample_pdf(), "Testemunha", [0.0; 4], 0).unwrap();
+
+    let err =
+      PdfSigner::add_empty_signature_field(prepared, "Testemunha", [0.0; 4], 0).unwrap_err();
+    assert!(err.to_string().contains("já existe"));
+    let err =
+      PdfSigner::add_empty_signature_field(sample_pdf(), "Testemunha", [0.0; 4], 99).unwrap_err();
+    assert!(err.to_string().contains("página 99"));
+    let err =
+      PdfSigner::add_empty_signature_field(b"PK\x03\x04".to_vec(), "X", [0.0; 4], 0).unwrap_err();
+    assert!(matches!(err, PdfSignError::InvalidPdf(_)));
+  }
+
+  #[test]
+  fn test_sign_pdf_reason_vocabulary() {
+    let reasons = vec!["Aprovo".to_string(), "Ciente (sem aprovação)".to_string()];
+    let prepared = PdfSigner::add_empty_signature_field_with_reasons(
+      sample_pdf(),
+      "Aprovador",
+      [0.0; 4],
+      0,
+      &reasons,
+    )
+    .unwrap();
+    let field_obj = find_signature_field(&prepared, "Aprovador").unwrap();
+    let field = String::from_utf8_lossy(find_object_section(&prepared, field_obj).unwrap());
+    assert!(
+      field.contains("/SV << /Type /SV /Reasons [(Aprovo) (Ciente \\(sem aprovação\\))] /Ff 8 >>"),
+      "{}",
+      field
+    );
+
+    let config = SignatureConfig {
+      reason: Some("Revisei".to_string()),
+      allowed_reasons: Some(reasons.clone()),
+      fill_existing_field: Some("Aprovador".to_string()),
+      ..offline_config()
+    };
+    let err = test_signer()
+      .sign_pdf(prepared.clone(), &config)
+      .unwrap_err();
+    assert!(matches!(err, PdfSignError::SigningError(_)));
+    assert!(err
+      .to_string()
+      .contains("\"Revisei\" não está entre os permitidos"));
+
+    // Sem vocabulário qualquer motivo vale; com ele, só os da lista
+    let signer = test_signer();
+    signer
+      .sign_pdf(
+        prepared.clone(),
+        &SignatureConfig {
+          allowed_reasons: None,
+          ..config.clone()
+        },
+      )
+      .unwrap();
+    let signed = signer
+      .sign_pdf(
+        prepared,
+        &SignatureConfig {
+          reason: Some("Aprovo".to_string()),
+          ..config
+        },
+      )
+      .unwrap();
+    verify_first_signature(&signed);
+  }
+
+  #[test]
+  fn test_chain_info_follows_issuer_order() {
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let (intermediate_key, intermediate) =
+      issue_identity("AC Intermediaria", Some((&root_key, &root)), true);
+    let (leaf_key, leaf) = issue_identity(
+      "Signatario",
+      Some((&intermediate_key, &intermediate)),
+      false,
+    );
+    // A cadeia vai no PFX fora de ordem: a raiz antes da intermediária
+    let pfx = pkcs12_bytes(&leaf_key, &leaf, &[root, intermediate], "senha");
+    let signer = PdfSigner::from_pfx_bytes(&pfx, "senha").unwrap();
+
+    let chain = signer.chain_info();
+
+    let names: Vec<&str> = chain.iter().map(|info| info.common_name.as_str()).collect();
+    assert_eq!(
+      names,
+      ["Signatario", "AC Intermediaria", "AC Raiz de Teste"]
+    );
+    assert_eq!(chain[0].issuer_dn, chain[1].subject_dn);
+    assert_eq!(chain[1].issuer_dn, chain[2].subject_dn);
+    assert!(chain.iter().all(|info| !info.expired));
+    assert_eq!(
+      chain[0].serial_number,
+      signer.get_certificate_info().serial_number
+    );
+  }
+
+  #[test]
+  fn test_from_pfx_without_cert_bag_key_id_finds_signer() {
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let (leaf_key, leaf) = issue_identity("Signatario", Some((&root_key, &root)), false);
+    // Só chave e "cadeia": o certificado do signatário vai sem localKeyId, depois da raiz
+    crate::test_support::keep_default_provider();
+    let mut ca = openssl::stack::Stack::new().unwrap();
+    ca.push(root).unwrap();
+    ca.push(leaf).unwrap();
+    let pfx = openssl::pkcs12::Pkcs12::builder()
+      .pkey(&leaf_key)
+      .ca(ca)
+      .build2("senha")
+      .unwrap()
+      .to_der()
+      .unwrap();
+
+    let signer = PdfSigner::from_pfx_bytes(&pfx, "senha").unwrap();
+    assert_eq!(signer.get_certificate_info().common_name, "Signatario");
+    let chain: Vec<String> = signer
+      .chain_info()
+      .into_iter()
+      .map(|info| info.common_name)
+      .collect();
+    assert_eq!(chain, ["Signatario", "AC Raiz de Teste"]);
+
+    let signed = signer.sign_pdf(sample_pdf(), &offline_config()).unwrap();
+    verify_first_signature(&signed);
+  }
+
+  #[test]
+  fn test_from_pfx_with_signer_selects_identity() {
+    use crate::test_support::multi_identity_pkcs12;
+
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let (first_key, first) = issue_identity("Primeira Identidade", Some((&root_key, &root)), false);
+    let (second_key, second) =
+      issue_identity("Segunda Identidade", Some((&root_key, &root)), false);
+    let pfx = multi_identity_pkcs12(&[(&first_key, &first), (&second_key, &second)], "senha");
+
+    // Sem seletor vale a primeira chave do arquivo
+    let default = PdfSigner::from_pfx_bytes(&pfx, "senha").unwrap();
+    assert_eq!(
+      default.get_certificate_info().common_name,
+      "Primeira Identidade"
+    );
+
+    let serial = hex::encode(second.serial_number().to_bn().unwrap().to_vec());
+    let by_serial = SignerSelector::SerialNumber(serial.to_uppercase());
+    let signer = PdfSigner::from_pfx_bytes_with_signer(&pfx, "senha", &by_serial).unwrap();
+    assert_eq!(
+      signer.get_certificate_info().common_name,
+      "Segunda Identidade"
+    );
+    let chain: Vec<String> = signer
+      .chain_info()
+      .into_iter()
+      .map(|info| info.common_name)
+      .collect();
+    assert_eq!(chain, ["Segunda Identidade", "Primeira Identidade"]);
+
+    // A chave escolhida é a do certificado: a assinatura confere com ele
+    let signed = signer.sign_pdf(sample_pdf(), &offline_config()).unwrap();
+    verify_first_signature(&signed);
+    let signed_data = decode_signed_data(&extract_cms(&signed));
+    let cms::signed_data::SignerIdentifier::IssuerAndSerialNumber(sid) =
+      &signed_data.signer_infos.0.get(0).unwrap().sid
+    else {
+      panic!("sid inesperado");
+    };
+    assert_eq!(
+      sid.serial_number.as_bytes(),
+      second.serial_number().to_bn().unwrap().to_vec()
+    );
+
+    let by_cn = SignerSelector::SubjectCn("segunda identidade".to_string());
+    let signer = PdfSigner::from_pfx_bytes_with_signer(&pfx, "senha", &by_cn).unwrap();
+    assert_eq!(
+      signer.get_certificate_info().common_name,
+      "Segunda Identidade"
+    );
+
+    let missing = SignerSelector::SubjectCn("Terceira Identidade".to_string());
+    let err = PdfSigner::from_pfx_bytes_with_signer(&pfx, "senha", &missing)
+      .err()
+      .unwrap();
+    assert!(err.to_string().contains("Terceira Identidade"));
+  }
+
+  #[test]
+  fn test_include_chain_in_cms() {
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let (intermediate_key, intermediate) =
+      issue_identity("AC Intermediaria", Some((&root_key, &root)), true);
+    let (leaf_key, leaf) = issue_identity(
+      "Signatario",
+      Some((&intermediate_key, &intermediate)),
+      false,
+    );
+    let pfx = pkcs12_bytes(&leaf_key, &leaf, &[intermediate, root], "senha");
+    let signer = PdfSigner::from_pfx_bytes(&pfx, "senha").unwrap();
+
+    for cms_backend in [CmsBackend::OpenSsl, CmsBackend::Rust] {
+      let certificate_count = |include_chain_in_cms: bool| {
+        let config = SignatureConfig {
+          cms_backend,
+          include_chain_in_cms,
+          ..offline_config()
+        };
+        let signed = signer.sign_pdf(sample_pdf(), &config).unwrap();
+        verify_first_signature(&signed);
+        decode_signed_data(&extract_cms(&signed))
+          .certificates
+          .map(|certificates| certificates.0.len())
+          .unwrap_or(0)
+      };
+
+      assert_eq!(certificate_count(true), 3, "{:?}", cms_backend);
+      assert_eq!(certificate_count(false), 1, "{:?}", cms_backend);
+    }
+  }
+
+  #[test]
+  fn test_signer_name_fallbacks() {
+    use crate::test_support::identity_with_subject;
+
+    let (ca_key, ca_cert) = issue_identity("AC Teste", None, true);
+    let issued = |subject: &[(&str, &str)]| {
+      let (key, cert) = identity_with_subject(subject, Some((&ca_key, &ca_cert)));
+      PdfSigner::from_pfx_bytes(&pkcs12_bytes(&key, &cert, &[], "segredo"), "segredo").unwrap()
+    };
+    let signed_name = |signer: &PdfSigner, config: &SignatureConfig| {
+      let signed = signer.sign_pdf(sample_pdf(), config).unwrap();
+      list_signatures(&signed)[0].signer_name.clone().unwrap()
+    };
+
+    // Nome explícito vence o CN, e é escapado como string literal
+    let with_cn = issued(&[("C", "BR"), ("O", "Empresa"), ("CN", "Maria")]);
+    let config = SignatureConfig {
+      signer_name: Some("Maria (Diretora)".to_string()),
+      ..offline_config()
+    };
+    assert_eq!(signed_name(&with_cn, &config), "Maria (Diretora)");
+    assert_eq!(signed_name(&with_cn, &offline_config()), "Maria");
+
+    // Sem CN: organização, depois CN do emissor
+    let without_cn = issued(&[("C", "BR"), ("O", "Empresa Ltda")]);
+    assert_eq!(signed_name(&without_cn, &offline_config()), "Empresa Ltda");
+    let only_country = issued(&[("C", "BR")]);
+    assert_eq!(signed_name(&only_country, &offline_config()), "AC Teste");
+
+    // Autoassinado sem CN nem O: não há o que exibir
+    let (key, cert) = identity_with_subject(&[("C", "BR")], None);
+    let anonymous =
+      PdfSigner::from_pfx_bytes(&pkcs12_bytes(&key, &cert, &[], "segredo"), "segredo").unwrap();
+    assert_eq!(signed_name(&anonymous, &offline_config()), "Unknown");
+  }
+
+  #[test]
+  fn test_sign_pdf_missing_existing_field() {
+    let signer = test_signer();
+    let config = SignatureConfig {
+      fill_existing_field: Some("Inexistente".to_string()),
+      ..offline_config()
+    };
+    let err = signer
+      .sign_pdf(template_with_empty_field(), &config)
+      .unwrap_err();
+    assert!(err.to_string().contains("não encontrado"));
+  }
+
+  #[test]
+  fn test_check_byte_range_coverage() {
+    assert!(check_byte_range_coverage(&[0, 100, 200, 50], 250).is_ok());
+
+    // Fim do arquivo fora do intervalo, intervalo além do arquivo e início deslocado
+    for (range, len) in [
+      ([0, 100, 200, 49], 250),
+      ([0, 100, 200, 51], 250),
+      ([1, 99, 200, 50], 250),
+      ([0, 100, usize::MAX, 1], 250),
+    ] {
+      assert!(matches!(
+        check_byte_range_coverage(&range, len),
+        Err(PdfSignError::SigningError(_))
+      ));
+    }
+  }
+
+  #[test]
+  fn test_check_references_detects_dangling_objects() {
+    let signer = test_signer();
+    let pdf = remove_trailing_newline(sample_pdf());
+    let config = offline_config();
+    let update = signer
+      .build_incremental_update(&pdf, pdf.len(), &config)
+      .unwrap();
+    let sig_obj = get_next_object_number(&pdf).unwrap() as usize;
+    assert!(update.check_references(&pdf, sig_obj, true).is_ok());
+
+    let corrupted = |from: &str, to: &str| {
+      let text = String::from_utf8_lossy(&update.tail).replacen(from, to, 1);
+      IncrementalUpdate {
+        base: update.base,
+        tail: text.into_bytes(),
+        byte_range_pos: update.byte_range_pos,
+        byte_range_len: update.byte_range_len,
+        placeholder_pos: update.placeholder_pos,
+        placeholder_len: update.placeholder_len,
+        date_pos: update.date_pos,
+        sig_size: update.sig_size,
+        signing_time: update.signing_time,
+        sig_obj: update.sig_obj,
+        signer_name: update.signer_name.clone(),
+        signed_field: update.signed_field.clone(),
+        dss_target: DssTarget {
+          unchanged_catalog: update.dss_target.unchanged_catalog.clone(),
+          previous_dss: update.dss_target.previous_dss.clone(),
+          ..update.dss_target
+        },
+      }
+    };
+    let catalog_info = extract_catalog_info(&pdf).unwrap();
+
+    // /Root apontando para um objeto inexistente
+    let root = format!("/Root {} 0 R", catalog_info.catalog_obj);
+    let err = corrupted(&root, "/Root 9999 0 R")
+      .check_references(&pdf, sig_obj, true)
+      .unwrap_err();
+    assert!(err.to_string().contains("/Root aponta para o objeto 9999"));
+
+    // /Pages do Catalog novo pendente
+    let pages = format!("/Pages {} 0 R", catalog_info.pages_ref);
+    let err = corrupted(&pages, "/Pages 9999 0 R")
+      .check_references(&pdf, sig_obj, true)
+      .unwrap_err();
+    assert!(err.to_string().contains("/Pages aponta para o objeto 9999"));
+
+    // Catalog deslocado em relação ao offset da xref
+    let catalog_header = format!("\n{} 0 obj\n<<\n/Type /Catalog", catalog_info.catalog_obj);
+    let err = corrupted(&catalog_header, &format!("\n{}", catalog_header))
+      .check_references(&pdf, sig_obj, true)
+      .unwrap_err();
+    assert!(matches!(err, PdfSignError::InvalidPdf(_)));
+    assert!(err.to_string().contains("declarado na xref"));
+
+    // Widget que não referencia a assinatura (mesmo tamanho: os offsets continuam valendo)
+    let value = format!("/V {} 0 R", sig_obj);
+    let err = corrupted(&value, &format!("/X {} 0 R", sig_obj))
+      .check_references(&pdf, sig_obj, true)
+      .unwrap_err();
+    assert!(err.to_string().contains("nenhum campo"));
+  }
+
+  /// Percorre a cadeia de xref a partir do último startxref, como um leitor estrito:
+  /// cada seção precisa começar em "xref", ter entradas de 20 bytes e cada objeto
+  /// em uso precisa apontar para "N G obj". Retorna quantas seções definem o objeto 0
+  fn walk_xref_chain(pdf: &[u8]) -> usize {
+    let mut offset = find_startxref(pdf).expect("startxref ausente");
+    let mut visited = Vec::new();
+    let mut object_zero_definitions = 0;
+
+    loop {
+      assert!(!visited.contains(&offset), "ciclo na cadeia de xref");
+      visited.push(offset);
+      assert!(
+        pdf[offset..].starts_with(b"xref"),
+        "xref não começa no offset {}",
+        offset
+      );
+
+      let mut pos = offset + 4;
+      loop {
+        while pdf[pos].is_ascii_whitespace() {
+          pos += 1;
+        }
+        if pdf[pos..].starts_with(b"trailer") {
+          break;
+        }
+        let line_end = pos
+          + pdf[pos..]
+            .iter()
+            .position(|c| *c == b'\r' || *c == b'\n')
+            .unwrap();
+        let header = String::from_utf8_lossy(&pdf[pos..line_end]).to_string();
+        let mut numbers = header
+          .split_whitespace()
+          .map(|n| n.parse::<usize>().unwrap());
+        let (first, count) = (numbers.next().unwrap(), numbers.next().unwrap());
+        pos = line_end;
+        while pdf[pos] == b'\r' || pdf[pos] == b'\n' {
+          pos += 1;
+        }
+
+        for obj in first..first + count {
+          let entry = &pdf[pos..pos + 20];
+          assert!(
+            matches!(&entry[18..], b" \n" | b" \r" | b"\r\n"),
+            "entrada de xref fora do tamanho fixo: {:?}",
+            String::from_utf8_lossy(entry)
+          );
+          if obj == 0 {
+            object_zero_definitions += 1;
+            assert_eq!(&entry[..18], b"0000000000 65535 f");
+          } else if entry[17] == b'n' {
+            let target: usize = String::from_utf8_lossy(&entry[..10]).parse().unwrap();
+            let generation: u16 = String::from_utf8_lossy(&entry[11..16]).parse().unwrap();
+            assert!(
+              pdf[target..].starts_with(format!("{} {} obj", obj, generation).as_bytes()),
+              "objeto {} não está no offset {}",
+              obj,
+              target
+            );
+          }
+          pos += 20;
+        }
+      }
+
+      let trailer_end = pdf[pos..]
+        .windows(b"startxref".len())
+        .position(|w| w == b"startxref")
+        .map(|p| pos + p)
+        .unwrap_or(pdf.len());
+      match read_number_after(&pdf[pos..trailer_end], b"/Prev") {
+        Some(prev) => offset = prev,
+        None => break,
+      }
+    }
+
+    object_zero_definitions
+  }
+
+  #[test]
+  fn test_sign_twice_keeps_strict_xref_chain() {
+    let signer = test_signer();
+    let config = offline_config();
+    let original = sample_pdf();
+    let original_zero_definitions = walk_xref_chain(&original);
+
+    let once = signer.sign_pdf(original, &config).unwrap();
+    let twice = signer.sign_pdf(once.clone(), &config).unwrap();
+
+    // As atualizações incrementais não redefinem o objeto 0
+    assert_eq!(walk_xref_chain(&once), original_zero_definitions);
+    assert_eq!(walk_xref_chain(&twice), original_zero_definitions);
+    assert!(!String::from_utf8_lossy(&twice[once.len()..]).contains("65535 f"));
+  }
+
+  /// Amostra com `count` atualizações incrementais, cada uma regravando o Catalog
+  fn with_incremental_updates(mut pdf: Vec<u8>, count: usize) -> Vec<u8> {
+    let catalog = extract_catalog_info(&pdf).unwrap();
+    let size = get_next_object_number(&pdf).unwrap() as usize;
+    for _ in 0..count {
+      let catalog_section = find_object_section(&pdf, catalog.catalog_obj)
+        .unwrap()
+        .to_vec();
+      let prev_xref = find_startxref(&pdf).unwrap();
+
+      pdf.push(b'\n');
+      let catalog_pos = pdf.len();
+      pdf.extend_from_slice(&catalog_section);
+      pdf.extend_from_slice(b"endobj\n");
+      let xref_start = pdf.len();
+      pdf.extend_from_slice(
+        build_xref_table(&mut [(catalog.catalog_obj, catalog_pos)], &pdf, size).as_bytes(),
+      );
+      pdf.extend_from_slice(
+        format!(
+          "trailer\n<<\n/Size {}\n/Prev {}\n/Root {} 0 R\n>>\nstartxref\n{}\n%%EOF\n",
+          size, prev_xref, catalog.catalog_obj, xref_start
+        )
+        .as_bytes(),
+      );
+    }
+    pdf
+  }
+
+  /// Troca o offset do último startxref
+  fn with_startxref(pdf: &[u8], offset: usize) -> Vec<u8> {
+    let pos = pdf
+      .windows(b"startxref".len())
+      .rposition(|w| w == b"startxref")
+      .unwrap();
+    let mut broken = pdf[..pos].to_vec();
+    broken.extend_from_slice(format!("startxref\n{}\n%%EOF\n", offset).as_bytes());
+    broken
+  }
+
+  #[test]
+  fn test_sign_pdf_after_three_incremental_updates() {
+    let signer = test_signer();
+    let config = offline_config();
+    let original = with_incremental_updates(sample_pdf(), 3);
+    let original_zero_definitions = walk_xref_chain(&original);
+    let prev_xref = find_startxref(&original).unwrap();
+
+    let signed = signer.sign_pdf(original.clone(), &config).unwrap();
+
+    // O /Prev da assinatura encadeia a xref da última das três atualizações
+    let tail = &signed[original.len() - 1..];
+    assert_eq!(read_number_after(tail, b"/Prev"), Some(prev_xref));
+    assert_eq!(walk_xref_chain(&signed), original_zero_definitions);
+    verify_first_signature(&signed);
+
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("pdfsigner-triple-{}.pdf", std::process::id()));
+    let output = dir.join(format!(
+      "pdfsigner-triple-{}-signed.pdf",
+      std::process::id()
+    ));
+    fs::write(&input, &original).unwrap();
+    let result = signer.sign_pdf_file_to_file(&input, &output, &config);
+    let streamed = fs::read(&output);
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&output).ok();
+    result.unwrap();
+    assert_eq!(
+      walk_xref_chain(&streamed.unwrap()),
+      original_zero_definitions
+    );
+  }
+
+  #[test]
+  fn test_sign_pdf_rejects_startxref_outside_xref() {
+    let signer = test_signer();
+    let config = offline_config();
+    let original = with_incremental_updates(sample_pdf(), 3);
+    // Offset de um objeto comum (o Catalog regravado) e offset além do fim do arquivo
+    let catalog = extract_catalog_info(&original).unwrap();
+    let catalog_pattern = format!("\n{} 0 obj", catalog.catalog_obj);
+    let catalog_pos = original
+      .windows(catalog_pattern.len())
+      .rposition(|w| w == catalog_pattern.as_bytes())
+      .unwrap()
+      + 1;
+
+    let dir = std::env::temp_dir();
+    for (i, offset) in [catalog_pos, original.len() + 100].into_iter().enumerate() {
+      let broken = with_startxref(&original, offset);
+      let err = signer.sign_pdf(broken.clone(), &config).unwrap_err();
+      assert!(err.to_string().contains("não é uma seção xref"), "{}", err);
+
+      let input = dir.join(format!("pdfsigner-xref-{}-{}.pdf", std::process::id(), i));
+      let output = dir.join(format!(
+        "pdfsigner-xref-{}-{}-signed.pdf",
+        std::process::id(),
+        i
+      ));
+      fs::write(&input, &broken).unwrap();
+      let result = signer.sign_pdf_file_to_file(&input, &output, &config);
+      fs::remove_file(&input).unwrap();
+      let err = result.unwrap_err();
+      assert!(err.to_string().contains("não é uma seção xref"), "{}", err);
+      assert!(!output.exists());
+    }
+  }
+
+  #[test]
+  fn test_sign_pdf_requires_startxref() {
+    let original = sample_pdf();
+    let end = original
+      .windows(b"startxref".len())
+      .rposition(|w| w == b"startxref")
+      .unwrap();
+    let mut broken = original[..end].to_vec();
+    broken.extend_from_slice(b"%%EOF\n");
+
+    // Sem startxref a xref nova ficaria com /Prev 0
+    let err = test_signer()
+      .sign_pdf(broken.clone(), &offline_config())
+      .unwrap_err();
+    assert!(matches!(&err, PdfSignError::InvalidPdf(message) if message.contains("startxref")));
+    let err =
+      PdfSigner::add_empty_signature_field(broken.clone(), "Cliente", [0.0; 4], 0).unwrap_err();
+    assert!(matches!(&err, PdfSignError::InvalidPdf(message) if message.contains("startxref")));
+
+    // O carimbo do documento é recusado antes de consultar a TSA (inalcançável aqui)
+    let config = SignatureConfig {
+      tsa_url: Some("http://127.0.0.1:9/".to_string()),
+      ..offline_config()
+    };
+    let err = PdfSigner::add_document_timestamp(broken, &config).unwrap_err();
+    assert!(matches!(&err, PdfSignError::InvalidPdf(message) if message.contains("startxref")));
+  }
+
+  #[test]
+  fn test_sign_pdf_numbers_objects_from_trailer_size() {
+    use crate::signatures::list_signatures;
+
+    // /Size acima do maior objeto (números já liberados): os novos começam nele
+    // (o do trailer para onde aponta o startxref, o da primeira página no linearizado)
+    let mut pdf = sample_pdf();
+    let size = pdf
+      .windows(b"/Size 19".len())
+      .position(|w| w == b"/Size 19")
+      .unwrap();
+    pdf[size..size + b"/Size 23".len()].copy_from_slice(b"/Size 23");
+    assert_eq!(get_next_object_number(&pdf).unwrap(), 19);
+
+    // A análise prevê o mesmo número que a assinatura usa
+    let signer = test_signer();
+    assert_eq!(
+      signer.analyze(&pdf, &offline_config()).unwrap().next_obj,
+      23
+    );
+    let signed = signer.sign_pdf(pdf.clone(), &offline_config()).unwrap();
+    verify_first_signature(&signed);
+    assert_eq!(list_signatures(&signed)[0].object_number, 23);
+    walk_xref_chain(&signed);
+
+    // O carimbo do documento também numera a partir do /Size
+    let tsa = MockTsa::new();
+    let server = MockServer::start(move |_, request| MockResponse::ok(tsa.respond(&request.body)));
+    let config = SignatureConfig {
+      tsa_url: Some(server.url()),
+      ..offline_config()
+    };
+    let original_len = remove_trailing_newline(pdf.clone()).len();
+    let stamped = PdfSigner::add_document_timestamp(pdf, &config).unwrap();
+    let update = String::from_utf8_lossy(&stamped[original_len..]).to_string();
+    assert!(
+      update.starts_with("\n23 0 obj\n<<\n/Type /DocTimeStamp\n"),
+      "{}",
+      update
+    );
+    assert!(update.contains("/Size 24\n"), "{}", update);
+    walk_xref_chain(&stamped);
+  }
+}
+
+/// Caminho sem OpenSSL (o do wasm): os testes nativos acima dependem do OpenSSL
+#[cfg(all(test, not(feature = "native")))]
+mod rust_only_tests {
+  use super::*;
+
+  #[test]
+  fn test_default_config_signs_without_openssl() {
+    let pfx = include_bytes!("../__test__/certificado-a1.pfx");
+    let pdf = include_bytes!("../__test__/pdf_sample_2.pdf").to_vec();
+    let signer = PdfSigner::from_pfx_bytes(pfx, "123456").unwrap();
+
+    // O padrão é B-B, sem validação da cadeia nem rede: funciona igual ao nativo
+    let (signed, report) = signer
+      .sign_pdf_with_report(pdf, &SignatureConfig::default())
+      .unwrap();
+    assert_eq!(report.pades_level, PadesLevel::BB);
+    assert!(report.warnings.is_empty());
+
+    let entry = &crate::signatures::list_signatures(&signed)[0];
+    let range = &entry.byte_range;
+    let mut covered = signed[..range[1]].to_vec();
+    covered.extend_from_slice(&signed[range[2]..range[2] + range[3]]);
+    cms_signer::verify_contents(
+      &signed[range[1]..range[2]],
+      &DigestAlgorithm::Sha256.digest(&covered),
+      DigestAlgorithm::Sha256,
+    )
+    .unwrap();
+  }
+}
@@ -0,0 +1,303 @@
+/// Leitura de PKCS#12 (PFX/P12) em Rust puro, para o build sem OpenSSL
+///
+/// Suporta PBES2 (PBKDF2 + AES/3DES, padrão do OpenSSL 3 e dos certificados
+/// A1 atuais); arquivos com a PBE legada do PKCS#12 (RC2-40, 3DES) caem no crate p12
+use der::asn1::OctetString;
+use der::{AnyRef, Decode, Encode};
+use pkcs12::cert_type::CertBag;
+use pkcs12::pfx::Pfx;
+use pkcs12::safe_bag::SafeBag;
+use pkcs12::{
+  PKCS_12_CERT_BAG_OID, PKCS_12_KEY_BAG_OID, PKCS_12_PKCS8_KEY_BAG_OID, PKCS_12_X509_CERT_OID,
+};
+use pkcs8::pkcs5::EncryptionScheme;
+use pkcs8::EncryptedPrivateKeyInfo;
+use rsa::pkcs1::EncodeRsaPublicKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+
+use crate::error::{PdfSignError, Result};
+use crate::pdfsigner::SignerSelector;
+
+/// Chave e certificados extraídos do PFX, com o certificado da chave primeiro
+pub struct PfxContents {
+  pub private_key: RsaPrivateKey,
+  pub certificates: Vec<Vec<u8>>,
+}
+
+/// Chaves PKCS#8 e certificados DER, na ordem em que aparecem no arquivo
+type RawBags = (Vec<Vec<u8>>, Vec<Vec<u8>>);
+
+fn decoding_error(message: impl Into<String>) -> PdfSignError {
+  PdfSignError::DecodingError(message.into())
+}
+
+/// Conteúdo interno do [0] EXPLICIT de um SafeBag
+fn bag_content(bag: &SafeBag) -> Result<&[u8]> {
+  AnyRef::from_der(&bag.bag_value)
+    .map(|value| value.value())
+    .map_err(|e| decoding_error(format!("SafeBag inválido: {}", e)))
+}
+
+fn decrypt_safe_contents(data: &[u8], password: &str) -> Result<Vec<u8>> {
+  let encrypted = cms::encrypted_data::EncryptedData::from_der(data)
+    .map_err(|e| decoding_error(format!("EncryptedData inválido no PKCS#12: {}", e)))?;
+  let info = encrypted.enc_content_info;
+  let algorithm = info
+    .content_enc_alg
+    .to_der()
+    .map_err(|e| decoding_error(format!("Algoritmo inválido no PKCS#12: {}", e)))?;
+  let scheme = EncryptionScheme::from_der(&algorithm).map_err(|e| {
+    decoding_error(format!(
+      "Algoritmo de cifra do PKCS#12 não suportado ({}): {}",
+      info.content_enc_alg.oid, e
+    ))
+  })?;
+  let ciphertext = info
+    .encrypted_content
+    .ok_or_else(|| decoding_error("EncryptedData sem conteúdo no PKCS#12"))?;
+
+  scheme
+    .decrypt(password, ciphertext.as_bytes())
+    .map_err(|_| PdfSignError::InvalidPassword)
+}
+
+fn collect_bags(pfx_data: &[u8], password: &str) -> Result<Vec<SafeBag>> {
+  use const_oid::db::rfc5911::{ID_DATA, ID_ENCRYPTED_DATA};
+
+  let pfx =
+    Pfx::from_der(pfx_data).map_err(|e| decoding_error(format!("PKCS#12 inválido: {}", e)))?;
+  if pfx.auth_safe.content_type != ID_DATA {
+    return Err(decoding_error(
+      "PKCS#12 com integridade por chave pública não é suportado",
+    ));
+  }
+  let auth_safe = pfx
+    .auth_safe
+    .content
+    .decode_as::<OctetString>()
+    .map_err(|e| decoding_error(format!("AuthenticatedSafe inválido: {}", e)))?;
+  let contents = Vec::<cms::content_info::ContentInfo>::from_der(auth_safe.as_bytes())
+    .map_err(|e| decoding_error(format!("AuthenticatedSafe inválido: {}", e)))?;
+
+  let mut bags = Vec::new();
+  for content in contents {
+    let safe_contents = match content.content_type {
+      oid if oid == ID_DATA => content
+        .content
+        .decode_as::<OctetString>()
+        .map_err(|e| decoding_error(format!("SafeContents inválido: {}", e)))?
+        .into_bytes(),
+      oid if oid == ID_ENCRYPTED_DATA => {
+        let data = content
+          .content
+          .to_der()
+          .map_err(|e| decoding_error(format!("EncryptedData inválido no PKCS#12: {}", e)))?;
+        decrypt_safe_contents(&data, password)?
+      }
+      _ => continue,
+    };
+    bags.extend(
+      Vec::<SafeBag>::from_der(&safe_contents)
+        .map_err(|e| decoding_error(format!("SafeContents inválido: {}", e)))?,
+    );
+  }
+
+  Ok(bags)
+}
+
+/// Extrai chave e certificados com PBES2
+fn parse_pbes2(pfx_data: &[u8], password: &str) -> Result<RawBags> {
+  let mut keys = Vec::new();
+  let mut certificates = Vec::new();
+
+  for bag in collect_bags(pfx_data, password)? {
+    match bag.bag_id {
+      oid if oid == PKCS_12_PKCS8_KEY_BAG_OID => {
+        let encrypted = EncryptedPrivateKeyInfo::try_from(bag_content(&bag)?)
+          .map_err(|e| decoding_error(format!("Chave cifrada inválida no PKCS#12: {}", e)))?;
+        let key = encrypted
+          .decrypt(password)
+          .map_err(|_| PdfSignError::InvalidPassword)?;
+        keys.push(key.as_bytes().to_vec());
+      }
+      oid if oid == PKCS_12_KEY_BAG_OID => keys.push(bag_content(&bag)?.to_vec()),
+      oid if oid == PKCS_12_CERT_BAG_OID => {
+        let cert_bag = CertBag::from_der(bag_content(&bag)?)
+          .map_err(|e| decoding_error(format!("CertBag inválido: {}", e)))?;
+        if cert_bag.cert_id == PKCS_12_X509_CERT_OID {
+          certificates.push(cert_bag.cert_value.into_bytes());
+        }
+      }
+      _ => {}
+    }
+  }
+
+  Ok((keys, certificates))
+}
+
+/// Extrai chave e certificados com a PBE legada, via crate p12
+fn parse_legacy(pfx_data: &[u8], password: &str) -> Result<RawBags> {
+  let pfx = p12::PFX::parse(pfx_data)
+    .map_err(|e| decoding_error(format!("Erro ao parsear PKCS#12: {:?}", e)))?;
+  if !pfx.verify_mac(password) {
+    return Err(PdfSignError::InvalidPassword);
+  }
+  let keys = pfx
+    .key_bags(password)
+    .map_err(|e| decoding_error(format!("Erro ao descriptografar PKCS#12: {:?}", e)))?;
+  let certificates = pfx
+    .cert_x509_bags(password)
+    .map_err(|e| decoding_error(format!("Erro ao descriptografar PKCS#12: {:?}", e)))?;
+  Ok((keys, certificates))
+}
+
+/// Se o certificado carrega a chave pública do par
+fn certificate_matches_key(cert_der: &[u8], public_key_der: &[u8]) -> bool {
+  x509_cert::Certificate::from_der(cert_der).is_ok_and(|cert| {
+    cert
+      .tbs_certificate
+      .subject_public_key_info
+      .subject_public_key
+      .raw_bytes()
+      == public_key_der
+  })
+}
+
+/// Todas as chaves PKCS#8 e certificados DER do PFX, na ordem em que aparecem
+pub fn bags(pfx_data: &[u8], password: &str) -> Result<RawBags> {
+  match parse_pbes2(pfx_data, password) {
+    Ok(parsed) => Ok(parsed),
+    Err(PdfSignError::InvalidPassword) => Err(PdfSignError::InvalidPassword),
+    Err(error) => parse_legacy(pfx_data, password).map_err(|legacy_error| match legacy_error {
+      PdfSignError::InvalidPassword => legacy_error,
+      _ => error,
+    }),
+  }
+}
+
+fn decode_key(key_der: &[u8]) -> Result<RsaPrivateKey> {
+  RsaPrivateKey::from_pkcs8_der(key_der)
+    .map_err(|e| decoding_error(format!("Erro ao decodificar chave privada: {:?}", e)))
+}
+
+fn public_key_der(private_key: &RsaPrivateKey) -> Result<Vec<u8>> {
+  RsaPublicKey::from(private_key)
+    .to_pkcs1_der()
+    .map(|der| der.as_bytes().to_vec())
+    .map_err(|e| decoding_error(format!("Erro ao exportar chave pública: {:?}", e)))
+}
+
+/// Lê o PFX e devolve a chave RSA e os certificados, o da chave primeiro
+///
+/// Sem `signer` vale a primeira chave do arquivo; com ele, o certificado escolhido e
+/// a chave que corresponde a ele, e os demais certificados viram a cadeia
+pub fn parse(
+  pfx_data: &[u8],
+  password: &str,
+  signer: Option<&SignerSelector>,
+) -> Result<PfxContents> {
+  let (keys, mut certificates) = bags(pfx_data, password)?;
+
+  let (private_key, leaf) = match signer {
+    None => {
+      let key_der = keys
+        .first()
+        .ok_or_else(|| decoding_error("Nenhuma chave privada encontrada no PKCS#12"))?;
+      let private_key = decode_key(key_der)?;
+      let public_key_der = public_key_der(&private_key)?;
+      let leaf = certificates
+        .iter()
+        .position(|cert| certificate_matches_key(cert, &public_key_der))
+        .ok_or(PdfSignError::InvalidCertificate)?;
+      (private_key, leaf)
+    }
+    Some(signer) => {
+      let leaf = certificates
+        .iter()
+        .position(|cert| signer.matches(cert))
+        .ok_or_else(|| signer.not_found())?;
+      let private_key = keys
+        .iter()
+        .filter_map(|key_der| decode_key(key_der).ok())
+        .find(|key| {
+          public_key_der(key).is_ok_and(|der| certificate_matches_key(&certificates[leaf], &der))
+        })
+        .ok_or_else(|| signer.key_not_found())?;
+      (private_key, leaf)
+    }
+  };
+  let leaf = certificates.remove(leaf);
+  certificates.insert(0, leaf);
+
+  Ok(PfxContents {
+    private_key,
+    certificates,
+  })
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+  use super::*;
+  use crate::test_support::{issue_identity, keep_default_provider, pkcs12_bytes};
+  use rsa::pkcs8::EncodePrivateKey;
+
+  #[test]
+  fn test_parse_pbes2_puts_signer_certificate_first() {
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let (leaf_key, leaf) = issue_identity("Signatario", Some((&root_key, &root)), false);
+    let pfx = pkcs12_bytes(&leaf_key, &leaf, std::slice::from_ref(&root), "segredo");
+
+    let contents = parse(&pfx, "segredo", None).unwrap();
+    assert_eq!(contents.certificates.len(), 2);
+    assert_eq!(contents.certificates[0], leaf.to_der().unwrap());
+    assert_eq!(contents.certificates[1], root.to_der().unwrap());
+    assert_eq!(
+      contents.private_key.to_pkcs8_der().unwrap().as_bytes(),
+      leaf_key.private_key_to_pkcs8().unwrap()
+    );
+
+    assert!(matches!(
+      parse(&pfx, "errada", None),
+      Err(PdfSignError::InvalidPassword)
+    ));
+  }
+
+  #[test]
+  fn test_parse_legacy_triple_des() {
+    use openssl::nid::Nid;
+    use openssl::pkcs12::Pkcs12;
+
+    keep_default_provider();
+    let (key, cert) = issue_identity("Signatario", None, false);
+    let pfx = Pkcs12::builder()
+      .name("teste")
+      .pkey(&key)
+      .cert(&cert)
+      .key_algorithm(Nid::PBE_WITHSHA1AND3_KEY_TRIPLEDES_CBC)
+      .cert_algorithm(Nid::PBE_WITHSHA1AND3_KEY_TRIPLEDES_CBC)
+      .mac_md(openssl::hash::MessageDigest::sha1())
+      .build2("segredo")
+      .unwrap()
+      .to_der()
+      .unwrap();
+
+    let contents = parse(&pfx, "segredo", None).unwrap();
+    assert_eq!(contents.certificates, vec![cert.to_der().unwrap()]);
+    assert!(matches!(
+      parse(&pfx, "errada", None),
+      Err(PdfSignError::InvalidPassword)
+    ));
+  }
+
+  #[test]
+  fn test_parse_sample_certificate() {
+    let pfx = std::fs::read(concat!(
+      env!("CARGO_MANIFEST_DIR"),
+      "/__test__/certificado-a1.pfx"
+    ))
+    .unwrap();
+    let contents = parse(&pfx, "123456", None).unwrap();
+    assert!(!contents.certificates.is_empty());
+  }
+}
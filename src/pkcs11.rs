@@ -0,0 +1,279 @@
+/// Assinatura com a chave de um token PKCS#11 (cartão ou token A3, HSM)
+///
+/// A chave privada não sai do token: o CMS é montado pelo backend Rust e só a
+/// operação RSA/ECDSA sobre o hash dos atributos assinados é feita pelo módulo
+use std::path::Path;
+use std::sync::Mutex;
+
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::error::{Error as CryptokiError, RvError};
+use cryptoki::mechanism::rsa::{PkcsMgfType, PkcsPssParams};
+use cryptoki::mechanism::{Mechanism, MechanismType};
+use cryptoki::object::{
+  Attribute, AttributeType, CertificateType, KeyType as TokenKeyType, ObjectClass, ObjectHandle,
+};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::{AuthPin, Ulong};
+use der::asn1::UintRef;
+use der::{Encode, Sequence};
+
+use crate::certificate::Certificate;
+use crate::cms_signer::KeyType;
+use crate::error::{PdfSignError, Result};
+use crate::rust_cms;
+use crate::signature_config::{DigestAlgorithm, SignatureScheme};
+
+/// Chave privada no token, com a sessão autenticada usada para assinar
+pub struct TokenKey {
+  // Session não é Sync: as assinaturas no mesmo token são serializadas
+  session: Mutex<Session>,
+  key: ObjectHandle,
+  key_type: KeyType,
+}
+
+/// Chave, certificado do signatário e cadeia lidos do token
+pub struct TokenIdentity {
+  pub key: TokenKey,
+  pub certificate: Certificate,
+  pub chain: Vec<Certificate>,
+}
+
+/// Ecdsa-Sig-Value (RFC 3279), formato da assinatura ECDSA no SignerInfo
+#[derive(Sequence)]
+struct EcdsaSigValue<'a> {
+  r: UintRef<'a>,
+  s: UintRef<'a>,
+}
+
+fn token_error(context: &str, error: CryptokiError) -> PdfSignError {
+  PdfSignError::SigningError(format!("{}: {}", context, error))
+}
+
+/// Abre uma sessão no slot, autentica com o PIN e localiza a chave pelo CKA_LABEL
+///
+/// O certificado do signatário é o do token com o mesmo CKA_ID da chave (ou, sem
+/// CKA_ID, o mesmo CKA_LABEL); os demais certificados X.509 do token formam a cadeia
+pub fn open(module_path: &Path, slot: u64, pin: &str, key_label: &str) -> Result<TokenIdentity> {
+  let pkcs11 =
+    Pkcs11::new(module_path).map_err(|e| token_error("Erro ao carregar o módulo PKCS#11", e))?;
+  // Outro componente do processo pode já ter inicializado o mesmo módulo
+  match pkcs11.initialize(CInitializeArgs::OsThreads) {
+    Ok(()) | Err(CryptokiError::Pkcs11(RvError::CryptokiAlreadyInitialized, _)) => {}
+    Err(e) => return Err(token_error("Erro ao inicializar o módulo PKCS#11", e)),
+  }
+
+  let slot = pkcs11
+    .get_slots_with_token()
+    .map_err(|e| token_error("Erro ao listar os slots PKCS#11", e))?
+    .into_iter()
+    .find(|candidate| candidate.id() == slot)
+    .ok_or_else(|| {
+      PdfSignError::SigningError(format!(
+        "slot PKCS#11 {} não existe ou está sem token",
+        slot
+      ))
+    })?;
+  let session = pkcs11
+    .open_ro_session(slot)
+    .map_err(|e| token_error("Erro ao abrir sessão no token", e))?;
+  session
+    .login(UserType::User, Some(&AuthPin::new(pin.to_string())))
+    .map_err(|e| token_error("Erro ao autenticar no token (PIN)", e))?;
+
+  let key = session
+    .find_objects(&[
+      Attribute::Class(ObjectClass::PRIVATE_KEY),
+      Attribute::Label(key_label.as_bytes().to_vec()),
+    ])
+    .map_err(|e| token_error("Erro ao procurar a chave no token", e))?
+    .into_iter()
+    .next()
+    .ok_or_else(|| {
+      PdfSignError::SigningError(format!("chave \"{}\" não encontrada no token", key_label))
+    })?;
+
+  let mut key_type = KeyType::Other;
+  let mut key_id = Vec::new();
+  for attribute in session
+    .get_attributes(key, &[AttributeType::KeyType, AttributeType::Id])
+    .map_err(|e| token_error("Erro ao ler atributos da chave", e))?
+  {
+    match attribute {
+      Attribute::KeyType(kind) if kind == TokenKeyType::RSA => key_type = KeyType::Rsa,
+      Attribute::KeyType(kind) if kind == TokenKeyType::EC => key_type = KeyType::Ec,
+      Attribute::Id(id) => key_id = id,
+      _ => {}
+    }
+  }
+
+  let mut certificate = None;
+  let mut chain = Vec::new();
+  for handle in session
+    .find_objects(&[
+      Attribute::Class(ObjectClass::CERTIFICATE),
+      Attribute::CertificateType(CertificateType::X_509),
+    ])
+    .map_err(|e| token_error("Erro ao procurar certificados no token", e))?
+  {
+    let (mut id, mut label, mut value) = (Vec::new(), Vec::new(), Vec::new());
+    for attribute in session
+      .get_attributes(
+        handle,
+        &[
+          AttributeType::Id,
+          AttributeType::Label,
+          AttributeType::Value,
+        ],
+      )
+      .map_err(|e| token_error("Erro ao ler certificado do token", e))?
+    {
+      match attribute {
+        Attribute::Id(bytes) => id = bytes,
+        Attribute::Label(bytes) => label = bytes,
+        Attribute::Value(bytes) => value = bytes,
+        _ => {}
+      }
+    }
+
+    let matches_key = if key_id.is_empty() {
+      label == key_label.as_bytes()
+    } else {
+      id == key_id
+    };
+    let cert = Certificate::from_der(value)?;
+    if matches_key && certificate.is_none() {
+      certificate = Some(cert);
+    } else {
+      chain.push(cert);
+    }
+  }
+  let certificate = certificate.ok_or_else(|| {
+    PdfSignError::SigningError(format!(
+      "certificado da chave \"{}\" não encontrado no token",
+      key_label
+    ))
+  })?;
+
+  Ok(TokenIdentity {
+    key: TokenKey {
+      session: Mutex::new(session),
+      key,
+      key_type,
+    },
+    certificate,
+    chain,
+  })
+}
+
+impl TokenKey {
+  pub fn key_type(&self) -> KeyType {
+    self.key_type
+  }
+
+  /// Assina o hash já calculado (dos atributos assinados) com a chave do token
+  pub fn sign(
+    &self,
+    scheme: SignatureScheme,
+    digest: DigestAlgorithm,
+    hashed: &[u8],
+  ) -> Result<Vec<u8>> {
+    let (mechanism, data) = match scheme {
+      // CKM_RSA_PKCS só aplica o padding: o DigestInfo vai junto com o hash
+      SignatureScheme::Pkcs1v15 => {
+        let mut digest_info = rust_cms::padding(digest).prefix.to_vec();
+        digest_info.extend_from_slice(hashed);
+        (Mechanism::RsaPkcs, digest_info)
+      }
+      SignatureScheme::Pss => {
+        let (hash_alg, mgf) = pss_hash(digest);
+        let s_len = Ulong::try_from(hashed.len())
+          .map_err(|e| token_error("Erro ao montar parâmetros PSS", e))?;
+        let params = PkcsPssParams {
+          hash_alg,
+          mgf,
+          s_len,
+        };
+        (Mechanism::RsaPkcsPss(params), hashed.to_vec())
+      }
+      SignatureScheme::Ecdsa => (Mechanism::Ecdsa, hashed.to_vec()),
+      SignatureScheme::Ed25519 => {
+        return Err(PdfSignError::SigningError(
+          "Ed25519 não é suportado na assinatura com token PKCS#11".to_string(),
+        ))
+      }
+    };
+
+    let session = self
+      .session
+      .lock()
+      .map_err(|_| PdfSignError::SigningError("sessão PKCS#11 inutilizada".to_string()))?;
+    let signature = session
+      .sign(&mechanism, self.key, &data)
+      .map_err(|e| token_error("Erro ao assinar com o token PKCS#11", e))?;
+
+    if scheme == SignatureScheme::Ecdsa {
+      ecdsa_der(&signature)
+    } else {
+      Ok(signature)
+    }
+  }
+}
+
+/// Hash e MGF1 do PSS, os mesmos do digest dos atributos (como no backend OpenSSL)
+fn pss_hash(digest: DigestAlgorithm) -> (MechanismType, PkcsMgfType) {
+  match digest {
+    DigestAlgorithm::Sha1 => (MechanismType::SHA1, PkcsMgfType::MGF1_SHA1),
+    DigestAlgorithm::Sha256 => (MechanismType::SHA256, PkcsMgfType::MGF1_SHA256),
+    DigestAlgorithm::Sha384 => (MechanismType::SHA384, PkcsMgfType::MGF1_SHA384),
+    DigestAlgorithm::Sha512 => (MechanismType::SHA512, PkcsMgfType::MGF1_SHA512),
+  }
+}
+
+/// Converte a assinatura ECDSA do PKCS#11 (r || s, mesmo tamanho) para DER
+fn ecdsa_der(raw: &[u8]) -> Result<Vec<u8>> {
+  if raw.is_empty() || !raw.len().is_multiple_of(2) {
+    return Err(PdfSignError::SigningError(format!(
+      "assinatura ECDSA do token com tamanho inválido ({} bytes)",
+      raw.len()
+    )));
+  }
+  let (r, s) = raw.split_at(raw.len() / 2);
+  let encoding_error = |e: der::Error| {
+    PdfSignError::SigningError(format!("Erro ao codificar assinatura ECDSA: {}", e))
+  };
+  EcdsaSigValue {
+    r: UintRef::new(r).map_err(encoding_error)?,
+    s: UintRef::new(s).map_err(encoding_error)?,
+  }
+  .to_der()
+  .map_err(encoding_error)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_ecdsa_der_encodes_r_and_s_as_integers() {
+    // r com o bit alto ligado ganha um 0x00; s com zeros à esquerda perde os zeros
+    let mut raw = vec![0x80; 32];
+    raw.extend_from_slice(&[0u8; 31]);
+    raw.push(0x05);
+
+    let der = ecdsa_der(&raw).unwrap();
+
+    assert_eq!(&der[..4], &[0x30, 0x26, 0x02, 0x21]);
+    assert_eq!(der[4], 0x00);
+    assert_eq!(&der[5..37], &[0x80; 32]);
+    assert_eq!(&der[37..], &[0x02, 0x01, 0x05]);
+  }
+
+  #[test]
+  fn test_ecdsa_der_rejects_odd_length() {
+    assert!(matches!(
+      ecdsa_der(&[1, 2, 3]),
+      Err(PdfSignError::SigningError(_))
+    ));
+    assert!(ecdsa_der(&[]).is_err());
+  }
+}
@@ -0,0 +1,159 @@
+/// Coleta de dados de revogação (OCSP e CRL) para PAdES-B-LT
+///
+/// As respostas são obtidas para cada certificado da cadeia que tenha emissor
+/// conhecido e gravadas depois no /DSS do documento
+use openssl::hash::MessageDigest;
+use openssl::ocsp::{OcspCertId, OcspRequest, OcspResponse, OcspResponseStatus};
+use openssl::x509::{X509Crl, X509Ref, X509VerifyResult, X509};
+
+use crate::cache;
+use crate::error::{PdfSignError, Result};
+use crate::network::{get_with_retry, http_client, post_with_retry};
+use crate::signature_config::SignatureConfig;
+
+/// Material de validação a ser gravado no /DSS
+#[derive(Debug, Default)]
+pub struct RevocationData {
+  pub certs: Vec<Vec<u8>>,
+  pub crls: Vec<Vec<u8>>,
+  pub ocsps: Vec<Vec<u8>>,
+}
+
+fn network_error(message: impl Into<String>) -> PdfSignError {
+  PdfSignError::NetworkError(message.into())
+}
+
+/// URLs HTTP dos pontos de distribuição de CRL do certificado
+pub fn crl_urls(cert: &X509Ref) -> Vec<String> {
+  let Some(points) = cert.crl_distribution_points() else {
+    return Vec::new();
+  };
+
+  points
+    .iter()
+    .filter_map(|point| point.distpoint())
+    .filter_map(|name| name.fullname())
+    .flat_map(|names| names.iter())
+    .filter_map(|name| name.uri())
+    .filter(|uri| uri.starts_with("http://") || uri.starts_with("https://"))
+    .map(str::to_string)
+    .collect()
+}
+
+/// URLs dos responders OCSP declarados no Authority Information Access
+pub fn ocsp_urls(cert: &X509Ref) -> Vec<String> {
+  cert
+    .ocsp_responders()
+    .map(|responders| responders.iter().map(|url| url.to_string()).collect())
+    .unwrap_or_default()
+}
+
+fn find_issuer<'a>(cert: &X509Ref, chain: &'a [X509]) -> Option<&'a X509> {
+  chain
+    .iter()
+    .find(|candidate| candidate.issued(cert) == X509VerifyResult::OK)
+}
+
+fn is_self_signed(cert: &X509Ref) -> bool {
+  cert.issued(cert) == X509VerifyResult::OK
+}
+
+fn fetch_ocsp(
+  cert: &X509Ref,
+  issuer: &X509Ref,
+  url: &str,
+  config: &SignatureConfig,
+) -> Result<Vec<u8>> {
+  let cert_id = OcspCertId::from_cert(MessageDigest::sha1(), cert, issuer)
+    .map_err(|e| network_error(format!("Erro ao montar CertID OCSP: {}", e)))?;
+  let mut request = OcspRequest::new()
+    .map_err(|e| network_error(format!("Erro ao criar requisição OCSP: {}", e)))?;
+  request
+    .add_id(cert_id)
+    .map_err(|e| network_error(format!("Erro ao criar requisição OCSP: {}", e)))?;
+  let request_der = request
+    .to_der()
+    .map_err(|e| network_error(format!("Erro ao codificar requisição OCSP: {}", e)))?;
+
+  let client = http_client(config.network_timeout).map_err(network_error)?;
+  let response_der = post_with_retry(
+    &client,
+    url,
+    "application/ocsp-request",
+    &request_der,
+    &config.retry_policy,
+    None,
+  )
+  .map_err(|e| network_error(format!("Falha ao consultar OCSP {}: {}", url, e)))?;
+
+  let response = OcspResponse::from_der(&response_der)
+    .map_err(|e| network_error(format!("Resposta OCSP inválida de {}: {}", url, e)))?;
+  if response.status() != OcspResponseStatus::SUCCESSFUL {
+    return Err(network_error(format!(
+      "Responder OCSP {} retornou status {:?}",
+      url,
+      response.status()
+    )));
+  }
+
+  Ok(response_der)
+}
+
+/// Baixa a CRL, ou a reaproveita do cache do processo enquanto não passar do nextUpdate
+fn fetch_crl(url: &str, config: &SignatureConfig) -> Result<Vec<u8>> {
+  if let Some(crl_der) = cache::crl(url) {
+    return Ok(crl_der);
+  }
+
+  let client = http_client(config.network_timeout).map_err(network_error)?;
+  let crl_der = get_with_retry(&client, url, &config.retry_policy)
+    .map_err(|e| network_error(format!("Falha ao baixar CRL {}: {}", url, e)))?;
+
+  X509Crl::from_der(&crl_der)
+    .map_err(|e| network_error(format!("CRL inválida em {}: {}", url, e)))?;
+  cache::store_crl(url, &crl_der);
+  Ok(crl_der)
+}
+
+/// Obtém OCSP e/ou CRL de cada certificado da cadeia conforme a configuração
+///
+/// O OCSP é tentado primeiro; a CRL é usada quando solicitada ou quando
+/// nenhuma resposta OCSP pôde ser obtida para o certificado
+pub fn collect_revocation_data(chain: &[X509], config: &SignatureConfig) -> Result<RevocationData> {
+  let mut data = RevocationData::default();
+
+  for cert in chain {
+    let der = cert
+      .to_der()
+      .map_err(|e| PdfSignError::DecodingError(format!("Erro ao codificar certificado: {}", e)))?;
+    if !data.certs.contains(&der) {
+      data.certs.push(der);
+    }
+
+    if is_self_signed(cert) {
+      continue;
+    }
+    let Some(issuer) = find_issuer(cert, chain) else {
+      continue;
+    };
+
+    let mut has_ocsp = false;
+    if config.include_ocsp {
+      if let Some(url) = ocsp_urls(cert).first() {
+        data.ocsps.push(fetch_ocsp(cert, issuer, url, config)?);
+        has_ocsp = true;
+      }
+    }
+
+    if config.include_crl || (config.include_ocsp && !has_ocsp) {
+      if let Some(url) = crl_urls(cert).first() {
+        let crl = fetch_crl(url, config)?;
+        if !data.crls.contains(&crl) {
+          data.crls.push(crl);
+        }
+      }
+    }
+  }
+
+  Ok(data)
+}
@@ -0,0 +1,277 @@
+/// Construção do CMS SignedData em Rust puro, sem OpenSSL
+///
+/// Padrão com a feature `cms-rustcrypto` e no build wasm; no build nativo também
+/// pode ser escolhido via `CmsBackend::Rust`. Gera a mesma estrutura do backend OpenSSL:
+/// atributos assinados contentType, signingTime e messageDigest e assinatura
+/// RSA (PKCS#1 v1.5 ou PSS) sobre o SET OF desses atributos. Com a feature `pkcs11`
+/// a assinatura dos atributos também pode ser feita por um token (RSA ou ECDSA)
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use cms::cert::{CertificateChoices, IssuerAndSerialNumber};
+use cms::content_info::{CmsVersion, ContentInfo};
+use cms::signed_data::{
+  CertificateSet, EncapsulatedContentInfo, SignedData, SignerIdentifier, SignerInfo, SignerInfos,
+};
+use const_oid::db::rfc5911::{
+  ID_CONTENT_TYPE, ID_DATA, ID_MESSAGE_DIGEST, ID_SIGNED_DATA, ID_SIGNING_TIME,
+};
+use const_oid::db::rfc5912::{
+  ECDSA_WITH_SHA_256, ECDSA_WITH_SHA_384, ECDSA_WITH_SHA_512, ID_RSASSA_PSS, RSA_ENCRYPTION,
+};
+use const_oid::ObjectIdentifier;
+use der::asn1::{Any, GeneralizedTime, OctetString, SetOfVec, UtcTime};
+use der::{Decode, Encode};
+use rsa::pkcs1::RsaPssParams;
+use rsa::{Pkcs1v15Sign, Pss, RsaPrivateKey};
+use x509_cert::attr::Attribute;
+use x509_cert::spki::AlgorithmIdentifierOwned;
+use x509_cert::time::Time;
+
+use crate::certificate::Certificate;
+use crate::cms_signer::CmsContent;
+use crate::error::{PdfSignError, Result};
+#[cfg(feature = "pkcs11")]
+use crate::pkcs11::TokenKey;
+use crate::signature_config::{DigestAlgorithm, SignatureScheme};
+
+/// ecdsa-with-SHA1 (RFC 5758 só lista os digests SHA-2)
+const ECDSA_WITH_SHA_1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.1");
+
+/// Chave que assina os atributos
+pub enum SignerKey<'a> {
+  /// Chave RSA em memória (PFX)
+  Rsa(&'a RsaPrivateKey),
+  /// Chave em token PKCS#11: só o hash dos atributos vai ao token
+  #[cfg(feature = "pkcs11")]
+  Token(&'a TokenKey),
+}
+
+/// Parâmetros do signatário para a construção do CMS
+pub struct RustCmsParams<'a> {
+  pub key: SignerKey<'a>,
+  pub cert: &'a Certificate,
+  pub chain: &'a [Certificate],
+  pub digest: DigestAlgorithm,
+  pub scheme: SignatureScheme,
+  pub detached: bool,
+  /// Instante do atributo signingTime (o mesmo do /M no PDF)
+  pub signing_time: DateTime<Utc>,
+}
+
+fn encoding_error(context: &str, error: der::Error) -> PdfSignError {
+  PdfSignError::SigningError(format!("{}: {}", context, error))
+}
+
+pub(crate) fn padding(digest: DigestAlgorithm) -> Pkcs1v15Sign {
+  match digest {
+    DigestAlgorithm::Sha1 => Pkcs1v15Sign::new::<sha1::Sha1>(),
+    DigestAlgorithm::Sha256 => Pkcs1v15Sign::new::<sha2::Sha256>(),
+    DigestAlgorithm::Sha384 => Pkcs1v15Sign::new::<sha2::Sha384>(),
+    DigestAlgorithm::Sha512 => Pkcs1v15Sign::new::<sha2::Sha512>(),
+  }
+}
+
+/// PSS com MGF1 do mesmo digest e salt do tamanho do digest, como o OpenSSL
+pub(crate) fn pss(digest: DigestAlgorithm) -> (Pss, RsaPssParams<'static>) {
+  let salt_len = digest.digest(b"").len();
+  match digest {
+    DigestAlgorithm::Sha1 => (
+      Pss::new_with_salt::<sha1::Sha1>(salt_len),
+      RsaPssParams::new::<sha1::Sha1>(salt_len as u8),
+    ),
+    DigestAlgorithm::Sha256 => (
+      Pss::new_with_salt::<sha2::Sha256>(salt_len),
+      RsaPssParams::new::<sha2::Sha256>(salt_len as u8),
+    ),
+    DigestAlgorithm::Sha384 => (
+      Pss::new_with_salt::<sha2::Sha384>(salt_len),
+      RsaPssParams::new::<sha2::Sha384>(salt_len as u8),
+    ),
+    DigestAlgorithm::Sha512 => (
+      Pss::new_with_salt::<sha2::Sha512>(salt_len),
+      RsaPssParams::new::<sha2::Sha512>(salt_len as u8),
+    ),
+  }
+}
+
+/// signatureAlgorithm do SignerInfo para o esquema e o digest
+fn signature_algorithm(
+  scheme: SignatureScheme,
+  digest: DigestAlgorithm,
+) -> Result<AlgorithmIdentifierOwned> {
+  match scheme {
+    SignatureScheme::Pkcs1v15 => Ok(AlgorithmIdentifierOwned {
+      oid: RSA_ENCRYPTION,
+      parameters: Some(Any::null()),
+    }),
+    SignatureScheme::Pss => {
+      let parameters = pss(digest)
+        .1
+        .to_der()
+        .and_then(|der| Any::from_der(&der))
+        .map_err(|e| encoding_error("Erro ao codificar parâmetros PSS", e))?;
+      Ok(AlgorithmIdentifierOwned {
+        oid: ID_RSASSA_PSS,
+        parameters: Some(parameters),
+      })
+    }
+    // RFC 5758: os parâmetros do ecdsa-with-SHA* são omitidos
+    SignatureScheme::Ecdsa => Ok(AlgorithmIdentifierOwned {
+      oid: match digest {
+        DigestAlgorithm::Sha1 => ECDSA_WITH_SHA_1,
+        DigestAlgorithm::Sha256 => ECDSA_WITH_SHA_256,
+        DigestAlgorithm::Sha384 => ECDSA_WITH_SHA_384,
+        DigestAlgorithm::Sha512 => ECDSA_WITH_SHA_512,
+      },
+      parameters: None,
+    }),
+    SignatureScheme::Ed25519 => Err(PdfSignError::SigningError(
+      "Ed25519 não é suportado pelo backend Rust do CMS".to_string(),
+    )),
+  }
+}
+
+/// Assina o hash dos atributos e devolve a assinatura com o signatureAlgorithm
+fn sign_digest(
+  params: &RustCmsParams<'_>,
+  hashed: &[u8],
+) -> Result<(Vec<u8>, AlgorithmIdentifierOwned)> {
+  let algorithm = signature_algorithm(params.scheme, params.digest)?;
+  let signature = match params.key {
+    SignerKey::Rsa(key) => match params.scheme {
+      SignatureScheme::Pkcs1v15 => key
+        .sign(padding(params.digest), hashed)
+        .map_err(|e| PdfSignError::RsaError(e.to_string()))?,
+      SignatureScheme::Pss => key
+        .sign_with_rng(&mut rand::thread_rng(), pss(params.digest).0, hashed)
+        .map_err(|e| PdfSignError::RsaError(e.to_string()))?,
+      scheme => {
+        return Err(PdfSignError::SigningError(format!(
+          "esquema de assinatura {:?} não é suportado pela chave RSA",
+          scheme
+        )))
+      }
+    },
+    #[cfg(feature = "pkcs11")]
+    SignerKey::Token(token) => token.sign(params.scheme, params.digest, hashed)?,
+  };
+  Ok((signature, algorithm))
+}
+
+/// signingTime como UTCTime até 2049 e GeneralizedTime depois (RFC 5652, 11.3)
+fn signing_time(instant: DateTime<Utc>) -> Result<Time> {
+  let since_epoch = Duration::from_secs(instant.timestamp().max(0) as u64);
+  UtcTime::from_unix_duration(since_epoch)
+    .map(Time::UtcTime)
+    .or_else(|_| GeneralizedTime::from_unix_duration(since_epoch).map(Time::GeneralTime))
+    .map_err(|e| encoding_error("Erro ao montar signingTime", e))
+}
+
+fn attribute(oid: ObjectIdentifier, value: impl Encode) -> Result<Attribute> {
+  let value = value
+    .to_der()
+    .and_then(|der| Any::from_der(&der))
+    .map_err(|e| encoding_error("Erro ao codificar atributo", e))?;
+  Ok(Attribute {
+    oid,
+    values: SetOfVec::try_from(vec![value])
+      .map_err(|e| encoding_error("Erro ao montar atributo", e))?,
+  })
+}
+
+fn decode_certificate(cert: &Certificate) -> Result<x509_cert::Certificate> {
+  x509_cert::Certificate::from_der(cert.der())
+    .map_err(|e| PdfSignError::DecodingError(format!("Erro ao decodificar certificado: {}", e)))
+}
+
+/// Assina o conteúdo e retorna o ContentInfo SignedData em DER
+pub fn sign(params: &RustCmsParams<'_>, content: CmsContent<'_>) -> Result<Vec<u8>> {
+  let signer_cert = decode_certificate(params.cert)?;
+  let digest_alg = AlgorithmIdentifierOwned {
+    oid: params.digest.oid(),
+    parameters: None,
+  };
+
+  let message_digest = OctetString::new(content.message_digest(params.digest))
+    .map_err(|e| encoding_error("Erro ao montar messageDigest", e))?;
+
+  // SetOfVec ordena pelos bytes DER (X.690, 11.6): a saída não depende da ordem abaixo
+  let signed_attrs = SetOfVec::try_from(vec![
+    attribute(ID_CONTENT_TYPE, ID_DATA)?,
+    attribute(ID_SIGNING_TIME, signing_time(params.signing_time)?)?,
+    attribute(ID_MESSAGE_DIGEST, message_digest)?,
+  ])
+  .map_err(|e| encoding_error("Erro ao montar atributos assinados", e))?;
+
+  // A assinatura cobre o DER dos atributos como SET OF (tag 0x31), não o [0] implícito
+  let signed_attrs_der = signed_attrs
+    .to_der()
+    .map_err(|e| encoding_error("Erro ao codificar atributos assinados", e))?;
+  let (signature, signature_algorithm) =
+    sign_digest(params, &params.digest.digest(&signed_attrs_der))?;
+
+  let signer_info = SignerInfo {
+    version: CmsVersion::V1,
+    sid: SignerIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+      issuer: signer_cert.tbs_certificate.issuer.clone(),
+      serial_number: signer_cert.tbs_certificate.serial_number.clone(),
+    }),
+    digest_alg: digest_alg.clone(),
+    signed_attrs: Some(signed_attrs),
+    signature_algorithm,
+    signature: OctetString::new(signature)
+      .map_err(|e| encoding_error("Erro ao montar assinatura", e))?,
+    unsigned_attrs: None,
+  };
+
+  let mut certificates = vec![CertificateChoices::Certificate(signer_cert)];
+  // O PFX pode repetir o certificado do signatário na cadeia
+  for cert in params.chain.iter().filter(|c| c.der() != params.cert.der()) {
+    certificates.push(CertificateChoices::Certificate(decode_certificate(cert)?));
+  }
+
+  let econtent = match (params.detached, content) {
+    (true, _) => None,
+    (false, CmsContent::Data(data)) => {
+      let content =
+        OctetString::new(data).map_err(|e| encoding_error("Erro ao encapsular conteúdo", e))?;
+      Some(
+        Any::encode_from(&content).map_err(|e| encoding_error("Erro ao encapsular conteúdo", e))?,
+      )
+    }
+    (false, CmsContent::Digest(_)) => {
+      return Err(PdfSignError::SigningError(
+        "CMS a partir do digest só pode ser detached".to_string(),
+      ))
+    }
+  };
+
+  let signed_data = SignedData {
+    version: CmsVersion::V1,
+    digest_algorithms: SetOfVec::try_from(vec![digest_alg])
+      .map_err(|e| encoding_error("Erro ao montar digestAlgorithms", e))?,
+    encap_content_info: EncapsulatedContentInfo {
+      econtent_type: ID_DATA,
+      econtent,
+    },
+    certificates: Some(CertificateSet(
+      SetOfVec::try_from(certificates)
+        .map_err(|e| encoding_error("Erro ao montar certificados", e))?,
+    )),
+    crls: None,
+    signer_infos: SignerInfos(
+      SetOfVec::try_from(vec![signer_info])
+        .map_err(|e| encoding_error("Erro ao montar SignerInfos", e))?,
+    ),
+  };
+
+  let content_info = ContentInfo {
+    content_type: ID_SIGNED_DATA,
+    content: Any::encode_from(&signed_data)
+      .map_err(|e| encoding_error("Erro ao codificar SignedData", e))?,
+  };
+  content_info
+    .to_der()
+    .map_err(|e| encoding_error("Erro ao codificar CMS", e))
+}
@@ -0,0 +1,280 @@
+/// Dicionário de assinatura (/Type /Sig) com a ordem dos campos explícita
+use std::ops::Range;
+
+use crate::error::{PdfSignError, Result};
+use crate::signature_config::{SigDictOrder, TextEncoding, TextLimit, TextOverflow};
+use crate::utils::{pdf_literal_string, pdf_name, pdf_text_string};
+
+/// Valor provisório de /ByteRange: 10 dígitos por número (até ~10GB) e 17 espaços
+/// de folga depois do ], como no node-signpdf
+pub const BYTE_RANGE_PLACEHOLDER: &str =
+  "[0000000000 0000000000 0000000000 0000000000]                 ";
+
+/// Valor provisório de /M (D:YYYYMMDDHHmmSSZ), trocado pelo instante da assinatura
+pub const DATE_PLACEHOLDER: &str = "D:00000000000000Z";
+
+/// Campos de texto e data gravados entre /Contents e /Reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SigDictKey {
+  Reason,
+  Date,
+  ContactInfo,
+  Name,
+  Location,
+}
+
+impl SigDictOrder {
+  fn keys(&self) -> [SigDictKey; 5] {
+    use SigDictKey::*;
+    match self {
+      SigDictOrder::NodeSignpdf => [Reason, Date, ContactInfo, Name, Location],
+      SigDictOrder::DateFirst => [Date, Reason, ContactInfo, Name, Location],
+    }
+  }
+}
+
+/// Onde caem os placeholders no dicionário montado
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigDictSpans {
+  pub byte_range: Range<usize>,
+  pub contents: Range<usize>,
+  pub date: Range<usize>,
+}
+
+/// Campos do dicionário de assinatura
+///
+/// /Type, /Filter, /SubFilter, /ByteRange e /Contents vêm sempre primeiro, e
+/// /Reference e /Prop_Build sempre por último; `order` decide só o meio
+#[derive(Debug, Clone)]
+pub struct SigDictBuilder<'a> {
+  pub filter: &'a str,
+  pub sub_filter: &'a str,
+  pub contents_placeholder: &'a str,
+  pub reason: &'a str,
+  pub contact_info: &'a str,
+  pub name: &'a str,
+  pub location: &'a str,
+  /// Entrada /Reference já montada, ou vazia
+  pub reference_entry: &'a str,
+  pub app_name: &'a str,
+  pub app_version: &'a str,
+  pub text_encoding: TextEncoding,
+  pub order: SigDictOrder,
+  pub text_limit: Option<TextLimit>,
+}
+
+impl SigDictBuilder<'_> {
+  /// Monta o objeto `obj 0 obj ... endobj` e anota as posições dos placeholders
+  ///
+  /// As posições são anotadas enquanto o dicionário é escrito: mudar o espaçamento
+  /// do modelo não exige procurar "/ByteRange [" ou "/Contents <" depois
+  pub fn build(&self, obj: u32) -> Result<(String, SigDictSpans)> {
+    let mut dict = format!(
+      "{} 0 obj\n<<\n/Type /Sig\n/Filter {}\n/SubFilter /{}\n/ByteRange ",
+      obj,
+      pdf_name(self.filter),
+      self.sub_filter
+    );
+    let byte_range = push_span(&mut dict, BYTE_RANGE_PLACEHOLDER);
+    dict.push_str("\n/Contents ");
+    let contents = push_span(&mut dict, self.contents_placeholder);
+    dict.push('\n');
+
+    let mut date = 0..0;
+    for key in self.order.keys() {
+      match key {
+        SigDictKey::Date => {
+          dict.push_str("/M (");
+          date = push_span(&mut dict, DATE_PLACEHOLDER);
+          dict.push_str(")\n");
+        }
+        SigDictKey::Reason => dict.push_str(&self.text_entry("/Reason", self.reason)?),
+        SigDictKey::ContactInfo => {
+          dict.push_str(&self.text_entry("/ContactInfo", self.contact_info)?)
+        }
+        SigDictKey::Name => dict.push_str(&self.text_entry("/Name", self.name)?),
+        SigDictKey::Location => dict.push_str(&self.text_entry("/Location", self.location)?),
+      }
+    }
+
+    dict.push_str(&format!(
+      "{}/Prop_Build <<\n/Filter <<\n/Name {}\n>>\n/App <<\n/Name {}\n/REx {}\n>>\n>>\n>>\nendobj\n",
+      self.reference_entry,
+      pdf_name(self.filter),
+      pdf_name(self.app_name),
+      pdf_literal_string(self.app_version)
+    ));
+
+    Ok((
+      dict,
+      SigDictSpans {
+        byte_range,
+        contents,
+        date,
+      },
+    ))
+  }
+
+  /// Campos de texto vazios ficam de fora em vez de virar "()"
+  fn text_entry(&self, key: &str, value: &str) -> Result<String> {
+    let value = match self.text_limit {
+      Some(limit) => limit_text(key, value, limit)?,
+      None => value.to_string(),
+    };
+    Ok(optional_entry(
+      key,
+      &value,
+      pdf_text_string(&value, self.text_encoding),
+    ))
+  }
+}
+
+/// Aplica `limit` a `value`; o limite conta caracteres, não os bytes gravados
+fn limit_text(key: &str, value: &str, limit: TextLimit) -> Result<String> {
+  const ELLIPSIS: &str = "...";
+  if value.chars().count() <= limit.max_chars {
+    return Ok(value.to_string());
+  }
+  match limit.overflow {
+    TextOverflow::Error => Err(PdfSignError::SigningError(format!(
+      "{} passa do limite de {} caracteres",
+      key, limit.max_chars
+    ))),
+    // Reticências ASCII: não forçam UTF-16BE num texto que era ASCII
+    TextOverflow::Truncate => {
+      let kept = limit.max_chars.saturating_sub(ELLIPSIS.len());
+      let mut text: String = value.chars().take(kept).collect();
+      text.push_str(&ELLIPSIS[..limit.max_chars - kept]);
+      Ok(text)
+    }
+  }
+}
+
+/// Entrada `key literal` do dicionário de assinatura, omitida quando `value` é vazio
+fn optional_entry(key: &str, value: &str, literal: String) -> String {
+  if value.is_empty() {
+    String::new()
+  } else {
+    format!("{} {}\n", key, literal)
+  }
+}
+
+/// Acrescenta `value` a `text` e devolve o intervalo que ele ocupa
+pub fn push_span(text: &mut String, value: &str) -> Range<usize> {
+  let start = text.len();
+  text.push_str(value);
+  start..text.len()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn builder(order: SigDictOrder) -> SigDictBuilder<'static> {
+    SigDictBuilder {
+      filter: "Adobe.PPKLite",
+      sub_filter: "adbe.pkcs7.detached",
+      contents_placeholder: "<0000>",
+      reason: "Aprovado",
+      contact_info: "suporte@exemplo.com.br",
+      name: "Maria",
+      location: "Brasil",
+      reference_entry: "",
+      app_name: "pdfsigner-rs",
+      app_version: "1.0",
+      text_encoding: TextEncoding::Auto,
+      order,
+      text_limit: None,
+    }
+  }
+
+  #[test]
+  fn test_default_order_matches_node_signpdf_layout() {
+    let (dict, spans) = builder(SigDictOrder::default()).build(7).unwrap();
+    assert_eq!(
+      dict,
+      "7 0 obj\n<<\n/Type /Sig\n/Filter /Adobe.PPKLite\n/SubFilter /adbe.pkcs7.detached\n\
+       /ByteRange [0000000000 0000000000 0000000000 0000000000]                 \n\
+       /Contents <0000>\n/Reason (Aprovado)\n/M (D:00000000000000Z)\n\
+       /ContactInfo (suporte@exemplo.com.br)\n/Name (Maria)\n/Location (Brasil)\n\
+       /Prop_Build <<\n/Filter <<\n/Name /Adobe.PPKLite\n>>\n/App <<\n/Name /pdfsigner-rs\n\
+       /REx (1.0)\n>>\n>>\n>>\nendobj\n"
+    );
+    assert_eq!(&dict[spans.byte_range], BYTE_RANGE_PLACEHOLDER);
+    assert_eq!(&dict[spans.contents], "<0000>");
+    assert_eq!(&dict[spans.date], DATE_PLACEHOLDER);
+  }
+
+  #[test]
+  fn test_date_first_order_and_omitted_entries() {
+    let (dict, spans) = SigDictBuilder {
+      reason: "",
+      reference_entry: "/Reference [<< >>]\n",
+      ..builder(SigDictOrder::DateFirst)
+    }
+    .build(7)
+    .unwrap();
+    assert!(dict.contains(
+      "/Contents <0000>\n/M (D:00000000000000Z)\n/ContactInfo (suporte@exemplo.com.br)\n\
+       /Name (Maria)\n/Location (Brasil)\n/Reference [<< >>]\n/Prop_Build <<"
+    ));
+    assert_eq!(&dict[spans.date], DATE_PLACEHOLDER);
+
+    let (dict, _) = builder(SigDictOrder::DateFirst).build(7).unwrap();
+    assert!(dict.contains("/M (D:00000000000000Z)\n/Reason (Aprovado)\n/ContactInfo"));
+  }
+
+  #[test]
+  fn test_text_limit_truncates_or_fails() {
+    let reason = "Aprovado pela diretoria em reunião";
+    let truncate = TextLimit {
+      max_chars: 11,
+      overflow: TextOverflow::Truncate,
+    };
+    assert_eq!(
+      limit_text("/Reason", reason, truncate).unwrap(),
+      "Aprovado..."
+    );
+    assert_eq!(limit_text("/Reason", "Curto", truncate).unwrap(), "Curto");
+    // Limite menor que as reticências: sobram só os pontos que cabem
+    let tiny = TextLimit {
+      max_chars: 2,
+      ..truncate
+    };
+    assert_eq!(limit_text("/Reason", reason, tiny).unwrap(), "..");
+    // Conta caracteres, não bytes: "São Paulo" tem 9
+    let nine = TextLimit {
+      max_chars: 9,
+      ..truncate
+    };
+    assert_eq!(
+      limit_text("/Location", "São Paulo", nine).unwrap(),
+      "São Paulo"
+    );
+
+    let (dict, _) = SigDictBuilder {
+      reason,
+      text_limit: Some(truncate),
+      ..builder(SigDictOrder::default())
+    }
+    .build(7)
+    .unwrap();
+    assert!(dict.contains("/Reason (Aprovado...)\n"));
+    assert!(dict.contains("/ContactInfo (suporte@...)\n"));
+    assert!(dict.contains("/Name (Maria)\n"));
+
+    let error = SigDictBuilder {
+      reason,
+      text_limit: Some(TextLimit {
+        max_chars: 30,
+        overflow: TextOverflow::Error,
+      }),
+      ..builder(SigDictOrder::default())
+    }
+    .build(7)
+    .unwrap_err();
+    assert!(
+      matches!(error, PdfSignError::SigningError(ref message) if message.contains("/Reason"))
+    );
+  }
+}
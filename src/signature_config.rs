@@ -1,23 +1,509 @@
+use std::time::Duration;
+
 /// Configuração para assinatura PAdES
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct SignatureConfig {
-  /// Motivo da assinatura
-  pub reason: String,
+  /// Motivo da assinatura; `None` usa o padrão do certificado ("Assinatura digital
+  /// conforme ICP-Brasil" para certificados ICP-Brasil, nenhum para os demais) e
+  /// `Some("")` omite /Reason
+  pub reason: Option<String>,
+  /// Vocabulário controlado de motivos: quando informado, `reason` precisa ser um deles
+  pub allowed_reasons: Option<Vec<String>>,
   /// Localização da assinatura
   pub location: String,
   /// Informações de contato
   pub contact_info: String,
-  /// URL do servidor de timestamp (TSA)
+  /// Nome do signatário gravado em /Name; `None` usa o CN do certificado (na falta
+  /// dele, a organização ou o CN do emissor)
+  pub signer_name: Option<String>,
+  /// Instante gravado em /M e no signingTime do CMS; `None` usa o relógio na hora da assinatura
+  pub signing_time: Option<chrono::DateTime<chrono::Utc>>,
+  /// URL do servidor de timestamp (TSA); sem TSA padrão, B-T ou acima exige informar
+  /// uma (ex: [`ITI_TSA_URL`])
   pub tsa_url: Option<String>,
-  /// Validar cadeia ICP-Brasil
+  /// TSAs alternativas, tentadas em ordem quando `tsa_url` (ou a anterior da lista) falha
+  pub tsa_urls: Vec<String>,
+  /// Credenciais exigidas por TSAs comerciais, enviadas só a `tsa_url` (nunca às
+  /// alternativas de `tsa_urls`)
+  pub tsa_auth: Option<TsaAuth>,
+  /// Validar a cadeia do signatário antes de assinar (padrão: `false`)
+  ///
+  /// Ainda não há raízes ICP-Brasil embutidas: com `true`, é preciso informar
+  /// `trust_anchors` ou `trust_anchors_path`, ou a assinatura falha com
+  /// `IcpBrasilValidationError`
   pub validate_icp_brasil: bool,
+  /// Âncoras de confiança adicionais para a validação da cadeia (PEM ou DER)
+  pub trust_anchors: Option<Vec<Vec<u8>>>,
+  /// Arquivo com âncoras de confiança (PEM com um ou mais certificados, ou DER)
+  pub trust_anchors_path: Option<String>,
+  /// Se as âncoras informadas complementam ou substituem as raízes ICP-Brasil embutidas
+  pub trust_anchors_mode: TrustAnchorsMode,
   /// Incluir OCSP (Online Certificate Status Protocol)
   pub include_ocsp: bool,
   /// Incluir CRL (Certificate Revocation List)
   pub include_crl: bool,
-  /// Nível PAdES (B-B, B-T, B-LT, B-LTA)
+  /// Nível PAdES (B-B, B-T, B-LT, B-LTA); o padrão B-B não acessa a rede
   pub pades_level: PadesLevel,
+  /// Se um `pades_level` inalcançável com esta configuração é rebaixado ou gera erro
+  pub pades_level_mode: PadesLevelMode,
+  /// Nome (/T) de um campo de assinatura vazio já existente a ser preenchido,
+  /// em vez de criar o campo Signature1
+  pub fill_existing_field: Option<String>,
+  /// Aparência visível desenhada no campo de `fill_existing_field` (/AP /N do widget)
+  pub appearance: Option<SignatureAppearance>,
+  /// Bloqueio de campos do formulário após a assinatura (/Lock + FieldMDP)
+  pub lock: Option<FieldLock>,
+  /// Assinatura de direitos de uso (/Perms /UR3) em vez da assinatura de aprovação:
+  /// habilita recursos do leitor e não cria campo de formulário
+  pub usage_rights: Option<UsageRights>,
+  /// Handler da assinatura em /Filter e /Prop_Build /Filter (padrão: Adobe.PPKLite),
+  /// gravado como objeto nome
+  pub filter: String,
+  /// Nome da aplicação registrado em /Prop_Build /App
+  pub app_name: String,
+  /// Versão da aplicação registrada em /Prop_Build /App
+  pub app_version: String,
+  /// Caractere que completa o hex do CMS até o fim do placeholder de /Contents
+  pub contents_padding: ContentsPadding,
+  /// Algoritmo de digest do ByteRange e do CMS (os dois são sempre o mesmo)
+  pub digest_algorithm: DigestAlgorithm,
+  /// /DigestMethod das transformações FieldMDP e UR3 em /Reference; `None` usa
+  /// `digest_algorithm`, o mesmo do CMS
+  pub reference_digest_method: Option<DigestAlgorithm>,
+  /// Esquema da assinatura (signatureAlgorithm do SignerInfo)
+  pub signature_scheme: SignatureScheme,
+  /// Formato de /Contents (/SubFilter); `Pkcs7Sha1` é legado e não é PAdES
+  pub sub_filter: SubFilter,
+  /// Referência cruzada da atualização incremental: tabela clássica ou xref stream
+  pub xref_format: XrefFormat,
+  /// Codificação de /Reason, /Location, /ContactInfo e /Name
+  pub text_encoding: TextEncoding,
+  /// Ordem de /Reason, /M, /ContactInfo, /Name e /Location no dicionário de assinatura
+  pub sig_dict_order: SigDictOrder,
+  /// Tamanho máximo de /Reason, /Location, /ContactInfo e /Name; `None` não limita
+  pub text_limit: Option<TextLimit>,
+  /// Implementação usada para montar o CMS da assinatura
+  pub cms_backend: CmsBackend,
+  /// Inclui a cadeia do PFX no CMS; com `false` o CMS leva só o certificado do
+  /// signatário, e as ACs intermediárias ficam a cargo do DSS (B-LT) ou do validador
+  pub include_chain_in_cms: bool,
+  /// Política de novas tentativas para as chamadas de rede (TSA, OCSP e CRL)
+  pub retry_policy: RetryPolicy,
+  /// Tempo limite de cada requisição de saída (TSA, OCSP, CRL e S3)
+  pub network_timeout: Duration,
+  /// Relê a assinatura gravada antes de devolver o PDF (ByteRange, digest e CMS contra
+  /// o certificado embutido) e falha se ela não conferir
+  pub self_verify: bool,
+  /// Preserva a conformidade PDF/A (ex: PDF/A-2b): recusa documentos sem a declaração
+  /// pdfaid no XMP, criptografados ou com campos que ficariam fora da norma
+  pub pdfa_mode: bool,
+  /// Tamanho máximo do PDF de entrada, em bytes; acima dele a assinatura falha com
+  /// `InvalidPdf` antes de ler o documento (`None` = sem limite)
+  pub max_input_bytes: Option<u64>,
+  /// Sem acesso à rede: TSA, OCSP e CRL não são consultados e a assinatura sai em B-B
+  /// (com `PadesLevelMode::Strict`, pedir B-T ou acima falha)
+  pub offline: bool,
+  /// Assina mesmo que o keyUsage do certificado não tenha digitalSignature nem
+  /// nonRepudiation (validadores estritos rejeitam essa assinatura)
+  pub ignore_key_usage: bool,
+}
+
+/// Como as âncoras da configuração se combinam com as raízes embutidas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum TrustAnchorsMode {
+  /// Usa as raízes ICP-Brasil embutidas mais as âncoras informadas
+  Supplement,
+  /// Usa apenas as âncoras informadas
+  Replace,
+}
+
+/// Implementação que monta e assina o CMS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum CmsBackend {
+  /// API CMS do OpenSSL (padrão no build nativo sem `cms-rustcrypto`)
+  #[cfg(feature = "native")]
+  OpenSsl,
+  /// Montagem com os crates RustCrypto e a chave RSA (padrão com `cms-rustcrypto`,
+  /// única opção sem a feature `native`)
+  Rust,
+}
+
+impl Default for CmsBackend {
+  fn default() -> Self {
+    #[cfg(all(feature = "native", not(feature = "cms-rustcrypto")))]
+    return CmsBackend::OpenSsl;
+    #[cfg(any(feature = "cms-rustcrypto", not(feature = "native")))]
+    return CmsBackend::Rust;
+  }
+}
+
+/// TSA pública do ITI, usada pelo Node quando o nível pede carimbo sem `tsaUrl`
+pub const ITI_TSA_URL: &str = "http://timestamp.iti.gov.br/";
+
+/// Tempo limite padrão das requisições de rede
+pub const DEFAULT_NETWORK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Novas tentativas com backoff exponencial e jitter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+  /// Quantidade de novas tentativas após a primeira falha
+  pub max_retries: u32,
+  /// Atraso base, dobrado a cada nova tentativa
+  pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+  fn default() -> Self {
+    Self {
+      max_retries: 3,
+      base_delay: Duration::from_millis(500),
+    }
+  }
+}
+
+/// Padding do /Contents após o CMS em hex
+///
+/// Os leitores ignoram o que vem depois do DER, mas alguns validadores só aceitam um dos dois
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)]
+pub enum ContentsPadding {
+  /// "0", como o node-signpdf
+  #[default]
+  Zero,
+  /// "f"
+  F,
+}
+
+impl ContentsPadding {
+  /// Caractere hex usado no padding
+  pub fn hex_char(&self) -> char {
+    match self {
+      ContentsPadding::Zero => '0',
+      ContentsPadding::F => 'f',
+    }
+  }
+}
+
+/// Algoritmo de digest usado no messageDigest do CMS e no hash do ByteRange
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum DigestAlgorithm {
+  /// SHA-1: apenas para validadores legados
+  Sha1,
+  /// SHA-256: padrão ICP-Brasil atual
+  Sha256,
+  Sha384,
+  Sha512,
+}
+
+impl DigestAlgorithm {
+  /// OID do algoritmo (digestAlgorithm do CMS)
+  pub fn oid(&self) -> const_oid::ObjectIdentifier {
+    use const_oid::db::rfc5912::{ID_SHA_1, ID_SHA_256, ID_SHA_384, ID_SHA_512};
+
+    match self {
+      DigestAlgorithm::Sha1 => ID_SHA_1,
+      DigestAlgorithm::Sha256 => ID_SHA_256,
+      DigestAlgorithm::Sha384 => ID_SHA_384,
+      DigestAlgorithm::Sha512 => ID_SHA_512,
+    }
+  }
+
+  /// Calcula o digest dos dados
+  pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+    use sha2::Digest;
+
+    match self {
+      DigestAlgorithm::Sha1 => sha1::Sha1::digest(data).to_vec(),
+      DigestAlgorithm::Sha256 => sha2::Sha256::digest(data).to_vec(),
+      DigestAlgorithm::Sha384 => sha2::Sha384::digest(data).to_vec(),
+      DigestAlgorithm::Sha512 => sha2::Sha512::digest(data).to_vec(),
+    }
+  }
+
+  /// Nome em /DigestMethod do /Reference (Tabela 253 da ISO 32000-1)
+  pub fn pdf_name(&self) -> &'static str {
+    match self {
+      DigestAlgorithm::Sha1 => "SHA1",
+      DigestAlgorithm::Sha256 => "SHA256",
+      DigestAlgorithm::Sha384 => "SHA384",
+      DigestAlgorithm::Sha512 => "SHA512",
+    }
+  }
+
+  /// Hasher incremental, para calcular o digest de dados lidos em blocos
+  pub fn hasher(&self) -> Box<dyn sha2::digest::DynDigest> {
+    match self {
+      DigestAlgorithm::Sha1 => Box::new(sha1::Sha1::default()),
+      DigestAlgorithm::Sha256 => Box::new(sha2::Sha256::default()),
+      DigestAlgorithm::Sha384 => Box::new(sha2::Sha384::default()),
+      DigestAlgorithm::Sha512 => Box::new(sha2::Sha512::default()),
+    }
+  }
+}
+
+/// Esquema de assinatura do SignerInfo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)]
+pub enum SignatureScheme {
+  /// RSA PKCS#1 v1.5 (rsaEncryption)
+  #[default]
+  Pkcs1v15,
+  /// RSASSA-PSS com MGF1 e salt do tamanho do digest
+  Pss,
+  /// Ed25519 (RFC 8419); exige chave Ed25519, digest SHA-512 e o backend OpenSSL
+  Ed25519,
+  /// ECDSA com o digest configurado (ecdsa-with-SHA*); exige chave EC
+  Ecdsa,
+}
+
+/// Formato da assinatura gravada em /Contents (/SubFilter, ISO 32000-1, 12.8.3.3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)]
+pub enum SubFilter {
+  /// CMS destacado sobre os bytes do ByteRange, base do PAdES
+  #[default]
+  Pkcs7Detached,
+  /// Legado, para validadores antigos: o CMS encapsula o SHA-1 dos bytes do ByteRange.
+  /// Não é PAdES, exige `DigestAlgorithm::Sha1` e é recusado no modo PDF/A
+  Pkcs7Sha1,
+}
+
+impl SubFilter {
+  /// Nome PDF gravado em /SubFilter, sem a barra
+  pub fn name(&self) -> &'static str {
+    match self {
+      SubFilter::Pkcs7Detached => "adbe.pkcs7.detached",
+      SubFilter::Pkcs7Sha1 => "adbe.pkcs7.sha1",
+    }
+  }
+}
+
+/// Como a atualização incremental grava a referência cruzada
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)]
+pub enum XrefFormat {
+  /// Tabela "xref" clássica seguida do trailer
+  #[default]
+  Table,
+  /// Objeto /Type /XRef (PDF 1.5+) com os dados do trailer no dicionário, para
+  /// documentos que já usam xref streams não virarem arquivos híbridos
+  Stream,
+}
+
+/// Codificação das strings de texto do dicionário de assinatura (/Reason, /Location,
+/// /ContactInfo e /Name)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)]
+pub enum TextEncoding {
+  /// String literal quando o texto é ASCII e UTF-16BE em hex quando não é
+  #[default]
+  Auto,
+  /// Sempre string literal, com os bytes UTF-8 do texto
+  Literal,
+  /// Sempre UTF-16BE com BOM, em string hex
+  Utf16Be,
+}
+
+/// Ordem dos campos de texto e de /M no dicionário de assinatura
+///
+/// O Adobe Reader aceita as duas; alguns validadores esperam /M logo depois de /Contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)]
+pub enum SigDictOrder {
+  /// A do node-signpdf: /Reason, /M, /ContactInfo, /Name e /Location
+  #[default]
+  NodeSignpdf,
+  /// /M primeiro, seguido de /Reason, /ContactInfo, /Name e /Location
+  DateFirst,
+}
+
+/// Limite de caracteres dos campos de texto do dicionário de assinatura
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub struct TextLimit {
+  /// Caracteres (não bytes) permitidos em cada campo, já contando as reticências
+  pub max_chars: usize,
+  pub overflow: TextOverflow,
+}
+
+/// O que fazer com um campo de texto maior que `TextLimit::max_chars`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)]
+pub enum TextOverflow {
+  /// Corta o texto e termina com "..."
+  #[default]
+  Truncate,
+  /// Falha a assinatura com `SigningError`
+  Error,
+}
+
+/// Autenticação HTTP na TSA (cabeçalho Authorization)
+#[derive(Clone, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum TsaAuth {
+  /// HTTP Basic com usuário e senha
+  Basic { user: String, password: String },
+  /// Token de portador (`Authorization: Bearer ...`)
+  Bearer(String),
+}
+
+// Senhas e tokens não aparecem em logs da configuração
+impl std::fmt::Debug for TsaAuth {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      TsaAuth::Basic { user, .. } => f
+        .debug_struct("Basic")
+        .field("user", user)
+        .field("password", &"***")
+        .finish(),
+      TsaAuth::Bearer(_) => f.debug_tuple("Bearer").field(&"***").finish(),
+    }
+  }
+}
+
+/// Campos bloqueados pela assinatura (/Action do dicionário /Lock)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum FieldLock {
+  /// Bloqueia todos os campos do formulário
+  All,
+  /// Bloqueia apenas os campos listados
+  Include(Vec<String>),
+  /// Bloqueia todos os campos exceto os listados
+  Exclude(Vec<String>),
+}
+
+impl FieldLock {
+  /// Nome PDF da ação (/All, /Include ou /Exclude)
+  pub fn action(&self) -> &'static str {
+    match self {
+      FieldLock::All => "All",
+      FieldLock::Include(_) => "Include",
+      FieldLock::Exclude(_) => "Exclude",
+    }
+  }
+
+  /// Nomes dos campos afetados (ausente para /All)
+  pub fn fields(&self) -> Option<&[String]> {
+    match self {
+      FieldLock::All => None,
+      FieldLock::Include(fields) | FieldLock::Exclude(fields) => Some(fields),
+    }
+  }
+}
+
+/// Aparência visível da assinatura, composta num único form XObject
+///
+/// Os elementos são desenhados na ordem da lista: cada um fica por cima dos anteriores
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureAppearance {
+  /// Camadas da aparência, da mais ao fundo para a mais à frente
+  pub elements: Vec<AppearanceElement>,
+  /// Formato (strftime) da data que substitui `{date}` nos textos; padrão
+  /// "%d/%m/%Y %H:%M:%S". Só afeta o desenho: /M e o CMS seguem em UTC
+  pub date_format: String,
+  /// Fuso da data desenhada; padrão: horário de Brasília (UTC-3)
+  pub date_offset: chrono::FixedOffset,
+}
+
+impl Default for SignatureAppearance {
+  fn default() -> Self {
+    Self {
+      elements: Vec::new(),
+      date_format: "%d/%m/%Y %H:%M:%S".to_string(),
+      date_offset: chrono::FixedOffset::west_opt(3 * 3600).expect("UTC-3 é um fuso válido"),
+    }
+  }
+}
+
+/// Camada da aparência: uma imagem ou um bloco de texto num retângulo do campo
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppearanceElement {
+  /// Conteúdo desenhado
+  pub content: AppearanceContent,
+  /// [x, y, largura, altura] em pontos, a partir do canto inferior esquerdo do campo;
+  /// o que passa do retângulo é cortado
+  pub rect: [f64; 4],
+}
+
+/// Conteúdo de uma camada da aparência
+#[derive(Clone, PartialEq)]
+pub enum AppearanceContent {
+  /// Imagem JPEG (baseline ou progressiva), esticada até o retângulo
+  Image(Vec<u8>),
+  /// Texto em Helvetica, alinhado ao topo e à esquerda; `\n` quebra a linha e
+  /// `{date}` vira o instante da assinatura no `date_format` da aparência.
+  /// Caracteres fora do Latin-1 saem como `?`
+  Text { text: String, font_size: f64 },
+  /// QR code com a URL informada, no maior quadrado centralizado no retângulo;
+  /// `{sha256}` na URL é trocado pelo SHA-256 (hex) do PDF antes da assinatura
+  QrCode(String),
+}
+
+// Os bytes da imagem não cabem num log: o Debug mostra só o tamanho
+impl std::fmt::Debug for AppearanceContent {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AppearanceContent::Image(data) => f
+        .debug_tuple("Image")
+        .field(&format!("{} bytes", data.len()))
+        .finish(),
+      AppearanceContent::Text { text, font_size } => f
+        .debug_struct("Text")
+        .field("text", text)
+        .field("font_size", font_size)
+        .finish(),
+      AppearanceContent::QrCode(url) => f.debug_tuple("QrCode").field(url).finish(),
+    }
+  }
+}
+
+/// Direitos de uso concedidos pela transformação UR3 (Tabela 255 da ISO 32000-1)
+///
+/// Cada lista guarda os nomes PDF sem a barra (ex: "FillIn"); listas vazias são omitidas
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(dead_code)]
+pub struct UsageRights {
+  /// /Document, ex: "FullSave"
+  pub document: Vec<String>,
+  /// /Form, ex: "FillIn", "Import", "Export", "SubmitStandalone", "SpawnTemplate"
+  pub form: Vec<String>,
+  /// /Annots, ex: "Create", "Delete", "Modify", "Copy", "Import", "Export"
+  pub annots: Vec<String>,
+  /// /Signature, ex: "Modify"
+  pub signature: Vec<String>,
+  /// /EF (arquivos anexos), ex: "Create", "Delete", "Modify", "Import"
+  pub embedded_files: Vec<String>,
+  /// /Msg exibida pelo leitor ao abrir o documento
+  pub message: Option<String>,
+  /// /P true: os direitos valem só com leitores que os reconhecem e restringem os demais
+  pub restrict_others: bool,
+}
+
+impl Default for UsageRights {
+  /// Salvar, preencher e enviar formulários, comentar e assinar
+  fn default() -> Self {
+    let names = |list: &[&str]| list.iter().map(|name| name.to_string()).collect();
+    Self {
+      document: names(&["FullSave"]),
+      form: names(&[
+        "FillIn",
+        "Import",
+        "Export",
+        "SubmitStandalone",
+        "SpawnTemplate",
+      ]),
+      annots: names(&["Create", "Delete", "Modify", "Copy", "Import", "Export"]),
+      signature: names(&["Modify"]),
+      embedded_files: Vec::new(),
+      message: None,
+      restrict_others: false,
+    }
+  }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -34,17 +520,118 @@ pub enum PadesLevel {
   BLTA,
 }
 
+/// O que fazer quando a configuração não alcança o `pades_level` pedido
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)]
+pub enum PadesLevelMode {
+  /// Assina no maior nível alcançável e registra o aviso no `SignReport`
+  #[default]
+  Downgrade,
+  /// Recusa a assinatura
+  Strict,
+}
+
+impl SignatureConfig {
+  /// TSAs na ordem em que são tentadas: `tsa_url` e depois as de `tsa_urls`
+  pub(crate) fn tsa_candidates(&self) -> Vec<&str> {
+    self
+      .tsa_url
+      .iter()
+      .chain(&self.tsa_urls)
+      .map(String::as_str)
+      .collect()
+  }
+
+  /// Maior nível PAdES que a configuração produz, limitado ao `pades_level` pedido
+  ///
+  /// Devolve também o motivo de cada rebaixamento: B-T exige uma TSA, B-LT exige
+  /// OCSP ou CRL, e o carimbo de arquivo do B-LTA fica a cargo de
+  /// `PdfSigner::add_document_timestamp`, chamado depois da assinatura. Com `offline`
+  /// qualquer nível acima de B-B é rebaixado, já que todos dependem da rede
+  pub fn effective_pades_level(&self) -> (PadesLevel, Vec<String>) {
+    let mut level = self.pades_level;
+    let mut reasons = Vec::new();
+
+    if self.offline && level > PadesLevel::BB {
+      reasons.push(format!(
+        "{:?} exige acesso à rede (TSA, OCSP ou CRL), desligado por offline: rebaixado para BB",
+        level
+      ));
+      return (PadesLevel::BB, reasons);
+    }
+
+    if level >= PadesLevel::BT && self.tsa_candidates().is_empty() {
+      reasons.push(format!(
+        "{:?} exige carimbo do tempo, mas tsa_url não foi informada: rebaixado para BB",
+        level
+      ));
+      level = PadesLevel::BB;
+    }
+    if level >= PadesLevel::BLT && !(self.include_ocsp || self.include_crl) {
+      reasons.push(format!(
+        "{:?} exige include_ocsp ou include_crl: rebaixado para BT",
+        level
+      ));
+      level = PadesLevel::BT;
+    }
+    if level == PadesLevel::BLTA {
+      reasons.push(
+        "BLTA exige carimbo de arquivo (DocTimeStamp), que a assinatura não grava: rebaixado \
+         para BLT; chame add_document_timestamp sobre o PDF assinado para chegar ao BLTA"
+          .to_string(),
+      );
+      level = PadesLevel::BLT;
+    }
+
+    (level, reasons)
+  }
+}
+
 impl Default for SignatureConfig {
   fn default() -> Self {
     Self {
-      reason: "Assinatura digital conforme ICP-Brasil".to_string(),
+      reason: None,
       location: "Brasil".to_string(),
       contact_info: String::new(),
-      tsa_url: Some("http://timestamp.iti.gov.br/".to_string()),
-      validate_icp_brasil: true,
+      allowed_reasons: None,
+      signer_name: None,
+      signing_time: None,
+      tsa_url: None,
+      tsa_urls: Vec::new(),
+      tsa_auth: None,
+      validate_icp_brasil: false,
+      trust_anchors: None,
+      trust_anchors_path: None,
+      trust_anchors_mode: TrustAnchorsMode::Supplement,
       include_ocsp: true,
       include_crl: true,
-      pades_level: PadesLevel::BLT,
+      pades_level: PadesLevel::BB,
+      pades_level_mode: PadesLevelMode::Downgrade,
+      fill_existing_field: None,
+      appearance: None,
+      lock: None,
+      usage_rights: None,
+      filter: "Adobe.PPKLite".to_string(),
+      app_name: "pdfsigner-rs".to_string(),
+      app_version: env!("CARGO_PKG_VERSION").to_string(),
+      contents_padding: ContentsPadding::default(),
+      digest_algorithm: DigestAlgorithm::Sha256,
+      reference_digest_method: None,
+      signature_scheme: SignatureScheme::default(),
+      sub_filter: SubFilter::default(),
+      xref_format: XrefFormat::default(),
+      text_encoding: TextEncoding::default(),
+      sig_dict_order: SigDictOrder::default(),
+      text_limit: None,
+      cms_backend: CmsBackend::default(),
+      include_chain_in_cms: true,
+      retry_policy: RetryPolicy::default(),
+      network_timeout: DEFAULT_NETWORK_TIMEOUT,
+      self_verify: false,
+      pdfa_mode: false,
+      max_input_bytes: None,
+      offline: false,
+      ignore_key_usage: false,
     }
   }
 }
@@ -56,8 +643,58 @@ mod tests {
   #[test]
   fn test_signature_config_default() {
     let config = SignatureConfig::default();
-    assert_eq!(config.pades_level, PadesLevel::BLT);
-    assert!(config.validate_icp_brasil);
+    // Rede só quando pedida: B-B e nenhuma TSA
+    assert_eq!(config.pades_level, PadesLevel::BB);
+    assert_eq!(config.tsa_url, None);
+    assert_eq!(config.effective_pades_level(), (PadesLevel::BB, Vec::new()));
+    // Sem raízes embutidas, validar por padrão faria toda assinatura falhar
+    assert!(!config.validate_icp_brasil);
+    assert_eq!(config.filter, "Adobe.PPKLite");
+  }
+
+  #[test]
+  fn test_effective_pades_level() {
+    let config = |pades_level, tsa: bool, include_ocsp, include_crl| SignatureConfig {
+      pades_level,
+      tsa_url: tsa.then(|| "http://tsa.local/".to_string()),
+      include_ocsp,
+      include_crl,
+      ..SignatureConfig::default()
+    };
+
+    // Combinações consistentes não são rebaixadas
+    for (level, tsa, ocsp, crl) in [
+      (PadesLevel::BB, false, false, false),
+      (PadesLevel::BT, true, false, false),
+      (PadesLevel::BLT, true, true, false),
+      (PadesLevel::BLT, true, false, true),
+    ] {
+      assert_eq!(
+        config(level, tsa, ocsp, crl).effective_pades_level(),
+        (level, Vec::new())
+      );
+    }
+
+    // Sem TSA não há carimbo do tempo, qualquer que seja o nível pedido
+    for level in [PadesLevel::BT, PadesLevel::BLT, PadesLevel::BLTA] {
+      let (effective, reasons) = config(level, false, true, true).effective_pades_level();
+      assert_eq!(effective, PadesLevel::BB);
+      assert_eq!(reasons.len(), 1);
+      assert!(reasons[0].contains("tsa_url"));
+    }
+
+    let (effective, reasons) = config(PadesLevel::BLT, true, false, false).effective_pades_level();
+    assert_eq!(effective, PadesLevel::BT);
+    assert!(reasons[0].contains("include_ocsp"));
+
+    let (effective, reasons) = config(PadesLevel::BLTA, true, false, false).effective_pades_level();
+    assert_eq!(effective, PadesLevel::BT);
+    assert_eq!(reasons.len(), 1);
+
+    let (effective, reasons) = config(PadesLevel::BLTA, true, true, true).effective_pades_level();
+    assert_eq!(effective, PadesLevel::BLT);
+    assert!(reasons[0].contains("DocTimeStamp"));
+    assert!(reasons[0].contains("add_document_timestamp"));
   }
 
   #[test]
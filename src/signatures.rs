@@ -0,0 +1,347 @@
+/// Inspeção das assinaturas já existentes em um PDF
+///
+/// Varre os dicionários /Type /Sig na ordem em que aparecem no arquivo, o que
+/// corresponde à ordem das atualizações incrementais
+use chrono::{DateTime, Utc};
+
+use crate::certificate::Certificate;
+use crate::cms_signer;
+use crate::error::{PdfSignError, Result};
+use crate::pdfsigner::{certificate_info, CertificateInfo};
+use crate::signature_config::{DigestAlgorithm, SubFilter};
+use crate::timestamp::signature_timestamp_time;
+use crate::utils::{
+  find_object_section, object_generation, object_number_before, read_dict_string,
+};
+
+/// Resumo de uma assinatura encontrada no documento
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureEntry {
+  /// Número do objeto do dicionário de assinatura
+  pub object_number: usize,
+  /// Nome do campo (/T) que referencia a assinatura
+  pub field_name: Option<String>,
+  /// Nome do signatário (/Name)
+  pub signer_name: Option<String>,
+  /// Motivo (/Reason)
+  pub reason: Option<String>,
+  /// Data da assinatura (/M), no formato de data PDF
+  pub signing_date: Option<String>,
+  /// Formato da assinatura (/SubFilter)
+  pub sub_filter: Option<String>,
+  /// Intervalos cobertos pela assinatura (/ByteRange)
+  pub byte_range: Vec<usize>,
+  /// Instante do carimbo do tempo embutido no CMS (genTime), se a assinatura for B-T
+  pub timestamp_time: Option<DateTime<Utc>>,
+}
+
+/// Conferência de uma assinatura em `check_integrity`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureIntegrity {
+  /// Número do objeto do dicionário de assinatura
+  pub object_number: usize,
+  /// Nome do campo (/T) que referencia a assinatura
+  pub field_name: Option<String>,
+  /// O conteúdo coberto pelo /ByteRange confere com o messageDigest do CMS
+  pub unmodified: bool,
+  /// Por que a conferência falhou ou não pôde ser feita
+  pub error: Option<String>,
+}
+
+/// Resultado de `check_integrity` para o documento inteiro
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityReport {
+  /// Uma entrada por assinatura, na ordem das atualizações incrementais
+  pub signatures: Vec<SignatureIntegrity>,
+  /// Bytes depois do trecho coberto pela última assinatura
+  pub appended_bytes: usize,
+}
+
+/// Posições de "/Type /Sig" (ou "/Type/Sig") que não são /SigRef, /SigFieldLock etc.
+fn find_sig_markers(pdf_data: &[u8]) -> Vec<usize> {
+  let mut positions = Vec::new();
+  for marker in [b"/Type /Sig" as &[u8], b"/Type/Sig"] {
+    let mut pos = 0;
+    while let Some(relative) = pdf_data[pos..]
+      .windows(marker.len())
+      .position(|w| w == marker)
+    {
+      let start = pos + relative;
+      let next = pdf_data.get(start + marker.len());
+      if !next.is_some_and(|c| c.is_ascii_alphanumeric()) {
+        positions.push(start);
+      }
+      pos = start + marker.len();
+    }
+  }
+  positions.sort_unstable();
+  positions
+}
+
+fn read_name(section: &[u8], key: &[u8]) -> Option<String> {
+  let pos = section
+    .windows(key.len() + 1)
+    .position(|w| &w[..key.len()] == key && !w[key.len()].is_ascii_alphanumeric())?;
+  let value = section[pos + key.len()..].trim_ascii_start();
+  let value = value.strip_prefix(b"/")?;
+  let end = value
+    .iter()
+    .position(|c| c.is_ascii_whitespace() || b"/<>[]()".contains(c))
+    .unwrap_or(value.len());
+  Some(String::from_utf8_lossy(&value[..end]).to_string())
+}
+
+fn read_byte_range(section: &[u8]) -> Vec<usize> {
+  let Some(pos) = section
+    .windows(b"/ByteRange".len())
+    .position(|w| w == b"/ByteRange")
+  else {
+    return Vec::new();
+  };
+  let after = &section[pos + b"/ByteRange".len()..];
+  let (Some(open), Some(close)) = (
+    after.iter().position(|&c| c == b'['),
+    after.iter().position(|&c| c == b']'),
+  ) else {
+    return Vec::new();
+  };
+
+  String::from_utf8_lossy(&after[open + 1..close])
+    .split_whitespace()
+    .filter_map(|n| n.parse().ok())
+    .collect()
+}
+
+/// Hex de /Contents, sem os delimitadores: o intervalo entre as duas partes do ByteRange
+fn contents_hex<'a>(pdf_data: &'a [u8], byte_range: &[usize]) -> Option<&'a [u8]> {
+  let &[start, first_len, second_start, _] = byte_range else {
+    return None;
+  };
+  let contents = pdf_data.get(start.checked_add(first_len)?..second_start)?;
+  contents.strip_prefix(b"<")?.strip_suffix(b">")
+}
+
+/// genTime do carimbo do CMS gravado em /Contents
+fn read_timestamp_time(pdf_data: &[u8], byte_range: &[usize]) -> Option<DateTime<Utc>> {
+  signature_timestamp_time(&hex::decode(contents_hex(pdf_data, byte_range)?).ok()?)
+}
+
+/// Nome do campo cujo /V aponta para o dicionário de assinatura
+fn find_field_name(pdf_data: &[u8], sig_obj: usize) -> Option<String> {
+  let reference = format!("/V {} {} R", sig_obj, object_generation(pdf_data, sig_obj));
+  let mut end = pdf_data.len();
+
+  // A revisão mais recente do campo vence
+  while let Some(pos) = pdf_data[..end]
+    .windows(reference.len())
+    .rposition(|w| w == reference.as_bytes())
+  {
+    end = pos;
+    if pdf_data
+      .get(pos + reference.len())
+      .is_some_and(|c| c.is_ascii_digit())
+    {
+      continue;
+    }
+    let field_obj = object_number_before(pdf_data, pos)?;
+    let section = find_object_section(pdf_data, field_obj)?;
+    return read_dict_string(section, b"/T");
+  }
+
+  None
+}
+
+/// Lista as assinaturas do documento na ordem das atualizações incrementais
+pub fn list_signatures(pdf_data: &[u8]) -> Vec<SignatureEntry> {
+  let mut entries: Vec<SignatureEntry> = Vec::new();
+
+  for marker in find_sig_markers(pdf_data) {
+    let Some(object_number) = object_number_before(pdf_data, marker) else {
+      continue;
+    };
+    // Um objeto redefinido em atualização posterior é a mesma assinatura
+    if entries.iter().any(|e| e.object_number == object_number) {
+      continue;
+    }
+    let Some(section) = find_object_section(pdf_data, object_number) else {
+      continue;
+    };
+
+    let byte_range = read_byte_range(section);
+    entries.push(SignatureEntry {
+      object_number,
+      field_name: find_field_name(pdf_data, object_number),
+      signer_name: read_dict_string(section, b"/Name"),
+      reason: read_dict_string(section, b"/Reason"),
+      signing_date: read_dict_string(section, b"/M"),
+      sub_filter: read_name(section, b"/SubFilter"),
+      timestamp_time: read_timestamp_time(pdf_data, &byte_range),
+      byte_range,
+    });
+  }
+
+  entries
+}
+
+/// Indica se o documento já tem ao menos uma assinatura de fato
+///
+/// Procura dicionários /Type /Sig cujo /ByteRange aponte para um /Contents
+/// preenchido. Um campo preparado e ainda não assinado (/FT /Sig sem /V) não tem
+/// esse dicionário, e um placeholder só de zeros não conta como assinatura
+pub fn is_signed(pdf_data: &[u8]) -> bool {
+  find_sig_markers(pdf_data).into_iter().any(|marker| {
+    object_number_before(pdf_data, marker)
+      .and_then(|object_number| find_object_section(pdf_data, object_number))
+      .and_then(|section| {
+        contents_hex(pdf_data, &read_byte_range(section)).map(|hex_value| {
+          hex_value
+            .iter()
+            .any(|&c| c != b'0' && c.is_ascii_hexdigit())
+        })
+      })
+      .unwrap_or(false)
+  })
+}
+
+/// ByteRange de quatro números que começa no início do arquivo e cabe nele
+pub fn checked_byte_range(entry: Option<&SignatureEntry>, len: usize) -> Result<[usize; 4]> {
+  let range: [usize; 4] = entry
+    .and_then(|entry| entry.byte_range.as_slice().try_into().ok())
+    .ok_or_else(|| PdfSignError::SigningError("assinatura sem /ByteRange válido".to_string()))?;
+  let gap_start = range[0].checked_add(range[1]);
+  let end = range[2].checked_add(range[3]);
+  if range[0] != 0 || gap_start.is_none_or(|gap| gap >= range[2]) || end.is_none_or(|end| end > len)
+  {
+    return Err(PdfSignError::SigningError(format!(
+      "/ByteRange {:?} não corresponde ao arquivo de {} bytes",
+      range, len
+    )));
+  }
+  Ok(range)
+}
+
+/// Digest dos bytes cobertos pelo /ByteRange da última assinatura
+///
+/// É o conteúdo que a assinatura de fato protege: o arquivo até ela, menos o
+/// /Contents. Atualizações posteriores (como o /DSS) não mudam esse valor
+pub fn byte_range_digest(pdf_data: &[u8], digest: DigestAlgorithm) -> Result<Vec<u8>> {
+  let range = checked_byte_range(list_signatures(pdf_data).last(), pdf_data.len())?;
+  let mut hasher = digest.hasher();
+  hasher.update(&pdf_data[range[0]..range[0] + range[1]]);
+  hasher.update(&pdf_data[range[2]..range[2] + range[3]]);
+  Ok(hasher.finalize().to_vec())
+}
+
+/// Certificado do signatário da última assinatura, lido do CMS em /Contents
+///
+/// Serve para mostrar quem assinou um documento recebido; a assinatura não é
+/// validada, e `expired` considera o momento da consulta
+pub fn extract_signer_certificate(pdf_data: &[u8]) -> Result<CertificateInfo> {
+  let entry = list_signatures(pdf_data)
+    .pop()
+    .ok_or_else(|| PdfSignError::InvalidPdf("documento sem assinatura".to_string()))?;
+  let range = checked_byte_range(Some(&entry), pdf_data.len())?;
+  let der = cms_signer::signer_certificate_der(&pdf_data[range[0] + range[1]..range[2]])?;
+  Ok(certificate_info(&Certificate::from_der(der)?, Utc::now()))
+}
+
+/// Confere se o documento foi alterado depois de assinado
+///
+/// Para cada assinatura, recalcula o digest do /ByteRange e compara com o
+/// messageDigest do CMS; também mede o que veio depois da última assinatura. Um
+/// /DSS de B-LT ou uma nova assinatura acrescentados ali são legítimos, mas um
+/// intermediário que re-salva o PDF costuma alterar bytes cobertos. A assinatura
+/// do CMS e a cadeia não são validadas
+pub fn check_integrity(pdf_data: &[u8]) -> IntegrityReport {
+  let entries = list_signatures(pdf_data);
+  let appended_bytes = entries
+    .last()
+    .and_then(|entry| checked_byte_range(Some(entry), pdf_data.len()).ok())
+    .map_or(0, |range| pdf_data.len() - range[2] - range[3]);
+  let signatures = entries
+    .into_iter()
+    .map(|entry| {
+      let error = covered_content_matches(pdf_data, &entry).err();
+      SignatureIntegrity {
+        object_number: entry.object_number,
+        field_name: entry.field_name,
+        unmodified: error.is_none(),
+        error: error.map(|error| error.to_string()),
+      }
+    })
+    .collect();
+  IntegrityReport {
+    signatures,
+    appended_bytes,
+  }
+}
+
+/// Compara o digest do conteúdo coberto por `entry` com o messageDigest do CMS
+fn covered_content_matches(pdf_data: &[u8], entry: &SignatureEntry) -> Result<()> {
+  let range = checked_byte_range(Some(entry), pdf_data.len())?;
+  let (digest, message_digest) =
+    cms_signer::signed_message_digest(&pdf_data[range[0] + range[1]..range[2]])?;
+  let mut hasher = digest.hasher();
+  hasher.update(&pdf_data[range[0]..range[0] + range[1]]);
+  hasher.update(&pdf_data[range[2]..range[2] + range[3]]);
+  let mut covered = hasher.finalize().to_vec();
+  // No adbe.pkcs7.sha1 o CMS encapsula o hash do ByteRange, e o messageDigest é o
+  // hash desse conteúdo
+  if entry.sub_filter.as_deref() == Some(SubFilter::Pkcs7Sha1.name()) {
+    covered = digest.digest(&covered);
+  }
+  if covered != message_digest {
+    return Err(PdfSignError::SigningError(
+      "conteúdo coberto pelo /ByteRange não confere com o messageDigest do CMS".to_string(),
+    ));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_list_signatures_reads_fields() {
+    let pdf = b"%PDF-1.4\n5 0 obj\n<<\n/Type /Sig\n/SubFilter /adbe.pkcs7.detached\n/ByteRange [0 10 20 30]\n/Contents <00>\n/Reason (Aprova\\(do\\))\n/M (D:20240101120000Z)\n/Name (Maria)\n/Prop_Build <<\n/App <<\n/Name /pdfsigner-rs\n>>\n>>\n>>\nendobj\n6 0 obj\n<<\n/FT /Sig\n/T (Assinatura1)\n/V 5 0 R\n>>\nendobj\n";
+    let entries = list_signatures(pdf);
+
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry.object_number, 5);
+    assert_eq!(entry.field_name.as_deref(), Some("Assinatura1"));
+    assert_eq!(entry.signer_name.as_deref(), Some("Maria"));
+    assert_eq!(entry.reason.as_deref(), Some("Aprova(do)"));
+    assert_eq!(entry.signing_date.as_deref(), Some("D:20240101120000Z"));
+    assert_eq!(entry.sub_filter.as_deref(), Some("adbe.pkcs7.detached"));
+    assert_eq!(entry.byte_range, vec![0, 10, 20, 30]);
+    assert_eq!(entry.timestamp_time, None);
+  }
+
+  #[test]
+  fn test_is_signed_requires_populated_contents() {
+    let signed = b"%PDF-1.4\n5 0 obj\n<<\n/Type /Sig\n/ByteRange [0 65 71 17]\n/Contents <3082>\n>>\nendobj\n%%EOF\n";
+    assert!(is_signed(signed));
+
+    // Placeholder ainda não preenchido: /Contents só com zeros
+    let placeholder = String::from_utf8_lossy(signed).replace("<3082>", "<0000>");
+    assert!(!is_signed(placeholder.as_bytes()));
+    // ByteRange que não delimita o /Contents
+    let shifted = String::from_utf8_lossy(signed).replace("[0 65 71 17]", "[0 0 0 0]");
+    assert!(!is_signed(shifted.as_bytes()));
+    // Campo /FT /Sig sem /V e PDF sem assinatura
+    assert!(!is_signed(
+      b"%PDF-1.4\n4 0 obj\n<< /FT /Sig /T (Cliente) /Rect [0 0 10 10] >>\nendobj\n"
+    ));
+    assert!(!is_signed(
+      b"%PDF-1.4\n1 0 obj\n<< /Type /Catalog >>\nendobj\n"
+    ));
+  }
+
+  #[test]
+  fn test_list_signatures_ignores_sig_ref_and_lock() {
+    let pdf = b"%PDF-1.4\n3 0 obj\n<<\n/Type /SigFieldLock\n/Action /All\n>>\nendobj\n4 0 obj\n<<\n/Type /SigRef\n>>\nendobj\n";
+    assert!(list_signatures(pdf).is_empty());
+  }
+}
@@ -0,0 +1,688 @@
+/// Utilitários de teste: servidor HTTP local e TSA simulada
+///
+/// Os testes não podem depender de timestamp.iti.gov.br nem de responders
+/// OCSP reais, então as chamadas de rede apontam para um servidor em 127.0.0.1
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::raw::{c_int, c_uint, c_void};
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use const_oid::db::rfc5912::SHA_256_WITH_RSA_ENCRYPTION;
+use const_oid::ObjectIdentifier;
+use der::asn1::{Any, BitString, GeneralizedTime, Ia5String, Int, UtcTime};
+use der::{Decode, Encode};
+use foreign_types::ForeignType;
+use openssl::asn1::{Asn1Integer, Asn1Object, Asn1OctetString, Asn1Time};
+use openssl::bn::BigNum;
+use openssl::cms::{CMSOptions, CmsContentInfo};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::{PKey, PKeyRef, Private};
+use openssl::provider::Provider;
+use openssl::rsa::Rsa;
+use openssl::sign::Signer;
+use openssl::stack::Stack;
+use openssl::x509::extension::{
+  BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+};
+use openssl::x509::{X509Builder, X509Extension, X509NameBuilder, X509Ref, X509};
+use x509_cert::crl::{CertificateList, TbsCertList};
+use x509_cert::ext::pkix::crl::dp::DistributionPoint;
+use x509_cert::ext::pkix::name::{DistributionPointName, GeneralName};
+use x509_cert::ext::pkix::CrlDistributionPoints;
+use x509_cert::name::Name;
+use x509_cert::spki::AlgorithmIdentifierOwned;
+use x509_cert::time::Time;
+
+use crate::timestamp::{PkiStatusInfo, TimeStampReq, TimeStampResp, TstInfo};
+
+extern "C" {
+  fn CMS_set1_eContentType(
+    cms: *mut openssl_sys::CMS_ContentInfo,
+    oid: *const openssl_sys::ASN1_OBJECT,
+  ) -> c_int;
+
+  fn CMS_add1_signer(
+    cms: *mut openssl_sys::CMS_ContentInfo,
+    signer: *mut openssl_sys::X509,
+    pk: *mut openssl_sys::EVP_PKEY,
+    md: *const openssl_sys::EVP_MD,
+    flags: c_uint,
+  ) -> *mut c_void;
+
+  fn CMS_final(
+    cms: *mut openssl_sys::CMS_ContentInfo,
+    data: *mut openssl_sys::BIO,
+    dcont: *mut openssl_sys::BIO,
+    flags: c_uint,
+  ) -> c_int;
+}
+
+/// Requisição recebida pelo servidor simulado
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+  pub method: String,
+  pub path: String,
+  pub headers: Vec<(String, String)>,
+  pub body: Vec<u8>,
+}
+
+impl MockRequest {
+  /// Valor do cabeçalho, sem diferenciar maiúsculas no nome
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self
+      .headers
+      .iter()
+      .find(|(header, _)| header.eq_ignore_ascii_case(name))
+      .map(|(_, value)| value.as_str())
+  }
+}
+
+/// Resposta roteirizada do servidor simulado
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+  pub status: u16,
+  pub body: Vec<u8>,
+  pub delay: Option<Duration>,
+}
+
+impl MockResponse {
+  pub fn ok(body: Vec<u8>) -> Self {
+    Self {
+      status: 200,
+      body,
+      delay: None,
+    }
+  }
+
+  pub fn status(status: u16) -> Self {
+    Self {
+      status,
+      body: Vec::new(),
+      delay: None,
+    }
+  }
+
+  pub fn delayed(mut self, delay: Duration) -> Self {
+    self.delay = Some(delay);
+    self
+  }
+}
+
+type Handler = dyn Fn(usize, &MockRequest) -> MockResponse + Send + Sync;
+
+/// Servidor HTTP/1.1 mínimo que responde conforme o handler informado
+pub struct MockServer {
+  address: String,
+  requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+  /// Inicia o servidor; o handler recebe o índice da requisição e a requisição
+  pub fn start<F>(handler: F) -> Self
+  where
+    F: Fn(usize, &MockRequest) -> MockResponse + Send + Sync + 'static,
+  {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let counter = Arc::new(AtomicUsize::new(0));
+    let handler: Arc<Handler> = Arc::new(handler);
+
+    let recorded = Arc::clone(&requests);
+    thread::spawn(move || {
+      for stream in listener.incoming() {
+        let Ok(stream) = stream else { break };
+        let handler = Arc::clone(&handler);
+        let recorded = Arc::clone(&recorded);
+        let counter = Arc::clone(&counter);
+        thread::spawn(move || serve(stream, handler, recorded, counter));
+      }
+    });
+
+    Self { address, requests }
+  }
+
+  pub fn url(&self) -> String {
+    format!("{}/", self.address)
+  }
+
+  pub fn request_count(&self) -> usize {
+    self.requests.lock().unwrap().len()
+  }
+
+  pub fn requests(&self) -> Vec<MockRequest> {
+    self.requests.lock().unwrap().clone()
+  }
+}
+
+fn serve(
+  mut stream: TcpStream,
+  handler: Arc<Handler>,
+  recorded: Arc<Mutex<Vec<MockRequest>>>,
+  counter: Arc<AtomicUsize>,
+) {
+  let Some(request) = read_request(&mut stream) else {
+    return;
+  };
+  let index = counter.fetch_add(1, Ordering::SeqCst);
+  recorded.lock().unwrap().push(request.clone());
+
+  let response = handler(index, &request);
+  if let Some(delay) = response.delay {
+    thread::sleep(delay);
+  }
+
+  let head = format!(
+    "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+    response.status,
+    response.body.len()
+  );
+  let _ = stream.write_all(head.as_bytes());
+  let _ = stream.write_all(&response.body);
+  let _ = stream.flush();
+}
+
+fn read_request(stream: &mut TcpStream) -> Option<MockRequest> {
+  let mut buffer = Vec::new();
+  let mut chunk = [0u8; 4096];
+
+  let header_end = loop {
+    let read = stream.read(&mut chunk).ok()?;
+    if read == 0 {
+      return None;
+    }
+    buffer.extend_from_slice(&chunk[..read]);
+    if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+      break pos + 4;
+    }
+  };
+
+  let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+  let mut lines = head.lines();
+  let mut request_line = lines.next()?.split_whitespace();
+  let method = request_line.next()?.to_string();
+  let path = request_line.next()?.to_string();
+  let headers: Vec<(String, String)> = lines
+    .filter_map(|line| line.split_once(':'))
+    .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+    .collect();
+  let content_length = headers
+    .iter()
+    .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+    .and_then(|(_, value)| value.parse::<usize>().ok())
+    .unwrap_or(0);
+
+  while buffer.len() < header_end + content_length {
+    let read = stream.read(&mut chunk).ok()?;
+    if read == 0 {
+      break;
+    }
+    buffer.extend_from_slice(&chunk[..read]);
+  }
+
+  Some(MockRequest {
+    method,
+    path,
+    headers,
+    body: buffer[header_end..].to_vec(),
+  })
+}
+
+/// Mantém o provider padrão do OpenSSL carregado até o fim do processo de testes
+///
+/// Depois que o assinador carrega o provider legado explicitamente, o padrão deixa de
+/// ser carregado sozinho; gerar chaves antes disso em outra thread não pode depender dele
+pub fn keep_default_provider() {
+  static LOADED: Once = Once::new();
+  LOADED.call_once(|| std::mem::forget(Provider::load(None, "default").unwrap()));
+}
+
+/// Gera chave RSA e certificado; sem emissor o certificado é autoassinado
+///
+/// `ca` marca o certificado como autoridade (BasicConstraints CA:TRUE)
+pub fn issue_identity(
+  common_name: &str,
+  issuer: Option<(&PKeyRef<Private>, &X509Ref)>,
+  ca: bool,
+) -> (PKey<Private>, X509) {
+  build_identity(&[("CN", common_name)], issuer, ca, false, None)
+}
+
+/// Como `issue_identity`, com o DN do titular montado a partir de (campo, valor)
+pub fn identity_with_subject(
+  subject: &[(&str, &str)],
+  issuer: Option<(&PKeyRef<Private>, &X509Ref)>,
+) -> (PKey<Private>, X509) {
+  build_identity(subject, issuer, false, false, None)
+}
+
+/// Gera um par chave RSA / certificado autoassinado com uso de carimbo do tempo
+pub fn self_signed_identity(common_name: &str) -> (PKey<Private>, X509) {
+  build_identity(&[("CN", common_name)], None, false, true, None)
+}
+
+/// Como `issue_identity` (sem ser AC), com um ponto de distribuição de CRL em `crl_url`
+pub fn identity_with_crl(
+  common_name: &str,
+  issuer: (&PKeyRef<Private>, &X509Ref),
+  crl_url: &str,
+) -> (PKey<Private>, X509) {
+  build_identity(
+    &[("CN", common_name)],
+    Some(issuer),
+    false,
+    false,
+    Some(crl_url),
+  )
+}
+
+fn build_identity(
+  subject: &[(&str, &str)],
+  issuer: Option<(&PKeyRef<Private>, &X509Ref)>,
+  ca: bool,
+  time_stamping: bool,
+  crl_url: Option<&str>,
+) -> (PKey<Private>, X509) {
+  keep_default_provider();
+  let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+  let mut name = X509NameBuilder::new().unwrap();
+  for (field, value) in subject {
+    name.append_entry_by_text(field, value).unwrap();
+  }
+  let name = name.build();
+
+  let mut builder = X509Builder::new().unwrap();
+  builder.set_version(2).unwrap();
+  let serial =
+    Asn1Integer::from_bn(&BigNum::from_u32(SERIAL.fetch_add(1, Ordering::SeqCst) as u32).unwrap())
+      .unwrap();
+  builder.set_serial_number(&serial).unwrap();
+  builder.set_subject_name(&name).unwrap();
+  match issuer {
+    Some((_, issuer_cert)) => builder.set_issuer_name(issuer_cert.subject_name()).unwrap(),
+    None => builder.set_issuer_name(&name).unwrap(),
+  }
+  builder.set_pubkey(&pkey).unwrap();
+  builder
+    .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+    .unwrap();
+  builder
+    .set_not_after(&Asn1Time::days_from_now(365).unwrap())
+    .unwrap();
+
+  let mut constraints = BasicConstraints::new();
+  if ca {
+    constraints.critical().ca();
+  }
+  builder
+    .append_extension(constraints.build().unwrap())
+    .unwrap();
+  if ca {
+    builder
+      .append_extension(
+        KeyUsage::new()
+          .critical()
+          .key_cert_sign()
+          .crl_sign()
+          .build()
+          .unwrap(),
+      )
+      .unwrap();
+  }
+  if time_stamping {
+    builder
+      .append_extension(
+        ExtendedKeyUsage::new()
+          .critical()
+          .time_stamping()
+          .build()
+          .unwrap(),
+      )
+      .unwrap();
+  }
+
+  if let Some(url) = crl_url {
+    let point = DistributionPoint {
+      distribution_point: Some(DistributionPointName::FullName(vec![
+        GeneralName::UniformResourceIdentifier(Ia5String::new(url).unwrap()),
+      ])),
+      reasons: None,
+      crl_issuer: None,
+    };
+    let contents = CrlDistributionPoints(vec![point]).to_der().unwrap();
+    builder
+      .append_extension(
+        X509Extension::new_from_der(
+          &Asn1Object::from_str("2.5.29.31").unwrap(),
+          false,
+          &Asn1OctetString::new_from_bytes(&contents).unwrap(),
+        )
+        .unwrap(),
+      )
+      .unwrap();
+  }
+
+  let signing_key = issuer.map(|(key, _)| key).unwrap_or(&pkey);
+  builder.sign(signing_key, MessageDigest::sha256()).unwrap();
+
+  (pkey, builder.build())
+}
+
+/// Gera um par chave Ed25519 / certificado autoassinado
+pub fn ed25519_identity(common_name: &str) -> (PKey<Private>, X509) {
+  keep_default_provider();
+  let pkey = PKey::generate_ed25519().unwrap();
+  // Ed25519 não usa digest separado na assinatura do certificado
+  let cert = self_signed_with(&pkey, common_name, MessageDigest::null());
+  (pkey, cert)
+}
+
+/// Gera um par chave EC P-256 / certificado autoassinado
+pub fn ec_identity(common_name: &str) -> (PKey<Private>, X509) {
+  keep_default_provider();
+  let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+  let pkey = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+  let cert = self_signed_with(&pkey, common_name, MessageDigest::sha256());
+  (pkey, cert)
+}
+
+/// Gera um par chave RSA / certificado autoassinado cujo keyUsage só tem keyEncipherment
+pub fn key_encipherment_identity(common_name: &str) -> (PKey<Private>, X509) {
+  keep_default_provider();
+  let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+  let mut builder = self_signed_builder(&pkey, common_name);
+  builder
+    .append_extension(
+      KeyUsage::new()
+        .critical()
+        .key_encipherment()
+        .build()
+        .unwrap(),
+    )
+    .unwrap();
+  builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+  (pkey, builder.build())
+}
+
+fn self_signed_with(pkey: &PKeyRef<Private>, common_name: &str, digest: MessageDigest) -> X509 {
+  let mut builder = self_signed_builder(pkey, common_name);
+  builder.sign(pkey, digest).unwrap();
+  builder.build()
+}
+
+/// Certificado autoassinado ainda sem extensões nem assinatura
+fn self_signed_builder(pkey: &PKeyRef<Private>, common_name: &str) -> X509Builder {
+  let mut name = X509NameBuilder::new().unwrap();
+  name.append_entry_by_text("CN", common_name).unwrap();
+  let name = name.build();
+
+  let mut builder = X509Builder::new().unwrap();
+  builder.set_version(2).unwrap();
+  let serial =
+    Asn1Integer::from_bn(&BigNum::from_u32(SERIAL.fetch_add(1, Ordering::SeqCst) as u32).unwrap())
+      .unwrap();
+  builder.set_serial_number(&serial).unwrap();
+  builder.set_subject_name(&name).unwrap();
+  builder.set_issuer_name(&name).unwrap();
+  builder.set_pubkey(pkey).unwrap();
+  builder
+    .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+    .unwrap();
+  builder
+    .set_not_after(&Asn1Time::days_from_now(365).unwrap())
+    .unwrap();
+  builder
+}
+
+/// Certificado de usuário final no formato ICP-Brasil, emitido por `issuer`
+///
+/// DN com C=BR e O=ICP-Brasil, uso de chave de assinatura e os `other_names`
+/// (OID, valor) gravados como OCTET STRING no subjectAltName
+pub fn icp_brasil_identity(
+  common_name: &str,
+  other_names: &[(&str, &str)],
+  issuer: (&PKeyRef<Private>, &X509Ref),
+) -> (PKey<Private>, X509) {
+  keep_default_provider();
+  let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+  let mut name = X509NameBuilder::new().unwrap();
+  name.append_entry_by_text("C", "BR").unwrap();
+  name.append_entry_by_text("O", "ICP-Brasil").unwrap();
+  name.append_entry_by_text("CN", common_name).unwrap();
+  let name = name.build();
+
+  let mut builder = X509Builder::new().unwrap();
+  builder.set_version(2).unwrap();
+  let serial =
+    Asn1Integer::from_bn(&BigNum::from_u32(SERIAL.fetch_add(1, Ordering::SeqCst) as u32).unwrap())
+      .unwrap();
+  builder.set_serial_number(&serial).unwrap();
+  builder.set_subject_name(&name).unwrap();
+  builder.set_issuer_name(issuer.1.subject_name()).unwrap();
+  builder.set_pubkey(&pkey).unwrap();
+  builder
+    .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+    .unwrap();
+  builder
+    .set_not_after(&Asn1Time::days_from_now(365).unwrap())
+    .unwrap();
+  builder
+    .append_extension(
+      KeyUsage::new()
+        .critical()
+        .digital_signature()
+        .non_repudiation()
+        .key_encipherment()
+        .build()
+        .unwrap(),
+    )
+    .unwrap();
+  builder
+    .append_extension(
+      ExtendedKeyUsage::new()
+        .client_auth()
+        .email_protection()
+        .build()
+        .unwrap(),
+    )
+    .unwrap();
+
+  let mut san = SubjectAlternativeName::new();
+  san.email("fulano@example.com");
+  for (oid, value) in other_names {
+    let value = der::asn1::OctetString::new(value.as_bytes())
+      .unwrap()
+      .to_der()
+      .unwrap();
+    san.other_name2(Asn1Object::from_str(oid).unwrap(), &value);
+  }
+  let san = san
+    .build(&builder.x509v3_context(Some(issuer.1), None))
+    .unwrap();
+  builder.append_extension(san).unwrap();
+
+  builder.sign(issuer.0, MessageDigest::sha256()).unwrap();
+  (pkey, builder.build())
+}
+
+static SERIAL: AtomicUsize = AtomicUsize::new(1);
+
+/// CRL vazia (sem certificados revogados) assinada pela AC, válida desde agora
+///
+/// `next_update` é o prazo até a próxima CRL; `None` omite o nextUpdate
+pub fn issue_crl(
+  ca_key: &PKeyRef<Private>,
+  ca_cert: &X509Ref,
+  next_update: Option<Duration>,
+) -> Vec<u8> {
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+  let utc = |at: Duration| Time::UtcTime(UtcTime::from_unix_duration(at).unwrap());
+  let algorithm = AlgorithmIdentifierOwned {
+    oid: SHA_256_WITH_RSA_ENCRYPTION,
+    parameters: Some(Any::null()),
+  };
+  let tbs_cert_list = TbsCertList {
+    version: x509_cert::Version::V2,
+    signature: algorithm.clone(),
+    issuer: Name::from_der(&ca_cert.subject_name().to_der().unwrap()).unwrap(),
+    this_update: utc(Duration::from_secs(now.as_secs())),
+    next_update: next_update.map(|validity| utc(Duration::from_secs((now + validity).as_secs()))),
+    revoked_certificates: None,
+    crl_extensions: None,
+  };
+
+  let mut signer = Signer::new(MessageDigest::sha256(), ca_key).unwrap();
+  signer.update(&tbs_cert_list.to_der().unwrap()).unwrap();
+  CertificateList {
+    tbs_cert_list,
+    signature_algorithm: algorithm,
+    signature: BitString::from_bytes(&signer.sign_to_vec().unwrap()).unwrap(),
+  }
+  .to_der()
+  .unwrap()
+}
+
+/// Empacota chave, certificado e cadeia em um PFX protegido por senha
+pub fn pkcs12_bytes(
+  pkey: &PKeyRef<Private>,
+  cert: &X509Ref,
+  chain: &[X509],
+  password: &str,
+) -> Vec<u8> {
+  keep_default_provider();
+  let mut ca = Stack::new().unwrap();
+  for intermediate in chain {
+    ca.push(intermediate.clone()).unwrap();
+  }
+
+  let mut builder = Pkcs12::builder();
+  builder.name("teste").pkey(pkey).cert(cert).ca(ca);
+  builder.build2(password).unwrap().to_der().unwrap()
+}
+
+/// PFX com várias identidades (chave + certificado), na ordem dada
+///
+/// O builder do OpenSSL só aceita uma chave: cada identidade vira um PFX próprio e
+/// os SafeContents são juntados num AuthenticatedSafe com MAC novo
+pub fn multi_identity_pkcs12(
+  identities: &[(&PKeyRef<Private>, &X509Ref)],
+  password: &str,
+) -> Vec<u8> {
+  let mut contents = Vec::new();
+  for (pkey, cert) in identities {
+    let pfx = p12::PFX::parse(&pkcs12_bytes(pkey, cert, &[], password)).unwrap();
+    let p12::ContentInfo::Data(auth_safe) = pfx.auth_safe else {
+      panic!("AuthenticatedSafe inesperado");
+    };
+    contents.extend(Vec::<cms::content_info::ContentInfo>::from_der(&auth_safe).unwrap());
+  }
+  let auth_safe = contents.to_der().unwrap();
+
+  // Senha em BMPString com o terminador nulo, como o PKCS#12 deriva a chave do MAC
+  let mut bmp_password: Vec<u8> = password.encode_utf16().flat_map(u16::to_be_bytes).collect();
+  bmp_password.extend([0, 0]);
+  p12::PFX {
+    version: 3,
+    mac_data: Some(p12::MacData::new(&auth_safe, &bmp_password)),
+    auth_safe: p12::ContentInfo::Data(auth_safe),
+  }
+  .to_der()
+}
+
+/// TSA simulada que emite tokens RFC 3161 reais assinados com uma chave de teste
+pub struct MockTsa {
+  pkey: PKey<Private>,
+  cert: X509,
+}
+
+impl MockTsa {
+  pub fn new() -> Self {
+    let (pkey, cert) = self_signed_identity("TSA de Teste");
+    Self { pkey, cert }
+  }
+
+  /// Processa um TimeStampReq DER e devolve o TimeStampResp DER
+  pub fn respond(&self, request_der: &[u8]) -> Vec<u8> {
+    self.respond_with(request_der, |_| {})
+  }
+
+  /// Como `respond`, mas deixa o teste alterar o TSTInfo antes de assinar
+  pub fn respond_with(&self, request_der: &[u8], edit: impl FnOnce(&mut TstInfo)) -> Vec<u8> {
+    let request = TimeStampReq::from_der(request_der).unwrap();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    let mut tst_info = TstInfo {
+      version: 1,
+      policy: ObjectIdentifier::new_unwrap("2.16.76.1.7.1.1.1"),
+      message_imprint: request.message_imprint,
+      // Bit 62 ligado: serial positivo sem zeros à esquerda, como o DER exige
+      serial_number: Int::new(&((now.as_nanos() as u64 >> 2) | 1 << 62).to_be_bytes()).unwrap(),
+      gen_time: Any::encode_from(&GeneralizedTime::from_unix_duration(now).unwrap()).unwrap(),
+      accuracy: None,
+      ordering: false,
+      nonce: request.nonce,
+      tsa: None,
+      extensions: None,
+    };
+    edit(&mut tst_info);
+
+    let token = self.sign_tst_info(&tst_info.to_der().unwrap());
+    TimeStampResp {
+      status: PkiStatusInfo {
+        status: 0,
+        status_string: None,
+        fail_info: None,
+      },
+      time_stamp_token: Some(Any::from_der(&token).unwrap()),
+    }
+    .to_der()
+    .unwrap()
+  }
+
+  fn sign_tst_info(&self, tst_info: &[u8]) -> Vec<u8> {
+    let flags = CMSOptions::BINARY | CMSOptions::NOSMIMECAP | CMSOptions::PARTIAL;
+    let content_type = Asn1Object::from_str("1.2.840.113549.1.9.16.1.4").unwrap();
+    let chain = Stack::<X509>::new().unwrap();
+
+    unsafe {
+      let cms = CmsContentInfo::from_ptr(openssl_sys::CMS_sign(
+        ptr::null_mut(),
+        ptr::null_mut(),
+        chain.as_ptr(),
+        ptr::null_mut(),
+        flags.bits(),
+      ));
+      assert_eq!(
+        CMS_set1_eContentType(cms.as_ptr(), content_type.as_ptr()),
+        1
+      );
+      assert!(!CMS_add1_signer(
+        cms.as_ptr(),
+        self.cert.as_ptr(),
+        self.pkey.as_ptr(),
+        MessageDigest::sha256().as_ptr(),
+        flags.bits(),
+      )
+      .is_null());
+
+      let bio =
+        openssl_sys::BIO_new_mem_buf(tst_info.as_ptr() as *const c_void, tst_info.len() as c_int);
+      assert_eq!(
+        CMS_final(cms.as_ptr(), bio, ptr::null_mut(), flags.bits()),
+        1
+      );
+      openssl_sys::BIO_free_all(bio);
+
+      cms.to_der().unwrap()
+    }
+  }
+}
@@ -0,0 +1,479 @@
+/// Carimbo do tempo RFC 3161 para assinaturas PAdES-B-T
+///
+/// O token é solicitado sobre o valor da assinatura do SignerInfo e anexado
+/// como atributo não assinado (id-aa-signatureTimeStampToken). A leitura do
+/// genTime não depende da rede e vale também no build sem `native`
+use chrono::{DateTime, NaiveDateTime, Utc};
+use cms::content_info::ContentInfo;
+use cms::signed_data::{SignedData, SignerInfos};
+use const_oid::ObjectIdentifier;
+use der::asn1::{Any, BitString, Int, OctetString, SetOfVec};
+use der::{Decode, Encode, Sequence};
+use rand::Rng;
+use x509_cert::attr::Attribute;
+use x509_cert::ext::Extension;
+use x509_cert::spki::AlgorithmIdentifierOwned;
+
+use crate::error::{PdfSignError, Result};
+#[cfg(feature = "native")]
+use crate::network::{http_client, post_with_retry};
+use crate::signature_config::DigestAlgorithm;
+#[cfg(feature = "native")]
+use crate::signature_config::SignatureConfig;
+
+/// id-ct-TSTInfo (RFC 3161)
+pub const ID_CT_TST_INFO: ObjectIdentifier =
+  ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.1.4");
+
+/// id-aa-signatureTimeStampToken (RFC 3161, apêndice A)
+pub const ID_AA_SIGNATURE_TIME_STAMP_TOKEN: ObjectIdentifier =
+  ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.2.14");
+
+/// MessageImprint ::= SEQUENCE { hashAlgorithm, hashedMessage }
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct MessageImprint {
+  pub hash_algorithm: AlgorithmIdentifierOwned,
+  pub hashed_message: OctetString,
+}
+
+/// TimeStampReq (RFC 3161, seção 2.4.1)
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct TimeStampReq {
+  pub version: u8,
+  pub message_imprint: MessageImprint,
+  #[asn1(optional = "true")]
+  pub req_policy: Option<ObjectIdentifier>,
+  #[asn1(optional = "true")]
+  pub nonce: Option<Int>,
+  #[asn1(default = "Default::default")]
+  pub cert_req: bool,
+}
+
+/// PKIStatusInfo (RFC 3161, seção 2.4.2)
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct PkiStatusInfo {
+  pub status: u8,
+  #[asn1(optional = "true")]
+  pub status_string: Option<Vec<String>>,
+  #[asn1(optional = "true")]
+  pub fail_info: Option<BitString>,
+}
+
+/// TimeStampResp (RFC 3161, seção 2.4.2)
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct TimeStampResp {
+  pub status: PkiStatusInfo,
+  #[asn1(optional = "true")]
+  pub time_stamp_token: Option<Any>,
+}
+
+/// Accuracy (RFC 3161, seção 2.4.2)
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct Accuracy {
+  #[asn1(optional = "true")]
+  pub seconds: Option<u32>,
+  #[asn1(context_specific = "0", tag_mode = "IMPLICIT", optional = "true")]
+  pub millis: Option<u16>,
+  #[asn1(context_specific = "1", tag_mode = "IMPLICIT", optional = "true")]
+  pub micros: Option<u16>,
+}
+
+/// TSTInfo (RFC 3161, seção 2.4.2)
+///
+/// genTime fica como `Any` porque muitas TSAs incluem frações de segundo,
+/// que o `GeneralizedTime` do crate der não aceita
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct TstInfo {
+  pub version: u8,
+  pub policy: ObjectIdentifier,
+  pub message_imprint: MessageImprint,
+  pub serial_number: Int,
+  pub gen_time: Any,
+  #[asn1(optional = "true")]
+  pub accuracy: Option<Accuracy>,
+  #[asn1(default = "Default::default")]
+  pub ordering: bool,
+  #[asn1(optional = "true")]
+  pub nonce: Option<Int>,
+  #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+  pub tsa: Option<Any>,
+  #[asn1(context_specific = "1", tag_mode = "IMPLICIT", optional = "true")]
+  pub extensions: Option<Vec<Extension>>,
+}
+
+fn timestamp_error(message: impl Into<String>) -> PdfSignError {
+  PdfSignError::TimestampError(message.into())
+}
+
+fn decode_signed_data(cms_der: &[u8]) -> Result<SignedData> {
+  let content_info = ContentInfo::from_der(cms_der)
+    .map_err(|e| PdfSignError::DecodingError(format!("Erro ao decodificar CMS: {}", e)))?;
+  content_info
+    .content
+    .decode_as::<SignedData>()
+    .map_err(|e| PdfSignError::DecodingError(format!("Erro ao decodificar SignedData: {}", e)))
+}
+
+/// Nonce aleatório de 63 bits, positivo e com codificação DER mínima
+fn random_nonce() -> Result<Int> {
+  let mut bytes: [u8; 8] = rand::thread_rng().gen();
+  bytes[0] = (bytes[0] & 0x7f) | 0x40;
+  Int::new(&bytes).map_err(|e| timestamp_error(format!("Erro ao gerar nonce: {}", e)))
+}
+
+/// Monta a requisição para o hash informado, com um nonce aleatório
+pub fn build_request(digest: DigestAlgorithm, hashed_message: &[u8]) -> Result<TimeStampReq> {
+  Ok(TimeStampReq {
+    version: 1,
+    message_imprint: MessageImprint {
+      hash_algorithm: AlgorithmIdentifierOwned {
+        oid: digest.oid(),
+        parameters: None,
+      },
+      hashed_message: OctetString::new(hashed_message)
+        .map_err(|e| timestamp_error(format!("Erro ao montar requisição: {}", e)))?,
+    },
+    req_policy: None,
+    nonce: Some(random_nonce()?),
+    cert_req: true,
+  })
+}
+
+/// Extrai o token de uma resposta da TSA, conferindo o status
+///
+/// O TSTInfo precisa ecoar o nonce e o messageImprint da requisição; um token
+/// emitido para outra requisição (replay ou interceptação) é rejeitado
+pub fn parse_response(response_der: &[u8], request: &TimeStampReq) -> Result<Vec<u8>> {
+  let response = TimeStampResp::from_der(response_der)
+    .map_err(|e| timestamp_error(format!("Resposta da TSA inválida: {}", e)))?;
+
+  // 0 = granted, 1 = grantedWithMods; demais valores são rejeição
+  if response.status.status > 1 {
+    let detail = response
+      .status
+      .status_string
+      .map(|texts| texts.join("; "))
+      .unwrap_or_default();
+    return Err(timestamp_error(format!(
+      "TSA recusou a requisição (status {}) {}",
+      response.status.status, detail
+    )));
+  }
+
+  let token = response
+    .time_stamp_token
+    .ok_or_else(|| timestamp_error("Resposta da TSA sem token"))?
+    .to_der()
+    .map_err(|e| timestamp_error(format!("Erro ao codificar token: {}", e)))?;
+
+  let tst_info = decode_tst_info(&token)?;
+  if tst_info.nonce != request.nonce {
+    return Err(timestamp_error(
+      "nonce do carimbo difere do enviado na requisição",
+    ));
+  }
+  let imprint = &tst_info.message_imprint;
+  if imprint.hash_algorithm.oid != request.message_imprint.hash_algorithm.oid
+    || imprint.hashed_message != request.message_imprint.hashed_message
+  {
+    return Err(timestamp_error(
+      "messageImprint do carimbo difere do hash enviado na requisição",
+    ));
+  }
+
+  Ok(token)
+}
+
+/// TSTInfo encapsulado no token (um SignedData com eContentType id-ct-TSTInfo)
+fn decode_tst_info(token: &[u8]) -> Result<TstInfo> {
+  let signed_data = decode_signed_data(token)?;
+  if signed_data.encap_content_info.econtent_type != ID_CT_TST_INFO {
+    return Err(timestamp_error("Token da TSA não contém TSTInfo"));
+  }
+  signed_data
+    .encap_content_info
+    .econtent
+    .ok_or_else(|| timestamp_error("Token da TSA sem TSTInfo"))?
+    .decode_as::<OctetString>()
+    .and_then(|content| TstInfo::from_der(content.as_bytes()))
+    .map_err(|e| timestamp_error(format!("TSTInfo inválido: {}", e)))
+}
+
+/// Instante do carimbo (genTime do TSTInfo), com as frações de segundo que a TSA informar
+pub fn token_gen_time(token: &[u8]) -> Result<DateTime<Utc>> {
+  let gen_time = decode_tst_info(token)?.gen_time;
+  let text = std::str::from_utf8(gen_time.value())
+    .map_err(|_| timestamp_error("genTime do carimbo não é texto"))?;
+  NaiveDateTime::parse_from_str(text, "%Y%m%d%H%M%S%.fZ")
+    .map(|time| time.and_utc())
+    .map_err(|e| timestamp_error(format!("genTime do carimbo inválido ({}): {}", text, e)))
+}
+
+/// Instante do carimbo do tempo anexado ao primeiro SignerInfo do CMS, se houver
+///
+/// Aceita o valor de /Contents decodificado, com o padding de zeros após o CMS
+pub fn signature_timestamp_time(contents: &[u8]) -> Option<DateTime<Utc>> {
+  let mut reader = der::SliceReader::new(contents).ok()?;
+  let signed_data = ContentInfo::decode(&mut reader)
+    .ok()?
+    .content
+    .decode_as::<SignedData>()
+    .ok()?;
+  let token = signed_data
+    .signer_infos
+    .0
+    .get(0)?
+    .unsigned_attrs
+    .as_ref()?
+    .iter()
+    .find(|attribute| attribute.oid == ID_AA_SIGNATURE_TIME_STAMP_TOKEN)?
+    .values
+    .get(0)?
+    .to_der()
+    .ok()?;
+  token_gen_time(&token).ok()
+}
+
+/// Solicita um carimbo do tempo sobre o valor da assinatura do CMS
+///
+/// As TSAs são tentadas em ordem (cada uma com a política de novas tentativas) até
+/// uma devolver um token válido; o retorno traz o token e a URL que o emitiu
+#[cfg(feature = "native")]
+pub fn request_signature_timestamp(
+  tsa_urls: &[&str],
+  cms_der: &[u8],
+  config: &SignatureConfig,
+) -> Result<(Vec<u8>, String)> {
+  let digest = config.digest_algorithm;
+  let signed_data = decode_signed_data(cms_der)?;
+  let signer_info = signed_data
+    .signer_infos
+    .0
+    .get(0)
+    .ok_or_else(|| PdfSignError::SigningError("CMS sem SignerInfo".to_string()))?;
+
+  request_timestamp(
+    tsa_urls,
+    &digest.digest(signer_info.signature.as_bytes()),
+    config,
+  )
+}
+
+/// Solicita um carimbo do tempo sobre `hashed_message`, calculado com o
+/// `digest_algorithm` da configuração
+///
+/// As TSAs são tentadas em ordem, como em `request_signature_timestamp`
+#[cfg(feature = "native")]
+pub fn request_timestamp(
+  tsa_urls: &[&str],
+  hashed_message: &[u8],
+  config: &SignatureConfig,
+) -> Result<(Vec<u8>, String)> {
+  let request = build_request(config.digest_algorithm, hashed_message)?;
+  let request_der = request
+    .to_der()
+    .map_err(|e| timestamp_error(format!("Erro ao codificar requisição: {}", e)))?;
+
+  let client = http_client(config.network_timeout).map_err(timestamp_error)?;
+  let mut failures = Vec::new();
+  for &tsa_url in tsa_urls {
+    // As credenciais são da TSA principal: as alternativas não as recebem
+    let auth = config
+      .tsa_auth
+      .as_ref()
+      .filter(|_| config.tsa_url.as_deref() == Some(tsa_url));
+    let token = post_with_retry(
+      &client,
+      tsa_url,
+      "application/timestamp-query",
+      &request_der,
+      &config.retry_policy,
+      auth,
+    )
+    .map_err(|e| format!("Falha ao contatar a TSA {}: {}", tsa_url, e))
+    .and_then(|response| {
+      parse_response(&response, &request).map_err(|e| match e {
+        PdfSignError::TimestampError(detail) => format!("TSA {}: {}", tsa_url, detail),
+        other => format!("TSA {}: {}", tsa_url, other),
+      })
+    });
+    match token {
+      Ok(token) => return Ok((token, tsa_url.to_string())),
+      Err(failure) => failures.push(failure),
+    }
+  }
+
+  Err(match failures.len() {
+    0 => timestamp_error("nenhuma TSA informada"),
+    1 => timestamp_error(failures.remove(0)),
+    _ => timestamp_error(format!(
+      "nenhuma das {} TSAs devolveu um carimbo válido: {}",
+      failures.len(),
+      failures.join("; ")
+    )),
+  })
+}
+
+/// Anexa o token como atributo não assinado do primeiro SignerInfo
+pub fn embed_signature_timestamp(cms_der: &[u8], token: &[u8]) -> Result<Vec<u8>> {
+  let mut signed_data = decode_signed_data(cms_der)?;
+
+  let token_value =
+    Any::from_der(token).map_err(|e| timestamp_error(format!("Token da TSA inválido: {}", e)))?;
+  let attribute = Attribute {
+    oid: ID_AA_SIGNATURE_TIME_STAMP_TOKEN,
+    values: SetOfVec::try_from(vec![token_value])
+      .map_err(|e| timestamp_error(format!("Erro ao montar atributo: {}", e)))?,
+  };
+
+  // SetOfVec não dá acesso mutável aos elementos: reconstrói o conjunto de SignerInfos
+  let mut signer_infos = signed_data.signer_infos.0.into_vec();
+  let signer_info = signer_infos
+    .first_mut()
+    .ok_or_else(|| PdfSignError::SigningError("CMS sem SignerInfo".to_string()))?;
+
+  let mut unsigned_attrs = signer_info.unsigned_attrs.take().unwrap_or_default();
+  unsigned_attrs
+    .insert(attribute)
+    .map_err(|e| timestamp_error(format!("Erro ao anexar token: {}", e)))?;
+  signer_info.unsigned_attrs = Some(unsigned_attrs);
+  signed_data.signer_infos = SignerInfos(
+    SetOfVec::try_from(signer_infos)
+      .map_err(|e| timestamp_error(format!("Erro ao montar SignerInfos: {}", e)))?,
+  );
+
+  let content_info = ContentInfo {
+    content_type: const_oid::db::rfc5911::ID_SIGNED_DATA,
+    content: Any::encode_from(&signed_data)
+      .map_err(|e| timestamp_error(format!("Erro ao codificar SignedData: {}", e)))?,
+  };
+  content_info
+    .to_der()
+    .map_err(|e| timestamp_error(format!("Erro ao codificar CMS: {}", e)))
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+  use super::*;
+  use crate::test_support::MockTsa;
+
+  #[test]
+  fn test_build_request_roundtrip() {
+    let hash = DigestAlgorithm::Sha256.digest(b"assinatura");
+    let request = build_request(DigestAlgorithm::Sha256, &hash).unwrap();
+    let decoded = TimeStampReq::from_der(&request.to_der().unwrap()).unwrap();
+
+    assert_eq!(decoded, request);
+    assert_eq!(request.version, 1);
+    assert!(request.cert_req);
+    assert_eq!(
+      request.message_imprint.hash_algorithm.oid,
+      DigestAlgorithm::Sha256.oid()
+    );
+    assert_eq!(request.message_imprint.hashed_message.as_bytes(), hash);
+  }
+
+  #[test]
+  fn test_parse_response_rejects_refused_status() {
+    let refused = TimeStampResp {
+      status: PkiStatusInfo {
+        status: 2,
+        status_string: Some(vec!["bad request".to_string()]),
+        fail_info: None,
+      },
+      time_stamp_token: None,
+    }
+    .to_der()
+    .unwrap();
+
+    let request = build_request(DigestAlgorithm::Sha256, &[0; 32]).unwrap();
+    let err = parse_response(&refused, &request).unwrap_err();
+    assert!(matches!(err, PdfSignError::TimestampError(_)));
+    assert!(err.to_string().contains("bad request"));
+  }
+
+  #[test]
+  fn test_parse_response_returns_token() {
+    let tsa = MockTsa::new();
+    let hash = DigestAlgorithm::Sha256.digest(b"assinatura");
+    let request = build_request(DigestAlgorithm::Sha256, &hash).unwrap();
+
+    let token = parse_response(&tsa.respond(&request.to_der().unwrap()), &request).unwrap();
+    let signed_data = decode_signed_data(&token).unwrap();
+    assert_eq!(signed_data.encap_content_info.econtent_type, ID_CT_TST_INFO);
+  }
+
+  #[test]
+  fn test_build_request_uses_fresh_nonce() {
+    let hash = DigestAlgorithm::Sha256.digest(b"assinatura");
+    let first = build_request(DigestAlgorithm::Sha256, &hash).unwrap();
+    let second = build_request(DigestAlgorithm::Sha256, &hash).unwrap();
+
+    assert!(first.nonce.is_some());
+    assert_ne!(first.nonce, second.nonce);
+  }
+
+  #[test]
+  fn test_token_gen_time_matches_encoded_time() {
+    use chrono::TimeZone;
+    use der::Tag;
+
+    let tsa = MockTsa::new();
+    let hash = DigestAlgorithm::Sha256.digest(b"assinatura");
+    let request = build_request(DigestAlgorithm::Sha256, &hash).unwrap();
+    let request_der = request.to_der().unwrap();
+
+    let whole = tsa.respond_with(&request_der, |tst_info| {
+      tst_info.gen_time = Any::new(Tag::GeneralizedTime, b"20240102030405Z".as_slice()).unwrap()
+    });
+    let token = parse_response(&whole, &request).unwrap();
+    assert_eq!(
+      token_gen_time(&token).unwrap(),
+      Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+    );
+
+    // Frações de segundo são preservadas
+    let fractional = tsa.respond_with(&request_der, |tst_info| {
+      tst_info.gen_time = Any::new(Tag::GeneralizedTime, b"20240102030405.25Z".as_slice()).unwrap()
+    });
+    let token = parse_response(&fractional, &request).unwrap();
+    assert_eq!(
+      token_gen_time(&token).unwrap(),
+      Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap() + chrono::Duration::milliseconds(250)
+    );
+  }
+
+  #[test]
+  fn test_parse_response_rejects_wrong_nonce() {
+    let tsa = MockTsa::new();
+    let hash = DigestAlgorithm::Sha256.digest(b"assinatura");
+    let request = build_request(DigestAlgorithm::Sha256, &hash).unwrap();
+    let request_der = request.to_der().unwrap();
+
+    // Resposta gravada para outra requisição, reapresentada pela TSA
+    let replayed = tsa.respond_with(&request_der, |tst_info| {
+      tst_info.nonce = Some(Int::new(&[0x42]).unwrap())
+    });
+    let err = parse_response(&replayed, &request).unwrap_err();
+    assert!(matches!(err, PdfSignError::TimestampError(_)));
+    assert!(err.to_string().contains("nonce"));
+
+    let omitted = tsa.respond_with(&request_der, |tst_info| tst_info.nonce = None);
+    assert!(parse_response(&omitted, &request).is_err());
+  }
+
+  #[test]
+  fn test_parse_response_rejects_wrong_message_imprint() {
+    let tsa = MockTsa::new();
+    let hash = DigestAlgorithm::Sha256.digest(b"assinatura");
+    let request = build_request(DigestAlgorithm::Sha256, &hash).unwrap();
+
+    let response = tsa.respond_with(&request.to_der().unwrap(), |tst_info| {
+      tst_info.message_imprint.hashed_message =
+        OctetString::new(DigestAlgorithm::Sha256.digest(b"outra assinatura")).unwrap()
+    });
+    let err = parse_response(&response, &request).unwrap_err();
+    assert!(matches!(err, PdfSignError::TimestampError(_)));
+    assert!(err.to_string().contains("messageImprint"));
+  }
+}
@@ -0,0 +1,377 @@
+/// Dicionários de transformação de assinatura (/Reference) e de bloqueio de campos (/Lock)
+use crate::error::{PdfSignError, Result};
+use crate::signature_config::{DigestAlgorithm, FieldLock, UsageRights};
+use crate::utils::{pdf_literal_string, pdf_name};
+
+/// Monta o par `/Action ... /Fields [...]` compartilhado por /Lock e pelos /TransformParams
+fn lock_entries(lock: &FieldLock) -> String {
+  let mut entries = format!("/Action /{}", lock.action());
+  if let Some(fields) = lock.fields() {
+    let names: Vec<String> = fields.iter().map(|f| pdf_literal_string(f)).collect();
+    entries.push_str(&format!("\n/Fields [{}]", names.join(" ")));
+  }
+  entries
+}
+
+/// Dicionário /Lock do widget de assinatura (Tabela 233 da ISO 32000-1)
+pub fn build_lock_dict(lock: &FieldLock) -> String {
+  format!("<<\n/Type /SigFieldLock\n{}\n>>", lock_entries(lock))
+}
+
+/// Método de transformação de um /Reference (Tabela 253 da ISO 32000-1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformMethod {
+  DocMDP,
+  UR3,
+  FieldMDP,
+}
+
+impl TransformMethod {
+  fn name(&self) -> &'static str {
+    match self {
+      TransformMethod::DocMDP => "DocMDP",
+      TransformMethod::UR3 => "UR3",
+      TransformMethod::FieldMDP => "FieldMDP",
+    }
+  }
+
+  /// Versão dos /TransformParams (/V) de cada método
+  fn params_version(&self) -> &'static str {
+    match self {
+      TransformMethod::DocMDP | TransformMethod::FieldMDP => "1.2",
+      TransformMethod::UR3 => "2.2",
+    }
+  }
+}
+
+/// /TransformParams de cada método, guardados tipados para `validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TransformParams {
+  /// Alterações permitidas (/P)
+  DocMDP(u8),
+  /// Campos bloqueados (/Action e /Fields)
+  FieldMDP(FieldLock),
+  /// Entradas dos direitos de uso, já serializadas
+  UR3(Vec<String>),
+}
+
+impl TransformParams {
+  fn method(&self) -> TransformMethod {
+    match self {
+      TransformParams::DocMDP(_) => TransformMethod::DocMDP,
+      TransformParams::FieldMDP(_) => TransformMethod::FieldMDP,
+      TransformParams::UR3(_) => TransformMethod::UR3,
+    }
+  }
+
+  /// Entradas dos /TransformParams, sem /Type e /V
+  fn entries(&self) -> Vec<String> {
+    match self {
+      TransformParams::DocMDP(permissions) => vec![format!("/P {}", permissions)],
+      TransformParams::FieldMDP(lock) => vec![lock_entries(lock)],
+      TransformParams::UR3(entries) => entries.clone(),
+    }
+  }
+}
+
+/// Dicionário de referência de assinatura (/Type /SigRef)
+///
+/// As chaves obrigatórias de cada método são conferidas em `build`: um /Reference
+/// malformado invalida a assinatura no Acrobat sem nenhuma mensagem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigReference {
+  params: TransformParams,
+  digest: DigestAlgorithm,
+  /// Objeto (número, geração) analisado pela transformação (/Data), exigido pelo FieldMDP
+  data: Option<(usize, u16)>,
+}
+
+impl SigReference {
+  /// DocMDP da assinatura de certificação, com as alterações permitidas em /P (1 a 3)
+  #[allow(dead_code)]
+  pub fn doc_mdp(permissions: u8, digest: DigestAlgorithm) -> Self {
+    Self {
+      params: TransformParams::DocMDP(permissions),
+      digest,
+      data: None,
+    }
+  }
+
+  /// FieldMDP correspondente ao /Lock, analisado a partir do Catalog `catalog`
+  /// (número, geração)
+  ///
+  /// Validadores estritos só respeitam o bloqueio quando o dicionário de
+  /// assinatura carrega essa referência com os mesmos parâmetros do /Lock
+  pub fn field_mdp(lock: &FieldLock, catalog: (usize, u16), digest: DigestAlgorithm) -> Self {
+    Self {
+      params: TransformParams::FieldMDP(lock.clone()),
+      digest,
+      data: Some(catalog),
+    }
+  }
+
+  /// UR3 da assinatura de direitos de uso (Tabela 255 da ISO 32000-1)
+  pub fn ur3(rights: &UsageRights, digest: DigestAlgorithm) -> Self {
+    let mut params = Vec::new();
+    for (key, names) in [
+      ("/Document", &rights.document),
+      ("/Form", &rights.form),
+      ("/Annots", &rights.annots),
+      ("/Signature", &rights.signature),
+      ("/EF", &rights.embedded_files),
+    ] {
+      if !names.is_empty() {
+        let names: Vec<String> = names.iter().map(|n| pdf_name(n)).collect();
+        params.push(format!("{} [{}]", key, names.join(" ")));
+      }
+    }
+    if let Some(message) = &rights.message {
+      params.push(format!("/Msg {}", pdf_literal_string(message)));
+    }
+    if rights.restrict_others {
+      params.push("/P true".to_string());
+    }
+    Self {
+      params: TransformParams::UR3(params),
+      digest,
+      data: None,
+    }
+  }
+
+  /// Serializa o dicionário depois de conferir as chaves exigidas pelo método
+  pub fn build(&self) -> Result<String> {
+    self.validate()?;
+    let method = self.params.method();
+    let mut dict = format!(
+      "<<\n/Type /SigRef\n/TransformMethod /{}\n/TransformParams <<\n/Type /TransformParams\n",
+      method.name()
+    );
+    for entry in self.params.entries() {
+      dict.push_str(&entry);
+      dict.push('\n');
+    }
+    dict.push_str(&format!("/V /{}\n>>\n", method.params_version()));
+    if let Some((obj, generation)) = self.data {
+      dict.push_str(&format!("/Data {} {} R\n", obj, generation));
+    }
+    dict.push_str(&format!("/DigestMethod /{}\n>>", self.digest.pdf_name()));
+    Ok(dict)
+  }
+
+  fn validate(&self) -> Result<()> {
+    let invalid = |reason: &str| {
+      Err(PdfSignError::SigningError(format!(
+        "/Reference {} inválido: {}",
+        self.params.method().name(),
+        reason
+      )))
+    };
+    match &self.params {
+      TransformParams::DocMDP(permissions) => {
+        if !(1..=3).contains(permissions) {
+          return invalid("/P precisa ser 1, 2 ou 3");
+        }
+      }
+      TransformParams::FieldMDP(lock) => {
+        if lock.fields().is_some_and(<[String]>::is_empty) {
+          return invalid("/Include e /Exclude exigem ao menos um campo em /Fields");
+        }
+        if self.data.is_none() {
+          return invalid("/Data ausente");
+        }
+      }
+      TransformParams::UR3(_) => {}
+    }
+    Ok(())
+  }
+}
+
+/// Entrada `/Reference [...]` do dicionário de assinatura; vazia sem transformações,
+/// já que um array vazio não acrescenta nada
+pub fn build_reference_entry(references: &[SigReference]) -> Result<String> {
+  if references.is_empty() {
+    return Ok(String::new());
+  }
+  let dicts = references
+    .iter()
+    .map(SigReference::build)
+    .collect::<Result<Vec<_>>>()?;
+  Ok(format!("/Reference [{}]\n", dicts.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Confere a estrutura comum a qualquer /SigRef e devolve os /TransformParams
+  fn assert_sig_ref(reference: &str, method: &str, digest: &str) -> String {
+    assert!(
+      reference.starts_with("<<\n/Type /SigRef\n"),
+      "{}",
+      reference
+    );
+    assert!(reference.ends_with(&format!("/DigestMethod /{}\n>>", digest)));
+    assert!(reference.contains(&format!("/TransformMethod /{}\n", method)));
+    let start = reference.find("/TransformParams <<\n").unwrap();
+    let end = reference[start..].find("\n>>\n").unwrap() + start;
+    let params = reference[start..end].to_string();
+    assert!(params.contains("/Type /TransformParams\n"));
+    // Dicionários balanceados
+    assert_eq!(
+      reference.matches("<<").count(),
+      reference.matches(">>").count()
+    );
+    params
+  }
+
+  #[test]
+  fn test_lock_all_has_no_fields() {
+    let lock = FieldLock::All;
+    assert_eq!(
+      build_lock_dict(&lock),
+      "<<\n/Type /SigFieldLock\n/Action /All\n>>"
+    );
+    let reference = SigReference::field_mdp(&lock, (1, 0), DigestAlgorithm::Sha256)
+      .build()
+      .unwrap();
+    let params = assert_sig_ref(&reference, "FieldMDP", "SHA256");
+    assert!(params.ends_with("/Action /All\n/V /1.2"));
+    assert!(reference.contains("\n>>\n/Data 1 0 R\n"));
+    assert!(!reference.contains("/Fields"));
+  }
+
+  #[test]
+  fn test_lock_include_lists_fields() {
+    let lock = FieldLock::Include(vec!["Nome".to_string(), "CPF (titular)".to_string()]);
+    assert_eq!(
+      build_lock_dict(&lock),
+      "<<\n/Type /SigFieldLock\n/Action /Include\n/Fields [(Nome) (CPF \\(titular\\))]\n>>"
+    );
+    assert!(
+      SigReference::field_mdp(&lock, (1, 0), DigestAlgorithm::Sha256)
+        .build()
+        .unwrap()
+        .contains("/Action /Include\n/Fields [(Nome) (CPF \\(titular\\))]")
+    );
+  }
+
+  #[test]
+  fn test_lock_exclude_lists_fields() {
+    let lock = FieldLock::Exclude(vec!["Observacoes".to_string()]);
+    assert!(build_lock_dict(&lock).contains("/Action /Exclude\n/Fields [(Observacoes)]"));
+    assert!(
+      SigReference::field_mdp(&lock, (1, 0), DigestAlgorithm::Sha256)
+        .build()
+        .unwrap()
+        .contains("/Action /Exclude\n/Fields [(Observacoes)]")
+    );
+  }
+
+  #[test]
+  fn test_field_mdp_requires_fields_and_data() {
+    let empty = SigReference::field_mdp(
+      &FieldLock::Include(Vec::new()),
+      (1, 0),
+      DigestAlgorithm::Sha256,
+    );
+    assert!(matches!(
+      empty.build(),
+      Err(PdfSignError::SigningError(message)) if message.contains("/Fields")
+    ));
+
+    let without_data = SigReference {
+      data: None,
+      ..SigReference::field_mdp(&FieldLock::All, (1, 0), DigestAlgorithm::Sha256)
+    };
+    assert!(without_data.build().is_err());
+
+    // A lista vazia é recusada nas duas ações; qualquer nome listado basta
+    let exclude_empty = SigReference::field_mdp(
+      &FieldLock::Exclude(Vec::new()),
+      (1, 0),
+      DigestAlgorithm::Sha256,
+    );
+    assert!(exclude_empty.build().is_err());
+    let parenthesized = FieldLock::Include(vec!["(".to_string()]);
+    assert!(
+      SigReference::field_mdp(&parenthesized, (1, 0), DigestAlgorithm::Sha256)
+        .build()
+        .is_ok()
+    );
+  }
+
+  #[test]
+  fn test_field_mdp_data_keeps_catalog_generation() {
+    let reference = SigReference::field_mdp(&FieldLock::All, (7, 2), DigestAlgorithm::Sha256)
+      .build()
+      .unwrap();
+    assert!(reference.contains("\n>>\n/Data 7 2 R\n"), "{}", reference);
+  }
+
+  #[test]
+  fn test_doc_mdp_reference() {
+    let reference = SigReference::doc_mdp(2, DigestAlgorithm::Sha512)
+      .build()
+      .unwrap();
+    let params = assert_sig_ref(&reference, "DocMDP", "SHA512");
+    assert!(params.ends_with("/P 2\n/V /1.2"));
+    assert!(!reference.contains("/Data"));
+
+    for permissions in [0, 4] {
+      assert!(SigReference::doc_mdp(permissions, DigestAlgorithm::Sha256)
+        .build()
+        .is_err());
+    }
+  }
+
+  #[test]
+  fn test_ur3_reference_default_rights() {
+    let reference = SigReference::ur3(&UsageRights::default(), DigestAlgorithm::Sha256)
+      .build()
+      .unwrap();
+    assert_eq!(
+      reference,
+      "<<\n/Type /SigRef\n/TransformMethod /UR3\n/TransformParams <<\n/Type /TransformParams\n/Document [/FullSave]\n/Form [/FillIn /Import /Export /SubmitStandalone /SpawnTemplate]\n/Annots [/Create /Delete /Modify /Copy /Import /Export]\n/Signature [/Modify]\n/V /2.2\n>>\n/DigestMethod /SHA256\n>>"
+    );
+    assert_sig_ref(&reference, "UR3", "SHA256");
+  }
+
+  #[test]
+  fn test_ur3_reference_omits_empty_lists() {
+    let rights = UsageRights {
+      document: Vec::new(),
+      form: vec!["FillIn".to_string()],
+      annots: Vec::new(),
+      signature: Vec::new(),
+      embedded_files: vec!["Import".to_string()],
+      message: Some("Formulario (gov.br)".to_string()),
+      restrict_others: true,
+    };
+    let reference = SigReference::ur3(&rights, DigestAlgorithm::Sha256)
+      .build()
+      .unwrap();
+    assert!(reference.contains(
+      "/Type /TransformParams\n/Form [/FillIn]\n/EF [/Import]\n/Msg (Formulario \\(gov.br\\))\n/P true\n/V /2.2"
+    ));
+    assert!(!reference.contains("/Document"));
+    assert!(!reference.contains("/Annots"));
+  }
+
+  #[test]
+  fn test_reference_entry() {
+    assert_eq!(build_reference_entry(&[]).unwrap(), "");
+
+    let references = [
+      SigReference::doc_mdp(1, DigestAlgorithm::Sha256),
+      SigReference::field_mdp(&FieldLock::All, (7, 0), DigestAlgorithm::Sha256),
+    ];
+    let entry = build_reference_entry(&references).unwrap();
+    assert!(entry.starts_with("/Reference [<<\n/Type /SigRef\n/TransformMethod /DocMDP"));
+    assert!(entry.ends_with(">>]\n"));
+    assert!(entry.contains(">> <<\n/Type /SigRef\n/TransformMethod /FieldMDP"));
+
+    // Uma referência inválida derruba a entrada inteira
+    let invalid = [SigReference::doc_mdp(9, DigestAlgorithm::Sha256)];
+    assert!(build_reference_entry(&invalid).is_err());
+  }
+}
@@ -0,0 +1,182 @@
+/// Âncoras de confiança e validação da cadeia do signatário
+///
+/// As âncoras informadas na configuração complementam ou substituem as raízes
+/// ICP-Brasil embutidas, já que as ACs intermediárias mudam com frequência
+use std::fs;
+
+use openssl::stack::Stack;
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::{X509Ref, X509StoreContext, X509};
+
+use crate::cache;
+use crate::error::{PdfSignError, Result};
+use crate::signature_config::{SignatureConfig, TrustAnchorsMode};
+
+/// Raízes ICP-Brasil distribuídas com o crate (PEM)
+///
+/// Ainda não há certificados embutidos: enquanto a lista estiver vazia, a
+/// validação depende das âncoras informadas em `trust_anchors`/`trust_anchors_path`
+const ICP_BRASIL_ROOTS: &[&[u8]] = &[];
+
+fn validation_error(message: impl Into<String>) -> PdfSignError {
+  PdfSignError::IcpBrasilValidationError(message.into())
+}
+
+/// Lê certificados em PEM (um ou vários em sequência) ou em DER
+pub fn parse_anchors(data: &[u8]) -> Result<Vec<X509>> {
+  if data.windows(11).any(|w| w == b"-----BEGIN ") {
+    let certs = X509::stack_from_pem(data)
+      .map_err(|e| validation_error(format!("Âncora PEM inválida: {}", e)))?;
+    if certs.is_empty() {
+      return Err(validation_error("Arquivo PEM sem certificados"));
+    }
+    return Ok(certs);
+  }
+
+  X509::from_der(data)
+    .map(|cert| vec![cert])
+    .map_err(|e| validation_error(format!("Âncora DER inválida: {}", e)))
+}
+
+/// Monta a lista de âncoras conforme a configuração
+///
+/// O conteúdo já interpretado fica no cache do processo; o arquivo de
+/// `trust_anchors_path` é relido a cada chamada, então trocá-lo vale na hora
+pub fn resolve_anchors(config: &SignatureConfig) -> Result<Vec<X509>> {
+  let mut anchors = Vec::new();
+
+  if config.trust_anchors_mode == TrustAnchorsMode::Supplement {
+    for root in ICP_BRASIL_ROOTS {
+      anchors.extend(cache::trust_anchors(root, parse_anchors)?);
+    }
+  }
+
+  for data in config.trust_anchors.iter().flatten() {
+    anchors.extend(cache::trust_anchors(data, parse_anchors)?);
+  }
+
+  if let Some(path) = &config.trust_anchors_path {
+    let data = fs::read(path).map_err(|e| {
+      validation_error(format!(
+        "Erro ao ler âncoras de confiança em {}: {}",
+        path, e
+      ))
+    })?;
+    anchors.extend(cache::trust_anchors(&data, parse_anchors)?);
+  }
+
+  Ok(anchors)
+}
+
+/// Verifica se o certificado do signatário encadeia até uma das âncoras
+pub fn validate_chain(cert: &X509Ref, chain: &[X509], config: &SignatureConfig) -> Result<()> {
+  let anchors = resolve_anchors(config)?;
+  if anchors.is_empty() {
+    return Err(validation_error(
+      "nenhuma âncora de confiança configurada (informe trust_anchors ou trust_anchors_path)",
+    ));
+  }
+
+  let mut store = X509StoreBuilder::new()
+    .map_err(|e| validation_error(format!("Erro ao criar trust store: {}", e)))?;
+  for anchor in anchors {
+    store
+      .add_cert(anchor)
+      .map_err(|e| validation_error(format!("Erro ao adicionar âncora: {}", e)))?;
+  }
+  let store = store.build();
+
+  let mut untrusted =
+    Stack::new().map_err(|e| validation_error(format!("Erro ao criar cadeia: {}", e)))?;
+  for intermediate in chain {
+    untrusted
+      .push(intermediate.clone())
+      .map_err(|e| validation_error(format!("Erro ao montar cadeia: {}", e)))?;
+  }
+
+  let mut context = X509StoreContext::new()
+    .map_err(|e| validation_error(format!("Erro ao criar contexto de validação: {}", e)))?;
+  let verified = context
+    .init(&store, cert, &untrusted, |ctx| {
+      let ok = ctx.verify_cert()?;
+      Ok((ok, ctx.error()))
+    })
+    .map_err(|e| validation_error(format!("Erro ao validar cadeia: {}", e)))?;
+
+  match verified {
+    (true, _) => Ok(()),
+    (false, error) => Err(validation_error(format!(
+      "certificado não encadeia até uma âncora confiável: {}",
+      error.error_string()
+    ))),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::issue_identity;
+
+  fn config_with(anchors: Option<Vec<Vec<u8>>>, mode: TrustAnchorsMode) -> SignatureConfig {
+    SignatureConfig {
+      trust_anchors: anchors,
+      trust_anchors_mode: mode,
+      ..SignatureConfig::default()
+    }
+  }
+
+  #[test]
+  fn test_validate_chain_with_custom_root() {
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let (inter_key, intermediate) =
+      issue_identity("AC Intermediaria de Teste", Some((&root_key, &root)), true);
+    let (_, leaf) = issue_identity("Signatario", Some((&inter_key, &intermediate)), false);
+
+    let config = config_with(
+      Some(vec![root.to_pem().unwrap()]),
+      TrustAnchorsMode::Replace,
+    );
+    validate_chain(&leaf, std::slice::from_ref(&intermediate), &config).unwrap();
+
+    // DER também é aceito
+    let config = config_with(
+      Some(vec![root.to_der().unwrap()]),
+      TrustAnchorsMode::Supplement,
+    );
+    validate_chain(&leaf, &[intermediate], &config).unwrap();
+  }
+
+  #[test]
+  fn test_validate_chain_fails_without_custom_root() {
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let (_, leaf) = issue_identity("Signatario", Some((&root_key, &root)), false);
+
+    let err =
+      validate_chain(&leaf, &[], &config_with(None, TrustAnchorsMode::Supplement)).unwrap_err();
+    assert!(matches!(err, PdfSignError::IcpBrasilValidationError(_)));
+
+    let (_, other_root) = issue_identity("Outra AC Raiz", None, true);
+    let config = config_with(
+      Some(vec![other_root.to_pem().unwrap()]),
+      TrustAnchorsMode::Replace,
+    );
+    assert!(validate_chain(&leaf, &[], &config).is_err());
+  }
+
+  #[test]
+  fn test_anchors_from_file() {
+    let (root_key, root) = issue_identity("AC Raiz de Teste", None, true);
+    let (_, leaf) = issue_identity("Signatario", Some((&root_key, &root)), false);
+
+    let path = std::env::temp_dir().join(format!("pdfsigner-anchors-{}.pem", std::process::id()));
+    fs::write(&path, root.to_pem().unwrap()).unwrap();
+    let config = SignatureConfig {
+      trust_anchors_path: Some(path.to_string_lossy().to_string()),
+      ..SignatureConfig::default()
+    };
+    let result = validate_chain(&leaf, &[], &config);
+    fs::remove_file(&path).unwrap();
+
+    result.unwrap();
+  }
+}
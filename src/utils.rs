@@ -1,20 +1,181 @@
 /// Utilidades para manipulação de PDFs
+use std::io::Read;
+
 use crate::error::{PdfSignError, Result};
+use crate::signature_config::TextEncoding;
 
 /// Remove trailing newlines do PDF (compatível com node-signpdf)
 ///
 /// Node-signpdf remove `\n` e `\r` finais antes de processar o PDF
 /// para garantir cálculos corretos do ByteRange
 pub fn remove_trailing_newline(mut pdf: Vec<u8>) -> Vec<u8> {
-  // Remove \n final
-  while pdf.last() == Some(&b'\n') {
-    pdf.pop();
+  let len = pdf.len() - trailing_newline_len(&pdf);
+  pdf.truncate(len);
+  pdf
+}
+
+/// Quantidade de bytes que `remove_trailing_newline` tira do fim do PDF
+pub fn trailing_newline_len(pdf: &[u8]) -> usize {
+  // Primeiro os \n finais, depois os \r que ficarem antes deles
+  let lf = pdf.iter().rev().take_while(|&&b| b == b'\n').count();
+  let cr = pdf[..pdf.len() - lf]
+    .iter()
+    .rev()
+    .take_while(|&&b| b == b'\r')
+    .count();
+  lf + cr
+}
+
+/// Lê o PDF em blocos mantendo só a estrutura: o conteúdo dos streams é descartado
+///
+/// Dicionários, xref e trailer continuam no lugar, o que basta para os scanners
+/// deste módulo em arquivos grandes; os offsets deixam de valer, então o
+/// resultado não é um PDF válido
+pub fn read_pdf_skeleton<R: Read>(mut reader: R) -> std::io::Result<Vec<u8>> {
+  const STREAM: &[u8] = b"stream";
+  const END_STREAM: &[u8] = b"endstream";
+
+  let mut skeleton = Vec::new();
+  let mut chunk = vec![0u8; 64 * 1024];
+  let mut after_keyword = false;
+  // Últimos bytes descartados do stream, para achar o "endstream" entre blocos
+  let mut discarded: Option<Vec<u8>> = None;
+
+  loop {
+    let read = reader.read(&mut chunk)?;
+    if read == 0 {
+      break;
+    }
+    for &byte in &chunk[..read] {
+      if let Some(window) = discarded.as_mut() {
+        if window.len() == END_STREAM.len() {
+          window.remove(0);
+        }
+        window.push(byte);
+        if window == END_STREAM {
+          skeleton.extend_from_slice(b"\nendstream");
+          discarded = None;
+        }
+        continue;
+      }
+
+      // A palavra-chave só abre o stream quando seguida de fim de linha
+      if after_keyword {
+        after_keyword = false;
+        if byte == b'\r' || byte == b'\n' {
+          discarded = Some(Vec::with_capacity(END_STREAM.len()));
+          continue;
+        }
+      }
+      skeleton.push(byte);
+      after_keyword = skeleton.ends_with(STREAM) && !skeleton.ends_with(END_STREAM);
+    }
+  }
+
+  Ok(skeleton)
+}
+
+/// Lê a versão do cabeçalho `%PDF-x.y`
+///
+/// A especificação permite lixo antes do cabeçalho, desde que ele apareça
+/// nos primeiros 1024 bytes do arquivo
+pub fn parse_pdf_version(pdf_data: &[u8]) -> Option<(u8, u8)> {
+  let header = b"%PDF-";
+  let search_area = &pdf_data[..pdf_data.len().min(1024)];
+  let header_pos = search_area
+    .windows(header.len())
+    .position(|w| w == header)?;
+
+  let version = &pdf_data[header_pos + header.len()..];
+  let major_len = version.iter().take_while(|b| b.is_ascii_digit()).count();
+  if major_len == 0 || version.get(major_len) != Some(&b'.') {
+    return None;
   }
-  // Remove \r final
-  while pdf.last() == Some(&b'\r') {
-    pdf.pop();
+
+  let minor = &version[major_len + 1..];
+  let minor_len = minor.iter().take_while(|b| b.is_ascii_digit()).count();
+  if minor_len == 0 {
+    return None;
   }
-  pdf
+
+  let major = std::str::from_utf8(&version[..major_len])
+    .ok()?
+    .parse()
+    .ok()?;
+  let minor = std::str::from_utf8(&minor[..minor_len])
+    .ok()?
+    .parse()
+    .ok()?;
+  Some((major, minor))
+}
+
+/// Indica se o PDF é linearizado ("fast web view")
+///
+/// O dicionário /Linearized precisa ser o primeiro objeto do arquivo, dentro dos
+/// primeiros 1024 bytes (ISO 32000-1, Anexo F); um /Linearized em outro lugar não conta
+pub fn is_linearized(pdf_data: &[u8]) -> bool {
+  let head = &pdf_data[..pdf_data.len().min(1024)];
+  let Some(first_obj) = head.windows(3).position(|w| w == b"obj") else {
+    return false;
+  };
+  let first_object = &head[first_obj..];
+  let end = first_object
+    .windows(6)
+    .position(|w| w == b"endobj")
+    .unwrap_or(first_object.len());
+  dict_body(&first_object[..end]).is_some_and(|dict| dict_has_key(dict, b"/Linearized"))
+}
+
+/// Serializa um texto como string literal PDF, escapando `\`, `(` e `)`
+pub fn pdf_literal_string(text: &str) -> String {
+  let mut literal = String::with_capacity(text.len() + 2);
+  literal.push('(');
+  for c in text.chars() {
+    match c {
+      '\\' | '(' | ')' => {
+        literal.push('\\');
+        literal.push(c);
+      }
+      _ => literal.push(c),
+    }
+  }
+  literal.push(')');
+  literal
+}
+
+/// Serializa um texto como string de texto PDF na codificação pedida
+///
+/// Em `Auto`, só textos com caracteres fora do ASCII vão para UTF-16BE: a string hex
+/// ocupa quatro dígitos por caractere, e o ASCII é lido igual em PDFDocEncoding
+pub fn pdf_text_string(text: &str, encoding: TextEncoding) -> String {
+  match encoding {
+    TextEncoding::Auto if text.is_ascii() => pdf_literal_string(text),
+    TextEncoding::Literal => pdf_literal_string(text),
+    TextEncoding::Auto | TextEncoding::Utf16Be => {
+      let mut hex = String::from("<FEFF");
+      for unit in text.encode_utf16() {
+        hex.push_str(&format!("{:04X}", unit));
+      }
+      hex.push('>');
+      hex
+    }
+  }
+}
+
+/// Serializa um texto como objeto nome PDF (`/Nome`)
+///
+/// Bytes fora do intervalo imprimível, delimitadores e `#` são codificados como `#XX`
+pub fn pdf_name(name: &str) -> String {
+  let mut encoded = String::with_capacity(name.len() + 1);
+  encoded.push('/');
+  for &b in name.as_bytes() {
+    if (0x21..=0x7e).contains(&b) && !b"#()<>[]{}/%".contains(&b) {
+      encoded.push(b as char);
+    } else {
+      encoded.push_str(&format!("#{:02X}", b));
+    }
+  }
+  encoded
 }
 
 /// Encontra o próximo número de objeto disponível no PDF
@@ -22,12 +183,13 @@ pub fn get_next_object_number(pdf_data: &[u8]) -> Result<u32> {
   let pdf_str = String::from_utf8_lossy(pdf_data);
   let mut max_obj: u32 = 0;
 
-  for line in pdf_str.lines() {
-    if let Some(num_str) = line.split_whitespace().next() {
-      if let Ok(num) = num_str.parse::<u32>() {
-        if line.contains("0 obj") {
-          max_obj = max_obj.max(num);
-        }
+  // PDFs gerados no Mac antigo usam só \r como fim de linha
+  // Qualquer geração conta: "12 1 obj" ocupa o número 12 tanto quanto "12 0 obj"
+  for line in pdf_str.split(['\n', '\r']) {
+    // PDFs compactos colam o próximo cabeçalho ao "endobj", na mesma linha
+    for head in std::iter::once(line).chain(line.split("endobj").skip(1)) {
+      if let Some((num, _)) = parse_object_header(head.trim_start().as_bytes()) {
+        max_obj = max_obj.max(num as u32);
       }
     }
   }
@@ -37,6 +199,7 @@ pub fn get_next_object_number(pdf_data: &[u8]) -> Result<u32> {
 
 /// Estrutura com informações do Catalog do PDF
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub struct PdfCatalogInfo {
   pub catalog_obj: usize,
   pub pages_ref: usize,
@@ -46,13 +209,14 @@ pub struct PdfCatalogInfo {
 /// Extrai informações do Catalog do PDF de forma robusta
 /// Funciona mesmo com PDFs reconstruídos que têm estruturas não padrão
 pub fn extract_catalog_info(pdf_data: &[u8]) -> Result<PdfCatalogInfo> {
-  let pdf_str = String::from_utf8_lossy(pdf_data);
-
   // Primeiro, tenta encontrar o Catalog via startxref/trailer/Root
-  let catalog_obj = find_catalog_from_trailer(&pdf_str).unwrap_or_else(|| {
-    // Fallback: busca por /Type /Catalog diretamente
-    find_catalog_by_pattern(pdf_data).unwrap_or(1)
-  });
+  let catalog_obj = parse_trailer(pdf_data)
+    .root
+    .map(|(obj, _)| obj)
+    .unwrap_or_else(|| {
+      // Fallback: busca por /Type /Catalog diretamente
+      find_catalog_by_pattern(pdf_data).unwrap_or(1)
+    });
 
   // Busca a referência /Pages dentro do Catalog
   let pages_ref = find_pages_ref_in_catalog(pdf_data, catalog_obj).unwrap_or_else(|| {
@@ -73,26 +237,6 @@ pub fn extract_catalog_info(pdf_data: &[u8]) -> Result<PdfCatalogInfo> {
   })
 }
 
-/// Encontra o objeto Catalog através do trailer (método correto)
-fn find_catalog_from_trailer(pdf_str: &str) -> Option<usize> {
-  // Busca o último trailer (em caso de atualizações incrementais)
-  let trailer_pos = pdf_str.rfind("trailer")?;
-  let trailer_section = &pdf_str[trailer_pos..];
-
-  // Procura /Root N 0 R
-  let root_pos = trailer_section.find("/Root")?;
-  let after_root = &trailer_section[root_pos + 5..];
-
-  // Extrai o número do objeto
-  for word in after_root.split_whitespace() {
-    if let Ok(num) = word.parse::<usize>() {
-      return Some(num);
-    }
-  }
-
-  None
-}
-
 /// Busca o Catalog por padrão /Type /Catalog ou /Type/Catalog (fallback)
 fn find_catalog_by_pattern(pdf_data: &[u8]) -> Option<usize> {
   // Tenta ambos os padrões: com e sem espaço
@@ -103,27 +247,13 @@ fn find_catalog_by_pattern(pdf_data: &[u8]) -> Option<usize> {
       .windows(catalog_marker.len())
       .position(|w| w == *catalog_marker)
     {
-      // Procura para trás para encontrar "N 0 obj"
+      // Procura para trás para encontrar "N G obj"
       // Aumentado para 2000 bytes pois PDFs podem ter objetos muito grandes
       let search_start = catalog_start.saturating_sub(2000);
-      let obj_pattern = b" 0 obj";
-
-      if let Some(obj_pos) = pdf_data[search_start..catalog_start]
-        .windows(obj_pattern.len())
-        .rposition(|w| w == obj_pattern)
+      if let Some(obj_num) =
+        object_number_before(&pdf_data[search_start..], catalog_start - search_start)
       {
-        let actual_pos = search_start + obj_pos;
-        let mut num_start = actual_pos;
-
-        while num_start > 0 && pdf_data[num_start - 1] >= b'0' && pdf_data[num_start - 1] <= b'9' {
-          num_start -= 1;
-        }
-
-        if let Ok(obj_str) = std::str::from_utf8(&pdf_data[num_start..actual_pos]) {
-          if let Ok(obj_num) = obj_str.trim().parse::<usize>() {
-            return Some(obj_num);
-          }
-        }
+        return Some(obj_num);
       }
     }
   }
@@ -133,21 +263,10 @@ fn find_catalog_by_pattern(pdf_data: &[u8]) -> Option<usize> {
 
 /// Encontra a referência /Pages dentro de um objeto Catalog
 fn find_pages_ref_in_catalog(pdf_data: &[u8], catalog_obj: usize) -> Option<usize> {
-  // Busca o objeto do Catalog
-  let catalog_pattern = format!("{} 0 obj", catalog_obj);
-  let catalog_start = pdf_data
-    .windows(catalog_pattern.len())
-    .position(|w| w == catalog_pattern.as_bytes())?;
-
-  // Encontra o fim do objeto (endobj)
-  let catalog_end = pdf_data[catalog_start..]
-    .windows(b"endobj".len())
-    .position(|w| w == b"endobj")?
-    + catalog_start;
+  // Busca o objeto do Catalog, em qualquer geração
+  let catalog_section = find_object_section(pdf_data, catalog_obj)?;
 
-  let catalog_section = &pdf_data[catalog_start..catalog_end];
-
-  // Busca /Pages N 0 R
+  // Busca /Pages N G R
   let pages_pos = catalog_section
     .windows(b"/Pages".len())
     .position(|w| w == b"/Pages")?;
@@ -167,22 +286,10 @@ fn find_pages_ref_in_catalog(pdf_data: &[u8], catalog_obj: usize) -> Option<usiz
 
 /// Verifica se o Catalog já tem AcroForm
 fn check_catalog_has_acroform(pdf_data: &[u8], catalog_obj: usize) -> bool {
-  let catalog_pattern = format!("{} 0 obj", catalog_obj);
-  if let Some(catalog_start) = pdf_data
-    .windows(catalog_pattern.len())
-    .position(|w| w == catalog_pattern.as_bytes())
-  {
-    if let Some(catalog_end) = pdf_data[catalog_start..]
-      .windows(b"endobj".len())
-      .position(|w| w == b"endobj")
-    {
-      let catalog_section = &pdf_data[catalog_start..catalog_start + catalog_end];
-      return catalog_section
-        .windows(b"/AcroForm".len())
-        .any(|w| w == b"/AcroForm");
-    }
-  }
-  false
+  // Catalog vigente: numa atualização incremental o AcroForm pode ter sido acrescentado depois
+  find_object_section(pdf_data, catalog_obj)
+    .and_then(dict_body)
+    .is_some_and(|catalog| dict_has_key(catalog, b"/AcroForm"))
 }
 
 /// Estrutura com informações da primeira página do PDF
@@ -191,12 +298,62 @@ pub struct PdfPageInfo {
   pub first_page_obj: usize,
 }
 
+/// Resultado de `validate_pdf`: o que os scanners da assinatura encontram no documento
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfValidation {
+  /// Cabeçalho, Catalog e primeira página encontrados: o documento pode ser assinado
+  pub is_valid: bool,
+  /// Versão do cabeçalho `%PDF-x.y` (ex: "1.7")
+  pub pdf_version: Option<String>,
+  /// O Catalog tem /AcroForm
+  pub has_acroform: bool,
+  pub first_page_found: bool,
+  pub catalog_found: bool,
+  /// Motivo do primeiro problema encontrado, quando inválido
+  pub error: Option<String>,
+}
+
+/// Confere, sem assinar, se o PDF passa pela leitura de versão, Catalog e primeira página
+///
+/// Permite recusar um upload antes de carregar o certificado
+pub fn validate_pdf(pdf_data: &[u8]) -> PdfValidation {
+  let version = parse_pdf_version(pdf_data);
+  // `extract_catalog_info` cai no objeto 1 quando não acha o Catalog: ele só conta
+  // como encontrado se o objeto existir no arquivo
+  let catalog = extract_catalog_info(pdf_data)
+    .ok()
+    .filter(|info| find_object_section(pdf_data, info.catalog_obj).is_some());
+  let first_page = extract_first_page_info(pdf_data);
+
+  let error = if version.is_none() {
+    Some("arquivo não é um PDF (cabeçalho %PDF-x.y ausente)".to_string())
+  } else if catalog.is_none() {
+    Some("Catalog do PDF não encontrado".to_string())
+  } else {
+    first_page.as_ref().err().map(ToString::to_string)
+  };
+  PdfValidation {
+    is_valid: error.is_none(),
+    pdf_version: version.map(|(major, minor)| format!("{}.{}", major, minor)),
+    has_acroform: catalog.as_ref().is_some_and(|info| info.has_acroform),
+    first_page_found: first_page.is_ok(),
+    catalog_found: catalog.is_some(),
+    error,
+  }
+}
+
 /// Extrai informações sobre a primeira página do PDF de forma robusta
+///
+/// A primeira página exibida vem da árvore de páginas (/Pages -> /Kids), que pode
+/// listar as páginas fora da ordem em que os objetos aparecem no arquivo
 pub fn extract_first_page_info(pdf_data: &[u8]) -> Result<PdfPageInfo> {
-  // Método 1: Busca /Type /Page diretamente (mais simples e funciona com PDFs reconstruídos)
-  let first_page_obj = find_first_page_by_pattern(pdf_data).ok_or_else(|| {
-    PdfSignError::InvalidPdf("Não foi possível encontrar a primeira página".to_string())
-  })?;
+  // Método 1: percorre a árvore a partir do /Pages do Catalog
+  // Método 2: busca /Type /Page diretamente (PDFs reconstruídos com a árvore quebrada)
+  let first_page_obj = find_page_object(pdf_data, 0)
+    .or_else(|| find_first_page_by_pattern(pdf_data))
+    .ok_or_else(|| {
+      PdfSignError::InvalidPdf("Não foi possível encontrar a primeira página".to_string())
+    })?;
 
   Ok(PdfPageInfo { first_page_obj })
 }
@@ -231,28 +388,13 @@ fn find_first_page_by_pattern(pdf_data: &[u8]) -> Option<usize> {
         }
 
         // Encontrou um "/Type /Page" ou "/Type/Page" válido (não é /Pages)
-        // Procura para trás para encontrar "N 0 obj"
+        // Procura para trás para encontrar "N G obj"
         // Aumentado para 2000 bytes pois PDFs podem ter objetos muito grandes (ex: muitos recursos)
         let search_start = page_start.saturating_sub(2000);
-        let obj_pattern = b" 0 obj";
-
-        if let Some(obj_pos) = pdf_data[search_start..page_start]
-          .windows(obj_pattern.len())
-          .rposition(|w| w == obj_pattern)
+        if let Some(obj_num) =
+          object_number_before(&pdf_data[search_start..], page_start - search_start)
         {
-          let actual_pos = search_start + obj_pos;
-          let mut num_start = actual_pos;
-
-          while num_start > 0 && pdf_data[num_start - 1] >= b'0' && pdf_data[num_start - 1] <= b'9'
-          {
-            num_start -= 1;
-          }
-
-          if let Ok(obj_str) = std::str::from_utf8(&pdf_data[num_start..actual_pos]) {
-            if let Ok(obj_num) = obj_str.trim().parse::<usize>() {
-              return Some(obj_num);
-            }
-          }
+          return Some(obj_num);
         }
 
         // Se não conseguiu extrair o número, continua buscando
@@ -277,27 +419,13 @@ fn find_pages_object(pdf_data: &[u8]) -> Option<usize> {
       .windows(pages_marker.len())
       .position(|w| w == *pages_marker)
     {
-      // Procura para trás para encontrar "N 0 obj"
+      // Procura para trás para encontrar "N G obj"
       // Aumentado para 2000 bytes pois PDFs podem ter objetos muito grandes
       let search_start = pages_start.saturating_sub(2000);
-      let obj_pattern = b" 0 obj";
-
-      if let Some(obj_pos) = pdf_data[search_start..pages_start]
-        .windows(obj_pattern.len())
-        .rposition(|w| w == obj_pattern)
+      if let Some(obj_num) =
+        object_number_before(&pdf_data[search_start..], pages_start - search_start)
       {
-        let actual_pos = search_start + obj_pos;
-        let mut num_start = actual_pos;
-
-        while num_start > 0 && pdf_data[num_start - 1] >= b'0' && pdf_data[num_start - 1] <= b'9' {
-          num_start -= 1;
-        }
-
-        if let Ok(obj_str) = std::str::from_utf8(&pdf_data[num_start..actual_pos]) {
-          if let Ok(obj_num) = obj_str.trim().parse::<usize>() {
-            return Some(obj_num);
-          }
-        }
+        return Some(obj_num);
       }
     }
   }
@@ -308,12 +436,7 @@ fn find_pages_object(pdf_data: &[u8]) -> Option<usize> {
 /// Valida que o objeto Pages existe e é válido
 fn validate_pages_object(pdf_data: &[u8], pages_obj: usize) -> Option<usize> {
   // Verifica se existe um objeto com esse número
-  let obj_pattern = format!("{} 0 obj", pages_obj);
-
-  if pdf_data
-    .windows(obj_pattern.len())
-    .any(|w| w == obj_pattern.as_bytes())
-  {
+  if find_object_header(pdf_data, pages_obj).is_some() {
     return Some(pages_obj);
   }
 
@@ -321,10 +444,1037 @@ fn validate_pages_object(pdf_data: &[u8], pages_obj: usize) -> Option<usize> {
   find_pages_object(pdf_data)
 }
 
+/// Objetos referenciados ("N G R") no array direto da chave, na ordem do array
+pub fn read_ref_array(section: &[u8], key: &[u8]) -> Option<Vec<usize>> {
+  let rest = &section[find_dict_key(section, key)? + key.len()..];
+  let start = rest.iter().position(|c| !c.is_ascii_whitespace())?;
+  if rest[start] != b'[' {
+    return None;
+  }
+  let end = start + rest[start..].iter().position(|&c| c == b']')?;
+  let items = String::from_utf8_lossy(&rest[start + 1..end]).to_string();
+  let words: Vec<&str> = items.split_whitespace().collect();
+  Some(
+    words
+      .windows(3)
+      .filter(|w| w[2] == "R")
+      .filter_map(|w| w[0].parse().ok())
+      .collect(),
+  )
+}
+
+/// Objeto da página `page_index` (a partir de 0), em ordem de leitura na árvore /Kids
+pub fn find_page_object(pdf_data: &[u8], page_index: usize) -> Option<usize> {
+  let mut pending = vec![extract_catalog_info(pdf_data).ok()?.pages_ref];
+  let mut remaining = page_index;
+  let mut visited = Vec::new();
+
+  while let Some(obj) = pending.pop() {
+    // Árvores malformadas podem ter ciclos
+    if visited.contains(&obj) {
+      return None;
+    }
+    visited.push(obj);
+
+    let section = find_object_section(pdf_data, obj)?;
+    match read_ref_array(section, b"/Kids") {
+      Some(kids) => pending.extend(kids.into_iter().rev()),
+      None if remaining == 0 => return Some(obj),
+      None => remaining -= 1,
+    }
+  }
+
+  None
+}
+
+/// /MediaBox da página como [x1 y1 x2 y2], com x1 <= x2 e y1 <= y2
+///
+/// A chave é herdável: sem ela na página, vale a do nó /Pages mais próximo
+pub fn extract_page_mediabox(pdf_data: &[u8], page_obj: usize) -> Option<[f64; 4]> {
+  let mut obj = page_obj;
+  let mut visited = Vec::new();
+
+  loop {
+    // Árvores malformadas podem ter ciclos
+    if visited.contains(&obj) {
+      return None;
+    }
+    visited.push(obj);
+
+    let dict = dict_body(find_object_section(pdf_data, obj)?)?;
+    let entries = dict_entries(dict);
+    if let Some((key, entry)) = entries.iter().find(|(key, _)| *key == b"/MediaBox") {
+      let [x1, y1, x2, y2] = read_number_array(pdf_data, &entry[key.len()..])?;
+      return Some([x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)]);
+    }
+    obj = entries
+      .iter()
+      .find(|(key, _)| *key == b"/Parent")
+      .and_then(|(key, entry)| read_number_after(entry, key))?;
+  }
+}
+
+/// Array de quatro números, direto ("[0 0 612 792]") ou num objeto indireto ("5 0 R")
+fn read_number_array(pdf_data: &[u8], value: &[u8]) -> Option<[f64; 4]> {
+  let text = String::from_utf8_lossy(value);
+  let text = text.trim();
+  let array = match text.strip_prefix('[') {
+    Some(array) => array.split(']').next()?.to_string(),
+    None => {
+      let obj = text
+        .strip_suffix('R')?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+      let section = String::from_utf8_lossy(find_object_section(pdf_data, obj)?).to_string();
+      let (_, rest) = section.split_once('[')?;
+      rest.split(']').next()?.to_string()
+    }
+  };
+  let numbers: Vec<f64> = array
+    .split_whitespace()
+    .map(|n| n.parse().ok())
+    .collect::<Option<_>>()?;
+  numbers.try_into().ok()
+}
+
+/// "N G obj" logo no início do trecho: número e geração do objeto
+pub fn parse_object_header(section: &[u8]) -> Option<(usize, u16)> {
+  if !section.first()?.is_ascii_digit() {
+    return None;
+  }
+  let head = String::from_utf8_lossy(&section[..section.len().min(48)]);
+  let mut words = head.split_ascii_whitespace();
+  let num = words.next()?.parse().ok()?;
+  let generation = words.next()?.parse().ok()?;
+  words
+    .next()?
+    .starts_with("obj")
+    .then_some((num, generation))
+}
+
+/// Cabeçalho "N G obj" cuja palavra "obj" começa em `obj_pos`: (início, número, geração)
+///
+/// O "obj" de "endobj" e números colados a outras palavras não contam; a exceção é o
+/// próprio "endobj", que PDFs compactos escrevem sem espaço antes do próximo objeto
+fn object_header_ending_at(pdf_data: &[u8], obj_pos: usize) -> Option<(usize, usize, u16)> {
+  let skip_back = |mut pos: usize, accept: fn(&u8) -> bool| {
+    while pos > 0 && accept(&pdf_data[pos - 1]) {
+      pos -= 1;
+    }
+    pos
+  };
+  let gen_end = skip_back(obj_pos, u8::is_ascii_whitespace);
+  let gen_start = skip_back(gen_end, u8::is_ascii_digit);
+  let num_end = skip_back(gen_start, u8::is_ascii_whitespace);
+  let num_start = skip_back(num_end, u8::is_ascii_digit);
+  if gen_end == obj_pos || gen_start == gen_end || num_end == gen_start || num_start == num_end {
+    return None;
+  }
+  if num_start > 0
+    && pdf_data[num_start - 1].is_ascii_alphanumeric()
+    && !pdf_data[..num_start].ends_with(b"endobj")
+  {
+    return None;
+  }
+
+  let number = |range: std::ops::Range<usize>| std::str::from_utf8(&pdf_data[range]).ok();
+  Some((
+    num_start,
+    number(num_start..num_end)?.parse().ok()?,
+    number(gen_start..gen_end)?.parse().ok()?,
+  ))
+}
+
+/// Início e geração do cabeçalho "N G obj" da revisão mais recente do objeto
+///
+/// O offset vem da cadeia de xref; só quando ela não resolve o objeto (xref quebrada,
+/// esqueleto sem os streams, object streams, objeto inexistente) o arquivo é varrido
+pub fn find_object_header(pdf_data: &[u8], obj_num: usize) -> Option<(usize, u16)> {
+  let from_xref = xref_object_offset(pdf_data, obj_num).and_then(|offset| {
+    parse_object_header(&pdf_data[offset..])
+      .filter(|(num, _)| *num == obj_num)
+      .map(|(_, generation)| (offset, generation))
+  });
+  if from_xref.is_some() {
+    return from_xref;
+  }
+
+  let mut end = pdf_data.len();
+
+  // Busca de trás para frente: atualizações incrementais redefinem objetos no fim do arquivo
+  while let Some(obj_pos) = pdf_data[..end].windows(3).rposition(|w| w == b"obj") {
+    end = obj_pos;
+    match object_header_ending_at(pdf_data, obj_pos) {
+      Some((start, num, generation)) if num == obj_num => return Some((start, generation)),
+      _ => continue,
+    }
+  }
+
+  None
+}
+
+/// Offset do objeto em uso segundo a xref mais recente que o lista, seguindo /Prev
+///
+/// Lê tabelas clássicas com entradas de 20 bytes e xref streams sem /Filter; uma
+/// entrada livre, uma tabela fora do padrão ou um objeto ausente de todas dão `None`
+fn xref_object_offset(pdf_data: &[u8], obj_num: usize) -> Option<usize> {
+  let mut offset = last_startxref(pdf_data)?;
+  let mut visited = Vec::new();
+  while !visited.contains(&offset) {
+    visited.push(offset);
+    let section = pdf_data.get(offset..)?;
+    let section = &section[skip_whitespace(section, 0)..];
+    if section.starts_with(b"xref") {
+      if let Some(entry) = xref_table_entry(section, obj_num)? {
+        return entry;
+      }
+    } else if let Some((_, entry)) = read_xref_stream(section)?
+      .into_iter()
+      .find(|(num, _)| *num == obj_num)
+    {
+      return Some(entry);
+    }
+    offset = read_number_after(trailer_dict_at(pdf_data, offset)?, b"/Prev")?;
+  }
+  None
+}
+
+/// Entrada do objeto numa tabela "xref" clássica
+///
+/// `Some(None)`: a tabela não lista o objeto; `Some(Some(offset))`: a entrada, `None`
+/// se ela está livre. `None` externo: tabela malformada
+fn xref_table_entry(section: &[u8], obj_num: usize) -> Option<Option<Option<usize>>> {
+  const ENTRY_LEN: usize = 20;
+  let mut pos = skip_whitespace(section, b"xref".len());
+  while section.get(pos).is_some_and(u8::is_ascii_digit) {
+    let first_end = token_end(section, pos);
+    let first = parse_integer(&section[pos..first_end])?;
+    let count_start = skip_whitespace(section, first_end);
+    let count_end = token_end(section, count_start);
+    let count = parse_integer(&section[count_start..count_end])?;
+    let entries = skip_whitespace(section, count_end);
+    if (first..first.saturating_add(count)).contains(&obj_num) {
+      let entry = section.get(entries + (obj_num - first) * ENTRY_LEN..)?;
+      let entry = entry.get(..ENTRY_LEN - 2)?;
+      let offset = parse_integer(&entry[..10])?;
+      return match (entry[10], entry[16], entry[17]) {
+        (b' ', b' ', b'n') => Some(Some(Some(offset))),
+        (b' ', b' ', b'f') => Some(Some(None)),
+        _ => None,
+      };
+    }
+    pos = skip_whitespace(section, entries.checked_add(count.checked_mul(ENTRY_LEN)?)?);
+  }
+  Some(None)
+}
+
+/// Geração da revisão mais recente do objeto (0 quando ele não existe no documento)
+///
+/// Objetos regravados numa atualização incremental mantêm o número e a geração
+pub fn object_generation(pdf_data: &[u8], obj_num: usize) -> u16 {
+  find_object_header(pdf_data, obj_num).map_or(0, |(_, generation)| generation)
+}
+
+/// Seção "N G obj ... endobj" da revisão mais recente do objeto
+pub fn find_object_section(pdf_data: &[u8], obj_num: usize) -> Option<&[u8]> {
+  let (obj_start, _) = find_object_header(pdf_data, obj_num)?;
+  let obj_end = endobj_position(&pdf_data[obj_start..])? + obj_start;
+  Some(&pdf_data[obj_start..obj_end])
+}
+
+/// Posição do "endobj" que fecha o objeto cujo cabeçalho abre `section`
+///
+/// A busca começa depois do objeto direto: um "endobj" dentro de uma string ou de um
+/// dicionário aninhado não encerra o objeto. Objetos desbalanceados ficam com o
+/// primeiro "endobj"
+pub fn endobj_position(section: &[u8]) -> Option<usize> {
+  let find = |from: usize| {
+    section[from..]
+      .windows(b"endobj".len())
+      .position(|w| w == b"endobj")
+      .map(|end| from + end)
+  };
+  let body = section.windows(3).position(|w| w == b"obj")? + 3;
+  let value_end = object_end(section, skip_whitespace(section, body));
+  find(value_end).or_else(|| find(body))
+}
+
+/// Número do objeto ("N G obj") que contém a posição informada
+pub fn object_number_before(pdf_data: &[u8], pos: usize) -> Option<usize> {
+  let mut end = pos;
+  while let Some(obj_pos) = pdf_data[..end].windows(3).rposition(|w| w == b"obj") {
+    end = obj_pos;
+    if let Some((_, num, _)) = object_header_ending_at(pdf_data, obj_pos) {
+      return Some(num);
+    }
+  }
+  None
+}
+
+/// Referência "N G R" logo após a chave (ex: "/Pages 3 1 R" -> (3, 1))
+pub fn read_reference(section: &[u8], key: &[u8]) -> Option<(usize, u16)> {
+  let rest = &section[find_dict_key(section, key)? + key.len()..];
+  let text = String::from_utf8_lossy(&rest[..rest.len().min(48)]);
+  let mut words = text.split(|c: char| c.is_ascii_whitespace() || "/<>[]()".contains(c));
+  let mut words = words.by_ref().filter(|w| !w.is_empty());
+  let num = words.next()?.parse().ok()?;
+  let generation = words.next()?.parse().ok()?;
+  (words.next()? == "R").then_some((num, generation))
+}
+
+/// Decodifica bytes de string PDF: UTF-16BE quando há BOM, senão PDFDocEncoding/Latin-1
+fn decode_pdf_text(bytes: &[u8]) -> String {
+  if let Some(utf16) = bytes.strip_prefix(&[0xfe, 0xff]) {
+    let units: Vec<u16> = utf16
+      .chunks(2)
+      .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]))
+      .collect();
+    return String::from_utf16_lossy(&units);
+  }
+  match std::str::from_utf8(bytes) {
+    Ok(text) => text.to_string(),
+    Err(_) => bytes.iter().map(|&b| b as char).collect(),
+  }
+}
+
+/// Lê uma string literal "( ... )" tratando escapes e parênteses balanceados
+fn parse_literal_string(value: &[u8]) -> Option<Vec<u8>> {
+  let mut out = Vec::new();
+  let mut depth = 0usize;
+  let mut iter = value.iter().copied().peekable();
+
+  while let Some(c) = iter.next() {
+    match c {
+      b'\\' => match iter.next()? {
+        b'n' => out.push(b'\n'),
+        b'r' => out.push(b'\r'),
+        b't' => out.push(b'\t'),
+        b'b' => out.push(0x08),
+        b'f' => out.push(0x0c),
+        b'\r' | b'\n' => {}
+        d @ b'0'..=b'7' => {
+          let mut code = (d - b'0') as u32;
+          for _ in 0..2 {
+            match iter.peek() {
+              Some(&n @ b'0'..=b'7') => {
+                code = code * 8 + (n - b'0') as u32;
+                iter.next();
+              }
+              _ => break,
+            }
+          }
+          out.push(code as u8);
+        }
+        other => out.push(other),
+      },
+      b'(' if depth == 0 && out.is_empty() => depth = 1,
+      b'(' => {
+        depth += 1;
+        out.push(c);
+      }
+      b')' => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(out);
+        }
+        out.push(c);
+      }
+      _ => out.push(c),
+    }
+  }
+
+  None
+}
+
+/// Valor textual de uma chave do dicionário (string literal ou hexadecimal)
+pub fn read_dict_string(section: &[u8], key: &[u8]) -> Option<String> {
+  let mut pos = 0;
+  while let Some(relative) = section[pos..].windows(key.len()).position(|w| w == key) {
+    let value_start = pos + relative + key.len();
+    pos = value_start;
+    if section
+      .get(value_start)
+      .is_some_and(|c| c.is_ascii_alphanumeric())
+    {
+      continue;
+    }
+
+    let value = section[value_start..].trim_ascii_start();
+    if value.starts_with(b"(") {
+      return parse_literal_string(value).map(|bytes| decode_pdf_text(&bytes));
+    }
+    if value.starts_with(b"<") && !value.starts_with(b"<<") {
+      let end = value.iter().position(|&c| c == b'>')?;
+      let mut digits: Vec<u8> = value[1..end]
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+      if digits.len() % 2 == 1 {
+        digits.push(b'0');
+      }
+      return hex::decode(digits)
+        .ok()
+        .map(|bytes| decode_pdf_text(&bytes));
+    }
+  }
+
+  None
+}
+
+/// Lê o primeiro inteiro após a chave informada (ex: "/DocMDP 12 0 R" -> 12)
+pub fn read_number_after(section: &[u8], key: &[u8]) -> Option<usize> {
+  let mut pos = 0;
+  while let Some(relative) = section[pos..].windows(key.len()).position(|w| w == key) {
+    let value_start = pos + relative + key.len();
+    // A chave precisa terminar ali: "/P" não pode casar com "/Perms"
+    if section
+      .get(value_start)
+      .is_some_and(|c| c.is_ascii_alphanumeric() && !c.is_ascii_digit())
+    {
+      pos = value_start;
+      continue;
+    }
+
+    let digits: String = section[value_start..]
+      .iter()
+      .skip_while(|c| c.is_ascii_whitespace())
+      .take_while(|c| c.is_ascii_digit())
+      .map(|&c| c as char)
+      .collect();
+    return digits.parse().ok();
+  }
+
+  None
+}
+
+/// Offset da xref indicado pelo último "startxref", aceitando qualquer fim de linha
+///
+/// Só lê o fim do arquivo: o trailer não é interpretado (para ele, `parse_trailer`)
+pub fn find_startxref(pdf_data: &[u8]) -> Option<usize> {
+  last_startxref(pdf_data)
+}
+
+/// Dados do trailer vigente (o da última atualização incremental)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrailerInfo {
+  /// Offset da xref indicado pelo último "startxref" válido
+  pub startxref: Option<usize>,
+  /// Catalog (/Root), com a geração
+  pub root: Option<(usize, u16)>,
+  /// xref da revisão anterior (/Prev)
+  pub prev: Option<usize>,
+  /// /Size: um a mais que o maior número de objeto
+  pub size: Option<usize>,
+  /// Os dois identificadores do arquivo (/ID), já decodificados
+  pub id: Option<[Vec<u8>; 2]>,
+}
+
+/// Lê o fim do arquivo: o último "startxref" e o trailer da xref para onde ele aponta
+///
+/// Aceita qualquer fim de linha, espaços e comentários entre os tokens; um
+/// "startxref" sem um número válido logo depois é ignorado em favor do anterior.
+/// O trailer é o da tabela (ou o dicionário da xref stream) no offset indicado e,
+/// se o offset não levar a uma xref, o último "trailer" ou xref stream do arquivo.
+/// Entrada malformada resulta em campos `None`, nunca em pânico
+pub fn parse_trailer(pdf_data: &[u8]) -> TrailerInfo {
+  let startxref = last_startxref(pdf_data);
+  let mut info = TrailerInfo {
+    startxref,
+    ..TrailerInfo::default()
+  };
+  let Some(dict) = startxref
+    .and_then(|offset| trailer_dict_at(pdf_data, offset))
+    .or_else(|| last_trailer_dict(pdf_data))
+  else {
+    return info;
+  };
+
+  for (key, entry) in dict_entries(dict) {
+    let value = entry[key.len()..].trim_ascii();
+    match key {
+      b"/Root" => info.root = read_reference(entry, key),
+      b"/Prev" => info.prev = parse_integer(value),
+      b"/Size" => info.size = parse_integer(value),
+      b"/ID" => info.id = parse_id(value),
+      _ => {}
+    }
+  }
+  info
+}
+
+/// Offset após o último "startxref" seguido de um inteiro
+fn last_startxref(pdf_data: &[u8]) -> Option<usize> {
+  let keyword = b"startxref";
+  let mut end = pdf_data.len();
+  while let Some(pos) = pdf_data[..end]
+    .windows(keyword.len())
+    .rposition(|w| w == keyword)
+  {
+    end = pos;
+    let after = pos + keyword.len();
+    let number_start = skip_whitespace(pdf_data, after);
+    // Separado do número por espaço ou comentário (ex: "startxref\r\n116")
+    if number_start == after {
+      continue;
+    }
+    if let Some(offset) = parse_integer(&pdf_data[number_start..token_end(pdf_data, number_start)])
+    {
+      return Some(offset);
+    }
+  }
+  None
+}
+
+/// Inteiro não negativo sem sinal; estouro de `usize` é rejeitado
+fn parse_integer(token: &[u8]) -> Option<usize> {
+  if token.is_empty() || !token.iter().all(u8::is_ascii_digit) {
+    return None;
+  }
+  std::str::from_utf8(token).ok()?.parse().ok()
+}
+
+/// Trailer da xref que começa em `offset`: o dicionário após "trailer" numa tabela
+/// clássica, ou o da própria xref stream
+fn trailer_dict_at(pdf_data: &[u8], offset: usize) -> Option<&[u8]> {
+  let section = pdf_data.get(offset..)?;
+  if !is_xref_section(section) {
+    return None;
+  }
+  let start = skip_whitespace(section, 0);
+  if section[start..].starts_with(b"xref") {
+    let keyword = section
+      .windows(b"trailer".len())
+      .position(|w| w == b"trailer")?;
+    return dict_at(section, keyword + b"trailer".len());
+  }
+  let obj = section.windows(3).position(|w| w == b"obj")?;
+  dict_at(section, obj + 3)
+}
+
+/// Último "trailer" seguido de dicionário ou, se vier depois, a última xref stream
+fn last_trailer_dict(pdf_data: &[u8]) -> Option<&[u8]> {
+  let keyword = b"trailer";
+  let mut end = pdf_data.len();
+  let table = std::iter::from_fn(|| {
+    let pos = pdf_data[..end]
+      .windows(keyword.len())
+      .rposition(|w| w == keyword)?;
+    end = pos;
+    Some(pos)
+  })
+  .find_map(|pos| dict_at(pdf_data, pos + keyword.len()).map(|dict| (pos, dict)));
+
+  let stream = pdf_data
+    .windows(b"/XRef".len())
+    .rposition(|w| w == b"/XRef")
+    .and_then(|pos| pdf_data[..pos].windows(3).rposition(|w| w == b"obj"))
+    .and_then(|obj| dict_at(pdf_data, obj + 3).map(|dict| (obj, dict)));
+
+  table
+    .into_iter()
+    .chain(stream)
+    .max_by_key(|(pos, _)| *pos)
+    .map(|(_, dict)| dict)
+}
+
+/// Conteúdo do dicionário "<< ... >>" que começa em `pos` (após espaços e comentários)
+fn dict_at(data: &[u8], pos: usize) -> Option<&[u8]> {
+  let start = skip_whitespace(data, pos);
+  if !data[start..].starts_with(b"<<") {
+    return None;
+  }
+  let end = object_end(data, start);
+  (end >= start + 4 && data[..end].ends_with(b">>")).then(|| &data[start + 2..end - 2])
+}
+
+/// /ID: array com duas strings, hexadecimais ou literais
+fn parse_id(value: &[u8]) -> Option<[Vec<u8>; 2]> {
+  let inner = value.strip_prefix(b"[")?.strip_suffix(b"]")?;
+  let mut strings = Vec::new();
+  let mut pos = skip_whitespace(inner, 0);
+  while pos < inner.len() {
+    let end = match inner[pos] {
+      b'<' => pos + inner[pos..].iter().position(|&c| c == b'>')? + 1,
+      b'(' => literal_string_end(inner, pos),
+      _ => return None,
+    };
+    let token = &inner[pos..end];
+    strings.push(if token[0] == b'<' {
+      let mut digits: Vec<u8> = token[1..token.len() - 1]
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+      if digits.len() % 2 == 1 {
+        digits.push(b'0');
+      }
+      hex::decode(digits).ok()?
+    } else {
+      parse_literal_string(token)?
+    });
+    pos = skip_whitespace(inner, end);
+  }
+  strings.try_into().ok()
+}
+
+/// Indica se o trecho começa numa seção de referência cruzada: a palavra "xref"
+/// ou um objeto "N G obj" de /Type /XRef (xref stream, PDF 1.5+)
+pub fn is_xref_section(section: &[u8]) -> bool {
+  let start = section
+    .iter()
+    .position(|c| !c.is_ascii_whitespace())
+    .unwrap_or(section.len());
+  let section = &section[start..];
+  if section.starts_with(b"xref") {
+    return section.get(4).is_some_and(|c| c.is_ascii_whitespace());
+  }
+
+  // "N G obj" seguido do dicionário, que termina antes do conteúdo do stream
+  let mut words = section
+    .split(|c| c.is_ascii_whitespace())
+    .filter(|w| !w.is_empty());
+  let is_number = |w: Option<&[u8]>| w.is_some_and(|w| w.iter().all(u8::is_ascii_digit));
+  if !is_number(words.next()) || !is_number(words.next()) {
+    return false;
+  }
+  if !words.next().is_some_and(|w| w.starts_with(b"obj")) {
+    return false;
+  }
+  let head_end = section
+    .windows(b"stream".len())
+    .position(|w| w == b"stream")
+    .unwrap_or(section.len());
+  find_dict_key(&section[..head_end], b"/XRef").is_some()
+}
+
+/// Entradas em uso (objeto, offset) de uma xref stream sem /Filter, como as gravadas
+/// com `XrefFormat::Stream`; streams comprimidos não são lidos
+pub fn read_xref_stream(section: &[u8]) -> Option<Vec<(usize, usize)>> {
+  let head_end = section
+    .windows(b"stream".len())
+    .position(|w| w == b"stream")?;
+  let dict = dict_body(&section[..head_end])?;
+  if !is_xref_section(section) || dict_has_key(dict, b"/Filter") {
+    return None;
+  }
+  let integers = |key: &[u8]| -> Option<Vec<usize>> {
+    let rest = &dict[find_dict_key(dict, key)? + key.len()..];
+    let start = rest.iter().position(|c| !c.is_ascii_whitespace())?;
+    let end = start + rest[start..].iter().position(|&c| c == b']')?;
+    (rest[start] == b'[').then_some(())?;
+    String::from_utf8_lossy(&rest[start + 1..end])
+      .split_whitespace()
+      .map(|n| n.parse().ok())
+      .collect()
+  };
+  let widths = integers(b"/W").filter(|w| w.len() == 3)?;
+  let index = integers(b"/Index").unwrap_or(vec![0, read_number_after(dict, b"/Size")?]);
+
+  // O stream começa depois do fim de linha que segue a palavra "stream"
+  let mut data_start = head_end + b"stream".len();
+  data_start += match section.get(data_start..data_start + 2) {
+    Some(b"\r\n") => 2,
+    _ => 1,
+  };
+  let data = section.get(data_start..data_start + read_number_after(dict, b"/Length")?)?;
+  let row_len: usize = widths.iter().sum();
+  let field = |row: &[u8], at: usize, width: usize| {
+    row[at..at + width]
+      .iter()
+      .fold(0usize, |value, &byte| value << 8 | byte as usize)
+  };
+
+  let mut rows = data.chunks_exact(row_len);
+  let mut entries = Vec::new();
+  for range in index.chunks_exact(2) {
+    for obj in range[0]..range[0] + range[1] {
+      let row = rows.next()?;
+      // Sem a largura do tipo, toda entrada é do tipo 1 (em uso, com offset)
+      let kind = if widths[0] == 0 {
+        1
+      } else {
+        field(row, 0, widths[0])
+      };
+      if kind == 1 {
+        entries.push((obj, field(row, widths[0], widths[1])));
+      }
+    }
+  }
+  Some(entries)
+}
+
+/// Conteúdo entre o primeiro "<<" e o último ">>" de uma seção de objeto
+pub fn dict_body(section: &[u8]) -> Option<&[u8]> {
+  let start = section.windows(2).position(|w| w == b"<<")? + 2;
+  let end = section.windows(2).rposition(|w| w == b">>")?;
+  (end >= start).then(|| &section[start..end])
+}
+
+/// Posição da chave no dicionário, ignorando chaves mais longas com o mesmo prefixo
+fn find_dict_key(dict: &[u8], key: &[u8]) -> Option<usize> {
+  let mut pos = 0;
+  while let Some(relative) = dict[pos..].windows(key.len()).position(|w| w == key) {
+    let key_pos = pos + relative;
+    if !dict
+      .get(key_pos + key.len())
+      .is_some_and(|c| c.is_ascii_alphanumeric())
+    {
+      return Some(key_pos);
+    }
+    pos = key_pos + key.len();
+  }
+  None
+}
+
+/// Indica se o dicionário contém a chave (ex: /V, sem casar com /Version)
+pub fn dict_has_key(dict: &[u8], key: &[u8]) -> bool {
+  find_dict_key(dict, key).is_some()
+}
+
+/// Entradas de primeiro nível do dicionário, como (chave, trecho "chave valor")
+///
+/// O trecho vem intacto mesmo quando o valor ocupa várias linhas ou aninha
+/// dicionários, arrays e strings (ex: /Names, /OpenAction); "N G R" é um valor só
+pub fn dict_entries(dict: &[u8]) -> Vec<(&[u8], &[u8])> {
+  let mut entries = Vec::new();
+  let mut pos = skip_whitespace(dict, 0);
+  while dict.get(pos) == Some(&b'/') {
+    let key_end = token_end(dict, pos + 1);
+    let value_start = skip_whitespace(dict, key_end);
+    let mut value_end = object_end(dict, value_start);
+    if let Some(reference_end) = reference_end(dict, value_start, value_end) {
+      value_end = reference_end;
+    }
+    if value_end == value_start {
+      break;
+    }
+    entries.push((&dict[pos..key_end], &dict[pos..value_end]));
+    pos = skip_whitespace(dict, value_end);
+  }
+  entries
+}
+
+/// Valor da entrada de primeiro nível `key`, sem a chave (ex: "<< ... >>" ou "5 0 R")
+pub fn dict_entry_value<'a>(dict: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+  dict_entries(dict)
+    .into_iter()
+    .find(|(entry_key, _)| *entry_key == key)
+    .map(|(_, entry)| entry[key.len()..].trim_ascii())
+}
+
+/// Corpo do dicionário em `key`, escrito direto (`/K << ... >>`) ou numa referência
+/// (`/K 5 0 R`) resolvida em `pdf_data`
+pub fn resolve_dict<'a>(pdf_data: &'a [u8], dict: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+  let value = dict_entry_value(dict, key)?;
+  if value.starts_with(b"<<") {
+    return value.strip_prefix(b"<<")?.strip_suffix(b">>");
+  }
+  let (obj, _) = read_reference(dict, key)?;
+  find_object_section(pdf_data, obj).and_then(dict_body)
+}
+
+/// Itens do array em `key` (o que fica entre "[" e "]"), direto ou numa referência
+pub fn resolve_array<'a>(pdf_data: &'a [u8], dict: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+  let mut value = dict_entry_value(dict, key)?;
+  if !value.starts_with(b"[") {
+    let (obj, _) = read_reference(dict, key)?;
+    let section = find_object_section(pdf_data, obj)?;
+    let body = section.windows(3).position(|w| w == b"obj")? + 3;
+    value = section[body..].trim_ascii();
+  }
+  value
+    .strip_prefix(b"[")?
+    .strip_suffix(b"]")
+    .map(<[u8]>::trim_ascii)
+}
+
+/// Pula espaços e comentários (%... até o fim da linha)
+fn skip_whitespace(data: &[u8], mut pos: usize) -> usize {
+  while let Some(&c) = data.get(pos) {
+    if c == b'%' {
+      pos += data[pos..]
+        .iter()
+        .position(|&c| c == b'\r' || c == b'\n')
+        .unwrap_or(data.len() - pos);
+    } else if c.is_ascii_whitespace() || c == 0 {
+      pos += 1;
+    } else {
+      break;
+    }
+  }
+  pos
+}
+
+/// Fim de um token regular (nome, número, true, null...), parando nos delimitadores
+fn token_end(data: &[u8], pos: usize) -> usize {
+  pos
+    + data[pos..]
+      .iter()
+      .position(|&c| c.is_ascii_whitespace() || b"/<>[](){}%".contains(&c))
+      .unwrap_or(data.len() - pos)
+}
+
+/// Fim do objeto direto que começa em `start` (após o ">>", "]", ")" ou token)
+fn object_end(data: &[u8], start: usize) -> usize {
+  let mut pos = start;
+  let mut depth = 0usize;
+  while pos < data.len() {
+    match data[pos] {
+      b'(' => pos = literal_string_end(data, pos),
+      b'<' if data.get(pos + 1) == Some(&b'<') => {
+        depth += 1;
+        pos += 2;
+      }
+      b'<' => {
+        pos += data[pos..]
+          .iter()
+          .position(|&c| c == b'>')
+          .map_or(data.len() - pos, |end| end + 1)
+      }
+      b'[' => {
+        depth += 1;
+        pos += 1;
+      }
+      b'>' | b']' if depth == 0 => return pos,
+      b'>' => {
+        depth -= 1;
+        pos += if data.get(pos + 1) == Some(&b'>') {
+          2
+        } else {
+          1
+        };
+      }
+      b']' => {
+        depth -= 1;
+        pos += 1;
+      }
+      b'/' => pos = token_end(data, pos + 1),
+      c if c.is_ascii_whitespace() || c == b'%' => {
+        pos = skip_whitespace(data, pos);
+        continue;
+      }
+      _ => pos = token_end(data, pos).max(pos + 1),
+    }
+    if depth == 0 {
+      return pos;
+    }
+  }
+  pos
+}
+
+/// Depois de um inteiro em `start..value_end`, o fim de "N G R" se for uma referência
+fn reference_end(data: &[u8], start: usize, value_end: usize) -> Option<usize> {
+  let is_integer = |token: &[u8]| !token.is_empty() && token.iter().all(u8::is_ascii_digit);
+  if !is_integer(&data[start..value_end]) {
+    return None;
+  }
+  let generation_start = skip_whitespace(data, value_end);
+  let generation_end = token_end(data, generation_start);
+  if !is_integer(&data[generation_start..generation_end]) {
+    return None;
+  }
+  let r_start = skip_whitespace(data, generation_end);
+  (token_end(data, r_start) == r_start + 1 && data[r_start] == b'R').then_some(r_start + 1)
+}
+
+/// Posição após o ")" que fecha a string literal iniciada em `start`
+fn literal_string_end(data: &[u8], start: usize) -> usize {
+  let mut depth = 0usize;
+  let mut pos = start;
+  while pos < data.len() {
+    match data[pos] {
+      b'\\' => pos += 1,
+      b'(' => depth += 1,
+      b')' => {
+        depth -= 1;
+        if depth == 0 {
+          return pos + 1;
+        }
+      }
+      _ => {}
+    }
+    pos += 1;
+  }
+  data.len()
+}
+
+/// Remove uma entrada de valor simples (número ou nome) do dicionário
+pub fn remove_dict_entry(dict: &[u8], key: &[u8]) -> String {
+  let text = String::from_utf8_lossy(dict).to_string();
+  let Some(key_pos) = find_dict_key(dict, key) else {
+    return text;
+  };
+
+  let value_start = key_pos + key.len();
+  let rest = &dict[value_start..];
+  let skipped = rest.len() - rest.trim_ascii_start().len();
+  let value_len = rest[skipped..]
+    .iter()
+    .enumerate()
+    .position(|(i, c)| c.is_ascii_whitespace() || (i > 0 && b"/<>[]()".contains(c)))
+    .unwrap_or(rest.len() - skipped);
+
+  let mut out = dict[..key_pos].to_vec();
+  out.extend_from_slice(&dict[value_start + skipped + value_len..]);
+  String::from_utf8_lossy(&out).to_string()
+}
+
+/// Objeto do campo de assinatura (/FT /Sig) com o nome /T informado
+pub fn find_signature_field(pdf_data: &[u8], field_name: &str) -> Option<usize> {
+  let mut found = None;
+  for marker in [b"/FT /Sig" as &[u8], b"/FT/Sig"] {
+    let mut pos = 0;
+    while let Some(relative) = pdf_data[pos..]
+      .windows(marker.len())
+      .position(|w| w == marker)
+    {
+      let marker_pos = pos + relative;
+      pos = marker_pos + marker.len();
+
+      let Some(obj) = object_number_before(pdf_data, marker_pos) else {
+        continue;
+      };
+      // Usa a revisão mais recente do objeto, que pode ter recebido /V depois
+      let matches = find_object_section(pdf_data, obj)
+        .and_then(|section| read_dict_string(section, b"/T"))
+        .is_some_and(|name| name == field_name);
+      if matches {
+        found = Some(obj);
+      }
+    }
+  }
+  found
+}
+
+/// Nível de permissão /P da assinatura de certificação (DocMDP), se houver
+///
+/// Segue /Root do trailer até /Perms /DocMDP do Catalog vigente e lê /P dos
+/// /TransformParams da assinatura referenciada; sem /P explícito vale o padrão 2
+/// (ISO 32000-1, Tabela 254). /P fora de 1 a 3 é recusado
+pub fn find_docmdp_permission(pdf_data: &[u8]) -> Result<Option<u8>> {
+  let catalog_obj = extract_catalog_info(pdf_data)?.catalog_obj;
+  let Some(catalog) = find_object_section(pdf_data, catalog_obj).and_then(dict_body) else {
+    return Ok(None);
+  };
+  let Some((sig_obj, _)) =
+    resolve_dict(pdf_data, catalog, b"/Perms").and_then(|perms| read_reference(perms, b"/DocMDP"))
+  else {
+    return Ok(None);
+  };
+  let Some(sig_section) = find_object_section(pdf_data, sig_obj) else {
+    return Ok(None);
+  };
+
+  let params = sig_section
+    .windows(b"/TransformParams".len())
+    .position(|w| w == b"/TransformParams")
+    .and_then(|pos| dict_at(sig_section, pos + b"/TransformParams".len()));
+  let Some(value) = params.and_then(|params| dict_entry_value(params, b"/P")) else {
+    return Ok(Some(2));
+  };
+  match std::str::from_utf8(value)
+    .ok()
+    .and_then(|p| p.parse::<u8>().ok())
+  {
+    Some(permission @ 1..=3) => Ok(Some(permission)),
+    _ => Err(PdfSignError::InvalidPdf(format!(
+      "DocMDP com /P {} (precisa ser 1, 2 ou 3)",
+      String::from_utf8_lossy(value)
+    ))),
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn test_first_page_follows_page_tree() {
+    // A página 4 vem antes no arquivo, mas a árvore exibe a 9 primeiro (via nó 3)
+    let pdf = b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 2 >>\nendobj\n4 0 obj\n<< /Type /Page /Parent 3 0 R /MediaBox [0 0 612 792] >>\nendobj\n3 0 obj\n<< /Type /Pages /Parent 2 0 R /Kids [9 0 R 4 0 R] /Count 2 >>\nendobj\n9 0 obj\n<< /Type /Page /Parent 3 0 R /MediaBox [0 0 612 792] >>\nendobj\ntrailer\n<< /Size 10 /Root 1 0 R >>\n%%EOF\n";
+
+    assert_eq!(extract_first_page_info(pdf).unwrap().first_page_obj, 9);
+    assert_eq!(find_first_page_by_pattern(pdf), Some(4));
+
+    // Sem árvore legível, a busca por /Type /Page continua valendo
+    let broken = String::from_utf8_lossy(pdf).replace("/Kids [3 0 R]", "/Kids [30 0 R]");
+    assert_eq!(
+      extract_first_page_info(broken.as_bytes())
+        .unwrap()
+        .first_page_obj,
+      4
+    );
+  }
+
+  #[test]
+  fn test_extract_page_mediabox_inherits_and_resolves_references() {
+    let pdf = b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n2 0 obj\n<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 /MediaBox [0 0 595 842] >>\nendobj\n3 0 obj\n<< /Type /Page /Parent 2 0 R /Resources << /Font << /F1 7 0 R >> >> >>\nendobj\n4 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox 5 0 R >>\nendobj\n5 0 obj\n[612 792 0 0]\nendobj\ntrailer\n<< /Size 6 /Root 1 0 R >>\n%%EOF\n";
+
+    // Página 3 herda do nó /Pages; a 4 aponta para um array indireto, invertido
+    assert_eq!(
+      extract_page_mediabox(pdf, 3),
+      Some([0.0, 0.0, 595.0, 842.0])
+    );
+    assert_eq!(
+      extract_page_mediabox(pdf, 4),
+      Some([0.0, 0.0, 612.0, 792.0])
+    );
+
+    let without_box = String::from_utf8_lossy(pdf).replace(" /MediaBox [0 0 595 842]", "");
+    assert_eq!(extract_page_mediabox(without_box.as_bytes(), 3), None);
+  }
+
+  #[test]
+  fn test_extract_page_mediabox_walks_up_nested_pages_nodes() {
+    // Página 9 -> nó 3 (sem /MediaBox) -> raiz 2, com a /MediaBox em objeto indireto
+    let pdf = b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 1 /MediaBox 6 0 R >>\nendobj\n3 0 obj\n<< /Type /Pages /Parent 2 0 R /Kids [9 0 R] /Count 1 >>\nendobj\n6 0 obj\n[ 0 0 841.89 595.28 ]\nendobj\n9 0 obj\n<< /Type /Page /Parent 3 0 R >>\nendobj\ntrailer\n<< /Size 10 /Root 1 0 R >>\n%%EOF\n";
+
+    assert_eq!(
+      extract_page_mediabox(pdf, find_page_object(pdf, 0).unwrap()),
+      Some([0.0, 0.0, 841.89, 595.28])
+    );
+
+    // Um /Parent que volta para a própria página não trava a busca
+    let cyclic = String::from_utf8_lossy(pdf).replace("/Parent 2 0 R", "/Parent 9 0 R");
+    assert_eq!(extract_page_mediabox(cyclic.as_bytes(), 9), None);
+  }
+
+  #[test]
+  fn test_dict_entries_keeps_nested_values_intact() {
+    let dict = b"/Type /Catalog /Pages 2 0 R /OpenAction [3 0 R\n  /FitH 800] % comentario\n/Names << /Dests 6 0 R /JavaScript << /Names [(a\\)b) 7 0 R] >> >>\n/Lang (pt-BR) /Version /1.7 /Metadata 9 0 R /ID <ABCD>";
+    let entries: Vec<(String, String)> = dict_entries(dict)
+      .into_iter()
+      .map(|(key, entry)| {
+        (
+          String::from_utf8_lossy(key).to_string(),
+          String::from_utf8_lossy(entry).to_string(),
+        )
+      })
+      .collect();
+
+    let expected = [
+      ("/Type", "/Type /Catalog"),
+      ("/Pages", "/Pages 2 0 R"),
+      ("/OpenAction", "/OpenAction [3 0 R\n  /FitH 800]"),
+      (
+        "/Names",
+        "/Names << /Dests 6 0 R /JavaScript << /Names [(a\\)b) 7 0 R] >> >>",
+      ),
+      ("/Lang", "/Lang (pt-BR)"),
+      ("/Version", "/Version /1.7"),
+      ("/Metadata", "/Metadata 9 0 R"),
+      ("/ID", "/ID <ABCD>"),
+    ];
+    assert_eq!(entries.len(), expected.len());
+    for ((key, entry), (expected_key, expected_entry)) in entries.iter().zip(expected) {
+      assert_eq!(key, expected_key);
+      assert_eq!(entry, expected_entry);
+    }
+  }
+
   #[test]
   fn test_remove_trailing_newline() {
     let pdf = b"test\n\n".to_vec();
@@ -336,10 +1486,547 @@ mod tests {
     assert_eq!(result, b"test");
   }
 
+  #[test]
+  fn test_parse_pdf_version() {
+    assert_eq!(
+      parse_pdf_version(b"%PDF-1.4\n%\xe2\xe3\n1 0 obj"),
+      Some((1, 4))
+    );
+    assert_eq!(parse_pdf_version(b"\xef\xbb\xbf%PDF-2.0\r\n"), Some((2, 0)));
+
+    assert_eq!(parse_pdf_version(b"PK\x03\x04word/document.xml"), None);
+    assert_eq!(parse_pdf_version(b"%PDF-"), None);
+    assert_eq!(parse_pdf_version(b"%PDF-1."), None);
+    assert_eq!(parse_pdf_version(b""), None);
+  }
+
+  #[test]
+  fn test_validate_pdf() {
+    let sample = std::fs::read(concat!(
+      env!("CARGO_MANIFEST_DIR"),
+      "/__test__/pdf_sample_2.pdf"
+    ))
+    .unwrap();
+    let valid = validate_pdf(&sample);
+    assert_eq!(
+      valid,
+      PdfValidation {
+        is_valid: true,
+        pdf_version: Some("1.4".to_string()),
+        has_acroform: false,
+        first_page_found: true,
+        catalog_found: true,
+        error: None,
+      }
+    );
+
+    // Não é PDF
+    let docx = validate_pdf(b"PK\x03\x04word/document.xml");
+    assert!(!docx.is_valid);
+    assert_eq!(docx.pdf_version, None);
+    assert!(!docx.catalog_found && !docx.first_page_found);
+    assert!(docx.error.unwrap().contains("%PDF-x.y"));
+
+    // Cabeçalho sem Catalog nem páginas (upload truncado)
+    let truncated = validate_pdf(&sample[..64]);
+    assert!(!truncated.is_valid);
+    assert_eq!(truncated.pdf_version.as_deref(), Some("1.4"));
+    assert!(!truncated.catalog_found);
+    assert!(truncated.error.is_some());
+
+    // Catalog com /AcroForm, mas sem nenhuma página
+    let no_pages = validate_pdf(
+      b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R /AcroForm << /Fields [] >> >>\nendobj\n2 0 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\ntrailer\n<< /Root 1 0 R >>\n",
+    );
+    assert!(!no_pages.is_valid);
+    assert!(no_pages.catalog_found && no_pages.has_acroform);
+    assert!(!no_pages.first_page_found);
+    assert!(no_pages.error.unwrap().contains("primeira página"));
+  }
+
+  #[test]
+  fn test_is_linearized() {
+    let sample = std::fs::read(concat!(
+      env!("CARGO_MANIFEST_DIR"),
+      "/__test__/pdf_sample_2.pdf"
+    ))
+    .unwrap();
+    assert!(is_linearized(&sample));
+    assert!(is_linearized(
+      b"%PDF-1.7\n%\xe2\xe3\n1 0 obj\n<< /Linearized 1.0 /L 9000 >>\nendobj\n"
+    ));
+
+    // /Linearized fora do primeiro objeto, ou depois dos primeiros 1024 bytes
+    assert!(!is_linearized(
+      b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog >>\nendobj\n2 0 obj\n<< /Linearized 1 >>\nendobj\n"
+    ));
+    let mut late = b"%PDF-1.7\n".to_vec();
+    late.extend(std::iter::repeat_n(b'%', 1100));
+    late.extend_from_slice(b"\n1 0 obj\n<< /Linearized 1 >>\nendobj\n");
+    assert!(!is_linearized(&late));
+    assert!(!is_linearized(b"%PDF-1.7\n"));
+  }
+
+  #[test]
+  fn test_pdf_literal_string() {
+    assert_eq!(pdf_literal_string("Assinatura1"), "(Assinatura1)");
+    assert_eq!(pdf_literal_string("a (b) c\\d"), "(a \\(b\\) c\\\\d)");
+  }
+
+  #[test]
+  fn test_pdf_name() {
+    assert_eq!(pdf_name("pdfsigner-rs"), "/pdfsigner-rs");
+    assert_eq!(pdf_name("Meu App#1"), "/Meu#20App#231");
+  }
+
+  #[test]
+  fn test_pdf_text_string() {
+    assert_eq!(pdf_text_string("Brasil", TextEncoding::Auto), "(Brasil)");
+    assert_eq!(
+      pdf_text_string("São Paulo", TextEncoding::Auto),
+      "<FEFF005300E3006F0020005000610075006C006F>"
+    );
+    assert_eq!(
+      pdf_text_string("São (SP)", TextEncoding::Literal),
+      "(São \\(SP\\))"
+    );
+    assert_eq!(
+      pdf_text_string("Rio", TextEncoding::Utf16Be),
+      "<FEFF00520069006F>"
+    );
+    // Fora do BMP vira par substituto
+    assert_eq!(
+      pdf_text_string("✓𝄞", TextEncoding::Auto),
+      "<FEFF2713D834DD1E>"
+    );
+  }
+
   #[test]
   fn test_get_next_object_number() {
     let pdf = b"1 0 obj\n<<\n>>\n5 0 obj\n<<\n>>\n";
     let result = get_next_object_number(pdf).unwrap();
     assert_eq!(result, 6);
   }
+
+  #[test]
+  fn test_get_next_object_number_cr_line_endings() {
+    let pdf = b"1 0 obj\r<<\r>>\rendobj\r18 0 obj\r<<\r>>\rendobj\r";
+    assert_eq!(get_next_object_number(pdf).unwrap(), 19);
+  }
+
+  #[test]
+  fn test_object_lookup_accepts_any_generation() {
+    let pdf = b"%PDF-1.7\n1 1 obj\n<< /Type /Catalog /Pages 12 2 R >>\nendobj\n12 2 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\n2 0 obj\n<< /Type /Page /Parent 12 2 R >>\nendobj\ntrailer\n<< /Root 1 1 R >>\n";
+    assert_eq!(get_next_object_number(pdf).unwrap(), 13);
+    assert_eq!(find_object_header(pdf, 12).map(|(_, g)| g), Some(2));
+    assert_eq!(object_generation(pdf, 1), 1);
+    assert_eq!(object_generation(pdf, 2), 0);
+    assert_eq!(object_generation(pdf, 99), 0);
+    // "2 obj" dentro de "12 2 obj" não é o objeto 2
+    assert!(find_object_section(pdf, 2).unwrap().starts_with(b"2 0 obj"));
+    assert_eq!(parse_object_header(b"12 2 obj\n<<"), Some((12, 2)));
+    assert_eq!(parse_object_header(b"\n12 2 obj"), None);
+    assert_eq!(
+      read_reference(b"/Pages 12 2 R /Count 1", b"/Pages"),
+      Some((12, 2))
+    );
+    assert_eq!(read_reference(b"/Pages 12", b"/Pages"), None);
+
+    let info = extract_catalog_info(pdf).unwrap();
+    assert_eq!((info.catalog_obj, info.pages_ref), (1, 12));
+  }
+
+  fn certified_pdf(permission: Option<u8>) -> Vec<u8> {
+    let p_entry = permission
+      .map(|p| format!("/P {}\n", p))
+      .unwrap_or_default();
+    format!(
+      "%PDF-1.7\n1 0 obj\n<<\n/Type /Catalog\n/Pages 2 0 R\n/Perms <<\n/DocMDP 17 0 R\n>>\n>>\nendobj\n7 0 obj\n<<\n/Type /Sig\n>>\nendobj\n17 0 obj\n<<\n/Type /Sig\n/Reference [<<\n/Type /SigRef\n/TransformMethod /DocMDP\n/TransformParams <<\n/Type /TransformParams\n{}/V /1.2\n>>\n>>]\n/Prop_Build <<\n>>\n>>\nendobj\n",
+      p_entry
+    )
+    .into_bytes()
+  }
+
+  #[test]
+  fn test_object_lookup_with_glued_endobj() {
+    // PDF compacto: nenhum espaço em volta de "endobj"
+    let pdf = b"%PDF-1.7\n1 0 obj<</Type/Catalog/Pages 2 0 R/AcroForm 4 0 R>>endobj2 0 obj<</Type/Pages/Kids[3 0 R]/Count 1>>endobj3 0 obj<</Type/Page/Parent 2 0 R/MediaBox[0 0 612 792]>>endobj4 0 obj<</Fields[]>>endobj\ntrailer<</Size 5/Root 1 0 R>>\n";
+    assert!(find_object_section(pdf, 2)
+      .unwrap()
+      .starts_with(b"2 0 obj<</Type/Pages"));
+    assert!(find_object_section(pdf, 3).unwrap().ends_with(b"792]>>"));
+    assert_eq!(object_number_before(pdf, pdf.len() - 30), Some(4));
+    assert_eq!(get_next_object_number(pdf).unwrap(), 5);
+
+    let info = extract_catalog_info(pdf).unwrap();
+    assert_eq!((info.catalog_obj, info.pages_ref), (1, 2));
+    assert!(info.has_acroform);
+    assert_eq!(find_page_object(pdf, 0), Some(3));
+    // Outras palavras coladas ao número continuam não formando cabeçalho
+    assert!(find_object_header(b"xendobjx7 0 obj<<>>", 7).is_none());
+  }
+
+  #[test]
+  fn test_find_object_section_skips_endobj_in_strings() {
+    let pdf = b"1 0 obj\n<< /Type /Catalog /Lang (endobj) /Pages 2 0 R >>\nendobj\n2 0 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\n";
+    let catalog = find_object_section(pdf, 1).unwrap();
+    assert!(catalog.ends_with(b"/Pages 2 0 R >>\n"));
+    assert_eq!(
+      read_reference(dict_body(catalog).unwrap(), b"/Pages"),
+      Some((2, 0))
+    );
+
+    // Dicionário sem fechamento: fica o primeiro "endobj", como antes
+    assert_eq!(endobj_position(b"3 0 obj\n<< /A (x)\nendobj\n"), Some(18));
+    assert_eq!(endobj_position(b"4 0 obj\n[1 2]endobj"), Some(13));
+  }
+
+  /// PDF com o Catalog e, depois dele, um stream cujo conteúdo imita "1 0 obj"
+  fn pdf_with_decoy_catalog() -> Vec<u8> {
+    let mut pdf = b"%PDF-1.7\n".to_vec();
+    let mut offsets = Vec::new();
+    for object in [
+      "1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n".to_string(),
+      "2 0 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\n".to_string(),
+      "3 0 obj\n<< /Length 26 >>\nstream\n1 0 obj\n<< /Decoy true >>\nendstream\nendobj\n"
+        .to_string(),
+    ] {
+      offsets.push(pdf.len());
+      pdf.extend_from_slice(object.as_bytes());
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(b"xref\n0 1\n0000000000 65535 f \n1 3\n");
+    for offset in offsets {
+      pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+      format!(
+        "trailer\n<< /Size 4 /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        xref
+      )
+      .as_bytes(),
+    );
+    pdf
+  }
+
+  #[test]
+  fn test_find_object_header_uses_xref_offsets() {
+    let pdf = pdf_with_decoy_catalog();
+    let catalog = find_object_section(&pdf, 1).unwrap();
+    assert!(dict_has_key(catalog, b"/Catalog"), "{:?}", catalog);
+    assert_eq!(xref_object_offset(&pdf, 1), Some(9));
+    assert_eq!(xref_object_offset(&pdf, 0), None);
+    assert_eq!(xref_object_offset(&pdf, 4), None);
+
+    // Sem xref válida a varredura de trás para frente continua valendo
+    let end = pdf.windows(9).rposition(|w| w == b"startxref").unwrap();
+    let broken = [&pdf[..end], b"startxref\n3\n%%EOF\n"].concat();
+    assert_eq!(xref_object_offset(&broken, 1), None);
+    assert!(find_object_section(&broken, 1).is_some_and(|section| dict_has_key(section, b"/Decoy")));
+    assert!(find_object_section(&broken, 2).is_some_and(|section| dict_has_key(section, b"/Pages")));
+  }
+
+  #[test]
+  fn test_xref_table_entry() {
+    let table =
+      b"xref\n0 2\n0000000000 65535 f \n0000000017 00000 n \n7 1\n0000000123 00002 n\r\ntrailer";
+    assert_eq!(xref_table_entry(table, 0), Some(Some(None)));
+    assert_eq!(xref_table_entry(table, 1), Some(Some(Some(17))));
+    assert_eq!(xref_table_entry(table, 7), Some(Some(Some(123))));
+    assert_eq!(xref_table_entry(table, 5), Some(None));
+    // Entradas de 19 bytes não cabem na conta de posição fixa
+    assert_eq!(
+      xref_table_entry(b"xref\n0 2\n0000000000 65535 f\n0000000017 00000 n\n", 1),
+      None
+    );
+  }
+
+  #[test]
+  fn test_read_xref_stream() {
+    // /Index com duas faixas; a entrada do tipo 0 (livre) fica de fora
+    let mut section =
+      b"9 0 obj\n<< /Type /XRef /Size 10 /W [1 2 0] /Index [0 1 8 2] /Length 9 >>\nstream\n"
+        .to_vec();
+    section.extend_from_slice(&[0, 0, 0, 1, 0x01, 0x2c, 1, 0x02, 0x00]);
+    section.extend_from_slice(b"\nendstream\nendobj\n");
+    assert_eq!(read_xref_stream(&section), Some(vec![(8, 300), (9, 512)]));
+
+    let compressed =
+      b"9 0 obj\n<< /Type /XRef /Filter /FlateDecode /W [1 2 0] /Length 3 >>\nstream\n\x78\x9c\x00";
+    assert_eq!(read_xref_stream(compressed), None);
+    assert_eq!(read_xref_stream(b"xref\n0 1\n0000000000 65535 f \n"), None);
+  }
+
+  #[test]
+  fn test_find_docmdp_permission() {
+    let permission = |pdf: &[u8]| find_docmdp_permission(pdf).unwrap();
+    assert_eq!(permission(&certified_pdf(Some(1))), Some(1));
+    assert_eq!(permission(&certified_pdf(Some(3))), Some(3));
+    assert_eq!(permission(&certified_pdf(None)), Some(2));
+    assert_eq!(
+      permission(b"%PDF-1.4\n1 0 obj\n<<\n/Type /Catalog\n>>\nendobj\n"),
+      None
+    );
+
+    // 257 não pode virar 1 por truncamento, e valores fora de 1 a 3 são recusados
+    for invalid in [0, 4, 257] {
+      let pdf = String::from_utf8(certified_pdf(Some(1)))
+        .unwrap()
+        .replace("/P 1\n", &format!("/P {}\n", invalid));
+      assert!(matches!(
+        find_docmdp_permission(pdf.as_bytes()),
+        Err(PdfSignError::InvalidPdf(message)) if message.contains(&invalid.to_string())
+      ));
+    }
+  }
+
+  #[test]
+  fn test_find_docmdp_permission_indirect_perms() {
+    let pdf = b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog /Perms 4 0 R >>\nendobj\n4 0 obj\n<< /DocMDP 5 0 R >>\nendobj\n5 0 obj\n<< /Type /Sig /Reference [<< /TransformMethod /DocMDP /TransformParams << /P 1 /V /1.2 >> >>] >>\nendobj\n";
+    assert_eq!(find_docmdp_permission(pdf).unwrap(), Some(1));
+  }
+
+  #[test]
+  fn test_find_docmdp_permission_reads_the_current_catalog() {
+    // /Perms fora do Catalog (num dicionário qualquer, depois dele) não certifica o documento
+    let pdf = b"%PDF-1.7\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n3 0 obj\n<< /Perms << /DocMDP 5 0 R >> >>\nendobj\n5 0 obj\n<< /Type /Sig /Reference [<< /TransformMethod /DocMDP /TransformParams << /P 1 /V /1.2 >> >>] >>\nendobj\ntrailer\n<< /Size 6 /Root 1 0 R >>\n";
+    assert_eq!(find_docmdp_permission(pdf).unwrap(), None);
+  }
+
+  #[test]
+  fn test_read_dict_string() {
+    let section = b"<< /Name /Adobe.PPKLite /T (Campo \\(1\\)) /Reason <FEFF00C7> /M (D:2024) >>";
+    assert_eq!(
+      read_dict_string(section, b"/T").as_deref(),
+      Some("Campo (1)")
+    );
+    assert_eq!(read_dict_string(section, b"/Reason").as_deref(), Some("Ç"));
+    assert_eq!(read_dict_string(section, b"/M").as_deref(), Some("D:2024"));
+    assert_eq!(read_dict_string(section, b"/Name"), None);
+  }
+
+  #[test]
+  fn test_find_signature_field_and_dict_helpers() {
+    let pdf = b"%PDF-1.4\n3 0 obj\n<< /FT /Tx /T (Nome) >>\nendobj\n4 0 obj\n<< /FT /Sig /T (Cliente) /Rect [0 0 10 10] >>\nendobj\n";
+    assert_eq!(find_signature_field(pdf, "Cliente"), Some(4));
+    assert_eq!(find_signature_field(pdf, "Nome"), None);
+
+    let body = dict_body(find_object_section(pdf, 4).unwrap()).unwrap();
+    assert!(!dict_has_key(body, b"/V"));
+    assert!(dict_has_key(body, b"/Rect"));
+    assert_eq!(
+      remove_dict_entry(b" /Fields [1 0 R] /SigFlags 1 /DA (x)", b"/SigFlags"),
+      " /Fields [1 0 R]  /DA (x)"
+    );
+  }
+
+  #[test]
+  fn test_find_startxref_any_line_ending() {
+    assert_eq!(
+      find_startxref(b"startxref\n10\n%%EOF\nstartxref\n20\n%%EOF\n"),
+      Some(20)
+    );
+    assert_eq!(
+      find_startxref(b"trailer\r<<>>\rstartxref\r116\r%%EOF\r"),
+      Some(116)
+    );
+    assert_eq!(find_startxref(b"%PDF-1.4\n"), None);
+  }
+
+  /// Revisão com tabela xref: objetos, "xref" no offset anotado e trailer com `trailer`
+  fn revision(pdf: &mut Vec<u8>, objects: &str, trailer: &str, tail: &str) -> usize {
+    pdf.extend_from_slice(objects.as_bytes());
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+      format!(
+        "xref\n0 1\n0000000000 65535 f \ntrailer\n<< {} >>\nstartxref{}{}",
+        trailer, tail, xref
+      )
+      .as_bytes(),
+    );
+    xref
+  }
+
+  #[test]
+  fn test_parse_trailer_reads_the_revision_at_startxref() {
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let first = revision(
+      &mut pdf,
+      "1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n",
+      "/Size 3 /Root 1 0 R /ID [<0A1B2> (ab\\)c)]",
+      "\r\n",
+    );
+    pdf.extend_from_slice(b"\r\n%%EOF\r\n");
+    let first_end = pdf.len();
+    let second = revision(
+      &mut pdf,
+      "5 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n",
+      &format!(
+        "/Size 6 /Prev {} /Root 5 1 R /Info << /Producer (x) >>",
+        first
+      ),
+      " % comentário\n",
+    );
+    pdf.extend_from_slice(b"\n%%EOF\n  \n% lixo depois do fim\n");
+
+    let info = parse_trailer(&pdf);
+    assert_eq!(info.startxref, Some(second));
+    assert_eq!(info.root, Some((5, 1)));
+    assert_eq!(
+      (info.prev, info.size, info.id),
+      (Some(first), Some(6), None)
+    );
+    assert_eq!(extract_catalog_info(&pdf).unwrap().catalog_obj, 5);
+
+    // Só a primeira revisão: /ID com hex ímpar e string literal com escape
+    let info = parse_trailer(&pdf[..first_end]);
+    assert_eq!(info.startxref, Some(first));
+    assert_eq!(
+      (info.root, info.prev, info.size),
+      (Some((1, 0)), None, Some(3))
+    );
+    assert_eq!(info.id, Some([vec![0x0a, 0x1b, 0x20], b"ab)c".to_vec()]));
+
+    // startxref sem número vale o anterior; offset que não leva a uma xref cai no
+    // último trailer do arquivo
+    let mut broken = pdf.clone();
+    broken.extend_from_slice(b"startxref\nabc\n%%EOF\n");
+    assert_eq!(parse_trailer(&broken).startxref, Some(second));
+    let mut misplaced = pdf[..pdf.len() - 40].to_vec();
+    misplaced.extend_from_slice(b"startxref\n3\n%%EOF\n");
+    let info = parse_trailer(&misplaced);
+    assert_eq!((info.startxref, info.root), (Some(3), Some((5, 1))));
+  }
+
+  #[test]
+  fn test_parse_trailer_reads_xref_stream_dict() {
+    let pdf = b"%PDF-1.5\n1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n9 0 obj\n<< /Type /XRef /Size 10 /W [1 2 2] /Prev 4 /Root 1 0 R /Length 0 >>\nstream\n\nendstream\nendobj\nstartxref\n58\n%%EOF\n";
+    let info = parse_trailer(pdf);
+    assert_eq!(info.startxref, Some(58));
+    assert_eq!(
+      (info.root, info.prev, info.size),
+      (Some((1, 0)), Some(4), Some(10))
+    );
+  }
+
+  #[test]
+  fn test_parse_trailer_survives_malformed_tails() {
+    for tail in [
+      b"" as &[u8],
+      b"startxref",
+      b"startxref\n",
+      b"startxref\n99999999999999999999999999999\n%%EOF",
+      b"startxref\n-12\n",
+      b"startxref12",
+      b"trailer",
+      b"trailer\n<<",
+      b"trailer\n<< /Root",
+      b"trailer\n<< /Root 1 >>",
+      b"trailer\n<< /ID [<zz> (a] /Size -1 /Prev 1.5 >>",
+      b"trailer\n<< /ID [(a) (b) (c)] >>\nstartxref\n0\n",
+      b"xref\ntrailer\n<< /Root 1 0 R\nstartxref\n0\n",
+      b">>]))<<[[startxref\r\r\n",
+    ] {
+      let info = parse_trailer(tail);
+      assert_eq!((info.root, info.id), (None, None), "{:?}", tail);
+    }
+    assert_eq!(
+      parse_trailer(b"startxref\n99999999999999999999999999999\n").startxref,
+      None
+    );
+    assert_eq!(parse_trailer(b"startxref%c\n12\n").startxref, Some(12));
+
+    // Cortes e bytes trocados por delimitadores num tail válido não causam pânico
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    revision(
+      &mut pdf,
+      "1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n",
+      "/Size 3 /Root 1 0 R /ID [<0A1B> (a\\(b)] /Info << /T [(x) <41>] >>",
+      "\r\n",
+    );
+    pdf.extend_from_slice(b"\r\n%%EOF\r\n");
+    for len in 0..=pdf.len() {
+      parse_trailer(&pdf[..len]);
+      parse_trailer(&pdf[len..]);
+    }
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    for _ in 0..2000 {
+      let mut mutated = pdf.clone();
+      for _ in 0..4 {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let pos = (seed % mutated.len() as u64) as usize;
+        let delimiters = b"<>[]()/%\\\r\n 07R";
+        mutated[pos] = delimiters[(seed >> 32) as usize % delimiters.len()];
+      }
+      parse_trailer(&mutated);
+    }
+  }
+
+  #[test]
+  fn test_find_page_object_walks_kids_in_order() {
+    let pdf = b"%PDF-1.7\n\
+1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n\
+2 0 obj\n<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 3 >>\nendobj\n\
+3 0 obj\n<< /Type /Pages /Kids [5 0 R\n6 0 R] /Count 2 >>\nendobj\n\
+4 0 obj\n<< /Type /Page /Parent 2 0 R >>\nendobj\n\
+5 0 obj\n<< /Type /Page /Parent 3 0 R >>\nendobj\n\
+6 0 obj\n<< /Type /Page /Parent 3 0 R >>\nendobj\n\
+trailer\n<< /Root 1 0 R >>\n";
+
+    assert_eq!(find_page_object(pdf, 0), Some(5));
+    assert_eq!(find_page_object(pdf, 1), Some(6));
+    assert_eq!(find_page_object(pdf, 2), Some(4));
+    assert_eq!(find_page_object(pdf, 3), None);
+    assert_eq!(
+      read_ref_array(b"<< /Kids [5 0 R 6 0 R] >>", b"/Kids"),
+      Some(vec![5, 6])
+    );
+    assert_eq!(read_ref_array(b"<< /Kids 9 0 R >>", b"/Kids"), None);
+  }
+
+  #[test]
+  fn test_is_xref_section() {
+    assert!(is_xref_section(b"xref\n0 1\n0000000000 65535 f \n"));
+    assert!(is_xref_section(b"\r\nxref\r\n0 1\r\n"));
+    assert!(is_xref_section(
+      b"12 0 obj\n<< /Type /XRef /Size 13 /W [1 2 1] >>\nstream\n"
+    ));
+    assert!(is_xref_section(b"12 0 obj<</Type/XRef/Size 13>>stream\n"));
+
+    assert!(!is_xref_section(b"xrefs\n"));
+    assert!(!is_xref_section(b"ef\n0 1\n"));
+    assert!(!is_xref_section(b"3 0 obj\n<< /Type /Page >>\nendobj\n"));
+    assert!(!is_xref_section(
+      b"4 0 obj\n<< /Length 9 >>\nstream\n/Type /XRef\nendstream\n"
+    ));
+    assert!(!is_xref_section(b""));
+  }
+
+  #[test]
+  fn test_read_pdf_skeleton_drops_stream_content() {
+    let mut pdf = b"%PDF-1.7\n5 0 obj\n<< /Length 200000 >>\nstream\r\n".to_vec();
+    // Conteúdo maior que o bloco de leitura, com texto que confundiria os scanners
+    pdf.extend_from_slice(&b"9 0 obj /Type /Catalog ".repeat(10_000));
+    pdf.extend_from_slice(
+      b"\nendstream\nendobj\n6 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n",
+    );
+    pdf.extend_from_slice(b"7 0 obj\n<< /S /streamer >>\nendobj\n");
+
+    let skeleton = read_pdf_skeleton(pdf.as_slice()).unwrap();
+    assert_eq!(
+      skeleton,
+      b"%PDF-1.7\n5 0 obj\n<< /Length 200000 >>\nstream\nendstream\nendobj\n6 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n7 0 obj\n<< /S /streamer >>\nendobj\n"
+    );
+    assert_eq!(get_next_object_number(&skeleton).unwrap(), 8);
+  }
+
+  #[test]
+  fn test_trailing_newline_len() {
+    assert_eq!(trailing_newline_len(b"%%EOF\r\n"), 2);
+    assert_eq!(trailing_newline_len(b"%%EOF\n\r"), 1);
+    assert_eq!(trailing_newline_len(b"%%EOF"), 0);
+    assert_eq!(trailing_newline_len(b"\n\n"), 2);
+  }
 }
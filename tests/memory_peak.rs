@@ -0,0 +1,205 @@
+//! Pico de memória da assinatura de PDFs grandes lidos do disco
+//!
+//! Este binário de teste tem o próprio `#[global_allocator]`, que contabiliza os bytes
+//! vivos de cada thread; os testes da biblioteca seguem com o alocador do sistema.
+//!
+//! O binding napi não linka fora do Node, então o teste só existe sem `native`:
+//! `cargo test --test memory_peak --no-default-features --features cms-rustcrypto,mmap`
+#![cfg(not(feature = "native"))]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use pdfsigner_rs::{list_signatures, PadesLevel, PdfSignError, PdfSigner, SignatureConfig};
+
+/// Limite de pico para quem não pode carregar o PDF inteiro em memória
+const MAX_PEAK: usize = 1024 * 1024;
+
+struct CountingAllocator;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+thread_local! {
+  static LIVE_BYTES: Cell<usize> = const { Cell::new(0) };
+  static PEAK_BYTES: Cell<usize> = const { Cell::new(0) };
+}
+
+fn track_alloc(size: usize) {
+  let _ = LIVE_BYTES.try_with(|live| {
+    live.set(live.get() + size);
+    let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(live.get())));
+  });
+}
+
+fn track_dealloc(size: usize) {
+  // Memória liberada em outra thread pode não ter sido contada nesta
+  let _ = LIVE_BYTES.try_with(|live| live.set(live.get().saturating_sub(size)));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    let ptr = System.alloc(layout);
+    if !ptr.is_null() {
+      track_alloc(layout.size());
+    }
+    ptr
+  }
+
+  unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+    let ptr = System.alloc_zeroed(layout);
+    if !ptr.is_null() {
+      track_alloc(layout.size());
+    }
+    ptr
+  }
+
+  unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    let new_ptr = System.realloc(ptr, layout, new_size);
+    if !new_ptr.is_null() {
+      track_dealloc(layout.size());
+      track_alloc(new_size);
+    }
+    new_ptr
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    System.dealloc(ptr, layout);
+    track_dealloc(layout.size());
+  }
+}
+
+/// Executa a operação e devolve o pico de bytes alocados por ela na thread atual
+fn peak_allocation<T>(operation: impl FnOnce() -> T) -> (T, usize) {
+  let baseline = LIVE_BYTES.with(Cell::get);
+  PEAK_BYTES.with(|peak| peak.set(baseline));
+  let result = operation();
+  let peak = PEAK_BYTES.with(Cell::get);
+  (result, peak.saturating_sub(baseline))
+}
+
+fn test_signer() -> PdfSigner {
+  let pfx_path = concat!(env!("CARGO_MANIFEST_DIR"), "/__test__/certificado-a1.pfx");
+  PdfSigner::from_pfx_file(pfx_path, "123456").unwrap()
+}
+
+fn offline_config() -> SignatureConfig {
+  SignatureConfig {
+    pades_level: PadesLevel::BB,
+    validate_icp_brasil: false,
+    ..SignatureConfig::default()
+  }
+}
+
+fn temp_path(name: &str) -> PathBuf {
+  std::env::temp_dir().join(format!(
+    "pdfsigner-peak-{}-{}.pdf",
+    name,
+    std::process::id()
+  ))
+}
+
+/// PDF de exemplo com uma atualização incremental que acrescenta um stream de
+/// `stream_len` bytes pseudoaleatórios
+fn large_pdf(stream_len: usize) -> Vec<u8> {
+  let mut pdf = fs::read(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/__test__/pdf_sample_2.pdf"
+  ))
+  .unwrap();
+  let text = String::from_utf8_lossy(&pdf).to_string();
+  let prev_xref = text[text.rfind("startxref").unwrap() + 9..]
+    .split_whitespace()
+    .next()
+    .unwrap()
+    .to_string();
+  let root = text[text.find("/Root").unwrap() + 5..]
+    .split_whitespace()
+    .next()
+    .unwrap()
+    .to_string();
+
+  pdf.push(b'\n');
+  let stream_pos = pdf.len();
+  pdf.extend_from_slice(format!("90 0 obj\n<< /Length {} >>\nstream\n", stream_len).as_bytes());
+  let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+  pdf.extend((0..stream_len).map(|_| {
+    state = state
+      .wrapping_mul(6364136223846793005)
+      .wrapping_add(1442695040888963407);
+    (state >> 56) as u8
+  }));
+  pdf.extend_from_slice(b"\nendstream\nendobj\n");
+  let xref_start = pdf.len();
+  pdf.extend_from_slice(
+    format!(
+      "xref\n90 1\n{:010} 00000 n \ntrailer\n<< /Size 91 /Prev {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+      stream_pos, prev_xref, root, xref_start
+    )
+    .as_bytes(),
+  );
+  pdf
+}
+
+#[test]
+fn test_max_input_bytes_rejects_before_reading() {
+  let config = SignatureConfig {
+    max_input_bytes: Some(1024 * 1024),
+    ..offline_config()
+  };
+  let signer = test_signer();
+
+  // Arquivo esparso de 64 MB: lê-lo inteiro apareceria no pico de alocação
+  let input = temp_path("oversized");
+  File::create(&input)
+    .unwrap()
+    .set_len(64 * 1024 * 1024)
+    .unwrap();
+  let (result, peak) = peak_allocation(|| signer.sign_pdf_with_path(&input, &config));
+  fs::remove_file(&input).unwrap();
+
+  assert!(matches!(result, Err(PdfSignError::InvalidPdf(_))));
+  assert!(peak < MAX_PEAK, "pico de {} bytes", peak);
+}
+
+#[test]
+fn test_sign_pdf_file_to_file_streams_large_pdf() {
+  let input = temp_path("large");
+  let output = temp_path("large-signed");
+  let original = large_pdf(12 * 1024 * 1024);
+  fs::write(&input, &original).unwrap();
+
+  let signer = test_signer();
+  let (result, peak) =
+    peak_allocation(|| signer.sign_pdf_file_to_file(&input, &output, &offline_config()));
+  result.unwrap();
+  let signed = fs::read(&output).unwrap();
+  fs::remove_file(&input).unwrap();
+  fs::remove_file(&output).unwrap();
+
+  // Só a atualização incremental e os blocos de leitura ficam em memória
+  assert!(peak < MAX_PEAK, "pico de {} bytes", peak);
+  assert_eq!(list_signatures(&signed).len(), 1);
+}
+
+#[test]
+#[cfg(feature = "mmap")]
+fn test_mapped_signing_allocates_only_the_update() {
+  let input = temp_path("mapped");
+  let output = temp_path("mapped-signed");
+  fs::write(&input, large_pdf(12 * 1024 * 1024)).unwrap();
+
+  let signer = test_signer();
+  let (report, peak) =
+    peak_allocation(|| signer.sign_pdf_mapped_to_file(&input, &output, &offline_config()));
+  report.unwrap();
+  let signed = fs::read(&output).unwrap();
+  fs::remove_file(&input).unwrap();
+  fs::remove_file(&output).unwrap();
+
+  // O original fica no mapeamento: só a atualização incremental é alocada
+  assert!(peak < MAX_PEAK, "pico de {} bytes", peak);
+  assert_eq!(list_signatures(&signed).len(), 1);
+}